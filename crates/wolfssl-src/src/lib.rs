@@ -5,6 +5,7 @@ use puffin_build::{library, vendor_dir};
 pub struct WolfSSLOptions {
     pub fix: Vec<String>,
     pub postauth: bool,
+    pub deterministic: bool,
 
     pub asan: bool,
     pub sancov: bool,
@@ -58,8 +59,13 @@ impl Artifacts {
 }
 
 pub fn build(options: &WolfSSLOptions) -> Artifacts {
-    let suffix = if options.asan { "-asan" } else { "" };
-    let name = format!("{}{suffix}", options.preset);
+    let asan_suffix = if options.asan { "-asan" } else { "" };
+    let deterministic_suffix = if options.deterministic {
+        "-deterministic"
+    } else {
+        ""
+    };
+    let name = format!("{}{asan_suffix}{deterministic_suffix}", options.preset);
 
     let mut config = library::Config::preset("wolfssl", &options.preset)
         .unwrap_or_else(|| panic!("missing preset wolfssl:{}", options.preset));
@@ -69,6 +75,7 @@ pub fn build(options: &WolfSSLOptions) -> Artifacts {
     config.option("gcov", options.gcov);
     config.option("llvm_cov", options.llvm_cov);
     config.option("postauth", options.postauth);
+    config.option("deterministic", options.deterministic);
     config.option("fix", options.fix.clone());
 
     let prefix = vendor_dir::from_env()
@@ -21,6 +21,7 @@ fn main() {
         asan: false,
         sancov: true,
         postauth: true,
+        deterministic: false,
         fix: vec![],
     });
 }
@@ -95,6 +95,7 @@ fn main() {
             "CVE-2022-42905".to_string(),
         ],
         postauth: (!cfg!(feature = "wolfssl-disable-postauth")),
+        deterministic: cfg!(feature = "deterministic"),
         asan: cfg!(feature = "asan"),
         sancov: cfg!(feature = "sancov"),
         gcov: cfg!(feature = "gcov"),
@@ -9,5 +9,55 @@ pub extern "C" fn put_rng_init() {
 
 #[no_mangle]
 pub extern "C" fn put_rng_reseed(buffer: *const u8, length: libc::size_t) {
-    log::warn!("[RNG] reseed failed: not implemented for wolfssl");
+    #[cfg(feature = "deterministic")]
+    {
+        let seed = if buffer.is_null() {
+            &[][..]
+        } else {
+            unsafe { std::slice::from_raw_parts(buffer, length) }
+        };
+        deterministic::reseed(seed);
+    }
+
+    #[cfg(not(feature = "deterministic"))]
+    log::warn!("[RNG] reseed failed: wolfssl is not built with the deterministic feature");
+}
+
+/// PRNG which replaces the RNG of wolfSSL, it is the same as in the LibreSSL harness
+#[cfg(feature = "deterministic")]
+mod deterministic {
+    use std::cell::Cell;
+
+    const DEFAULT_RNG_SEED: u64 = 42;
+
+    thread_local! {
+        static SEED: Cell<u64> = const { Cell::new(DEFAULT_RNG_SEED) };
+    }
+
+    pub fn reseed(buffer: &[u8]) {
+        let seed = buffer.get(..8).map_or(DEFAULT_RNG_SEED, |seed| {
+            u64::from_ne_bytes(seed.try_into().unwrap())
+        });
+        SEED.with(|cell| cell.set(seed));
+    }
+
+    /// Called by wolfSSL for all random bytes, see `CUSTOM_RAND_GENERATE_BLOCK`
+    #[no_mangle]
+    pub extern "C" fn put_rng_generate_block(output: *mut u8, size: u32) -> i32 {
+        if output.is_null() {
+            return -1;
+        }
+
+        let output = unsafe { std::slice::from_raw_parts_mut(output, size as usize) };
+        SEED.with(|cell| {
+            let mut seed = cell.get();
+            for byte in output {
+                seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+                *byte = (seed >> 33) as u8;
+            }
+            cell.set(seed);
+        });
+
+        0
+    }
 }
@@ -86,6 +86,10 @@ unsafe fn state<'a, S: 'a>(bio: *mut wolf::WOLFSSL_BIO) -> &'a mut StreamState<S
     &mut *(wolf::wolfSSL_BIO_get_data(bio) as *mut _)
 }
 
+pub unsafe fn get_ref<'a, S: 'a>(bio: *mut wolf::WOLFSSL_BIO) -> &'a S {
+    &state(bio).stream
+}
+
 pub unsafe fn get_mut<'a, S: 'a>(bio: *mut wolf::WOLFSSL_BIO) -> &'a mut S {
     &mut state(bio).stream
 }
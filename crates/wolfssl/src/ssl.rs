@@ -284,6 +284,24 @@ impl SslContextRef {
         }
     }
 
+    /// Configures the certificate verification method for new connections, but accepts any
+    /// certificate of the peer. This is only useful for testing.
+    pub fn set_verify_accept_any(&mut self, mode: SslVerifyMode) {
+        unsafe extern "C" fn accept_any(
+            _preverify_ok: c_int,
+            _store: *mut wolf::WOLFSSL_X509_STORE_CTX,
+        ) -> c_int {
+            1
+        }
+
+        // SAFETY: `self` owns a valid `WOLFSSL_CTX` for the duration of the call. The callback
+        // matches the `VerifyCallback` signature of wolfSSL, captures nothing and does not touch
+        // its arguments, so it stays valid for every connection created from the context.
+        unsafe {
+            wolf::wolfSSL_CTX_set_verify(self.as_ptr(), mode.bits() as c_int, Some(accept_any));
+        }
+    }
+
     #[cfg(not(feature = "wolfssl430"))]
     pub fn set_num_tickets(&mut self, n: u64) -> Result<(), ErrorStack> {
         unsafe { cvt(wolf::wolfSSL_CTX_set_num_tickets(self.as_ptr(), n)).map(|_| ()) }
@@ -431,6 +449,71 @@ impl SslRef {
         unsafe { wolf::wolfSSL_get_current_cipher_suite(self.as_ptr()) }
     }
 
+    /// Get the level and the description of the last alert which was sent
+    ///
+    /// This corresponds to the `last_tx` alert of [`wolfSSL_get_alert_history`].
+    ///
+    /// [`wolfSSL_get_alert_history`]: https://www.wolfssl.com/doxygen/group__IO.html
+    pub fn last_sent_alert(&self) -> Option<(u8, u8)> {
+        let mut history: wolf::WOLFSSL_ALERT_HISTORY = unsafe { std::mem::zeroed() };
+        unsafe { cvt(wolf::wolfSSL_get_alert_history(self.as_ptr(), &mut history)).ok()? };
+
+        let alert = history.last_tx;
+        if alert.code < 0 || alert.level < 0 {
+            return None;
+        }
+
+        Some((alert.level as u8, alert.code as u8))
+    }
+
+    /// Forget the alerts which were sent and received so far, such that
+    /// [`SslRef::last_sent_alert`] only returns the alerts which are sent afterwards
+    pub fn clear_alert_history(&mut self) {
+        unsafe {
+            let history = &mut (*self.as_ptr()).alert_history;
+            history.last_rx.code = -1;
+            history.last_rx.level = -1;
+            history.last_tx.code = -1;
+            history.last_tx.level = -1;
+        }
+    }
+
+    /// Get the result of the verification of the peer's certificate, `0` if it verified
+    ///
+    /// This corresponds to [`wolfSSL_get_verify_result`].
+    ///
+    /// [`wolfSSL_get_verify_result`]: https://www.wolfssl.com/doxygen/group__CertsKeys.html
+    pub fn verify_result(&self) -> i64 {
+        unsafe { wolf::wolfSSL_get_verify_result(self.as_ptr()) as i64 }
+    }
+
+    /// Whether the handshake resumed a session instead of establishing a new one
+    ///
+    /// This corresponds to [`wolfSSL_session_reused`].
+    ///
+    /// [`wolfSSL_session_reused`]: https://www.wolfssl.com/doxygen/group__IO.html
+    pub fn session_reused(&self) -> bool {
+        unsafe { wolf::wolfSSL_session_reused(self.as_ptr()) != 0 }
+    }
+
+    /// Get the master secret of the current session. Once a TLS 1.3 handshake completed, this is
+    /// the resumption master secret, padded with zeros to the longest supported hash.
+    ///
+    /// This corresponds to [`wolfSSL_SESSION_get_master_key`].
+    ///
+    /// [`wolfSSL_SESSION_get_master_key`]: https://www.wolfssl.com/doxygen/group__Setup.html
+    pub fn session_master_key(&self) -> Vec<u8> {
+        let mut data: [u8; 48] = [0; 48];
+        let len = unsafe {
+            let session = wolf::wolfSSL_get_session(self.as_ptr());
+            if session.is_null() {
+                return Vec::new();
+            }
+            wolf::wolfSSL_SESSION_get_master_key(session, data.as_mut_ptr(), data.len() as c_int)
+        };
+        Vec::from(&data[..len.clamp(0, data.len() as c_int) as usize])
+    }
+
     /// Get the current client random
     pub fn client_random(&self) -> Vec<u8> {
         let mut data: [u8; 32] = [0; 32];
@@ -626,6 +709,15 @@ impl<S: Read + Write> SslStream<S> {
         })
     }
 
+    /// Returns a shared reference to the underlying stream.
+    pub fn get_ref(&self) -> &S {
+        unsafe {
+            let bio = wolf::wolfSSL_SSL_get_rbio(self.ssl.as_ptr());
+
+            bio::get_ref(bio)
+        }
+    }
+
     /// Returns a mutable reference to the underlying stream.
     ///
     /// # Warning
@@ -125,6 +125,11 @@ impl Put {
             self.name()
         );
         println!("cargo:rustc-cfg=has_put=\"{}\"", self.name());
+        if matches!(self.harness.kind, harness::Kind::Rust) {
+            // Only the Rust harnesses implement the optional parts of the PUT interface, e.g. the
+            // alerts which the PUT sent
+            println!("cargo:rustc-cfg=rust_harness=\"{}\"", self.name());
+        }
 
         if self
             .library
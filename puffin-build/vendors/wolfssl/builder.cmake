@@ -1,6 +1,7 @@
 use_languages(C)
 
 option(postauth "Build with post-auth" ON)
+option(deterministic "Build with a PRNG which is reseeded by the harness" OFF)
 
 if(VENDOR_VERSION VERSION_LESS "5.2.0")
   declare_vulnerability("CVE-2022-25638" PATCH ${CMAKE_CURRENT_LIST_DIR}/patches/fix-CVE-2022-25638.patch)
@@ -75,6 +76,11 @@ autotools_builder(
     # FIXME broken: -DWC_RNG_SEED_CB              # makes test test_seed_cve_2022_38153 fail, but should be used when evaluating coverage to get same coverage than other fuzzers which use this flag to disable determinism
     # FIXME broken: -DWOLFSSL_GENSEED_FORTEST     # makes test test_seed_cve_2022_38153 fail, but should be used when evaluating coverage to get same coverage than other fuzzers which use this flag to disable determinism
 
+    # deterministic: all random bytes come from put_rng_generate_block, see wolfssl-sys
+    $<$<BOOL:${deterministic}>:-DCUSTOM_RAND_GENERATE_BLOCK=put_rng_generate_block>
+    $<$<BOOL:${deterministic}>:-include>
+    $<$<BOOL:${deterministic}>:${CMAKE_CURRENT_LIST_DIR}/deterministic_rand.h>
+
     # sancov
     $<$<BOOL:${sancov}>:-fsanitize-coverage=trace-pc-guard>
 
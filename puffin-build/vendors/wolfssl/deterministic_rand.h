@@ -0,0 +1,8 @@
+#ifndef PUFFIN_DETERMINISTIC_RAND_H
+#define PUFFIN_DETERMINISTIC_RAND_H
+
+// Source of all random bytes of wolfSSL when built with CUSTOM_RAND_GENERATE_BLOCK. It is
+// implemented by the wolfssl-sys crate and reseeded through put_rng_reseed.
+int put_rng_generate_block(unsigned char *output, unsigned int size);
+
+#endif
@@ -8,13 +8,14 @@
 use core::fmt;
 use std::fmt::Debug;
 use std::hash::Hash;
+use std::str::FromStr;
 
 use serde::{Deserialize, Serialize};
 
 use crate::algebra::ConcreteMessage;
 use crate::error::Error;
-use crate::protocol::{ProtocolBehavior, ProtocolTypes};
-use crate::put::Put;
+use crate::protocol::{ProtocolAlerts, ProtocolBehavior, ProtocolTypes};
+use crate::put::{Put, SessionData};
 use crate::stream::Stream;
 
 /// Copyable reference to an [`Agent`]. It identifies exactly one agent.
@@ -45,9 +46,33 @@ impl Default for AgentName {
     }
 }
 
+/// Human-readable names of the first agents, following the convention of the seed traces that the
+/// first agent is the client and the second one the server.
+const AGENT_NAMES: [&str; 2] = ["client", "server"];
+
 impl fmt::Display for AgentName {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.0)
+        match AGENT_NAMES.get(self.0 as usize) {
+            Some(name) => write!(f, "{}", name),
+            None => write!(f, "agent{}", self.0),
+        }
+    }
+}
+
+impl FromStr for AgentName {
+    type Err = Error;
+
+    /// Parses the names produced by [`fmt::Display`], as well as plain agent numbers
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(index) = AGENT_NAMES.iter().position(|name| *name == s) {
+            return Ok(Self(index as u8));
+        }
+
+        s.strip_prefix("agent")
+            .unwrap_or(s)
+            .parse::<u8>()
+            .map(Self)
+            .map_err(|_| Error::Agent(format!("Unknown agent name: {}", s)))
     }
 }
 
@@ -64,6 +89,12 @@ pub trait ProtocolDescriptorConfig:
     /// Indicates wheter a agent is reusable, ie. it's configuration is compatible with the new
     /// agent to spawn
     fn is_reusable_with(&self, other: &Self) -> bool;
+
+    /// Capabilities which a PUT needs to spawn an agent of this configuration, see
+    /// [`Factory::supports`](crate::put_registry::Factory::supports)
+    fn required_capabilities(&self) -> Vec<&str> {
+        vec![]
+    }
 }
 
 /// [`AgentDescriptor`]s act like a blueprint to spawn [`Agent`]s with a corresponding server or
@@ -143,11 +174,73 @@ impl<PB: ProtocolBehavior> Agent<PB> {
         self.put.progress()
     }
 
+    /// Advances the agent by at most one flight, see [`Put::progress_one_flight`].
+    pub fn progress_one_flight(&mut self) -> Result<(), Error> {
+        self.put.progress_one_flight()
+    }
+
+    /// Number of bytes the agent produced which were not yet taken from its outbound channel.
+    #[must_use]
+    pub fn pending_output_len(&self) -> usize {
+        self.put.pending_output_len()
+    }
+
+    /// Takes the bytes the agent buffered for sending, see [`Put::drain_output`].
+    pub fn drain_output(&mut self) -> Vec<u8> {
+        self.put.drain_output()
+    }
+
     pub fn reset(&mut self, new_name: AgentName) -> Result<(), Error> {
         self.descriptor.name = new_name;
         self.put.reset(new_name)
     }
 
+    /// Soft reset of the PUT, see [`Put::reset_soft`].
+    pub fn reset_soft(&mut self, new_name: AgentName) -> Result<bool, Error> {
+        let is_reset = self.put.reset_soft(new_name)?;
+        if is_reset {
+            self.descriptor.name = new_name;
+        }
+        Ok(is_reset)
+    }
+
+    /// Session negotiated by the agent, see [`Put::export_session`].
+    #[must_use]
+    pub fn export_session(&self) -> Option<SessionData> {
+        self.put.export_session()
+    }
+
+    /// Offers `session` for resumption in the next handshake, see [`Put::import_session`].
+    pub fn import_session(&mut self, session: &SessionData) -> Result<(), Error> {
+        self.put.import_session(session)
+    }
+
+    /// Last alert which the agent sent, see [`Put::last_alert`].
+    #[must_use]
+    pub fn last_alert(&self) -> Option<PB::Alert>
+    where
+        PB: ProtocolAlerts,
+    {
+        self.put.last_alert()
+    }
+
+    /// Forgets the last alert which the agent sent, see [`Put::clear_last_alert`].
+    pub fn clear_last_alert(&mut self) {
+        self.put.clear_last_alert();
+    }
+
+    /// Whether the alerts of the agent are observed, see [`Put::observes_alerts`].
+    #[must_use]
+    pub fn observes_alerts(&self) -> bool {
+        self.put.observes_alerts()
+    }
+
+    /// Extensions which the PUT of the agent implements, see [`Put::supported_extensions`].
+    #[must_use]
+    pub fn supported_extensions(&self) -> Vec<PB::Extension> {
+        self.put.supported_extensions()
+    }
+
     /// Shut down the agent by consuming it and returning a string that summarizes the execution.
     pub fn shutdown(&mut self) -> String {
         self.put.shutdown()
@@ -159,6 +252,12 @@ impl<PB: ProtocolBehavior> Agent<PB> {
         self.put.is_state_successful()
     }
 
+    /// Checks whether the agent finished its handshake, see [`Put::is_handshake_complete`].
+    #[must_use]
+    pub fn is_handshake_complete(&self) -> bool {
+        self.put.is_handshake_complete()
+    }
+
     /// Checks whether the agent is reusable with the descriptor.
     #[must_use]
     pub fn is_reusable_with(
@@ -196,3 +295,24 @@ impl<PB: ProtocolBehavior> Stream<PB> for Agent<PB> {
         self.put.take_message_from_outbound()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_log::test]
+    fn test_agent_name_round_trip() {
+        let mut name = AgentName::first();
+
+        for _ in 0..5 {
+            assert_eq!(name.to_string().parse::<AgentName>().unwrap(), name);
+            name = name.next();
+        }
+
+        assert_eq!(AgentName::first().to_string(), "client");
+        assert_eq!(AgentName::first().next().to_string(), "server");
+        assert_eq!(AgentName(4).to_string(), "agent4");
+        assert_eq!("2".parse::<AgentName>().unwrap(), AgentName(2));
+        assert!("eve".parse::<AgentName>().is_err());
+    }
+}
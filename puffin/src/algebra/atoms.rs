@@ -128,6 +128,10 @@ impl<PT: ProtocolTypes> Function<PT> {
         self.fn_container.attrs.is_get
     }
 
+    pub fn is_deprecated(&self) -> bool {
+        self.fn_container.attrs.is_deprecated
+    }
+
     #[must_use]
     pub fn new(shape: DynamicFunctionShape<PT>, dynamic_fn: Box<dyn DynamicFunction<PT>>) -> Self {
         let attrs = PT::signature()
@@ -1,11 +1,14 @@
 use std::any::TypeId;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::time::Instant;
 
 use anyhow::Context;
 use libafl::inputs::{BytesInput, HasBytesVec};
 use serde::{Deserialize, Serialize};
 
 use crate::algebra::dynamic_function::TypeShape;
-use crate::algebra::{ConcreteMessage, DYTerm, Term, TermType};
+use crate::algebra::term::LOG_MAX_NODES;
+use crate::algebra::{profile, ConcreteMessage, DYTerm, Term, TermType};
 use crate::error::Error;
 use crate::fuzzer::utils::TermPath;
 use crate::protocol::{EvaluatedTerm, ProtocolBehavior, ProtocolTypes};
@@ -90,6 +93,26 @@ pub fn replace_payloads<PT: ProtocolTypes, PB: ProtocolBehavior<ProtocolTypes =
     todo!("Done in another PR (bit mutations/term-evaluation)");
 }
 
+/// Identifies the evaluation of a term which neither depends on the knowledge through variables
+/// nor has payloads, see [`TraceContext::memoize_evaluation`].
+///
+/// Clones of a subterm share its [`resistant_id`](TermType::resistant_id), while mutations can
+/// change a subterm without changing its id, therefore the key also holds a hash of the structure
+/// of the subterm. Both are computed bottom-up while the term is evaluated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct MemoKey {
+    pub(crate) resistant_id: u32,
+    pub(crate) structure: u64,
+}
+
+/// Evaluation of a term by [`Term::eval_until_opaque`]: the evaluated term, the payloads to
+/// replace and the [`MemoKey`] of the term, `None` if its evaluation can not be memoized
+pub(crate) type EvalResult<'a, PT> = (
+    Box<dyn EvaluatedTerm<PT>>,
+    Vec<PayloadContext<'a, PT>>,
+    Option<MemoKey>,
+);
+
 impl<PT: ProtocolTypes> Term<PT> {
     /// Evaluate a term without replacing the payloads (returning the payloads with the
     /// corresponding paths instead, i.e., a `Vec<PayloadContext>` accumulator), except when
@@ -108,11 +131,14 @@ impl<PT: ProtocolTypes> Term<PT> {
         with_payloads: bool,
         sibling_has_payloads: bool,
         type_term: &TypeShape<PT>,
-    ) -> Result<(Box<dyn EvaluatedTerm<PT>>, Vec<PayloadContext<PT>>), Error>
+    ) -> Result<EvalResult<'_, PT>, Error>
     where
         PB: ProtocolBehavior<ProtocolTypes = PT>,
     {
-        log::debug!("[eval_until_opaque] [START]: Eval term:\n {self}");
+        log::debug!(
+            "[eval_until_opaque] [START]: Eval term:\n {}",
+            self.display_truncated(LOG_MAX_NODES)
+        );
         if let (true, Some(payload)) = (with_payloads, &self.payloads) {
             // TODO: investigate whether this value could be incorrect due to modifications to the
             // terms through mutations previously applied
@@ -127,7 +153,7 @@ impl<PT: ProtocolTypes> Term<PT> {
                     path: eval_tree.path.clone(),
                 }];
                 eval_tree.encode = Some(payload.payload_0.bytes().to_vec());
-                return Ok((di, p_c));
+                return Ok((di, p_c, None));
             }
             log::trace!("[eval_until_opaque] Attempt to skip evaluation failed, fall back to normal evaluation...");
         }
@@ -164,17 +190,25 @@ impl<PT: ProtocolTypes> Term<PT> {
                                 payloads: self.payloads.as_ref().unwrap(),
                                 path: eval_tree.path.clone(),
                             }],
+                            None,
                         ));
                     }
                 }
                 log::trace!("[eval_until_opaque] [Var] Did not add a payload for a leaf at path: {:?} and eval is: {:?}", eval_tree.path, PB::any_get_encoding(d.as_ref()));
-                Ok((d, vec![]))
+                Ok((d, vec![], None))
             }
             DYTerm::Application(func, args) => {
                 log::trace!(
                     "[eval_until_opaque] [App]: Application from path={:?}",
                     eval_tree.path
                 );
+                // Hash of the structure of the term, `None` once a subterm is found which can not
+                // be memoized
+                let mut structure = self.is_symbolic().then(|| {
+                    let mut hasher = ahash::RandomState::with_seeds(0, 0, 0, 0).build_hasher();
+                    func.name().hash(&mut hasher);
+                    hasher
+                });
                 let mut dynamic_args: Vec<Box<dyn EvaluatedTerm<PT>>> = Vec::new(); // will contain all the arguments on which to call the function symbol
                                                                                     // implementation
                 let mut all_payloads = vec![]; // will collect all payloads contexts of arguments (except those under opaque
@@ -189,7 +223,7 @@ impl<PT: ProtocolTypes> Term<PT> {
                     if with_payloads && self.is_opaque() && ti.has_payload_to_replace() {
                         // Fully evaluate this sub-term and consume the payloads
                         log::trace!("    * [eval_until_opaque] Opaque and has payloads: Inner call of eval on term: {}\n with #{} payloads", ti, ti.payloads_to_replace().len());
-                        let bi = ti.evaluate(ctx)?; // payloads in ti are consumed here!
+                        let bi = ti.evaluate_config_within_depth(ctx, true)?.0; // payloads in ti are consumed here!
                         let typei = func.shape().argument_types[i].clone();
                         let di = PB::try_read_bytes(&bi, typei.clone().into()) // TODO: to make this more robust, we might want to relax this when payloads are in deeper terms, then read there!
                             .with_context(|| {
@@ -208,6 +242,7 @@ impl<PT: ProtocolTypes> Term<PT> {
                             })?;
                         dynamic_args.push(di); // no need to add payloads to all_p as they were
                                                // consumed (opaque function symbol)
+                        structure = None;
                     } else {
                         let mut path_i = eval_tree.path.clone();
                         path_i.push(i); // adding `i` for i-th argument
@@ -216,7 +251,7 @@ impl<PT: ProtocolTypes> Term<PT> {
                         } else {
                             EvalTree::with_path(vec![]) // dummy eval_tree
                         };
-                        let (di, mut p_s) = ti.eval_until_opaque(
+                        let (di, mut p_s, key_i) = ti.eval_until_opaque(
                             &mut eval_tree_i,
                             ctx,
                             with_payloads,
@@ -224,6 +259,10 @@ impl<PT: ProtocolTypes> Term<PT> {
                             &func.shape().argument_types[i],
                         )?;
                         dynamic_args.push(di); // add the evaluation (Boc<dyn Any>) to the list of arguments
+                        match (&mut structure, key_i) {
+                            (Some(hasher), Some(key_i)) => hasher.write_u64(key_i.structure),
+                            _ => structure = None,
+                        }
                         if with_payloads {
                             eval_tree_args.push(eval_tree_i);
                             all_payloads.append(p_s.as_mut()); // collect the payloads
@@ -234,9 +273,37 @@ impl<PT: ProtocolTypes> Term<PT> {
                         );
                     }
                 }
-                log::trace!("[eval_until_opaque] Now calling the function symbol {} implementation and then updating payloads...", func.name());
-                let dynamic_fn = &func.dynamic_fn();
-                let result: Box<dyn EvaluatedTerm<PT>> = dynamic_fn(&dynamic_args)?; // evaluation of the function symbol implementation
+                let key = structure.map(|hasher| MemoKey {
+                    resistant_id: func.resistant_id,
+                    structure: hasher.finish(),
+                });
+                // Constants are cheaper to evaluate than to memoize
+                let memo_key = key.filter(|_| !args.is_empty());
+                let memoized = memo_key.and_then(|key| ctx.memoized_evaluation(key));
+
+                let result: Box<dyn EvaluatedTerm<PT>> = if let Some(result) = memoized {
+                    log::trace!(
+                        "[eval_until_opaque] Reusing the memoized evaluation of {}",
+                        func.name()
+                    );
+                    result
+                } else {
+                    log::trace!("[eval_until_opaque] Now calling the function symbol {} implementation and then updating payloads...", func.name());
+                    let dynamic_fn = &func.dynamic_fn();
+                    let result = if profile::is_profiling() {
+                        let start = Instant::now();
+                        let result = dynamic_fn(&dynamic_args);
+                        profile::record_call(func.name(), start.elapsed(), result.is_err());
+                        result?
+                    } else {
+                        // evaluation of the function symbol implementation
+                        dynamic_fn(&dynamic_args)?
+                    };
+                    if let Some(key) = memo_key {
+                        ctx.memoize_evaluation(key, result.as_ref());
+                    }
+                    result
+                };
 
                 if with_payloads && self.payloads.is_some() {
                     all_payloads.push(PayloadContext {
@@ -255,7 +322,7 @@ impl<PT: ProtocolTypes> Term<PT> {
                     eval_tree.encode = Some(eval);
                 }
 
-                Ok((result, all_payloads))
+                Ok((result, all_payloads, key))
             }
         }
     }
@@ -80,6 +80,10 @@ pub struct FunctionAttributes {
     /// Incidentally, its concretization does not contain all the conretizations of its arguments.
     /// Examples: `fn_get_server_key_share`.
     pub is_get: bool,
+    /// Whether the function symbol is being phased out of the signature. Deprecated symbols are
+    /// not introduced into new terms by the term zoo and the mutators, but terms which already use
+    /// them are still deserialized and evaluated.
+    pub is_deprecated: bool,
 }
 // TODO: add a uni test for making sure the given attributes are correct
 
@@ -89,6 +93,7 @@ impl Default for FunctionAttributes {
             is_opaque: false,
             is_list: false,
             is_get: false,
+            is_deprecated: false,
         }
     }
 }
@@ -210,6 +215,9 @@ impl<PT: ProtocolTypes> Clone for Box<dyn DynamicFunction<PT>> {
 /// * describe their shape during runtime
 /// * wrap them into a [`DynamicFunction`] which is callable with arbitrary data
 ///
+/// Function symbols take at most 9 arguments by reference and return `Result<R, FnError>`, where
+/// the function and `R` are `Send + Sync`.
+///
 /// Adapted from <https://jsdw.me/posts/rust-fn-traits/> but using type ids
 pub trait DescribableFunction<PT: ProtocolTypes, Types> {
     fn name(&'static self) -> &'static str;
@@ -217,8 +225,35 @@ pub trait DescribableFunction<PT: ProtocolTypes, Types> {
     fn make_dynamic(&'static self) -> Box<dyn DynamicFunction<PT>>;
 }
 
+/// Types which a function trait takes and returns, regardless of whether it is a valid function
+/// symbol, see [`assert_send_sync_function`]
+pub trait FunctionTypes<Types> {
+    type Arguments;
+    type Result;
+}
+
+/// Fails to compile if `f`, its arguments or its result are not `Send + Sync`, such that the type
+/// which can not be shared between threads is named in the error, instead of only reporting that
+/// [`DescribableFunction`] is not implemented. Called by
+/// [`define_signature`](crate::define_signature) for each function symbol.
+pub const fn assert_send_sync_function<F, Types>(_f: &F)
+where
+    F: FunctionTypes<Types> + Send + Sync,
+    F::Arguments: Send + Sync,
+    F::Result: Send + Sync,
+{
+}
+
 macro_rules! dynamic_fn {
     ($($arg:ident)* => $res:ident) => (
+    impl<F, $res, $($arg),*> FunctionTypes<($res, $($arg),*)> for F
+    where
+        F: Fn($(&$arg),*) -> Result<$res, FnError>,
+    {
+        type Arguments = ($($arg,)*);
+        type Result = $res;
+    }
+
     impl<F,PT : ProtocolTypes, $res: 'static, $($arg: 'static),*>
         DescribableFunction<PT, ($res, $($arg),*)> for F
     where
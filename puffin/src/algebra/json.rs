@@ -0,0 +1,82 @@
+//! Annotated JSON representation of [`Term`]s for external tooling.
+//!
+//! Unlike the serde-derived representation, the annotated JSON embeds human-readable names of
+//! functions and types. It is meant for analysis only and can not be deserialized into a
+//! [`Term`] again.
+
+use serde_json::{json, Value};
+
+use crate::algebra::{remove_prefix, DYTerm, Term};
+use crate::protocol::ProtocolTypes;
+
+impl<PT: ProtocolTypes> Term<PT> {
+    /// Lowers this term to JSON with `{fn, return_type, args}` nodes for function applications and
+    /// `{var, type, source}` nodes for variables. The `source` of a variable without source is
+    /// `null`.
+    #[must_use]
+    pub fn to_annotated_json(&self) -> Value {
+        match &self.term {
+            DYTerm::Variable(variable) => json!({
+                "var": variable.resistant_id,
+                "type": remove_prefix(variable.typ.name),
+                "source": variable.query.source.as_ref().map(ToString::to_string),
+            }),
+            DYTerm::Application(func, args) => json!({
+                "fn": remove_prefix(func.name()),
+                "return_type": remove_prefix(func.shape().return_type.name),
+                "args": args.iter().map(Term::to_annotated_json).collect::<Vec<_>>(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::AgentName;
+    use crate::algebra::test_signature::*;
+    use crate::term;
+    use crate::trace::{Action, Source};
+
+    #[test_log::test]
+    fn test_client_hello_annotated_json() {
+        let trace = setup_simple_trace();
+        let Action::Input(input) = &trace.steps[0].action else {
+            panic!("expected the ClientHello input");
+        };
+
+        let json = input.recipe.to_annotated_json();
+
+        assert_eq!(json["fn"], "fn_client_hello");
+        assert_eq!(json["return_type"], "HandshakeMessage");
+        assert_eq!(json["args"][0]["fn"], "fn_protocol_version12");
+        assert_eq!(json["args"].as_array().unwrap().len(), 6);
+    }
+
+    #[test_log::test]
+    fn test_variable_annotated_json() {
+        let server = AgentName::first();
+        let term: TestTerm = term! {
+            fn_encrypt12(((server, 0)/HandshakeMessage), fn_seq_0)
+        };
+
+        let json = term.to_annotated_json();
+        let variable = &json["args"][0];
+
+        assert_eq!(variable["type"], "HandshakeMessage");
+        assert_eq!(
+            variable["source"],
+            Source::Agent(server).to_string().as_str()
+        );
+
+        let DYTerm::Application(_, args) = &term.term else {
+            unreachable!()
+        };
+        let mut without_source = args[0].clone();
+        if let DYTerm::Variable(variable) = &mut without_source.term {
+            variable.query.source = None;
+        }
+
+        assert!(without_source.to_annotated_json()["source"].is_null());
+    }
+}
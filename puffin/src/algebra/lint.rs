@@ -0,0 +1,182 @@
+//! Detection of semantically-redundant constructions in [`Term`]s.
+//!
+//! Fuzzer-generated terms often contain redundancies, like a value which is wrapped twice by the
+//! same function. Such constructions are not necessarily mistakes, as they can be intentional, for
+//! example to test length fields. Therefore, linting only reports [`LintWarning`]s and never fails.
+
+use std::fmt;
+
+use crate::algebra::{DYTerm, Term};
+use crate::protocol::ProtocolTypes;
+
+/// A redundant construction found by [`Term::lint`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LintWarning {
+    /// A function of shape `T -> T` is directly applied to its own result, e.g. `f(f(x))`.
+    NestedWrap {
+        function: &'static str,
+        resistant_id: u32,
+    },
+    /// A list function appends the same arguments as the list function it is applied to, e.g.
+    /// `append(append(l, x), x)`.
+    RepeatedAppend {
+        function: &'static str,
+        resistant_id: u32,
+    },
+}
+
+impl fmt::Display for LintWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LintWarning::NestedWrap {
+                function,
+                resistant_id,
+            } => write!(f, "{} is wrapped by itself (id {})", function, resistant_id),
+            LintWarning::RepeatedAppend {
+                function,
+                resistant_id,
+            } => write!(
+                f,
+                "{} appends the same element repeatedly (id {})",
+                function, resistant_id
+            ),
+        }
+    }
+}
+
+impl<PT: ProtocolTypes> Term<PT> {
+    /// Reports all semantically-redundant constructions in this term, the outermost first.
+    pub fn lint(&self) -> Vec<LintWarning> {
+        let mut warnings = vec![];
+        lint_rec(self, &mut warnings);
+        warnings
+    }
+}
+
+fn lint_rec<PT: ProtocolTypes>(term: &Term<PT>, warnings: &mut Vec<LintWarning>) {
+    let DYTerm::Application(func, args) = &term.term else {
+        return;
+    };
+
+    let shape = func.shape();
+
+    if let [arg] = args.as_slice() {
+        if let DYTerm::Application(inner, _) = &arg.term {
+            if inner.name() == func.name() && shape.argument_types[0] == shape.return_type {
+                warnings.push(LintWarning::NestedWrap {
+                    function: func.name(),
+                    resistant_id: func.resistant_id,
+                });
+            }
+        }
+    }
+
+    if func.is_list() {
+        let is_repeated = args.iter().enumerate().any(|(i, arg)| match &arg.term {
+            DYTerm::Application(inner, inner_args) if inner.name() == func.name() => args
+                .iter()
+                .zip(inner_args)
+                .enumerate()
+                .all(|(j, (a, b))| j == i || is_same_term(a, b)),
+            _ => false,
+        });
+
+        if is_repeated {
+            warnings.push(LintWarning::RepeatedAppend {
+                function: func.name(),
+                resistant_id: func.resistant_id,
+            });
+        }
+    }
+
+    for arg in args {
+        lint_rec(arg, warnings);
+    }
+}
+
+/// Structural equality of terms, ignoring payloads and ids
+fn is_same_term<PT: ProtocolTypes>(a: &Term<PT>, b: &Term<PT>) -> bool {
+    match (&a.term, &b.term) {
+        (DYTerm::Variable(a), DYTerm::Variable(b)) => a == b,
+        (DYTerm::Application(fa, args_a), DYTerm::Application(fb, args_b)) => {
+            fa == fb
+                && args_a.len() == args_b.len()
+                && args_a.iter().zip(args_b).all(|(a, b)| is_same_term(a, b))
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algebra::test_signature::*;
+    use crate::term;
+    use crate::trace::Action;
+
+    #[test_log::test]
+    fn test_lint_nested_wrap() {
+        let term: TestTerm = term! {
+            fn_renegotiation_info_extension(
+                (fn_identity((fn_identity(fn_empty_bytes_vec))))
+            )
+        };
+
+        let warnings = term.lint();
+
+        assert_eq!(warnings.len(), 1);
+        assert!(matches!(
+            warnings[0],
+            LintWarning::NestedWrap {
+                function,
+                ..
+            } if function.ends_with("fn_identity")
+        ));
+    }
+
+    #[test_log::test]
+    fn test_lint_repeated_append() {
+        let term: TestTerm = term! {
+            fn_client_extensions_append(
+                (fn_client_extensions_append(
+                    fn_client_extensions_new,
+                    fn_signature_algorithm_extension
+                )),
+                fn_signature_algorithm_extension
+            )
+        };
+
+        let warnings = term.lint();
+
+        assert_eq!(warnings.len(), 1);
+        assert!(matches!(
+            warnings[0],
+            LintWarning::RepeatedAppend {
+                function,
+                ..
+            } if function.ends_with("fn_client_extensions_append")
+        ));
+    }
+
+    #[test_log::test]
+    fn test_lint_clean_term() {
+        let term: TestTerm = term! {
+            fn_client_extensions_append(
+                (fn_client_extensions_append(
+                    fn_client_extensions_new,
+                    fn_signature_algorithm_extension
+                )),
+                fn_ec_point_formats_extension
+            )
+        };
+
+        assert!(term.lint().is_empty());
+        assert!(setup_simple_trace()
+            .steps
+            .iter()
+            .all(|step| match &step.action {
+                Action::Input(input) => input.recipe.lint().is_empty(),
+                Action::Output(_) => true,
+            }));
+    }
+}
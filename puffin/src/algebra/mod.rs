@@ -41,7 +41,11 @@ pub mod atoms;
 pub mod bitstrings;
 pub mod dynamic_function;
 pub mod error;
+pub mod json;
+pub mod lint;
 pub mod macros;
+pub mod parser;
+pub mod profile;
 pub mod signature;
 pub mod term;
 
@@ -93,8 +97,11 @@ impl Matcher for AnyMatcher {
 #[allow(clippy::ptr_arg)]
 pub mod test_signature {
     use std::any::TypeId;
+    use std::convert::Infallible;
     use std::fmt;
     use std::io::Read;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
 
     use puffin_build::puffin;
     use serde::{Deserialize, Serialize};
@@ -102,7 +109,7 @@ pub mod test_signature {
     use crate::agent::{AgentDescriptor, AgentName, ProtocolDescriptorConfig};
     use crate::algebra::dynamic_function::{FunctionAttributes, TypeShape};
     use crate::algebra::error::FnError;
-    use crate::algebra::{AnyMatcher, Term};
+    use crate::algebra::{AnyMatcher, ConcreteMessage, Term};
     use crate::claims::{Claim, GlobalClaimList, SecurityViolationPolicy};
     use crate::codec::{CodecP, Reader};
     use crate::error::Error;
@@ -112,8 +119,9 @@ pub mod test_signature {
         ProtocolTypes,
     };
     use crate::put::{Put, PutDescriptor, PutOptions};
-    use crate::put_registry::Factory;
-    use crate::trace::{Action, InputAction, Knowledge, Source, Step, Trace};
+    use crate::put_registry::{Factory, PutRegistry};
+    use crate::stream::Stream;
+    use crate::trace::{Action, InputAction, Knowledge, OutputAction, Source, Step, Trace};
     use crate::{
         codec, define_signature, dummy_codec, dummy_extract_knowledge,
         dummy_extract_knowledge_codec, term,
@@ -252,6 +260,10 @@ pub mod test_signature {
         Ok(Vec::new())
     }
 
+    pub fn fn_identity(bytes: &Vec<u8>) -> Result<Vec<u8>, FnError> {
+        Ok(bytes.clone())
+    }
+
     pub fn fn_named_group_secp384r1() -> Result<Group, FnError> {
         Ok(Group)
     }
@@ -337,6 +349,9 @@ pub mod test_signature {
 
         Trace {
             prior_traces: vec![],
+            expected_outcome: None,
+            expected_flow: None,
+            seed: None,
             descriptors: vec![AgentDescriptor::from_name(server)],
             steps: vec![
                 Step {
@@ -373,7 +388,7 @@ pub mod test_signature {
         fn_protocol_version12
         fn_new_session_id
         fn_new_random
-        fn_client_extensions_append
+        fn_client_extensions_append [list]
         fn_client_extensions_new
         fn_support_group_extension
         fn_signature_algorithm_extension
@@ -500,7 +515,7 @@ pub mod test_signature {
         type C = TestClaim;
 
         fn check_violation(_claims: &[TestClaim]) -> Option<&'static str> {
-            panic!("Not implemented for test stub");
+            None
         }
     }
 
@@ -544,12 +559,13 @@ pub mod test_signature {
         }
     }
 
+    /// Flight of the test protocol, which consists of the bytes which a [`TestPut`] wrote
     #[derive(Debug, Clone, Default)]
-    pub struct TestOpaqueMessageFlight;
+    pub struct TestOpaqueMessageFlight(pub Vec<u8>);
 
     impl OpaqueProtocolMessageFlight<TestProtocolTypes, TestOpaqueMessage> for TestOpaqueMessageFlight {
         fn new() -> Self {
-            Self {}
+            Self::default()
         }
 
         fn push(&mut self, _msg: TestOpaqueMessage) {
@@ -565,23 +581,23 @@ pub mod test_signature {
 
     impl From<TestOpaqueMessage> for TestOpaqueMessageFlight {
         fn from(_value: TestOpaqueMessage) -> Self {
-            Self {}
+            Self::default()
         }
     }
 
     impl codec::Codec for TestOpaqueMessageFlight {
-        fn encode(&self, _bytes: &mut Vec<u8>) {
-            panic!("Not implemented for test stub");
+        fn encode(&self, bytes: &mut Vec<u8>) {
+            bytes.extend_from_slice(&self.0);
         }
 
-        fn read(_: &mut Reader) -> Option<Self> {
-            panic!("Not implemented for test stub");
+        fn read(reader: &mut Reader) -> Option<Self> {
+            Some(Self(reader.rest().to_vec()))
         }
     }
 
     impl From<TestMessageFlight> for TestOpaqueMessageFlight {
         fn from(_value: TestMessageFlight) -> Self {
-            Self {}
+            Self::default()
         }
     }
 
@@ -598,11 +614,29 @@ pub mod test_signature {
     }
 
     #[derive(Default, Clone, Debug, Hash, Serialize, Deserialize)]
-    pub struct TestPUTConfig;
+    pub struct TestPUTConfig {
+        /// Whether agents of this configuration are reusable, agents are not by default
+        pub reusable: bool,
+        /// Capabilities which a PUT needs to spawn the agent, e.g. its role
+        pub capabilities: Vec<String>,
+    }
 
     impl ProtocolDescriptorConfig for TestPUTConfig {
-        fn is_reusable_with(&self, _other: &Self) -> bool {
-            false
+        fn is_reusable_with(&self, other: &Self) -> bool {
+            self.reusable && other.reusable
+        }
+
+        fn required_capabilities(&self) -> Vec<&str> {
+            self.capabilities.iter().map(String::as_str).collect()
+        }
+    }
+
+    impl TestPUTConfig {
+        fn with_role(role: &str) -> Self {
+            Self {
+                reusable: false,
+                capabilities: vec![role.to_owned()],
+            }
         }
     }
 
@@ -617,6 +651,7 @@ pub mod test_signature {
 
     impl ProtocolBehavior for TestProtocolBehavior {
         type Claim = TestClaim;
+        type Extension = Infallible;
         type OpaqueProtocolMessage = TestOpaqueMessage;
         type OpaqueProtocolMessageFlight = TestOpaqueMessageFlight;
         type ProtocolMessage = TestMessage;
@@ -628,6 +663,27 @@ pub mod test_signature {
             panic!("Not implemented for test stub");
         }
 
+        /// The client and the server both produce their output once
+        fn default_trace(_put: PutDescriptor) -> Option<Trace<Self::ProtocolTypes>> {
+            let client = AgentName::first();
+            let server = client.next();
+
+            Some(Trace {
+                prior_traces: vec![],
+                expected_outcome: None,
+                expected_flow: None,
+                seed: None,
+                descriptors: vec![
+                    AgentDescriptor::from_config(client, TestPUTConfig::with_role("client")),
+                    AgentDescriptor::from_config(server, TestPUTConfig::with_role("server")),
+                ],
+                steps: vec![
+                    OutputAction::new_step(client),
+                    OutputAction::new_step(server),
+                ],
+            })
+        }
+
         fn try_read_bytes(
             _bitstring: &[u8],
             _ty: TypeId,
@@ -636,16 +692,177 @@ pub mod test_signature {
         }
     }
 
-    pub struct TestFactory;
+    /// Behavior of the PUTs which a [`TestFactory`] creates
+    #[derive(Clone, Debug, Default)]
+    pub struct TestPutBehavior {
+        /// Flights which the PUT outputs in this order
+        pub flights: Vec<Vec<u8>>,
+        /// Number of bytes which the PUT writes per call to [`Put::progress`], all if `None`
+        pub chunk: Option<usize>,
+        /// Whether [`Put::progress_one_flight`] stops after each flight
+        pub flight_boundaries: bool,
+        /// Whether [`Put::progress`] fails after writing, e.g. because the connection was aborted
+        pub fail: bool,
+    }
+
+    /// A PUT which writes the flights of its [`TestPutBehavior`] and ignores its input
+    pub struct TestPut {
+        descriptor: AgentDescriptor<TestPUTConfig>,
+        behavior: TestPutBehavior,
+        /// Bytes of the first flight which were written already
+        written: usize,
+        /// Complete flights which were not yet taken
+        outbound: Vec<Vec<u8>>,
+    }
+
+    impl TestPut {
+        pub fn new(descriptor: AgentDescriptor<TestPUTConfig>, behavior: TestPutBehavior) -> Self {
+            Self {
+                descriptor,
+                behavior,
+                written: 0,
+                outbound: vec![],
+            }
+        }
+
+        /// Writes up to `budget` bytes, stopping after the first complete flight if `one_flight`
+        fn write(&mut self, mut budget: usize, one_flight: bool) {
+            while let Some(flight) = self.behavior.flights.first() {
+                let written = (flight.len() - self.written).min(budget);
+                self.written += written;
+                budget -= written;
+                if self.written < flight.len() {
+                    return;
+                }
+
+                self.outbound.push(self.behavior.flights.remove(0));
+                self.written = 0;
+                if one_flight {
+                    return;
+                }
+            }
+        }
+    }
+
+    impl Stream<TestProtocolBehavior> for TestPut {
+        fn add_to_inbound(&mut self, _message: &ConcreteMessage) {}
+
+        fn take_message_from_outbound(&mut self) -> Result<Option<TestOpaqueMessageFlight>, Error> {
+            if self.outbound.is_empty() {
+                return Ok(None);
+            }
+
+            Ok(Some(TestOpaqueMessageFlight(self.outbound.remove(0))))
+        }
+    }
+
+    impl Put<TestProtocolBehavior> for TestPut {
+        fn progress(&mut self) -> Result<(), Error> {
+            self.write(self.behavior.chunk.unwrap_or(usize::MAX), false);
+            if self.behavior.fail {
+                // Only the written part of the current flight is left of the aborted connection
+                self.behavior
+                    .flights
+                    .truncate(usize::from(self.written > 0));
+                if let Some(flight) = self.behavior.flights.first_mut() {
+                    flight.truncate(self.written);
+                }
+                return Err(Error::Put("connection aborted mid-flight".to_owned()));
+            }
+            Ok(())
+        }
+
+        fn progress_one_flight(&mut self) -> Result<(), Error> {
+            if !self.behavior.flight_boundaries {
+                return self.progress();
+            }
+
+            self.write(self.behavior.chunk.unwrap_or(usize::MAX), true);
+            Ok(())
+        }
+
+        fn pending_output_len(&self) -> usize {
+            let unwritten: usize = self.behavior.flights.iter().map(Vec::len).sum();
+            let outbound: usize = self.outbound.iter().map(Vec::len).sum();
+            unwritten + outbound
+        }
+
+        fn drain_output(&mut self) -> Vec<u8> {
+            let mut drained = self.outbound.concat();
+            self.outbound.clear();
+            if self.written > 0 {
+                // The partially written flight is dropped
+                let flight = self.behavior.flights.remove(0);
+                drained.extend_from_slice(&flight[..self.written]);
+                self.written = 0;
+            }
+            drained
+        }
+
+        fn reset(&mut self, _new_name: AgentName) -> Result<(), Error> {
+            Ok(())
+        }
+
+        fn descriptor(&self) -> &AgentDescriptor<TestPUTConfig> {
+            &self.descriptor
+        }
+
+        fn describe_state(&self) -> String {
+            String::new()
+        }
+
+        fn is_state_successful(&self) -> bool {
+            !self.behavior.fail
+        }
+
+        fn shutdown(&mut self) -> String {
+            String::new()
+        }
+
+        fn version() -> String {
+            String::new()
+        }
+    }
+
+    /// A binding which creates [`TestPut`]s, by default ones which have nothing to output
+    #[derive(Clone, Default)]
+    pub struct TestFactory {
+        /// Behavior of the created PUTs
+        pub behavior: TestPutBehavior,
+        /// Capabilities which the binding supports, e.g. the roles of its agents
+        pub capabilities: Vec<&'static str>,
+        /// Whether creating an agent fails
+        pub broken: bool,
+        /// Number of agents after which the created PUTs fail, see [`TestPutBehavior::fail`]
+        pub healthy: Option<usize>,
+        /// Whether the binding reports no versions
+        pub unversioned: bool,
+        /// Seeds of [`Factory::rng_reseed_with`]
+        pub seeds: Arc<Mutex<Vec<u64>>>,
+        created: Arc<AtomicUsize>,
+    }
 
     impl Factory<TestProtocolBehavior> for TestFactory {
         fn create(
             &self,
-            _agent_descriptor: &AgentDescriptor<TestPUTConfig>,
+            agent_descriptor: &AgentDescriptor<TestPUTConfig>,
             _claims: &GlobalClaimList<<TestProtocolBehavior as ProtocolBehavior>::Claim>,
             _options: &PutOptions,
         ) -> Result<Box<dyn Put<TestProtocolBehavior>>, Error> {
-            panic!("Not implemented for test stub");
+            if self.broken {
+                return Err(Error::Put(format!(
+                    "failed to spawn {}",
+                    agent_descriptor.name
+                )));
+            }
+
+            let created = self.created.fetch_add(1, Ordering::SeqCst);
+            let unhealthy = self.healthy.is_some_and(|healthy| created >= healthy);
+            let behavior = TestPutBehavior {
+                fail: self.behavior.fail || unhealthy,
+                ..self.behavior.clone()
+            };
+            Ok(Box::new(TestPut::new(agent_descriptor.clone(), behavior)))
         }
 
         fn name(&self) -> String {
@@ -653,20 +870,45 @@ pub mod test_signature {
         }
 
         fn versions(&self) -> Vec<(String, String)> {
+            if self.unversioned {
+                return vec![];
+            }
+
             vec![(
                 "harness".to_string(),
                 format!("{} {}", self.name(), puffin::full_version()),
             )]
         }
 
-        fn supports(&self, _capability: &str) -> bool {
-            false
+        fn supports(&self, capability: &str) -> bool {
+            self.capabilities.contains(&capability)
         }
 
         fn clone_factory(&self) -> Box<dyn Factory<TestProtocolBehavior>> {
-            Box::new(TestFactory {})
+            Box::new(self.clone())
+        }
+
+        fn rng_reseed_with(&self, seed: u64) {
+            self.seeds.lock().unwrap().push(seed);
         }
     }
+
+    pub fn dummy_factory() -> Box<dyn Factory<TestProtocolBehavior>> {
+        Box::<TestFactory>::default()
+    }
+
+    /// Registry with the [`TestFactory`] as only and default PUT
+    pub fn dummy_registry() -> PutRegistry<TestProtocolBehavior> {
+        PutRegistry::new([("teststub", dummy_factory())], "teststub")
+    }
+
+    /// Registry with `factory` as only and default PUT
+    pub fn test_registry(factory: TestFactory) -> PutRegistry<TestProtocolBehavior> {
+        PutRegistry::new(
+            [("teststub", Box::new(factory) as Box<dyn Factory<_>>)],
+            "teststub",
+        )
+    }
 }
 
 #[cfg(test)]
@@ -674,11 +916,11 @@ mod tests {
     use super::test_signature::*;
     use crate::agent::AgentName;
     use crate::algebra::atoms::Variable;
-    use crate::algebra::dynamic_function::TypeShape;
+    use crate::algebra::dynamic_function::{DescribableFunction, TypeShape};
     use crate::algebra::signature::Signature;
     use crate::algebra::term::TermType;
     use crate::algebra::{AnyMatcher, DYTerm, Term};
-    use crate::put_registry::{Factory, PutRegistry};
+    use crate::put_registry::PutRegistry;
     use crate::term;
     use crate::trace::{Source, Spawner, TraceContext};
 
@@ -755,10 +997,6 @@ mod tests {
 
         log::debug!("{}", generated_term);
 
-        fn dummy_factory() -> Box<dyn Factory<TestProtocolBehavior>> {
-            Box::new(TestFactory)
-        }
-
         let registry =
             PutRegistry::<TestProtocolBehavior>::new([("teststub", dummy_factory())], "teststub");
 
@@ -869,4 +1107,22 @@ mod tests {
         let _graph = constructed_term.dot_subgraph(true, 0, "test");
         //println!("{}", graph);
     }
+
+    #[test_log::test]
+    fn test_function_serde_by_name() {
+        let term: TestTerm = term! {
+            fn_hmac256(fn_hmac256_new_key, fn_new_random)
+        };
+
+        let json = serde_json::to_string(&term).unwrap();
+        assert!(json.contains(fn_hmac256_new_key.name()));
+
+        let reloaded: TestTerm = serde_json::from_str(&json).unwrap();
+        assert_eq!(reloaded.to_string(), term.to_string());
+
+        // Functions which are not in the signature are not substituted by another function
+        let unknown = json.replace(fn_hmac256_new_key.name(), "fn_not_in_signature");
+        let err = serde_json::from_str::<TestTerm>(&unknown).unwrap_err();
+        assert!(err.to_string().contains("fn_not_in_signature"));
+    }
 }
@@ -0,0 +1,519 @@
+//! Parsing of [`Term`]s from the textual form which the [`term!`](crate::term) macro accepts.
+//!
+//! This allows to write terms at runtime, e.g. in configuration files or on the command line,
+//! without compiling them into the fuzzer. Function symbols are resolved against a [`Signature`],
+//! either by their full path or by their name without the module prefix like `fn_client_hello`.
+//! Variables are written as `(agent, counter)`, precomputations as `(!"label", counter)` and the
+//! output of a step as `(#step, counter)`. Their type is the one of the argument they are passed
+//! to, unless it is given explicitly as in
+//! `(server, 0)/Message`. Matchers and the `@` syntax of the macro refer to Rust values and are
+//! not supported.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::agent::AgentName;
+use crate::algebra::atoms::Function;
+use crate::algebra::dynamic_function::TypeShape;
+use crate::algebra::signature::{FunctionDefinition, Signature};
+use crate::algebra::{remove_prefix, DYTerm, Term};
+use crate::protocol::ProtocolTypes;
+use crate::trace::Source;
+
+/// Errors when parsing the textual form of a [`Term`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// The source is not a well-formed term, `position` is the byte offset of the error
+    Syntax { position: usize, message: String },
+    /// No function of the signature has this name
+    UnknownFunction(String),
+    /// Several functions of the signature have this name and need to be referred to by their full
+    /// path
+    AmbiguousFunction {
+        name: String,
+        candidates: Vec<String>,
+    },
+    /// No type of the signature has this name
+    UnknownType(String),
+    ArgumentCount {
+        function: String,
+        expected: usize,
+        found: usize,
+    },
+    /// A function returns a different type than the argument it is passed to
+    TypeMismatch {
+        function: String,
+        expected: String,
+        found: String,
+    },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Syntax { position, message } => {
+                write!(f, "syntax error at position {position}: {message}")
+            }
+            Self::UnknownFunction(name) => write!(f, "unknown function {name}"),
+            Self::AmbiguousFunction { name, candidates } => write!(
+                f,
+                "ambiguous function {name}, candidates are {}",
+                candidates.join(", ")
+            ),
+            Self::UnknownType(name) => write!(f, "unknown type {name}"),
+            Self::ArgumentCount {
+                function,
+                expected,
+                found,
+            } => write!(
+                f,
+                "function {function} expects {expected} arguments, found {found}"
+            ),
+            Self::TypeMismatch {
+                function,
+                expected,
+                found,
+            } => write!(
+                f,
+                "function {function} returns {found}, but {expected} is expected"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parses `src` into a [`Term`] whose function symbols are resolved against `signature`.
+pub fn parse_term<PT: ProtocolTypes>(
+    src: &str,
+    signature: &Signature<PT>,
+) -> Result<Term<PT>, ParseError> {
+    let mut parser = Parser {
+        src,
+        position: 0,
+        signature,
+    };
+
+    let term = parser.term(None)?;
+    parser.skip_whitespace();
+    if parser.position < src.len() {
+        return Err(parser.syntax_error("expected the end of the term"));
+    }
+
+    Ok(term)
+}
+
+struct Parser<'a, PT: ProtocolTypes> {
+    src: &'a str,
+    position: usize,
+    signature: &'a Signature<PT>,
+}
+
+impl<'a, PT: ProtocolTypes> Parser<'a, PT> {
+    /// Parses a term which is passed to an argument of type `expected`
+    fn term(&mut self, expected: Option<&TypeShape<PT>>) -> Result<Term<PT>, ParseError> {
+        self.skip_whitespace();
+
+        if self.eat('(') {
+            if self.at_query() {
+                return self.variable(expected);
+            }
+
+            let term = self.term(expected)?;
+            self.skip_whitespace();
+            self.expect(')')?;
+            return Ok(term);
+        }
+
+        match self.peek() {
+            Some('@') => Err(self.syntax_error("terms from Rust variables are not supported")),
+            Some(c) if c.is_alphabetic() || c == '_' => self.application(expected),
+            _ => Err(self.syntax_error("expected a function or a variable")),
+        }
+    }
+
+    /// Parses a function application, whose arguments are optional for constants
+    fn application(&mut self, expected: Option<&TypeShape<PT>>) -> Result<Term<PT>, ParseError> {
+        let name = self.path();
+        let (shape, dynamic_fn) = self.function(&name)?;
+        let func = Function::new(shape.clone(), dynamic_fn.clone());
+
+        let mut arguments = vec![];
+        self.skip_whitespace();
+        if self.eat('(') {
+            self.skip_whitespace();
+            if !self.eat(')') {
+                loop {
+                    let argument = shape.argument_types.get(arguments.len());
+                    if argument.is_none() {
+                        return Err(ParseError::ArgumentCount {
+                            function: name,
+                            expected: shape.argument_types.len(),
+                            found: arguments.len() + 1,
+                        });
+                    }
+                    arguments.push(self.term(argument)?);
+
+                    self.skip_whitespace();
+                    if self.eat(')') {
+                        break;
+                    }
+                    self.expect(',')?;
+                }
+            }
+        }
+
+        if arguments.len() != shape.argument_types.len() {
+            return Err(ParseError::ArgumentCount {
+                function: name,
+                expected: shape.argument_types.len(),
+                found: arguments.len(),
+            });
+        }
+
+        if let Some(expected) = expected {
+            if *expected != shape.return_type {
+                return Err(ParseError::TypeMismatch {
+                    function: name,
+                    expected: remove_prefix(expected.name),
+                    found: remove_prefix(shape.return_type.name),
+                });
+            }
+        }
+
+        Ok(Term::from(DYTerm::Application(func, arguments)))
+    }
+
+    /// Parses the query of a variable and its optional type after the opening parenthesis
+    fn variable(&mut self, expected: Option<&TypeShape<PT>>) -> Result<Term<PT>, ParseError> {
+        self.skip_whitespace();
+
+        let source = if self.eat('!') {
+            self.skip_whitespace();
+            Source::Label(Some(self.string()?))
+        } else if self.eat('#') {
+            self.skip_whitespace();
+            Source::Step(self.number("a step index")?)
+        } else {
+            let start = self.position;
+            let name = self.path();
+            let agent = name.parse::<AgentName>().map_err(|_| ParseError::Syntax {
+                position: start,
+                message: format!("unknown agent {name}"),
+            })?;
+            Source::Agent(agent)
+        };
+
+        self.skip_whitespace();
+        self.expect(',')?;
+        self.skip_whitespace();
+        let counter = self.number("a counter")?;
+        self.skip_whitespace();
+        self.expect(')')?;
+
+        self.skip_whitespace();
+        if self.peek() == Some('[') {
+            return Err(self.syntax_error("matchers are not supported"));
+        }
+
+        // As with the macro, an explicit type overrides the type of the argument
+        let typ = if self.eat('/') {
+            self.typ()?
+        } else {
+            expected.cloned().ok_or_else(|| {
+                self.syntax_error("the type of the variable is unknown, add it like `/Type`")
+            })?
+        };
+
+        let var = Signature::new_var(typ, Some(source), None, counter);
+        Ok(Term::from(DYTerm::Variable(var)))
+    }
+
+    /// Resolves `name` by its full path or by its name without the module prefix
+    fn function(&self, name: &str) -> Result<&'a FunctionDefinition<PT>, ParseError> {
+        if let Some(definition) = self.signature.functions_by_name.get(name) {
+            return Ok(definition);
+        }
+
+        let mut candidates: Vec<&FunctionDefinition<PT>> = self
+            .signature
+            .functions
+            .iter()
+            .filter(|(shape, _dynamic_fn)| remove_prefix(shape.name) == name)
+            .collect();
+
+        match candidates.len() {
+            0 => Err(ParseError::UnknownFunction(name.to_owned())),
+            1 => Ok(candidates.remove(0)),
+            _ => {
+                let mut candidates: Vec<String> = candidates
+                    .iter()
+                    .map(|(shape, _dynamic_fn)| shape.name.to_owned())
+                    .collect();
+                candidates.sort();
+
+                Err(ParseError::AmbiguousFunction {
+                    name: name.to_owned(),
+                    candidates,
+                })
+            }
+        }
+    }
+
+    /// Parses a type name which may contain generic arguments
+    fn typ(&mut self) -> Result<TypeShape<PT>, ParseError> {
+        self.skip_whitespace();
+
+        let start = self.position;
+        let mut depth = 0usize;
+        while let Some(c) = self.peek() {
+            match c {
+                '<' => depth += 1,
+                '>' if depth > 0 => depth -= 1,
+                ')' | ',' if depth == 0 => break,
+                _ => {}
+            }
+            self.position += c.len_utf8();
+        }
+
+        let name: String = self.src[start..self.position]
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect();
+        if name.is_empty() {
+            return Err(self.syntax_error("expected a type"));
+        }
+
+        self.signature
+            .types_by_name
+            .iter()
+            .find(|(full_name, _typ)| **full_name == name || remove_prefix(full_name) == name)
+            .map(|(_full_name, typ)| typ.clone())
+            .ok_or(ParseError::UnknownType(name))
+    }
+
+    /// Whether a query like `server, 0`, `!"label", 0` or `#1, 0` follows
+    fn at_query(&mut self) -> bool {
+        let start = self.position;
+        self.skip_whitespace();
+
+        let is_query = if matches!(self.peek(), Some('!' | '#')) {
+            true
+        } else {
+            let is_name = !self.path().is_empty();
+            self.skip_whitespace();
+            is_name && self.peek() == Some(',')
+        };
+
+        self.position = start;
+        is_query
+    }
+
+    /// Parses an identifier, which may be a path like `module::function`
+    fn path(&mut self) -> String {
+        let start = self.position;
+        while let Some(c) = self.peek() {
+            if c.is_alphanumeric() || c == '_' || c == ':' {
+                self.position += c.len_utf8();
+            } else {
+                break;
+            }
+        }
+        self.src[start..self.position].to_owned()
+    }
+
+    fn number<N: FromStr>(&mut self, expected: &str) -> Result<N, ParseError> {
+        let start = self.position;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.position += 1;
+        }
+
+        self.src[start..self.position]
+            .parse()
+            .map_err(|_| ParseError::Syntax {
+                position: start,
+                message: format!("expected {expected}"),
+            })
+    }
+
+    /// Parses a string literal without escape sequences
+    fn string(&mut self) -> Result<String, ParseError> {
+        self.expect('"')?;
+        let start = self.position;
+        let Some(len) = self.src[start..].find('"') else {
+            return Err(self.syntax_error("unterminated string"));
+        };
+        self.position += len + 1;
+
+        Ok(self.src[start..start + len].to_owned())
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek().filter(|c| c.is_whitespace()) {
+            self.position += c.len_utf8();
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.src[self.position..].chars().next()
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.position += expected.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), ParseError> {
+        if self.eat(expected) {
+            Ok(())
+        } else {
+            Err(self.syntax_error(&format!("expected `{expected}`")))
+        }
+    }
+
+    fn syntax_error(&self, message: &str) -> ParseError {
+        ParseError::Syntax {
+            position: self.position,
+            message: message.to_owned(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algebra::test_signature::{setup_simple_trace, *};
+    use crate::term;
+    use crate::trace::Action;
+
+    #[test_log::test]
+    fn test_parse_client_hello() {
+        let src = "
+            fn_client_hello(
+                fn_protocol_version12,
+                fn_new_random,
+                fn_new_session_id,
+                (fn_append_cipher_suite(
+                    (fn_new_cipher_suites()),
+                    fn_cipher_suite12
+                )),
+                fn_compressions,
+                (fn_client_extensions_append(
+                    (fn_client_extensions_append(
+                        (fn_client_extensions_append(
+                            (fn_client_extensions_append(
+                                (fn_client_extensions_append(
+                                    (fn_client_extensions_append(
+                                        fn_client_extensions_new,
+                                        (fn_support_group_extension(fn_named_group_secp384r1))
+                                    )),
+                                    fn_signature_algorithm_extension
+                                )),
+                                fn_ec_point_formats_extension
+                            )),
+                            fn_signed_certificate_timestamp_extension
+                        )),
+                        fn_renegotiation_info_extension(fn_empty_bytes_vec)
+                    )),
+                    fn_signature_algorithm_cert_extension
+                ))
+            )";
+
+        let Action::Input(input) = &setup_simple_trace().steps[0].action else {
+            panic!("the first step is not an input");
+        };
+
+        assert_eq!(
+            parse_term(src, &TEST_SIGNATURE).unwrap().to_string(),
+            input.recipe.to_string()
+        );
+    }
+
+    #[test_log::test]
+    fn test_parse_variables() {
+        let parsed = parse_term(
+            "puffin::algebra::test_signature::fn_encrypt12((server, 1), (!\"psk\", 0)/u32)",
+            &TEST_SIGNATURE,
+        )
+        .unwrap();
+
+        let server = AgentName::first().next();
+        let expected: TestTerm = term! {
+            fn_encrypt12(((server, 1)), ((!"psk", 0)/u32))
+        };
+        assert_eq!(parsed.to_string(), expected.to_string());
+
+        let parsed = parse_term("fn_encrypt12((#0, 0), fn_seq_0)", &TEST_SIGNATURE).unwrap();
+        let DYTerm::Application(_, args) = &parsed.term else {
+            panic!("not an application");
+        };
+        let DYTerm::Variable(variable) = &args[0].term else {
+            panic!("not a variable");
+        };
+        assert_eq!(variable.query.source, Some(Source::Step(0)));
+
+        assert!(matches!(
+            parse_term("(client, 0)", &TEST_SIGNATURE),
+            Err(ParseError::Syntax { .. })
+        ));
+    }
+
+    #[test_log::test]
+    fn test_parse_unknown_function() {
+        assert_eq!(
+            parse_term("fn_encrypt12(fn_finished, fn_unknown)", &TEST_SIGNATURE).unwrap_err(),
+            ParseError::UnknownFunction("fn_unknown".to_owned())
+        );
+    }
+
+    #[test_log::test]
+    fn test_parse_ambiguous_function() {
+        let mut definitions: Vec<_> = TEST_SIGNATURE
+            .functions
+            .iter()
+            .map(|fd| (fd.clone(), TEST_SIGNATURE.attrs_by_name[fd.0.name]))
+            .collect();
+        let mut duplicate = definitions
+            .iter()
+            .find(|((shape, _dynamic_fn), _attrs)| shape.name.ends_with("::fn_seq_0"))
+            .unwrap()
+            .clone();
+        duplicate.0 .0.name = "other::module::fn_duplicate";
+        definitions.push(duplicate.clone());
+        duplicate.0 .0.name = "another::module::fn_duplicate";
+        definitions.push(duplicate);
+        let signature = Signature::new(definitions);
+
+        assert!(parse_term("fn_seq_0", &signature).is_ok());
+        assert_eq!(
+            parse_term("fn_duplicate", &signature).unwrap_err(),
+            ParseError::AmbiguousFunction {
+                name: "fn_duplicate".to_owned(),
+                candidates: vec![
+                    "another::module::fn_duplicate".to_owned(),
+                    "other::module::fn_duplicate".to_owned()
+                ],
+            }
+        );
+    }
+
+    #[test_log::test]
+    fn test_parse_argument_errors() {
+        assert!(matches!(
+            parse_term("fn_encrypt12(fn_finished)", &TEST_SIGNATURE),
+            Err(ParseError::ArgumentCount {
+                expected: 2,
+                found: 1,
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse_term("fn_encrypt12(fn_seq_0, fn_seq_0)", &TEST_SIGNATURE),
+            Err(ParseError::TypeMismatch { .. })
+        ));
+    }
+}
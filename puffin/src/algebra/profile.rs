@@ -0,0 +1,213 @@
+//! Profiling the evaluation of [`Term`]s.
+//!
+//! In large recipes, a few function symbols can dominate the cost of an evaluation, e.g. those
+//! which derive keys or encrypt. [`Term::evaluate_profiled`] records the time spent in the
+//! implementation of each function symbol, such that the expensive ones can be optimized. The time
+//! of a call excludes the evaluation of its arguments.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::algebra::{ConcreteMessage, Term, TermType};
+use crate::error::Error;
+use crate::protocol::{ProtocolBehavior, ProtocolTypes};
+use crate::trace::TraceContext;
+
+thread_local! {
+    /// Profile of the evaluation in progress, `None` if no evaluation is profiled
+    static EVAL_PROFILE: RefCell<Option<EvalProfile>> = const { RefCell::new(None) };
+}
+
+/// Calls of a function symbol during a profiled evaluation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FunctionProfile {
+    pub calls: usize,
+    /// Number of calls which returned an error
+    pub errors: usize,
+    /// Cumulative time spent in the implementation of the function symbol
+    pub duration: Duration,
+}
+
+/// Cost of the function symbols during an evaluation, see [`Term::evaluate_profiled`]
+#[derive(Debug, Clone, Default)]
+pub struct EvalProfile {
+    functions: HashMap<&'static str, FunctionProfile>,
+}
+
+impl EvalProfile {
+    /// Profile of the function symbol `name`, `None` if it was not called
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&FunctionProfile> {
+        self.functions.get(name)
+    }
+
+    /// Profiles of all called function symbols, the most expensive first
+    #[must_use]
+    pub fn by_duration(&self) -> Vec<(&'static str, FunctionProfile)> {
+        let mut functions: Vec<_> = self
+            .functions
+            .iter()
+            .map(|(name, profile)| (*name, *profile))
+            .collect();
+        functions.sort_by(|(name_a, a), (name_b, b)| {
+            b.duration.cmp(&a.duration).then(name_a.cmp(name_b))
+        });
+        functions
+    }
+
+    /// The function symbol which took the most time in total
+    #[must_use]
+    pub fn most_expensive(&self) -> Option<(&'static str, FunctionProfile)> {
+        self.by_duration().into_iter().next()
+    }
+
+    /// Time spent in the implementations of all function symbols
+    #[must_use]
+    pub fn total_duration(&self) -> Duration {
+        self.functions
+            .values()
+            .map(|profile| profile.duration)
+            .sum()
+    }
+}
+
+/// Whether an evaluation is profiled on this thread, such that calls are only timed when needed
+pub(crate) fn is_profiling() -> bool {
+    EVAL_PROFILE.with(|profile| profile.borrow().is_some())
+}
+
+/// Adds a call of the function symbol `name` to the profile of the evaluation in progress
+pub(crate) fn record_call(name: &'static str, duration: Duration, failed: bool) {
+    EVAL_PROFILE.with(|profile| {
+        if let Some(profile) = profile.borrow_mut().as_mut() {
+            let function = profile.functions.entry(name).or_default();
+            function.calls += 1;
+            function.errors += usize::from(failed);
+            function.duration += duration;
+        }
+    });
+}
+
+impl<PT: ProtocolTypes> Term<PT> {
+    /// Evaluates the term like [`TermType::evaluate`] and records the time spent in each function
+    /// symbol.
+    ///
+    /// If the evaluation fails, the profile holds the calls up to and including the failing one.
+    /// Subterms whose evaluation was memoized in `ctx` do not call their function symbols again.
+    pub fn evaluate_profiled<PB>(
+        &self,
+        ctx: &TraceContext<PB>,
+    ) -> (Result<ConcreteMessage, Error>, EvalProfile)
+    where
+        PB: ProtocolBehavior<ProtocolTypes = PT>,
+    {
+        let outer = EVAL_PROFILE.with(|profile| profile.replace(Some(EvalProfile::default())));
+        let result = self.evaluate(ctx);
+        let profile = EVAL_PROFILE.with(|profile| profile.replace(outer));
+
+        (result, profile.unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::ptr_arg)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use crate::algebra::atoms::Function;
+    use crate::algebra::dynamic_function::DescribableFunction;
+    use crate::algebra::error::FnError;
+    use crate::algebra::signature::Signature;
+    use crate::algebra::test_signature::*;
+    use crate::algebra::DYTerm;
+    use crate::term;
+    use crate::trace::Spawner;
+
+    const SLOW: Duration = Duration::from_millis(20);
+
+    pub fn fn_slow(bytes: &Vec<u8>) -> Result<Vec<u8>, FnError> {
+        thread::sleep(SLOW);
+        Ok(bytes.clone())
+    }
+
+    pub fn fn_failing(_bytes: &Vec<u8>) -> Result<Vec<u8>, FnError> {
+        Err(FnError::Malformed("failing".to_string()))
+    }
+
+    fn apply(function: Function<TestProtocolTypes>, arg: TestTerm) -> TestTerm {
+        Term::from(DYTerm::Application(function, vec![arg]))
+    }
+
+    #[test_log::test]
+    fn test_evaluate_profiled() {
+        let mut ctx = TraceContext::new(Spawner::new(dummy_registry()));
+        let slow: Function<TestProtocolTypes> = Signature::new_function(&fn_slow);
+        let failing: Function<TestProtocolTypes> = Signature::new_function(&fn_failing);
+        let empty: TestTerm = term! { fn_empty_bytes_vec };
+
+        let term = apply(slow.clone(), apply(slow.clone(), empty.clone()));
+        let (result, profile) = term.evaluate_profiled(&ctx);
+        assert!(result.is_ok());
+
+        let (name, slow_profile) = profile.most_expensive().unwrap();
+        assert_eq!(name, slow.name());
+        assert_eq!(slow_profile.calls, 2);
+        assert_eq!(slow_profile.errors, 0);
+        assert!(slow_profile.duration >= 2 * SLOW);
+        assert_eq!(profile.get(fn_empty_bytes_vec.name()).unwrap().calls, 1);
+        assert!(profile.total_duration() >= slow_profile.duration);
+
+        // The calls before the failing one are kept
+        ctx.clear_evaluation_memo();
+        let term = apply(failing.clone(), apply(slow.clone(), empty));
+        let (result, profile) = term.evaluate_profiled(&ctx);
+        assert!(result.is_err());
+        assert_eq!(profile.get(slow.name()).unwrap().calls, 1);
+        let failing_profile = profile.get(failing.name()).unwrap();
+        assert_eq!(failing_profile.calls, 1);
+        assert_eq!(failing_profile.errors, 1);
+
+        // The profile ends with the evaluation
+        assert!(!is_profiling());
+    }
+
+    pub fn fn_concat3(a: &Vec<u8>, b: &Vec<u8>, c: &Vec<u8>) -> Result<Vec<u8>, FnError> {
+        Ok([a.as_slice(), b.as_slice(), c.as_slice()].concat())
+    }
+
+    #[test_log::test]
+    fn test_evaluate_memoizes_repeated_subterms() {
+        let mut ctx = TraceContext::new(Spawner::new(dummy_registry()));
+        let slow: Function<TestProtocolTypes> = Signature::new_function(&fn_slow);
+        let concat3: Function<TestProtocolTypes> = Signature::new_function(&fn_concat3);
+        let empty: TestTerm = term! { fn_empty_bytes_vec };
+
+        let repeated = apply(slow.clone(), apply(slow.clone(), empty));
+        let term = Term::from(DYTerm::Application(
+            concat3,
+            vec![repeated.clone(), repeated.clone(), repeated],
+        ));
+        let (result, profile) = term.evaluate_profiled(&ctx);
+        assert!(result.is_ok());
+        assert_eq!(profile.get(slow.name()).unwrap().calls, 2);
+
+        // The memo lives as long as the execution
+        let (_, profile) = term.evaluate_profiled(&ctx);
+        assert!(profile.get(slow.name()).is_none());
+        ctx.clear_evaluation_memo();
+        let (_, profile) = term.evaluate_profiled(&ctx);
+        assert_eq!(profile.get(slow.name()).unwrap().calls, 2);
+
+        // A mutated clone keeps the ids of the original, but only its unchanged subterms are
+        // memoized
+        let mut mutated = term.clone();
+        let DYTerm::Application(_, args) = &mut mutated.term else {
+            unreachable!()
+        };
+        args[0] = apply(slow.clone(), args[1].clone());
+        let (_, profile) = mutated.evaluate_profiled(&ctx);
+        assert_eq!(profile.get(slow.name()).unwrap().calls, 1);
+    }
+}
@@ -1,4 +1,5 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 
 use itertools::Itertools;
 use once_cell::sync::Lazy;
@@ -15,11 +16,44 @@ use crate::trace::{Query, Source};
 
 pub type FunctionDefinition<PT> = (DynamicFunctionShape<PT>, Box<dyn DynamicFunction<PT>>);
 
+/// A malformed function definition found by [`Signature::validate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignatureError {
+    /// A function has an empty name, such that terms can not refer to it.
+    EmptyName,
+    /// Several functions have the same name. Only one of them can be resolved when terms are
+    /// deserialized.
+    DuplicateName { function: &'static str },
+    /// The type `typ` used by `function` does not resolve to itself by its name, because another
+    /// type has the same name.
+    UnresolvableType {
+        function: &'static str,
+        typ: &'static str,
+    },
+}
+
+impl fmt::Display for SignatureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignatureError::EmptyName => write!(f, "a function has an empty name"),
+            SignatureError::DuplicateName { function } => {
+                write!(f, "{} is defined more than once", function)
+            }
+            SignatureError::UnresolvableType { function, typ } => write!(
+                f,
+                "type {} of {} does not resolve by its name",
+                typ, function
+            ),
+        }
+    }
+}
+
 /// Records a universe of functions.
 /// Signatures are containers for types and function symbols. They hold references to the concrete
 /// implementations of functions and the types of variables.
 pub struct Signature<PT: ProtocolTypes> {
     pub functions_by_name: HashMap<&'static str, FunctionDefinition<PT>>,
+    /// Functions by their return type, each sorted by function name
     pub functions_by_typ: HashMap<TypeShape<PT>, Vec<FunctionDefinition<PT>>>,
     pub functions: Vec<FunctionDefinition<PT>>,
     pub types_by_name: HashMap<&'static str, TypeShape<PT>>,
@@ -34,8 +68,25 @@ impl<PT: ProtocolTypes> std::fmt::Debug for Signature<PT> {
 
 impl<PT: ProtocolTypes> Signature<PT> {
     /// Construct a `Signature` from the given [`FunctionDefinition`]s.
+    ///
+    /// # Panics
+    ///
+    /// If the definitions are malformed, see [`Signature::validate`].
     #[must_use]
     pub fn new(definitions: Vec<(FunctionDefinition<PT>, FunctionAttributes)>) -> Self {
+        let signature = Self::new_unchecked(definitions);
+
+        if let Err(errors) = signature.validate() {
+            panic!(
+                "invalid signature: {}",
+                errors.iter().map(ToString::to_string).join(", ")
+            );
+        }
+
+        signature
+    }
+
+    fn new_unchecked(definitions: Vec<(FunctionDefinition<PT>, FunctionAttributes)>) -> Self {
         let attrs_by_name: HashMap<&'static str, FunctionAttributes> = definitions
             .clone()
             .iter()
@@ -47,11 +98,16 @@ impl<PT: ProtocolTypes> Signature<PT> {
             .map(|((shape, dynamic_fn), _attrs)| (shape.name, (shape, dynamic_fn)))
             .collect();
 
-        let functions_by_typ: HashMap<TypeShape<PT>, Vec<FunctionDefinition<PT>>> = definitions
+        let mut functions_by_typ: HashMap<TypeShape<PT>, Vec<FunctionDefinition<PT>>> = definitions
             .clone()
             .into_iter()
             .map(|(fd, _attrs)| fd)
             .into_group_map_by(|(shape, _dynamic_fn)| shape.return_type.clone());
+        // Candidates are picked by index using the RNG, so their order must not depend on the order
+        // in which the functions were defined
+        for candidates in functions_by_typ.values_mut() {
+            candidates.sort_by_key(|(shape, _dynamic_fn)| shape.name);
+        }
 
         let types_by_name: HashMap<&'static str, TypeShape<PT>> = definitions
             .clone()
@@ -79,6 +135,47 @@ impl<PT: ProtocolTypes> Signature<PT> {
         }
     }
 
+    /// Checks that the functions are well-formed: each has a unique, non-empty name and all their
+    /// types resolve by their name, like when terms are deserialized.
+    pub fn validate(&self) -> Result<(), Vec<SignatureError>> {
+        let mut errors = vec![];
+        let mut names = HashSet::new();
+
+        for (shape, _dynamic_fn) in &self.functions {
+            if shape.name.is_empty() {
+                errors.push(SignatureError::EmptyName);
+            } else if !names.insert(shape.name) {
+                errors.push(SignatureError::DuplicateName {
+                    function: shape.name,
+                });
+            }
+
+            for typ in shape.argument_types.iter().chain([&shape.return_type]) {
+                if self.types_by_name.get(typ.name) != Some(typ) {
+                    errors.push(SignatureError::UnresolvableType {
+                        function: shape.name,
+                        typ: typ.name,
+                    });
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Whether the function symbol `name` is deprecated, see
+    /// [`FunctionAttributes::is_deprecated`]
+    #[must_use]
+    pub fn is_deprecated(&self, name: &str) -> bool {
+        self.attrs_by_name
+            .get(name)
+            .map_or(false, |attrs| attrs.is_deprecated)
+    }
+
     /// Create a new [`Function`] distinct from all existing [`Function`]s.
     pub fn new_function<F: 'static, Types>(f: &'static F) -> Function<PT>
     where
@@ -154,10 +251,12 @@ macro_rules! define_signature {
                                     "opaque" => attrs.is_opaque = true,
                                     "list" => attrs.is_list = true,
                                     "get" => attrs.is_get = true,
+                                    "deprecated" => attrs.is_deprecated = true,
                                     _ => {},
                                 }
                             )*
                         }
+                        $crate::algebra::dynamic_function::assert_send_sync_function(&$f);
                         ($crate::algebra::dynamic_function::make_dynamic(&$f), attrs)
                     }
                 ),+
@@ -166,3 +265,74 @@ macro_rules! define_signature {
         });
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algebra::test_signature::{TestProtocolTypes, TEST_SIGNATURE};
+
+    fn candidate_names(
+        signature: &Signature<TestProtocolTypes>,
+        typ: &TypeShape<TestProtocolTypes>,
+    ) -> Vec<&'static str> {
+        signature.functions_by_typ[typ]
+            .iter()
+            .map(|(shape, _dynamic_fn)| shape.name)
+            .collect()
+    }
+
+    #[test_log::test]
+    fn test_functions_by_typ_order() {
+        let mut definitions: Vec<_> = TEST_SIGNATURE
+            .functions
+            .iter()
+            .map(|fd| (fd.clone(), TEST_SIGNATURE.attrs_by_name[fd.0.name]))
+            .collect();
+        let forward = Signature::new(definitions.clone());
+        definitions.reverse();
+        let backward = Signature::new(definitions);
+
+        let (typ, candidates) = forward
+            .functions_by_typ
+            .iter()
+            .max_by_key(|(_typ, candidates)| candidates.len())
+            .unwrap();
+        assert!(candidates.len() > 1);
+
+        let names = candidate_names(&forward, typ);
+        assert!(names.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert_eq!(names, candidate_names(&backward, typ));
+    }
+
+    #[test_log::test]
+    fn test_validate_duplicate_name() {
+        assert_eq!(TEST_SIGNATURE.validate(), Ok(()));
+
+        let mut definitions: Vec<_> = TEST_SIGNATURE
+            .functions
+            .iter()
+            .map(|fd| (fd.clone(), TEST_SIGNATURE.attrs_by_name[fd.0.name]))
+            .collect();
+        let duplicate = definitions[0].clone();
+        let name = duplicate.0 .0.name;
+        definitions.push(duplicate);
+
+        assert_eq!(
+            Signature::new_unchecked(definitions).validate(),
+            Err(vec![SignatureError::DuplicateName { function: name }])
+        );
+    }
+
+    #[test_log::test]
+    #[should_panic(expected = "is defined more than once")]
+    fn test_new_rejects_duplicate_name() {
+        let mut definitions: Vec<_> = TEST_SIGNATURE
+            .functions
+            .iter()
+            .map(|fd| (fd.clone(), TEST_SIGNATURE.attrs_by_name[fd.0.name]))
+            .collect();
+        definitions.push(definitions[0].clone());
+
+        let _ = Signature::new(definitions);
+    }
+}
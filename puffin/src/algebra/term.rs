@@ -1,7 +1,9 @@
 //! This module provides[`DYTerm`]sas well as iterators over them.
 
+use std::collections::HashMap;
 use std::fmt;
 use std::hash::Hash;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use itertools::Itertools;
 use libafl::inputs::BytesInput;
@@ -11,16 +13,35 @@ use super::atoms::{Function, Variable};
 use crate::algebra::bitstrings::{replace_payloads, EvalTree, Payloads};
 use crate::algebra::dynamic_function::TypeShape;
 use crate::error::Error;
+use crate::fuzzer::utils::find_term_by_term_path_mut;
 use crate::protocol::{EvaluatedTerm, ProtocolBehavior, ProtocolTypes};
-use crate::trace::TraceContext;
+use crate::trace::{Query, TraceContext};
 
 const SIZE_LEAF: usize = 1;
 const BITSTRING_NAME: &str = "BITSTRING_";
+/// Number of nodes after which terms are truncated in log messages
+pub(crate) const LOG_MAX_NODES: usize = 100;
+
+/// Default of the maximum depth of terms which are evaluated
+pub const DEFAULT_MAX_EVALUATION_DEPTH: usize = 256;
+
+static MAX_EVALUATION_DEPTH: AtomicUsize = AtomicUsize::new(DEFAULT_MAX_EVALUATION_DEPTH);
+
+/// Sets the maximum depth of terms which are evaluated. The evaluation is recursive, so deeper
+/// terms, e.g. from corrupted corpus files, could overflow the stack and abort the process.
+pub fn set_max_evaluation_depth(depth: usize) {
+    MAX_EVALUATION_DEPTH.store(depth, Ordering::Relaxed);
+}
+
+#[must_use]
+pub fn max_evaluation_depth() -> usize {
+    MAX_EVALUATION_DEPTH.load(Ordering::Relaxed)
+}
 
 pub type ConcreteMessage = Vec<u8>;
 
 /// A first-order term: either a [`Variable`] or an application of an [`Function`].
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize, Clone, Debug, Hash)]
 #[serde(bound = "PT: ProtocolTypes")]
 pub enum DYTerm<PT: ProtocolTypes> {
     /// A concrete but unspecified `Term` (e.g. `x`, `y`).
@@ -33,6 +54,22 @@ pub enum DYTerm<PT: ProtocolTypes> {
     Application(Function<PT>, Vec<Term<PT>>),
 }
 
+// Implemented by hand as deriving requires `PT: PartialEq`, which the protocol types do not
+// implement
+impl<PT: ProtocolTypes> PartialEq for DYTerm<PT> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Variable(a), Self::Variable(b)) => a == b,
+            (Self::Application(f, args), Self::Application(g, other_args)) => {
+                f == g && args == other_args
+            }
+            _ => false,
+        }
+    }
+}
+
+impl<PT: ProtocolTypes> Eq for DYTerm<PT> {}
+
 impl<PT: ProtocolTypes> fmt::Display for DYTerm<PT> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", display_term_at_depth(self, 0, false))
@@ -166,7 +203,7 @@ pub(crate) fn remove_fn_prefix(str: &str) -> String {
 
 /// `Term`s are `Term`s equipped with optional `Payloads` when they no longer are treated as
 /// symbolic terms.
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize, Clone, Debug, Hash)]
 #[serde(bound = "PT: ProtocolTypes")]
 pub struct Term<PT: ProtocolTypes> {
     pub term: DYTerm<PT>, // initial DY term
@@ -174,6 +211,14 @@ pub struct Term<PT: ProtocolTypes> {
                            * with term.evaluate() */
 }
 
+impl<PT: ProtocolTypes> PartialEq for Term<PT> {
+    fn eq(&self, other: &Self) -> bool {
+        self.term == other.term && self.payloads == other.payloads
+    }
+}
+
+impl<PT: ProtocolTypes> Eq for Term<PT> {}
+
 impl<PT: ProtocolTypes> Term<PT> {
     /// Height of term, considering non-symbolic terms as atoms
     pub fn height(&self) -> usize {
@@ -189,6 +234,79 @@ impl<PT: ProtocolTypes> Term<PT> {
         }
     }
 
+    /// Depth of term, including non-symbolic terms.
+    ///
+    /// Unlike [`Term::height`], this does not recurse and therefore works for arbitrarily deep
+    /// terms.
+    pub fn depth(&self) -> usize {
+        let mut depth = 0;
+        let mut stack = vec![(self, 1)];
+
+        while let Some((term, term_depth)) = stack.pop() {
+            depth = depth.max(term_depth);
+            if let DYTerm::Application(_, subterms) = &term.term {
+                stack.extend(subterms.iter().map(|subterm| (subterm, term_depth + 1)));
+            }
+        }
+
+        depth
+    }
+
+    /// Number of applications of each function symbol in this term, by function name
+    pub fn count_by_symbol(&self) -> HashMap<&'static str, usize> {
+        let mut counts = HashMap::new();
+
+        for subterm in self {
+            if let DYTerm::Application(func, _) = &subterm.term {
+                *counts.entry(func.name()).or_default() += 1;
+            }
+        }
+
+        counts
+    }
+
+    /// Function symbols of all applications in this term together with their paths, in pre-order
+    ///
+    /// A path lists the argument indices which lead from the root to the application, the root
+    /// itself has the empty path. A variable at the root yields no paths.
+    pub fn symbol_paths(&self) -> Vec<(Vec<usize>, &Function<PT>)> {
+        let mut paths = Vec::new();
+        let mut stack = vec![(self, Vec::new())];
+
+        while let Some((term, path)) = stack.pop() {
+            if let DYTerm::Application(func, subterms) = &term.term {
+                for (i, subterm) in subterms.iter().enumerate().rev() {
+                    let mut subterm_path = path.clone();
+                    subterm_path.push(i);
+                    stack.push((subterm, subterm_path));
+                }
+                paths.push((path, func));
+            }
+        }
+
+        paths
+    }
+
+    /// Replaces the subterm at `path` by `replacement`, see [`Term::symbol_paths`] for paths.
+    ///
+    /// Fails and leaves the term unchanged if `path` does not lead to a subterm or if the type of
+    /// `replacement` differs from the type of that subterm.
+    pub fn replace_at_path(&mut self, path: &[usize], replacement: Self) -> Result<(), Error> {
+        let subterm = find_term_by_term_path_mut(self, path)
+            .ok_or_else(|| Error::Term(format!("no subterm at path {path:?}")))?;
+
+        if subterm.get_type_shape() != replacement.get_type_shape() {
+            return Err(Error::Term(format!(
+                "can not replace the subterm of type {} at path {path:?} by a term of type {}",
+                subterm.get_type_shape(),
+                replacement.get_type_shape()
+            )));
+        }
+
+        subterm.mutate(replacement);
+        Ok(())
+    }
+
     /// When the term starts with a list function symbol
     pub fn is_list(&self) -> bool {
         match &self.term {
@@ -309,6 +427,84 @@ impl<PT: ProtocolTypes> Term<PT> {
     pub fn has_payload_to_replace_wo_root(&self) -> bool {
         has_payload_to_replace_rec(self, false)
     }
+
+    /// Reassign the ids of all symbols in pre-order, starting at `next_id`. Both the
+    /// `resistant_id` and the `unique_id` of a symbol are set to the same value.
+    pub fn normalize_ids(&mut self, next_id: &mut u32) {
+        match &mut self.term {
+            DYTerm::Variable(v) => {
+                v.unique_id = *next_id;
+                v.resistant_id = *next_id;
+                *next_id += 1;
+            }
+            DYTerm::Application(f, args) => {
+                f.unique_id = *next_id;
+                f.resistant_id = *next_id;
+                *next_id += 1;
+                for arg in args {
+                    arg.normalize_ids(next_id);
+                }
+            }
+        }
+    }
+
+    /// Replaces every variable which queries `query` by `value`, e.g. to instantiate a recipe with
+    /// concrete values. Returns the number of replaced variables.
+    ///
+    /// Fails without changing the term if the type of `value` differs from the type of a replaced
+    /// variable.
+    pub fn inline_variable(
+        &mut self,
+        query: &Query<PT::Matcher>,
+        value: Term<PT>,
+    ) -> Result<usize, Error> {
+        let value_type = value.get_type_shape();
+
+        for subterm in &*self {
+            if let DYTerm::Variable(variable) = &subterm.term {
+                if variable.query == *query && variable.typ != *value_type {
+                    return Err(Error::Term(format!(
+                        "Can not inline {} of type {} for variable {}",
+                        value, value_type, variable
+                    )));
+                }
+            }
+        }
+
+        fn rec<PT: ProtocolTypes>(
+            term: &mut Term<PT>,
+            query: &Query<PT::Matcher>,
+            value: &Term<PT>,
+        ) -> usize {
+            match &mut term.term {
+                DYTerm::Variable(variable) if variable.query == *query => {
+                    *term = value.clone();
+                    1
+                }
+                DYTerm::Variable(_) => 0,
+                DYTerm::Application(_, args) => {
+                    args.iter_mut().map(|arg| rec(arg, query, value)).sum()
+                }
+            }
+        }
+
+        Ok(rec(self, query, &value))
+    }
+
+    /// Like the [`Display`](fmt::Display) of the term, but renders at most `max_nodes` nodes in
+    /// pre-order. The omitted nodes are summarized as `…(+N more)`, which keeps logging of large
+    /// terms readable.
+    pub fn display_truncated(&self, max_nodes: usize) -> String {
+        let mut budget = max_nodes;
+        let rendered = display_term_truncated(self, 0, &mut budget);
+        let omitted = self.into_iter().count() - (max_nodes - budget);
+
+        match (rendered, omitted) {
+            (Some(rendered), 0) => rendered,
+            (Some(rendered), omitted) => format!("{rendered}\n…(+{omitted} more)"),
+            (None, omitted) => format!("…(+{omitted} more)"),
+        }
+    }
 }
 
 pub fn has_payload_to_replace_rec<PT: ProtocolTypes>(term: &Term<PT>, include_root: bool) -> bool {
@@ -378,6 +574,40 @@ fn display_term_at_depth<PT: ProtocolTypes>(
     }
 }
 
+/// Renders `term` like [`display_term_at_depth`], while each node takes one unit of `budget`.
+/// Arguments beyond the budget are left out.
+fn display_term_truncated<PT: ProtocolTypes>(
+    term: &Term<PT>,
+    depth: usize,
+    budget: &mut usize,
+) -> Option<String> {
+    if *budget == 0 {
+        return None;
+    }
+    *budget -= 1;
+
+    let tabs = "\t".repeat(depth);
+    let is_bitstring = if term.is_symbolic() { "" } else { "BS//" };
+    match &term.term {
+        DYTerm::Variable(v) => Some(format!("{tabs}{is_bitstring}{v}")),
+        DYTerm::Application(func, args) => {
+            let op_str = remove_prefix(func.name());
+            let return_type = remove_prefix(func.shape().return_type.name);
+            if args.is_empty() {
+                Some(format!("{tabs}{is_bitstring}{op_str} -> {return_type}"))
+            } else {
+                let args_str = args
+                    .iter()
+                    .map_while(|arg| display_term_truncated(arg, depth + 1, budget))
+                    .join(",\n");
+                Some(format!(
+                    "{tabs}{is_bitstring}{op_str}(\n{args_str}\n{tabs}) -> {return_type}"
+                ))
+            }
+        }
+    }
+}
+
 fn append_eval<'a, PT: ProtocolTypes>(term_eval: &'a Term<PT>, v: &mut Vec<&'a Term<PT>>) {
     match term_eval.term {
         DYTerm::Variable(_) => {}
@@ -391,9 +621,12 @@ fn append_eval<'a, PT: ProtocolTypes>(term_eval: &'a Term<PT>, v: &mut Vec<&'a T
     v.push(term_eval);
 }
 
-impl<PT: ProtocolTypes> TermType<PT> for Term<PT> {
-    /// Evaluate terms into bitstrings and `EvaluatedTerm` (considering Payloads)
-    fn evaluate_config<PB: ProtocolBehavior>(
+impl<PT: ProtocolTypes> Term<PT> {
+    /// Evaluates the term like [`TermType::evaluate_config`], but without checking its depth.
+    ///
+    /// The depth is checked once for the root of a term, hence its subterms are evaluated with
+    /// this function instead of checking their depth again.
+    pub(crate) fn evaluate_config_within_depth<PB: ProtocolBehavior>(
         &self,
         context: &TraceContext<PB>,
         with_payloads: bool,
@@ -401,9 +634,9 @@ impl<PT: ProtocolTypes> TermType<PT> for Term<PT> {
     where
         PB: ProtocolBehavior<ProtocolTypes = PT>,
     {
-        log::debug!("[evaluate_config] About to evaluate {}\n===================================================================", &self);
+        log::debug!("[evaluate_config] About to evaluate {}\n===================================================================", self.display_truncated(LOG_MAX_NODES));
         let mut eval_tree = EvalTree::empty();
-        let (m, all_payloads) = self.eval_until_opaque(
+        let (m, all_payloads, _) = self.eval_until_opaque(
             &mut eval_tree,
             context,
             with_payloads,
@@ -424,6 +657,31 @@ impl<PT: ProtocolTypes> TermType<PT> for Term<PT> {
             Ok((eval, m))
         }
     }
+}
+
+impl<PT: ProtocolTypes> TermType<PT> for Term<PT> {
+    /// Evaluate terms into bitstrings and `EvaluatedTerm` (considering Payloads)
+    fn evaluate_config<PB: ProtocolBehavior>(
+        &self,
+        context: &TraceContext<PB>,
+        with_payloads: bool,
+    ) -> Result<(ConcreteMessage, Box<dyn EvaluatedTerm<PT>>), Error>
+    where
+        PB: ProtocolBehavior<ProtocolTypes = PT>,
+    {
+        // Checked before anything recurses into the term, including its `Display`
+        let depth = self.depth();
+        if depth > max_evaluation_depth() {
+            log::debug!(
+                "[evaluate_config] Term of depth {} exceeds maximum depth {}",
+                depth,
+                max_evaluation_depth()
+            );
+            return Err(Error::Term("max evaluation depth exceeded".to_owned()));
+        }
+
+        self.evaluate_config_within_depth(context, with_payloads)
+    }
 
     fn resistant_id(&self) -> u32 {
         match &self.term {
@@ -566,7 +824,165 @@ impl<PT: ProtocolTypes> Subterms<PT, Term<PT>> for Vec<Term<PT>> {
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+    use crate::agent::AgentName;
     use crate::algebra::remove_prefix;
+    use crate::algebra::signature::Signature;
+    use crate::algebra::test_signature::*;
+    use crate::term;
+    use crate::trace::{Action, Spawner};
+
+    /// `fn_identity` applied `depth - 1` times to an empty vector
+    fn deep_term(depth: usize) -> TestTerm {
+        let identity: Function<TestProtocolTypes> = Signature::new_function(&fn_identity);
+        let mut term: TestTerm = term! { fn_empty_bytes_vec };
+
+        for _ in 1..depth {
+            term = Term::from(DYTerm::Application(identity.clone(), vec![term]));
+        }
+
+        term
+    }
+
+    #[test_log::test]
+    fn test_evaluate_max_depth() {
+        let registry = dummy_registry();
+        let ctx = TraceContext::new(Spawner::new(registry));
+
+        let valid = deep_term(DEFAULT_MAX_EVALUATION_DEPTH);
+        assert_eq!(valid.depth(), DEFAULT_MAX_EVALUATION_DEPTH);
+        assert!(valid.evaluate_dy(&ctx).is_ok());
+
+        let too_deep = deep_term(10 * DEFAULT_MAX_EVALUATION_DEPTH);
+        assert_eq!(too_deep.depth(), 10 * DEFAULT_MAX_EVALUATION_DEPTH);
+        assert!(matches!(
+            too_deep.evaluate_dy(&ctx),
+            Err(Error::Term(msg)) if msg == "max evaluation depth exceeded"
+        ));
+    }
+
+    #[test_log::test]
+    fn test_display_truncated() {
+        let small: TestTerm = term! { fn_encrypt12(fn_finished, fn_seq_0) };
+        assert_eq!(small.display_truncated(10), small.to_string());
+        // Exactly at the budget nothing is omitted
+        assert_eq!(small.display_truncated(3), small.to_string());
+        assert!(small.display_truncated(2).ends_with("\n…(+1 more)"));
+        assert_eq!(small.display_truncated(0), "…(+3 more)");
+
+        let large = deep_term(100);
+        let truncated = large.display_truncated(10);
+        assert!(truncated.len() < large.to_string().len());
+        assert!(truncated.ends_with("…(+90 more)"));
+        assert_eq!(truncated.matches("fn_identity").count(), 10);
+    }
+
+    #[test_log::test]
+    fn test_symbol_paths() {
+        let Action::Input(input) = &setup_simple_trace().steps[0].action else {
+            unreachable!()
+        };
+        let paths = input.recipe.symbol_paths();
+
+        assert_eq!(paths.len(), input.recipe.size());
+        assert_eq!(paths[0].0, Vec::<usize>::new());
+        assert_eq!(remove_prefix(paths[0].1.name()), "fn_client_hello");
+
+        let append_paths: Vec<_> = paths
+            .iter()
+            .filter(|(_, func)| remove_prefix(func.name()) == "fn_client_extensions_append")
+            .map(|(path, _)| path.clone())
+            .collect();
+        assert_eq!(
+            append_paths,
+            vec![
+                vec![5],
+                vec![5, 0],
+                vec![5, 0, 0],
+                vec![5, 0, 0, 0],
+                vec![5, 0, 0, 0, 0],
+                vec![5, 0, 0, 0, 0, 0],
+            ]
+        );
+        assert!(paths.contains(&(
+            vec![5, 0, 0, 0, 0, 0, 1, 0],
+            &Signature::new_function(&fn_named_group_secp384r1)
+        )));
+
+        let client = AgentName::first();
+        let variable: TestTerm = term! { (client, 0)/ProtocolVersion };
+        assert!(variable.symbol_paths().is_empty());
+    }
+
+    #[test_log::test]
+    fn test_replace_at_path() {
+        let Action::Input(input) = &setup_simple_trace().steps[0].action else {
+            unreachable!()
+        };
+        let mut recipe = input.recipe.clone();
+        let extension_path = [5, 0, 0, 0, 0, 0, 1];
+
+        recipe
+            .replace_at_path(&extension_path, term! {fn_signature_algorithm_extension})
+            .unwrap();
+
+        let paths = recipe.symbol_paths();
+        assert!(paths.contains(&(
+            extension_path.to_vec(),
+            &Signature::new_function(&fn_signature_algorithm_extension)
+        )));
+        assert_eq!(recipe.size(), input.recipe.size() - 1);
+
+        // The random of the ClientHello is not a protocol version
+        let before = recipe.clone();
+        assert!(recipe
+            .replace_at_path(&[1], term! {fn_protocol_version12})
+            .is_err());
+        assert!(recipe.replace_at_path(&[6], term! {fn_new_random}).is_err());
+        assert_eq!(recipe.to_string(), before.to_string());
+    }
+
+    #[test_log::test]
+    fn test_inline_variable() {
+        let registry = dummy_registry();
+        let ctx = TraceContext::new(Spawner::new(registry));
+
+        let client = AgentName::first();
+        let mut recipe: TestTerm = term! {
+            fn_client_hello(
+                ((client, 0)/ProtocolVersion),
+                fn_new_random,
+                fn_new_session_id,
+                fn_new_cipher_suites,
+                fn_compressions,
+                fn_client_extensions_new
+            )
+        };
+        let DYTerm::Application(_, args) = &recipe.term else {
+            unreachable!()
+        };
+        let DYTerm::Variable(variable) = &args[0].term else {
+            unreachable!()
+        };
+        let query = variable.query.clone();
+
+        // The knowledge of the protocol version is missing
+        assert!(recipe.evaluate_dy(&ctx).is_err());
+
+        let mismatch = recipe.inline_variable(&query, term! { fn_new_random });
+        assert!(matches!(mismatch, Err(Error::Term(_))));
+        assert!(recipe.evaluate_dy(&ctx).is_err());
+
+        let inlined = recipe.inline_variable(&query, term! { fn_protocol_version12 });
+        assert_eq!(inlined.unwrap(), 1);
+        assert!(recipe.evaluate_dy(&ctx).is_ok());
+        assert_eq!(
+            recipe
+                .inline_variable(&query, term! { fn_protocol_version12 })
+                .unwrap(),
+            0
+        );
+    }
 
     #[test_log::test]
     fn test_normal() {
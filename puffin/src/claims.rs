@@ -22,6 +22,11 @@ pub trait SecurityViolationPolicy {
     type C: Claim;
 
     fn check_violation(claims: &[Self::C]) -> Option<&'static str>;
+
+    /// Whether `claims` show that both peers finished the handshake
+    fn is_handshake_complete(_claims: &[Self::C]) -> bool {
+        false
+    }
 }
 
 #[derive(Default, Clone, Debug, PartialEq)]
@@ -86,6 +91,10 @@ impl<C: Claim> ClaimList<C> {
     pub fn claim_sized(&mut self, claim: C) {
         self.claims.push(claim);
     }
+
+    pub fn clear(&mut self) {
+        self.claims.clear();
+    }
 }
 
 #[derive(Default, Clone, PartialEq, Debug)]
@@ -2,8 +2,10 @@ use std::fs::File;
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 use std::process::ExitCode;
+use std::time::Duration;
 use std::{env, fs};
 
+use clap::error::ErrorKind;
 use clap::parser::ValuesRef;
 use clap::{arg, crate_authors, crate_name, value_parser, Command};
 use libafl::inputs::Input;
@@ -12,15 +14,18 @@ use puffin_build::puffin;
 
 use crate::agent::AgentName;
 use crate::algebra::TermType;
-use crate::execution::{ForkedRunner, Runner, TraceRunner};
+use crate::execution::{replay, run_in_subprocess, ForkedRunner, Runner, TraceRunner};
 use crate::experiment::{format_title, write_experiment_markdown};
+use crate::fuzzer::import::ImportFilter;
+use crate::fuzzer::objective_stop::objective_traces;
 use crate::fuzzer::sanitizer::asan::{asan_info, setup_asan_env};
 use crate::fuzzer::{start, FuzzerConfig};
-use crate::graphviz::write_graphviz;
+use crate::graphviz::{messages_to_dot, write_graphviz};
 use crate::log::config_default;
 use crate::protocol::ProtocolBehavior;
 use crate::put::PutDescriptor;
-use crate::put_registry::{PutRegistry, TCP_PUT};
+use crate::put_registry::{PutRegistry, SelfTestResult, TCP_PUT};
+use crate::trace::pcap::write_pcap;
 use crate::trace::{Action, Spawner, Trace, TraceContext};
 
 fn create_app<S>(title: S) -> Command
@@ -31,7 +36,7 @@ where
         .version(puffin::version())
         .author(crate_authors!())
         .about(title.as_ref().to_owned())
-        .arg(arg!(-T --put <T> "The PUT to use"))
+        .arg(arg!(-T --put <T> "The PUT to use").global(true))
         .arg(arg!(-c --cores [spec] "Sets the cores to use during fuzzing"))
         .arg(arg!(-s --seed [n] "(experimental) provide a seed for all clients")
             .value_parser(value_parser!(u64)))
@@ -39,12 +44,27 @@ where
             .value_parser(value_parser!(u16).range(1..)))
         .arg(arg!(-i --"max-iters" [i] "Maximum iterations to do")
             .value_parser(value_parser!(u64).range(0..)))
+        .arg(arg!(--"max-time" [secs] "Stop the campaign after this many seconds")
+            .value_parser(value_parser!(u64).range(1..)))
         .arg(arg!(--minimizer "Use a minimizer"))
         .arg(arg!(--tui "Display fuzzing logs using the interactive terminal UI"))
         .arg(arg!(--"put-use-clear" "Use clearing functionality instead of recreating puts"))
         .arg(arg!(--"no-launcher" "Do not use the convenient launcher"))
         .arg(arg!(--"wo-bit" "Disable bit-level mutations"))
         .arg(arg!(--"wo-dy" "Disable DY mutations"))
+        .arg(arg!(--resume "Resume the campaign from the last snapshot"))
+        .arg(arg!(--"snapshot-interval" [secs] "Seconds between two snapshots of the campaign")
+            .value_parser(value_parser!(u64).range(1..)))
+        .arg(arg!(--"max-corpus-size" [n] "Evict the least valuable corpus entries above this number of entries")
+            .value_parser(value_parser!(usize)))
+        .arg(arg!(--"stop-on-first-crash" "Stop fuzzing as soon as the first objective is found"))
+        .arg(arg!(--"decryption-patterns" "Keep inputs whose records decrypt in a new pattern"))
+        .arg(arg!(--"import-max-steps" [n] "Skip initial corpus traces with more steps")
+            .value_parser(value_parser!(usize)))
+        .arg(arg!(--"import-max-term-size" [n] "Skip initial corpus traces whose input terms are larger in total")
+            .value_parser(value_parser!(usize)))
+        .arg(arg!(--"import-max-term-depth" [n] "Skip initial corpus traces with a deeper input term")
+            .value_parser(value_parser!(usize)))
         .subcommands(vec![
             Command::new("quick-experiment").about("Starts a new experiment and writes the results out"),
             Command::new("experiment").about("Starts a new experiment and writes the results out")
@@ -52,19 +72,27 @@ where
                 .arg(arg!(-d --description [d] "Description of the experiment"))
             ,
             Command::new("seed").about("Generates seeds to ./seeds"),
+            Command::new("self-test").about("Executes the default trace with every PUT of the registry")
+                .arg(arg!(--determinism "Whether to also check that the PUTs behave deterministically after reseeding")),
             Command::new("plot")
                 .about("Plots a trace stored in a file")
                 .arg(arg!(<input> "The file which stores a trace"))
                 .arg(arg!(<format> "The format of the plot, can be svg or pdf"))
                 .arg(arg!(<output_prefix> "The file to which the trace should be written"))
                 .arg(arg!(--multiple "Whether we want to output multiple views, additionally to the combined view"))
-                .arg(arg!(--tree "Whether want to use tree mode in the combined view")),
+                .arg(arg!(--tree "Whether want to use tree mode in the combined view"))
+                .arg(arg!(--messages "Whether to execute the trace and plot the messages which the agents sent")),
             Command::new("execute")
                 .about("Executes a trace stored in a file. The exit code describes if more files are available for execution.")
                 .arg(arg!(<inputs> "The file which stores a trace").num_args(1..))
                 .arg(arg!(-n --number <n> "Amount of files to execute starting at index.").value_parser(value_parser!(usize)))
                 .arg(arg!(-i --index <i> "Index of file to execute.").value_parser(value_parser!(usize)))
-                .arg(arg!(-s --sort "Sort files in ascending order by the creation date before executing")),
+                .arg(arg!(-s --sort "Sort files in ascending order by the creation date before executing"))
+                .arg(arg!(--pcap "Whether to write the messages of each trace to a pcap file next to it, unless the PUT crashes")),
+            Command::new("replay")
+                .about("Executes a trace several times and reports whether the executions were deterministic")
+                .arg(arg!(<input> "The file which stores a trace"))
+                .arg(arg!(-n --count [n] "Number of executions").value_parser(value_parser!(usize))),
             Command::new("execute-traces")
                 .about("Executes traces stored in files.")
                 .arg(arg!(<inputs> "The file which stores a trace").num_args(1..)),
@@ -81,6 +109,8 @@ where
                 .arg(arg!(-t --host [h] "The host to connect to, or the server host"))
                 .arg(arg!(-p --port [n] "The client port to connect to, or the server port")
                     .value_parser(value_parser!(u16).range(1..)))
+                .arg(arg!(-A --agent [name] "The agent of the trace which runs as TCP client/server, e.g. server")
+                    .value_parser(value_parser!(AgentName)))
         ])
 }
 
@@ -97,7 +127,20 @@ where
         }
     };
 
-    let matches = create_app(title).get_matches();
+    let mut app = create_app(title);
+    let matches = app.get_matches_mut();
+
+    // Experiments always start in a new directory, so there is no snapshot to resume from. clap
+    // can only declare conflicts between arguments, not with subcommands.
+    if let Some(name @ ("experiment" | "quick-experiment")) = matches.subcommand_name() {
+        if matches.get_flag("resume") {
+            app.error(
+                ErrorKind::ArgumentConflict,
+                format!("the argument '--resume' cannot be used with '{name}'"),
+            )
+            .exit();
+        }
+    }
 
     let first_core = "0".to_string();
     let core_definition = matches.get_one("cores").unwrap_or(&first_core);
@@ -108,12 +151,23 @@ where
     let port: u16 = *matches.get_one::<u16>("port").unwrap_or(&1337u16);
     let static_seed: Option<u64> = matches.get_one("seed").copied();
     let max_iters: Option<u64> = matches.get_one("max-iters").copied();
+    let max_time: Option<u64> = matches.get_one("max-time").copied();
     let minimizer = matches.get_flag("minimizer");
     let tui = matches.get_flag("tui");
     let no_launcher = matches.get_flag("no-launcher");
     let put_use_clear = matches.get_flag("put-use-clear");
     let without_bit_level = matches.get_flag("wo-bit");
     let without_dy_mutations = matches.get_flag("wo-dy");
+    let resume = matches.get_flag("resume");
+    let snapshot_interval: u64 = *matches.get_one("snapshot-interval").unwrap_or(&600);
+    let max_corpus_size: Option<usize> = matches.get_one("max-corpus-size").copied();
+    let stop_on_first_objective = matches.get_flag("stop-on-first-crash");
+    let decryption_patterns = matches.get_flag("decryption-patterns");
+    let import_filter = ImportFilter {
+        max_steps: matches.get_one("import-max-steps").copied(),
+        max_term_size: matches.get_one("import-max-term-size").copied(),
+        max_term_depth: matches.get_one("import-max-term-depth").copied(),
+    };
     let target_put: Option<&String> = matches.get_one("put");
 
     let mut put_registry = put_registry.clone();
@@ -131,11 +185,8 @@ where
 
     log::info!("Version: {}", puffin::full_version());
     log::info!("Put Versions:");
-    for (id, put) in put_registry.puts() {
-        log::info!("{}:", id);
-        for (component, version) in put.versions().into_iter() {
-            log::info!("    {}: {}", component, version);
-        }
+    for line in put_registry.version_strings() {
+        log::info!("    {}", line);
     }
     log::info!("Default PUT: {}", put_registry.default_put_name());
 
@@ -156,6 +207,37 @@ where
             log::error!("Failed to create seeds on disk: {:?}", err);
             return ExitCode::FAILURE;
         }
+    } else if let Some(matches) = matches.subcommand_matches("self-test") {
+        let check_determinism = matches.get_flag("determinism");
+        let mut failed = false;
+
+        for (name, result) in put_registry.self_test() {
+            match result {
+                SelfTestResult::Passed if check_determinism => {
+                    let deterministic = put_registry
+                        .find_by_id(&name)
+                        .is_some_and(|factory| factory.verify_determinism());
+                    if deterministic {
+                        log::info!("{}: passed", name);
+                    } else {
+                        log::error!("{}: failed: executions are not deterministic", name);
+                        failed = true;
+                    }
+                }
+                SelfTestResult::Passed => log::info!("{}: passed", name),
+                SelfTestResult::Unsupported(agents) => {
+                    log::warn!("{}: skipped, agents {:?} are not supported", name, agents);
+                }
+                SelfTestResult::Failed(err) => {
+                    log::error!("{}: failed: {}", name, err);
+                    failed = true;
+                }
+            }
+        }
+
+        if failed {
+            return ExitCode::FAILURE;
+        }
     } else if let Some(matches) = matches.subcommand_matches("plot") {
         // Parse arguments
         let output_prefix: &String = matches.get_one("output_prefix").unwrap();
@@ -168,6 +250,18 @@ where
             log::error!("Failed to plot trace: {:?}", err);
             return ExitCode::FAILURE;
         }
+
+        if matches.get_flag("messages") {
+            let runner = Runner::new(
+                put_registry.clone(),
+                Spawner::new(put_registry).with_default(default_put),
+            );
+
+            if let Err(err) = plot_messages(&runner, input, format, output_prefix) {
+                log::error!("Failed to plot messages: {:?}", err);
+                return ExitCode::FAILURE;
+            }
+        }
     } else if let Some(matches) = matches.subcommand_matches("execute") {
         let inputs: ValuesRef<String> = matches.get_many("inputs").unwrap();
         let index: usize = *matches.get_one("index").unwrap_or(&0);
@@ -227,9 +321,11 @@ where
             Spawner::new(put_registry).with_default(default_put),
         );
 
+        let pcap = matches.get_flag("pcap");
+
         for path in lookup_paths {
             log::info!("Executing: {}", path.display());
-            execute(&runner, path);
+            execute(&runner, path, pcap);
         }
 
         if !lookup_paths.is_empty() {
@@ -287,10 +383,27 @@ where
 
         for path in paths {
             log::info!("Executing: {}", path.display());
-            execute(&runner, path);
+            execute(&runner, path, false);
         }
 
         return ExitCode::SUCCESS;
+    } else if let Some(matches) = matches.subcommand_matches("replay") {
+        let input: &String = matches.get_one("input").unwrap();
+        let count: usize = *matches.get_one("count").unwrap_or(&10);
+
+        let runner = Runner::new(
+            put_registry.clone(),
+            Spawner::new(put_registry).with_default(default_put),
+        );
+
+        match replay_trace(&runner, input, count) {
+            Ok(true) => {}
+            Ok(false) => return ExitCode::FAILURE,
+            Err(err) => {
+                log::error!("Failed to replay trace {input}: {err}");
+                return ExitCode::FAILURE;
+            }
+        }
     } else if let Some(matches) = matches.subcommand_matches("binary-attack") {
         let input: &String = matches.get_one("input").unwrap();
         let output: &String = matches.get_one("output").unwrap();
@@ -327,7 +440,10 @@ where
             options.push(("cwd", cwd));
         }
 
-        let server = trace.descriptors[0].name;
+        let server = matches
+            .get_one::<AgentName>("agent")
+            .copied()
+            .unwrap_or(trace.descriptors[0].name);
         let put = PutDescriptor::new(TCP_PUT, options);
         let runner = Runner::new(
             put_registry.clone(),
@@ -335,7 +451,6 @@ where
         );
         let mut context = runner.execute(trace).unwrap();
 
-        let server = AgentName::first();
         let shutdown = context.find_agent_mut(server).unwrap().shutdown();
         log::info!("{}", shutdown);
 
@@ -434,12 +549,20 @@ where
             initial_corpus_dir: PathBuf::from("./seeds"),
             static_seed,
             max_iters,
+            max_time: max_time.map(Duration::from_secs),
             core_definition: core_definition.to_string(),
             corpus_dir: experiment_path.join("corpus"),
             objective_dir: experiment_path.join("objective"),
             broker_port: port,
             stats_file: experiment_path.join("stats.json"),
             log_file: experiment_path.join("tlspuffin.log"),
+            snapshot_file: experiment_path.join("snapshot.json"),
+            snapshot_interval: Duration::from_secs(snapshot_interval),
+            resume,
+            max_corpus_size,
+            stop_on_first_objective,
+            import_filter,
+            decryption_patterns,
             minimizer,
             mutation_stage_config: Default::default(),
             mutation_config: Default::default(),
@@ -454,6 +577,8 @@ where
             config.mutation_config.with_dy = false;
         }
 
+        let objective_dir = config.objective_dir.clone();
+
         if let Err(err) = start::<PB>(&put_registry, default_put, config, handle) {
             match err {
                 libafl::Error::ShuttingDown => {
@@ -464,6 +589,12 @@ where
                 }
             }
         }
+
+        if stop_on_first_objective {
+            for path in objective_traces(&objective_dir) {
+                log::info!("Crashing trace: {}", path.display());
+            }
+        }
     }
 
     ExitCode::SUCCESS
@@ -507,6 +638,32 @@ fn plot<PB: ProtocolBehavior>(
     Ok(())
 }
 
+/// Executes the trace in `input` and plots the messages which the agents sent to
+/// `<output_prefix>_messages.<format>`
+fn plot_messages<PB: ProtocolBehavior>(
+    runner: &Runner<PB>,
+    input: &str,
+    format: &str,
+    output_prefix: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let trace = Trace::<PB::ProtocolTypes>::from_file(input)?;
+
+    let mut ctx = runner.new_context();
+    if let Err(err) = runner.execute_in(&mut ctx, &trace) {
+        // The messages sent until the error are still worth plotting
+        log::warn!("Execution of the trace failed: {err}");
+    }
+
+    write_graphviz(
+        format!("{output_prefix}_messages.{format}").as_str(),
+        format,
+        messages_to_dot(&ctx.sent_message_types()).as_str(),
+    )?;
+
+    log::info!("Created plot of the messages");
+    Ok(())
+}
+
 fn seed<PB: ProtocolBehavior>(
     _put_registry: &PutRegistry<PB>,
     put: PutDescriptor,
@@ -520,12 +677,13 @@ fn seed<PB: ProtocolBehavior>(
     Ok(())
 }
 
-fn execute<PB: ProtocolBehavior, P: AsRef<Path>>(runner: &Runner<PB>, input: P) {
-    let trace = if let Ok(t) = Trace::<PB::ProtocolTypes>::from_file(input.as_ref()) {
-        t
-    } else {
-        log::error!("Invalid trace file {}", input.as_ref().display());
-        return;
+fn execute<PB: ProtocolBehavior, P: AsRef<Path>>(runner: &Runner<PB>, input: P, pcap: bool) {
+    let trace = match Trace::<PB::ProtocolTypes>::from_file(input.as_ref()) {
+        Ok(t) => t,
+        Err(err) => {
+            log::error!("Invalid trace file {}: {err}", input.as_ref().display());
+            return;
+        }
     };
 
     log::info!("Agents: {:?}", &trace.descriptors);
@@ -533,14 +691,82 @@ fn execute<PB: ProtocolBehavior, P: AsRef<Path>>(runner: &Runner<PB>, input: P)
     // When generating coverage a crash means that no coverage is stored
     // By executing in a fork, even when that process crashes, the other executed code will still
     // yield coverage
-    let status = ForkedRunner::new(runner).execute(trace);
+    let status = if pcap {
+        let output = input.as_ref().with_extension("pcap");
+        run_in_subprocess(
+            || std::process::exit(execute_pcap(runner, &trace, &output)),
+            None::<Duration>,
+        )
+    } else {
+        ForkedRunner::new(runner).execute(&trace)
+    };
 
     match status {
-        Ok(s) => log::info!("execution finished with status {s:?}"),
+        Ok(s) => {
+            log::info!("execution finished with status {s:?}");
+
+            if let Some(expected) = trace.expected_outcome {
+                if !expected.matches(s) {
+                    log::error!("expected outcome {expected:?}, but execution finished with {s:?}");
+                }
+            }
+        }
         Err(reason) => panic!("failed to execute trace: {reason}"),
     }
 }
 
+/// Executes `trace` while recording the messages which cross the wire and writes them to `output`
+/// as a pcap file. Returns the exit code of the execution.
+fn execute_pcap<PB: ProtocolBehavior>(
+    runner: &Runner<PB>,
+    trace: &Trace<PB::ProtocolTypes>,
+    output: &Path,
+) -> i32 {
+    let mut ctx = runner.new_context();
+    ctx.record_wire();
+    let result = runner.execute_in(&mut ctx, trace);
+
+    // The messages sent until an error are still worth inspecting
+    match write_pcap(output, ctx.wire_records()) {
+        Ok(()) => log::info!("Wrote the messages to {}", output.display()),
+        Err(err) => log::error!("Failed to write pcap {}: {err}", output.display()),
+    }
+
+    i32::from(result.is_err())
+}
+
+/// Replays the trace in `input` `count` times and returns whether the executions were
+/// deterministic
+fn replay_trace<PB: ProtocolBehavior>(
+    runner: &Runner<PB>,
+    input: &str,
+    count: usize,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let trace = Trace::<PB::ProtocolTypes>::from_file(input)?;
+    let report = replay(runner, &trace, count);
+
+    for (i, run) in report.runs.iter().enumerate() {
+        match &run.error {
+            None => log::info!("Run #{i}: ok, output digest {:016x}", run.output_digest),
+            Some(err) => log::info!(
+                "Run #{i}: failed with {err}, output digest {:016x}",
+                run.output_digest
+            ),
+        }
+    }
+
+    let divergent = report.divergent_runs();
+    if divergent.is_empty() {
+        log::info!("All {count} executions of {input} behaved identically");
+    } else {
+        log::error!(
+            "Nondeterminism detected: runs {divergent:?} of {input} behaved differently than run #0"
+        );
+    }
+
+    Ok(report.is_deterministic())
+}
+
 fn binary_attack<PB: ProtocolBehavior>(
     input: &str,
     output: &str,
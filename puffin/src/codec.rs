@@ -2,6 +2,10 @@ use std::fmt::Debug;
 
 use crate::error::Error;
 
+/// Position of a [`Reader`] which it can be rewound to, see [`Reader::checkpoint`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checkpoint(usize);
+
 /// Read from a byte slice.
 pub struct Reader<'a> {
     buf: &'a [u8],
@@ -61,6 +65,35 @@ impl<'a> Reader<'a> {
     pub fn sub(&mut self, len: usize) -> Option<Reader> {
         self.take(len).map(Reader::init)
     }
+
+    /// Like [`Reader::sub`], but rejects a `len` above `max` before consuming any bytes. This
+    /// allows decoders to reject absurd length prefixes early.
+    pub fn sub_checked(&mut self, len: usize, max: usize) -> Option<Reader> {
+        if len > max {
+            return None;
+        }
+
+        self.sub(len)
+    }
+
+    /// Remembers the current position, such that a parser can try to read and rewind on failure.
+    #[must_use]
+    pub const fn checkpoint(&self) -> Checkpoint {
+        Checkpoint(self.offs)
+    }
+
+    /// Rewinds to a position remembered by [`Reader::checkpoint`].
+    ///
+    /// Only rewinding is allowed. If the checkpoint lies past the current position, the reader is
+    /// left untouched and `false` is returned.
+    pub fn restore(&mut self, checkpoint: Checkpoint) -> bool {
+        if checkpoint.0 > self.offs {
+            return false;
+        }
+
+        self.offs = checkpoint.0;
+        true
+    }
 }
 
 /// Things we can encode and read from a Reader.
@@ -359,11 +392,7 @@ pub fn read_vec_u16<T: Codec>(r: &mut Reader) -> Option<Vec<T>> {
 pub fn read_vec_u24_limited<T: Codec>(r: &mut Reader, max_bytes: usize) -> Option<Vec<T>> {
     let mut ret: Vec<T> = Vec::new();
     let len = <u24 as Codec>::read(r)?.0 as usize;
-    if len > max_bytes {
-        return None;
-    }
-
-    let mut sub = r.sub(len)?;
+    let mut sub = r.sub_checked(len, max_bytes)?;
 
     while sub.any_left() {
         ret.push(T::read(&mut sub)?);
@@ -417,3 +446,105 @@ impl<T: Codec + VecCodecWoSize> Codec for Vec<T> {
         Some(ret)
     }
 }
+
+/// Bytes per line of a [`hex_dump`]
+const HEX_DUMP_WIDTH: usize = 16;
+
+/// Formats `bytes` like `hexdump -C`, i.e. each line has an offset, up to 16 bytes in hex and the
+/// same bytes as ASCII. Bytes which are not printable are shown as `.`. The dump of an empty slice
+/// is empty.
+#[must_use]
+pub fn hex_dump(bytes: &[u8]) -> String {
+    let mut dump = String::new();
+
+    for (line, chunk) in bytes.chunks(HEX_DUMP_WIDTH).enumerate() {
+        dump.push_str(&format!("{:08x} ", line * HEX_DUMP_WIDTH));
+
+        for i in 0..HEX_DUMP_WIDTH {
+            if i % 8 == 0 {
+                dump.push(' ');
+            }
+            match chunk.get(i) {
+                Some(byte) => dump.push_str(&format!("{byte:02x} ")),
+                None => dump.push_str("   "),
+            }
+        }
+
+        let ascii: String = chunk
+            .iter()
+            .map(|&byte| {
+                if byte.is_ascii_graphic() || byte == b' ' {
+                    byte as char
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        dump.push_str(&format!(" |{ascii}|\n"));
+    }
+
+    dump
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_log::test]
+    fn test_hex_dump() {
+        let bytes: Vec<u8> = b"GET / HTTP/1.1\r\n".iter().copied().chain(0..3).collect();
+
+        assert_eq!(
+            hex_dump(&bytes),
+            "00000000  47 45 54 20 2f 20 48 54  54 50 2f 31 2e 31 0d 0a  |GET / HTTP/1.1..|\n\
+             00000010  00 01 02                                          |...|\n"
+        );
+        assert_eq!(hex_dump(&[]), "");
+    }
+
+    #[test_log::test]
+    fn test_reader_checkpoint_restore() {
+        let bytes = [0x01, 0x02, 0x03, 0x04, 0x05];
+        let mut reader = Reader::init(&bytes);
+
+        assert_eq!(<u8 as Codec>::read(&mut reader), Some(0x01));
+        let checkpoint = reader.checkpoint();
+        assert_eq!(<u16 as Codec>::read(&mut reader), Some(0x0203));
+        assert_eq!(reader.left(), 2);
+
+        assert!(reader.restore(checkpoint));
+        assert_eq!(reader.used(), 1);
+        assert_eq!(<u16 as Codec>::read(&mut reader), Some(0x0203));
+        assert_eq!(reader.rest(), &[0x04, 0x05]);
+    }
+
+    #[test_log::test]
+    fn test_reader_sub_checked() {
+        let bytes = [0x01, 0x02, 0x03, 0x04];
+
+        let mut reader = Reader::init(&bytes);
+        assert!(reader.sub_checked(3, 2).is_none());
+        assert_eq!(reader.used(), 0);
+
+        let mut sub = reader.sub_checked(2, 2).unwrap();
+        assert_eq!(sub.rest(), &[0x01, 0x02]);
+        assert_eq!(reader.left(), 2);
+
+        // the guard does not replace the bounds check of the buffer
+        assert!(reader.sub_checked(3, 4).is_none());
+    }
+
+    #[test_log::test]
+    fn test_reader_restore_forward() {
+        let bytes = [0x01, 0x02, 0x03];
+        let mut reader = Reader::init(&bytes);
+
+        let start = reader.checkpoint();
+        reader.take(2).unwrap();
+        let ahead = reader.checkpoint();
+        assert!(reader.restore(start));
+
+        assert!(!reader.restore(ahead));
+        assert_eq!(reader.used(), 0);
+    }
+}
@@ -1,5 +1,6 @@
 use std::{fmt, io};
 
+use crate::agent::AgentName;
 use crate::algebra::error::FnError;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -20,11 +21,43 @@ pub enum Error {
     Agent(String),
     /// Error while operating on a [`Stream`](crate::stream::Stream)
     Stream(String),
+    /// An operation, like the execution of a step, did not finish in time
+    Timeout(String),
     Extraction(),
     SecurityClaim(&'static str),
+    /// The messages which the agents sent differ from the
+    /// [`Trace::expected_flow`](crate::trace::Trace::expected_flow)
+    Flow(String),
+    /// Error which occurred while executing a step of a trace
+    Context {
+        /// Index of the step in its trace
+        step: usize,
+        agent: AgentName,
+        /// Function symbol at the root of the recipe, if the step is an input step
+        function: Option<String>,
+        source: Box<Error>,
+    },
 }
 
-impl std::error::Error for Error {}
+impl Error {
+    /// The error without the context in which it occurred
+    #[must_use]
+    pub fn root(&self) -> &Self {
+        match self {
+            Self::Context { source, .. } => source.root(),
+            err => err,
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Context { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
 
 impl From<anyhow::Error> for Error {
     fn from(value: anyhow::Error) -> Self {
@@ -45,10 +78,24 @@ impl fmt::Display for Error {
             Self::IO(err) => write!(f, "error in io of openssl (this should not happen): {err}"),
             Self::Agent(err) => write!(f, "error regarding an agent: {err}"),
             Self::Stream(err) => write!(f, "error in the stream: {err}"),
+            Self::Timeout(err) => write!(f, "timeout: {err}"),
             Self::Extraction() => write!(f, "error while extracting variable",),
             Self::SecurityClaim(msg) => {
                 write!(f, "error because a security violation occurred. msg: {msg}")
             }
+            Self::Flow(diff) => write!(f, "unexpected message flow:\n{diff}"),
+            Self::Context {
+                step,
+                agent,
+                function,
+                source,
+            } => {
+                write!(f, "step #{step} of {agent}")?;
+                if let Some(function) = function {
+                    write!(f, " with input {function}")?;
+                }
+                write!(f, ": {source}")
+            }
         }
     }
 }
@@ -61,6 +108,10 @@ impl From<FnError> for Error {
 
 impl From<io::Error> for Error {
     fn from(err: io::Error) -> Self {
-        Self::IO(err.to_string())
+        if err.kind() == io::ErrorKind::TimedOut {
+            Self::Timeout(err.to_string())
+        } else {
+            Self::IO(err.to_string())
+        }
     }
 }
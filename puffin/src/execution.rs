@@ -6,11 +6,13 @@ use nix::sys::signal::{kill, Signal};
 use nix::sys::wait::WaitStatus::{self, Exited, Signaled};
 use nix::sys::wait::{waitpid, WaitPidFlag};
 use nix::unistd::{fork, ForkResult, Pid};
+use serde::{Deserialize, Serialize};
 
+use crate::agent::AgentName;
 use crate::error::Error;
 use crate::protocol::ProtocolBehavior;
 use crate::put_registry::PutRegistry;
-use crate::trace::{Spawner, Trace, TraceContext};
+use crate::trace::{Action, Spawner, Step, Trace, TraceContext};
 
 pub trait TraceRunner {
     type PB: ProtocolBehavior;
@@ -35,6 +37,149 @@ impl<PB: ProtocolBehavior> Runner<PB> {
             spawner: spawner.into(),
         }
     }
+
+    #[must_use]
+    pub fn new_context(&self) -> TraceContext<PB> {
+        TraceContext::new(self.spawner.clone())
+    }
+
+    /// Executes `trace` in `ctx`, which is reused from previous executions.
+    ///
+    /// The agents of previous executions are soft reset if their PUT supports it, instead of
+    /// instantiating them anew. `ctx` has to use the same spawner as this runner, e.g. by
+    /// creating it with [`Runner::new_context`].
+    pub fn execute_in<T>(&self, ctx: &mut TraceContext<PB>, trace: T) -> Result<(), Error>
+    where
+        T: AsRef<Trace<PB::ProtocolTypes>>,
+    {
+        // We reseed all PUTs before executing a trace!
+        self.registry.determinism_reseed_all_factories();
+
+        ctx.reset();
+        trace.as_ref().execute(ctx)
+    }
+}
+
+/// Behavior of a single execution of a [`replay`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplayRun {
+    /// Error of the execution, `None` if it succeeded
+    pub error: Option<String>,
+    /// Digest of the bytes which the agents sent, see [`KnowledgeStore::output_digest`]
+    ///
+    /// [`KnowledgeStore::output_digest`]: crate::trace::KnowledgeStore::output_digest
+    pub output_digest: u64,
+}
+
+/// Executions of the same trace, see [`replay`]
+#[derive(Debug, Clone)]
+pub struct ReplayReport {
+    pub runs: Vec<ReplayRun>,
+}
+
+impl ReplayReport {
+    /// Indices of the executions which behaved differently than the first one
+    #[must_use]
+    pub fn divergent_runs(&self) -> Vec<usize> {
+        self.runs
+            .iter()
+            .enumerate()
+            .skip(1)
+            .filter(|(_, run)| *run != &self.runs[0])
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Whether all executions failed or succeeded alike and the agents sent the same bytes
+    #[must_use]
+    pub fn is_deterministic(&self) -> bool {
+        self.divergent_runs().is_empty()
+    }
+}
+
+/// Executes `trace` `count` times with fresh agents and records how each execution behaved.
+///
+/// The PUTs are reseeded before each execution, so a PUT which supports determinism is expected to
+/// behave the same in all executions. The executions are not forked, so a crash aborts the replay.
+pub fn replay<PB: ProtocolBehavior>(
+    runner: &Runner<PB>,
+    trace: &Trace<PB::ProtocolTypes>,
+    count: usize,
+) -> ReplayReport {
+    let runs = (0..count)
+        .map(|i| {
+            log::debug!("Replay #{i}");
+            let mut ctx = runner.new_context();
+            let result = runner.execute_in(&mut ctx, trace);
+
+            ReplayRun {
+                error: result.err().map(|err| err.to_string()),
+                output_digest: ctx.knowledge_store.output_digest(),
+            }
+        })
+        .collect();
+
+    ReplayReport { runs }
+}
+
+/// Removes the steps of `trace` which are not needed for `reproduces` to hold, e.g. to reduce an
+/// objective to the steps which trigger it. The steps are dropped one at a time, from the last to
+/// the first, and a candidate is kept if its execution still reproduces.
+///
+/// Before a candidate is executed, the input which follows the dropped step is evaluated on a
+/// [fork](TraceContext::fork_knowledge) of the knowledge preceding the dropped step, such that
+/// candidates whose next recipe can not be evaluated anymore are rejected without spawning agents.
+pub fn minimize<PB: ProtocolBehavior>(
+    runner: &Runner<PB>,
+    trace: &Trace<PB::ProtocolTypes>,
+    reproduces: impl Fn(&TraceContext<PB>, &Result<(), Error>) -> bool,
+) -> Trace<PB::ProtocolTypes> {
+    // Knowledge before each step of `trace`. Only steps after a dropped step change, so these
+    // stay valid for the prefixes of the candidates.
+    runner.registry.determinism_reseed_all_factories();
+    let mut ctx = runner.new_context();
+    let mut snapshots = vec![];
+    if trace.execute_until_step(&mut ctx, 0).is_ok() {
+        for i in 0..trace.steps.len() {
+            snapshots.push(ctx.fork_knowledge());
+            if trace.execute_steps(&mut ctx, i..i + 1).is_err() {
+                break;
+            }
+        }
+    }
+
+    let mut minimized = trace.clone();
+    for i in (0..trace.steps.len()).rev() {
+        let mut candidate = minimized.clone();
+        candidate.steps.remove(i);
+
+        let next = candidate.steps.get(i);
+        if let (
+            Some(snapshot),
+            Some(Step {
+                agent,
+                action: Action::Input(input),
+            }),
+        ) = (snapshots.get(i), next)
+        {
+            if input
+                .evaluate(*agent, &mut snapshot.fork_knowledge())
+                .is_err()
+            {
+                log::debug!("Keeping step #{i}, the next input can not be evaluated without it");
+                continue;
+            }
+        }
+
+        let mut ctx = runner.new_context();
+        let result = runner.execute_in(&mut ctx, &candidate);
+        if reproduces(&ctx, &result) {
+            log::debug!("Dropping step #{i}");
+            minimized = candidate;
+        }
+    }
+
+    minimized
 }
 
 impl<PB: ProtocolBehavior> TraceRunner for &Runner<PB> {
@@ -49,7 +194,7 @@ impl<PB: ProtocolBehavior> TraceRunner for &Runner<PB> {
         // We reseed all PUTs before executing a trace!
         self.registry.determinism_reseed_all_factories();
 
-        let mut ctx = TraceContext::new(self.spawner.clone());
+        let mut ctx = self.new_context();
         trace.as_ref().execute(&mut ctx)?;
         Ok(ctx)
     }
@@ -175,6 +320,12 @@ where
 
     watchdog.start(timeout.into());
 
+    // The child can exit before the signal handlers are registered, in which case its SIGCHLD is
+    // never observed
+    if let Ok(status) = waitpid(executor_pid, Some(WaitPidFlag::WNOHANG)).try_into() {
+        return Ok(status);
+    }
+
     let mut result = ExecutionStatus::Timeout;
     for info in &mut signals {
         let source = info.process.map(|p| p.pid);
@@ -236,6 +387,118 @@ pub enum ExecutionStatus {
     Failure(i32),
 }
 
+/// Outcome which the execution of a [`Trace`] can be expected to have.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Outcome {
+    /// The execution finishes without triggering an objective. Single steps may still have
+    /// failed, e.g. because a PUT rejected a message.
+    CleanClose,
+    /// A PUT rejects an input of the trace, such that the execution fails without crashing.
+    Rejected,
+    /// The execution crashes.
+    Crash,
+    /// The execution does not finish in time.
+    Timeout,
+}
+
+impl Outcome {
+    #[must_use]
+    pub const fn matches(self, status: ExecutionStatus) -> bool {
+        matches!(
+            (self, status),
+            (
+                Self::CleanClose,
+                ExecutionStatus::Success | ExecutionStatus::Failure(_)
+            ) | (Self::Rejected, ExecutionStatus::Failure(_))
+                | (Self::Crash, ExecutionStatus::Crashed)
+                | (Self::Timeout, ExecutionStatus::Timeout)
+        )
+    }
+}
+
+/// How the execution of a [`Trace`] ended
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Termination {
+    /// All steps were executed and the expected flow, if any, was observed
+    Completed,
+    /// A step failed, e.g. because a PUT rejected a message
+    Failed,
+    /// A security claim was violated
+    SecurityViolation,
+    /// The agents did not send the expected flow
+    UnexpectedFlow,
+}
+
+impl Termination {
+    /// The termination of an execution which ended with `result`
+    #[must_use]
+    pub fn of(result: &Result<(), Error>) -> Self {
+        match result {
+            Ok(()) => Self::Completed,
+            Err(err) => match err.root() {
+                Error::SecurityClaim(_) => Self::SecurityViolation,
+                Error::Flow(_) => Self::UnexpectedFlow,
+                _ => Self::Failed,
+            },
+        }
+    }
+}
+
+/// Metrics of a single step of an execution. Bytes and messages are counted from the view of the
+/// attacker, steps without I/O count zero.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StepMetrics {
+    pub agent: AgentName,
+    pub duration: Duration,
+    /// Bytes which the step injected into the inbound channel of the agent
+    pub bytes_to_agent: usize,
+    /// Bytes which the step took from the outbound channel of the agent
+    pub bytes_from_agent: usize,
+    /// Messages which the agent emitted during the step
+    pub messages_from_agent: usize,
+}
+
+impl StepMetrics {
+    #[must_use]
+    pub const fn new(agent: AgentName) -> Self {
+        Self {
+            agent,
+            duration: Duration::ZERO,
+            bytes_to_agent: 0,
+            bytes_from_agent: 0,
+            messages_from_agent: 0,
+        }
+    }
+}
+
+/// Metrics of the execution of a [`Trace`], see [`TraceContext::metrics`]. They are only collected
+/// when enabled by [`TraceContext::measure`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExecutionMetrics {
+    /// Metrics of the executed steps including a failing step. The steps of prior traces come
+    /// first.
+    pub steps: Vec<StepMetrics>,
+    /// How the execution ended, `None` if it did not end yet or only some steps were executed
+    pub termination: Option<Termination>,
+}
+
+impl ExecutionMetrics {
+    /// Time spent in the steps
+    #[must_use]
+    pub fn total_duration(&self) -> Duration {
+        self.steps.iter().map(|step| step.duration).sum()
+    }
+
+    /// Bytes exchanged with the agents in both directions
+    #[must_use]
+    pub fn bytes_exchanged(&self) -> usize {
+        self.steps
+            .iter()
+            .map(|step| step.bytes_to_agent + step.bytes_from_agent)
+            .sum()
+    }
+}
+
 impl TryFrom<Result<WaitStatus, Errno>> for ExecutionStatus {
     type Error = ForkError;
 
@@ -0,0 +1,402 @@
+//! Feedback on the outcomes of the record decryptions during an execution.
+//!
+//! Protocol implementations call [`record_decryption`](crate::protocol::record_decryption) whenever
+//! a record is decrypted, e.g. by the function symbols which decrypt TLS records with the keys of
+//! the attacker or by a PUT, see
+//! [`ProtocolBehavior::put_decryption_outcome`](crate::protocol::ProtocolBehavior::put_decryption_outcome).
+//! The [`DecryptionObserver`] collects the outcomes of an execution into a [`DecryptionPattern`],
+//! and the [`DecryptionPatternFeedback`] finds an input interesting if its sequence of successes
+//! and failures is new. An unusual sequence, like a record which fails to decrypt in between
+//! records which succeed, often points at an interesting state of the record layer.
+//!
+//! The fuzzer only uses the feedback with `--decryption-patterns`, otherwise the observer is
+//! [disabled](DecryptionObserver::disabled) and the decryptions are not recorded.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use libafl::corpus::Testcase;
+use libafl::events::EventFirer;
+use libafl::executors::ExitKind;
+use libafl::feedbacks::Feedback;
+use libafl::inputs::UsesInput;
+use libafl::observers::{Observer, ObserversTuple};
+use libafl::state::{HasNamedMetadata, State};
+use libafl::Error;
+use libafl_bolts::Named;
+use serde::{Deserialize, Serialize};
+
+use crate::fuzzer::interesting_reason::InterestingReasonsMetadata;
+use crate::fuzzer::stats_stage::CORPUS_NEW_DECRYPTION_PATTERNS;
+use crate::protocol::{finish_recording_decryptions, start_recording_decryptions};
+
+pub const DECRYPTION_OBSERVER_NAME: &str = "decryption_observer";
+
+/// Number of patterns after which no new pattern is interesting anymore. Long executions can
+/// produce arbitrarily many distinct patterns, which would otherwise flood the corpus.
+pub const MAX_DECRYPTION_PATTERNS: usize = 4096;
+
+/// Sequence of the decryption outcomes of an execution, `true` for a record which was decrypted
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct DecryptionPattern {
+    pub outcomes: Vec<bool>,
+}
+
+impl DecryptionPattern {
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.outcomes.is_empty()
+    }
+
+    fn hash_value(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+impl fmt::Display for DecryptionPattern {
+    /// Writes `S` for each success and `F` for each failure
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for success in &self.outcomes {
+            f.write_str(if *success { "S" } else { "F" })?;
+        }
+        Ok(())
+    }
+}
+
+/// An [`Observer`] which collects the outcomes of the calls to
+/// [`record_decryption`](crate::protocol::record_decryption) during an execution
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DecryptionObserver {
+    name: String,
+    enabled: bool,
+    pattern: DecryptionPattern,
+}
+
+impl DecryptionObserver {
+    #[must_use]
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            enabled: true,
+            pattern: DecryptionPattern::default(),
+        }
+    }
+
+    /// An observer which does not record the decryptions, such that its pattern is always empty
+    #[must_use]
+    pub fn disabled(name: &str) -> Self {
+        Self {
+            enabled: false,
+            ..Self::new(name)
+        }
+    }
+
+    /// Outcomes of the last execution
+    #[must_use]
+    pub fn pattern(&self) -> &DecryptionPattern {
+        &self.pattern
+    }
+
+    fn start(&mut self) {
+        self.pattern = DecryptionPattern::default();
+        if self.enabled {
+            start_recording_decryptions();
+        }
+    }
+
+    fn finish(&mut self) {
+        if !self.enabled {
+            return;
+        }
+        self.pattern = DecryptionPattern {
+            outcomes: finish_recording_decryptions(),
+        };
+    }
+}
+
+impl Named for DecryptionObserver {
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl<S: UsesInput> Observer<S> for DecryptionObserver {
+    fn pre_exec(&mut self, _state: &mut S, _input: &S::Input) -> Result<(), Error> {
+        self.start();
+        Ok(())
+    }
+
+    fn post_exec(
+        &mut self,
+        _state: &mut S,
+        _input: &S::Input,
+        _exit_kind: &ExitKind,
+    ) -> Result<(), Error> {
+        self.finish();
+        Ok(())
+    }
+}
+
+/// Hashes of the decryption patterns which the testcases of the corpus produced, kept in the fuzzer
+/// state such that they survive restarts of the client
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DecryptionPatternsMetadata {
+    pub seen: HashSet<u64>,
+}
+
+libafl_bolts::impl_serdeany!(DecryptionPatternsMetadata);
+
+/// A [`Feedback`] which finds an input interesting if the sequence of decryption outcomes observed
+/// by the [`DecryptionObserver`] is new.
+///
+/// Executions without any decryption are never interesting, as they tell nothing about the record
+/// layer. Neither are new patterns once [`MAX_DECRYPTION_PATTERNS`] are known.
+#[derive(Debug)]
+pub struct DecryptionPatternFeedback {
+    observer_name: String,
+    new_pattern: Option<DecryptionPattern>,
+}
+
+impl DecryptionPatternFeedback {
+    #[must_use]
+    pub fn new(observer: &DecryptionObserver) -> Self {
+        Self {
+            observer_name: observer.name().to_owned(),
+            new_pattern: None,
+        }
+    }
+}
+
+impl Named for DecryptionPatternFeedback {
+    fn name(&self) -> &str {
+        "DecryptionPatternFeedback"
+    }
+}
+
+impl<S> Feedback<S> for DecryptionPatternFeedback
+where
+    S: State + HasNamedMetadata,
+{
+    fn init_state(&mut self, state: &mut S) -> Result<(), Error> {
+        state.add_named_metadata(DecryptionPatternsMetadata::default(), self.name());
+        Ok(())
+    }
+
+    fn is_interesting<EM, OT>(
+        &mut self,
+        state: &mut S,
+        _manager: &mut EM,
+        _input: &S::Input,
+        observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error>
+    where
+        EM: EventFirer<State = S>,
+        OT: ObserversTuple<S>,
+    {
+        let observer = observers
+            .match_name::<DecryptionObserver>(&self.observer_name)
+            .ok_or_else(|| Error::key_not_found("DecryptionObserver not found".to_string()))?;
+        let pattern = observer.pattern();
+        let seen = &state
+            .named_metadata::<DecryptionPatternsMetadata>(self.name())?
+            .seen;
+
+        self.new_pattern = (!pattern.is_empty()
+            && seen.len() < MAX_DECRYPTION_PATTERNS
+            && !seen.contains(&pattern.hash_value()))
+        .then(|| pattern.clone());
+
+        Ok(self.new_pattern.is_some())
+    }
+
+    fn append_metadata<OT>(
+        &mut self,
+        state: &mut S,
+        _observers: &OT,
+        testcase: &mut Testcase<S::Input>,
+    ) -> Result<(), Error>
+    where
+        OT: ObserversTuple<S>,
+    {
+        let Some(pattern) = self.new_pattern.take() else {
+            return Ok(());
+        };
+
+        CORPUS_NEW_DECRYPTION_PATTERNS.increment();
+        InterestingReasonsMetadata::record(testcase, format!("new decryption pattern {pattern}"));
+        state
+            .named_metadata_mut::<DecryptionPatternsMetadata>(self.name())?
+            .seen
+            .insert(pattern.hash_value());
+
+        Ok(())
+    }
+
+    fn discard_metadata(&mut self, _state: &mut S, _input: &S::Input) -> Result<(), Error> {
+        self.new_pattern = None;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl::corpus::InMemoryCorpus;
+    use libafl::events::NopEventManager;
+    use libafl::state::{HasMetadata, StdState};
+    use libafl_bolts::rands::StdRand;
+    use libafl_bolts::tuples::tuple_list;
+
+    use super::*;
+    use crate::algebra::test_signature::{setup_simple_trace, TestTrace};
+    use crate::protocol::record_decryption;
+
+    type TestState =
+        StdState<TestTrace, InMemoryCorpus<TestTrace>, StdRand, InMemoryCorpus<TestTrace>>;
+
+    fn feedback() -> DecryptionPatternFeedback {
+        DecryptionPatternFeedback::new(&DecryptionObserver::new(DECRYPTION_OBSERVER_NAME))
+    }
+
+    fn state(feedback: &mut DecryptionPatternFeedback) -> TestState {
+        StdState::new(
+            StdRand::with_seed(1235),
+            InMemoryCorpus::new(),
+            InMemoryCorpus::new(),
+            feedback,
+            &mut (),
+        )
+        .unwrap()
+    }
+
+    /// Executes a trace which decrypts records with the `outcomes` and returns the reasons
+    /// recorded if the feedback finds it interesting
+    fn execute(
+        feedback: &mut DecryptionPatternFeedback,
+        state: &mut TestState,
+        outcomes: &[bool],
+    ) -> Option<Vec<String>> {
+        execute_observed(
+            feedback,
+            state,
+            DecryptionObserver::new(DECRYPTION_OBSERVER_NAME),
+            outcomes,
+        )
+    }
+
+    fn execute_observed(
+        feedback: &mut DecryptionPatternFeedback,
+        state: &mut TestState,
+        observer: DecryptionObserver,
+        outcomes: &[bool],
+    ) -> Option<Vec<String>> {
+        let mut manager = NopEventManager::new();
+        let trace = setup_simple_trace();
+
+        let mut observers = tuple_list!(observer);
+        observers.pre_exec_all(state, &trace).unwrap();
+        for success in outcomes {
+            record_decryption(*success);
+        }
+        observers
+            .post_exec_all(state, &trace, &ExitKind::Ok)
+            .unwrap();
+
+        let interesting = feedback
+            .is_interesting(state, &mut manager, &trace, &observers, &ExitKind::Ok)
+            .unwrap();
+        if !interesting {
+            feedback.discard_metadata(state, &trace).unwrap();
+            return None;
+        }
+
+        let mut testcase = Testcase::new(trace);
+        feedback
+            .append_metadata(state, &observers, &mut testcase)
+            .unwrap();
+
+        Some(
+            testcase
+                .metadata::<InterestingReasonsMetadata>()
+                .unwrap()
+                .reasons
+                .clone(),
+        )
+    }
+
+    #[test_log::test]
+    fn test_new_decryption_pattern() {
+        let mut feedback = feedback();
+        let mut state = state(&mut feedback);
+
+        assert_eq!(
+            execute(&mut feedback, &mut state, &[true, true]).unwrap(),
+            ["new decryption pattern SS"]
+        );
+        assert_eq!(execute(&mut feedback, &mut state, &[true, true]), None);
+
+        // a failure in between successes is a new pattern
+        assert_eq!(
+            execute(&mut feedback, &mut state, &[true, false, true]).unwrap(),
+            ["new decryption pattern SFS"]
+        );
+        assert_eq!(
+            execute(&mut feedback, &mut state, &[true, false, true]),
+            None
+        );
+
+        // the patterns are kept in the state, which is restored when a client restarts
+        let mut restarted = self::feedback();
+        assert_eq!(execute(&mut restarted, &mut state, &[true, true]), None);
+    }
+
+    #[test_log::test]
+    fn test_no_decryption_not_interesting() {
+        let mut feedback = feedback();
+        let mut state = state(&mut feedback);
+
+        assert_eq!(execute(&mut feedback, &mut state, &[]), None);
+        assert_eq!(execute(&mut feedback, &mut state, &[]), None);
+    }
+
+    #[test_log::test]
+    fn test_unobserved_decryptions_ignored() {
+        record_decryption(false);
+
+        let mut feedback = feedback();
+        let mut state = state(&mut feedback);
+        assert_eq!(
+            execute(&mut feedback, &mut state, &[true]).unwrap(),
+            ["new decryption pattern S"]
+        );
+    }
+
+    #[test_log::test]
+    fn test_disabled_observer_not_interesting() {
+        let mut feedback = feedback();
+        let mut state = state(&mut feedback);
+
+        let observer = DecryptionObserver::disabled(DECRYPTION_OBSERVER_NAME);
+        assert_eq!(
+            execute_observed(&mut feedback, &mut state, observer, &[true, false]),
+            None
+        );
+    }
+
+    #[test_log::test]
+    fn test_decryption_patterns_capped() {
+        let mut feedback = feedback();
+        let mut state = state(&mut feedback);
+        state
+            .named_metadata_mut::<DecryptionPatternsMetadata>(feedback.name())
+            .unwrap()
+            .seen
+            .extend(0..MAX_DECRYPTION_PATTERNS as u64);
+
+        assert_eq!(execute(&mut feedback, &mut state, &[true, false]), None);
+    }
+}
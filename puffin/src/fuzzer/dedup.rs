@@ -0,0 +1,321 @@
+//! Skipping of mutated inputs which were executed before.
+//!
+//! Mutations frequently produce an input which the fuzzer already executed, e.g. when a mutation
+//! swaps two equal subterms. The [`DedupMutationalStage`] records the structural hash of every
+//! input it executes in a [`BloomFilter`] and skips mutated inputs whose hash is already recorded.
+//! A false positive of the filter skips a new input, therefore the filter is sized such that this
+//! rarely happens and is cleared before it fills up.
+
+use std::marker::PhantomData;
+
+use libafl::corpus::{Corpus, CorpusId};
+use libafl::inputs::{Input, UsesInput};
+use libafl::mutators::{MutationResult, Mutator};
+use libafl::prelude::{Evaluator, UsesState};
+use libafl::stages::mutational::{MutatedTransform, MutatedTransformPost};
+use libafl::stages::Stage;
+use libafl::state::{HasCorpus, HasRand};
+use libafl::Error;
+use libafl_bolts::rands::Rand;
+
+use crate::fuzzer::stages::MutationalStagePhantom;
+use crate::protocol::ProtocolTypes;
+use crate::trace::Trace;
+
+/// Inputs with a cheap hash over their structure.
+pub trait HasStructuralHash {
+    fn structural_hash(&self) -> u64;
+}
+
+impl<PT: ProtocolTypes> HasStructuralHash for Trace<PT> {
+    fn structural_hash(&self) -> u64 {
+        self.fast_structural_hash()
+    }
+}
+
+/// Bits of the filter per recorded hash
+const BITS_PER_ENTRY: usize = 16;
+/// Bits set per recorded hash, which gives a false positive rate below 0.05% for a full filter
+const HASHES: u64 = 11;
+
+/// A bloom filter over 64-bit hashes.
+#[derive(Clone, Debug)]
+pub struct BloomFilter {
+    bits: Vec<u64>,
+    capacity: usize,
+    len: usize,
+}
+
+impl BloomFilter {
+    /// Creates a filter which is cleared once it recorded `capacity` hashes.
+    #[must_use]
+    pub fn new(capacity: usize) -> Self {
+        let words = ((capacity * BITS_PER_ENTRY + 63) / 64).max(1);
+
+        Self {
+            bits: vec![0; words],
+            capacity,
+            len: 0,
+        }
+    }
+
+    /// Whether `hash` was probably recorded before
+    #[must_use]
+    pub fn contains(&self, hash: u64) -> bool {
+        self.positions(hash)
+            .all(|(word, mask)| self.bits[word] & mask != 0)
+    }
+
+    /// Records `hash` and returns whether it was probably recorded before.
+    pub fn insert(&mut self, hash: u64) -> bool {
+        if self.len >= self.capacity {
+            self.bits.fill(0);
+            self.len = 0;
+        }
+
+        let seen = self.contains(hash);
+
+        for (word, mask) in self.positions(hash) {
+            self.bits[word] |= mask;
+        }
+
+        if !seen {
+            self.len += 1;
+        }
+
+        seen
+    }
+
+    /// Words and masks of the bits which represent `hash`
+    fn positions(&self, hash: u64) -> impl Iterator<Item = (usize, u64)> {
+        let num_bits = self.bits.len() as u64 * 64;
+        // Double hashing derives the positions of the bits from both halves of the hash
+        let (low, high) = (hash & 0xffff_ffff, (hash >> 32) | 1);
+
+        (0..HASHES).map(move |i| {
+            let bit = low.wrapping_add(i.wrapping_mul(high)) % num_bits;
+            ((bit / 64) as usize, 1 << (bit % 64))
+        })
+    }
+}
+
+/// Upper bound of the iterations of a [`DedupMutationalStage`], mimicking libafl's
+/// `StdMutationalStage`
+const MAX_ITERATIONS: u64 = 128;
+/// Number of hashes recorded until the filter of a [`DedupMutationalStage`] is cleared
+const FILTER_CAPACITY: usize = 1 << 20;
+
+/// A mutational stage which does not execute mutated inputs which it executed before.
+#[derive(Clone, Debug)]
+pub struct DedupMutationalStage<E, EM, I, M, Z> {
+    mutator: M,
+    seen: BloomFilter,
+    phantom: MutationalStagePhantom<E, EM, I, Z>,
+}
+
+impl<E, EM, I, M, Z> UsesState for DedupMutationalStage<E, EM, I, M, Z>
+where
+    E: UsesState<State = Z::State>,
+    EM: UsesState<State = Z::State>,
+    M: Mutator<I, Z::State>,
+    Z: Evaluator<E, EM>,
+    Z::State: HasCorpus + HasRand,
+{
+    type State = Z::State;
+}
+
+impl<E, EM, I, M, Z> Stage<E, EM, Z> for DedupMutationalStage<E, EM, I, M, Z>
+where
+    E: UsesState<State = Z::State>,
+    EM: UsesState<State = Z::State>,
+    M: Mutator<I, Z::State>,
+    Z: Evaluator<E, EM>,
+    Z::State: HasCorpus + HasRand,
+    I: MutatedTransform<<Z::State as UsesInput>::Input, Z::State> + Clone + HasStructuralHash,
+{
+    fn perform(
+        &mut self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut Z::State,
+        manager: &mut EM,
+        corpus_idx: CorpusId,
+    ) -> Result<(), Error> {
+        let num = 1 + state.rand_mut().below(MAX_ITERATIONS);
+
+        let mut testcase = state.corpus().get(corpus_idx)?.borrow_mut();
+        let Ok(input) = I::try_transform_from(&mut testcase, state, corpus_idx) else {
+            return Ok(());
+        };
+        drop(testcase);
+
+        // The input itself was executed before it was added to the corpus
+        self.seen.insert(input.structural_hash());
+
+        for i in 0..num as i32 {
+            let mut input = input.clone();
+
+            if self.mutator.mutate(state, &mut input, i)? == MutationResult::Skipped {
+                continue;
+            }
+
+            if self.seen.insert(input.structural_hash()) {
+                log::trace!("Skipping a mutated input which was executed before.");
+                continue;
+            }
+
+            let (untransformed, post) = input.try_transform_into(state)?;
+            let (_, corpus_idx) = fuzzer.evaluate_input(state, executor, manager, untransformed)?;
+
+            self.mutator.post_exec(state, i, corpus_idx)?;
+            post.post_exec(state, i, corpus_idx)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<E, EM, I, M, Z> DedupMutationalStage<E, EM, I, M, Z>
+where
+    I: Input,
+    E: UsesState<State = Z::State>,
+    EM: UsesState<State = Z::State>,
+    M: Mutator<I, Z::State>,
+    Z: Evaluator<E, EM>,
+{
+    pub fn new(mutator: M) -> Self {
+        Self {
+            mutator,
+            seen: BloomFilter::new(FILTER_CAPACITY),
+            phantom: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use libafl::corpus::{InMemoryCorpus, Testcase};
+    use libafl::events::NopEventManager;
+    use libafl::executors::{ExitKind, InProcessExecutor};
+    use libafl::feedbacks::ConstFeedback;
+    use libafl::fuzzer::StdFuzzer;
+    use libafl::schedulers::QueueScheduler;
+    use libafl::state::StdState;
+    use libafl_bolts::rands::StdRand;
+    use libafl_bolts::Named;
+
+    use super::*;
+    use crate::algebra::test_signature::{setup_simple_trace, TestTrace};
+
+    type TestState =
+        StdState<TestTrace, InMemoryCorpus<TestTrace>, StdRand, InMemoryCorpus<TestTrace>>;
+
+    /// Appends the first step to the trace if `grow` is set and leaves it unchanged otherwise
+    struct RepeatStepMutator {
+        grow: bool,
+    }
+
+    impl Named for RepeatStepMutator {
+        fn name(&self) -> &str {
+            "RepeatStepMutator"
+        }
+    }
+
+    impl Mutator<TestTrace, TestState> for RepeatStepMutator {
+        fn mutate(
+            &mut self,
+            _state: &mut TestState,
+            trace: &mut TestTrace,
+            _stage_idx: i32,
+        ) -> Result<MutationResult, Error> {
+            if self.grow {
+                trace.steps.push(trace.steps[0].clone());
+            }
+            Ok(MutationResult::Mutated)
+        }
+    }
+
+    /// Runs the stage once on a corpus of a single trace and returns the number of executions
+    fn executions(mutator: RepeatStepMutator) -> usize {
+        let executions = Cell::new(0);
+        let mut state: TestState = StdState::new(
+            StdRand::with_seed(1235),
+            InMemoryCorpus::new(),
+            InMemoryCorpus::new(),
+            &mut (),
+            &mut (),
+        )
+        .unwrap();
+        let id = state
+            .corpus_mut()
+            .add(Testcase::new(setup_simple_trace()))
+            .unwrap();
+
+        let mut fuzzer = StdFuzzer::new(
+            QueueScheduler::new(),
+            ConstFeedback::False,
+            ConstFeedback::False,
+        );
+        let mut manager = NopEventManager::new();
+        let mut harness = |_input: &TestTrace| {
+            executions.set(executions.get() + 1);
+            ExitKind::Ok
+        };
+        let mut executor =
+            InProcessExecutor::new(&mut harness, (), &mut fuzzer, &mut state, &mut manager)
+                .unwrap();
+
+        DedupMutationalStage::new(mutator)
+            .perform(&mut fuzzer, &mut executor, &mut state, &mut manager, id)
+            .unwrap();
+
+        executions.get()
+    }
+
+    #[test_log::test]
+    fn test_skip_duplicate_mutations() {
+        assert_eq!(executions(RepeatStepMutator { grow: false }), 0);
+        assert!(executions(RepeatStepMutator { grow: true }) > 0);
+    }
+
+    #[test_log::test]
+    fn test_bloom_filter_duplicates() {
+        let mut filter = BloomFilter::new(1000);
+
+        assert!(!filter.insert(42));
+        assert!(filter.insert(42));
+        assert!(!filter.insert(43));
+    }
+
+    #[test_log::test]
+    fn test_bloom_filter_false_positives_rare() {
+        let mut filter = BloomFilter::new(10_000);
+        let hash = |i: u64| i.wrapping_mul(0x9e37_79b9_7f4a_7c15).rotate_left(17);
+
+        for i in 0..10_000 {
+            filter.insert(hash(i));
+        }
+
+        assert!((0..10_000).all(|i| filter.contains(hash(i))));
+
+        let false_positives = (10_000..20_000)
+            .filter(|i| filter.contains(hash(*i)))
+            .count();
+        assert!(false_positives < 10, "{false_positives} false positives");
+    }
+
+    #[test_log::test]
+    fn test_bloom_filter_cleared_when_full() {
+        let mut filter = BloomFilter::new(2);
+
+        assert!(!filter.insert(1));
+        assert!(!filter.insert(2));
+        assert!(filter.contains(1));
+
+        // the filter forgets the recorded hashes instead of filling up
+        assert!(!filter.insert(3));
+        assert!(!filter.contains(1));
+    }
+}
@@ -0,0 +1,243 @@
+//! Bounding of the corpus size during long fuzzing campaigns.
+//!
+//! Without a bound, the on-disk corpus grows for as long as new coverage is found. Once the corpus
+//! exceeds the configured number of entries, the entries with the lowest value are evicted. An
+//! entry is worth less if it covers fewer edges and if its terms are larger. An entry which is the
+//! only one covering some edge is never evicted, even if this means the corpus stays above the
+//! bound.
+
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+use libafl::corpus::{Corpus, CorpusId};
+use libafl::feedbacks::MapIndexesMetadata;
+use libafl::inputs::UsesInput;
+use libafl::prelude::{Evaluator, UsesState};
+use libafl::stages::Stage;
+use libafl::state::{HasCorpus, HasMetadata};
+use libafl::Error;
+
+use crate::algebra::TermType;
+use crate::fuzzer::stages::StagePhantom;
+use crate::protocol::ProtocolTypes;
+use crate::trace::{Action, Trace};
+
+/// Inputs whose size is measured by the size of their terms.
+pub trait HasTermSize {
+    fn term_size(&self) -> usize;
+}
+
+impl<PT: ProtocolTypes> HasTermSize for Trace<PT> {
+    /// Sum of the sizes of all input recipes
+    fn term_size(&self) -> usize {
+        self.steps
+            .iter()
+            .map(|step| match &step.action {
+                Action::Input(input) => input.recipe.size(),
+                Action::Output(_) => 0,
+            })
+            .sum()
+    }
+}
+
+struct EvictionCandidate {
+    id: CorpusId,
+    edges: Vec<usize>,
+    term_size: usize,
+}
+
+/// Removes the lowest-value entries from the corpus of `state` until it holds at most
+/// `max_entries` entries, and returns the ids of the removed entries.
+///
+/// The currently scheduled entry and entries which uniquely cover an edge are never removed.
+pub fn evict_corpus_entries<S>(state: &mut S, max_entries: usize) -> Result<Vec<CorpusId>, Error>
+where
+    S: HasCorpus,
+    S::Input: HasTermSize,
+{
+    let corpus = state.corpus();
+    let excess = corpus.count().saturating_sub(max_entries);
+
+    if excess == 0 {
+        return Ok(vec![]);
+    }
+
+    let mut candidates = Vec::with_capacity(corpus.count());
+    let mut edge_counts: HashMap<usize, usize> = HashMap::new();
+
+    for id in corpus.ids() {
+        let mut testcase = corpus.get(id)?.borrow_mut();
+        let edges = testcase
+            .metadata::<MapIndexesMetadata>()
+            .map(|metadata| metadata.list.clone())
+            .unwrap_or_default();
+
+        for edge in &edges {
+            *edge_counts.entry(*edge).or_default() += 1;
+        }
+
+        candidates.push(EvictionCandidate {
+            id,
+            edges,
+            term_size: testcase.load_input(corpus)?.term_size(),
+        });
+    }
+
+    // Lowest value first: least covered edges, then largest terms
+    candidates.sort_by(|a, b| {
+        a.edges
+            .len()
+            .cmp(&b.edges.len())
+            .then(b.term_size.cmp(&a.term_size))
+    });
+
+    let current = *corpus.current();
+    let mut evicted = Vec::with_capacity(excess);
+
+    for candidate in candidates {
+        if evicted.len() == excess {
+            break;
+        }
+
+        if Some(candidate.id) == current || candidate.edges.iter().any(|e| edge_counts[e] == 1) {
+            continue;
+        }
+
+        // Edges shared with this entry might now be covered uniquely by another entry
+        for edge in &candidate.edges {
+            *edge_counts.get_mut(edge).unwrap() -= 1;
+        }
+
+        state.corpus_mut().remove(candidate.id)?;
+        evicted.push(candidate.id);
+    }
+
+    if evicted.len() < excess {
+        log::debug!(
+            "Corpus exceeds {} entries, but the remaining entries cover unique edges.",
+            max_entries
+        );
+    }
+
+    Ok(evicted)
+}
+
+/// A [`Stage`] which evicts entries from the corpus once it holds more than `max_entries`.
+#[derive(Clone, Debug)]
+pub struct EvictionStage<E, EM, Z> {
+    max_entries: Option<usize>,
+    phantom: StagePhantom<E, EM, Z>,
+}
+
+impl<E, EM, Z> UsesState for EvictionStage<E, EM, Z>
+where
+    E: UsesState<State = Z::State>,
+    EM: UsesState<State = Z::State>,
+    Z: Evaluator<E, EM>,
+{
+    type State = Z::State;
+}
+
+impl<E, EM, Z> Stage<E, EM, Z> for EvictionStage<E, EM, Z>
+where
+    E: UsesState<State = Z::State>,
+    EM: UsesState<State = Z::State>,
+    Z: Evaluator<E, EM>,
+    Z::State: HasCorpus,
+    <Z::State as UsesInput>::Input: HasTermSize,
+{
+    fn perform(
+        &mut self,
+        _fuzzer: &mut Z,
+        _executor: &mut E,
+        state: &mut Z::State,
+        _manager: &mut EM,
+        _corpus_idx: CorpusId,
+    ) -> Result<(), Error> {
+        let Some(max_entries) = self.max_entries else {
+            return Ok(());
+        };
+
+        // The scheduler picks entries at random and keeps no state about removed entries
+        for id in evict_corpus_entries(state, max_entries)? {
+            log::debug!("Evicted corpus entry {}.", id);
+        }
+
+        Ok(())
+    }
+}
+
+impl<E, EM, Z> EvictionStage<E, EM, Z>
+where
+    E: UsesState<State = Z::State>,
+    EM: UsesState<State = Z::State>,
+    Z: Evaluator<E, EM>,
+{
+    /// Creates the stage, which never evicts anything if `max_entries` is `None`.
+    pub fn new(max_entries: Option<usize>) -> Self {
+        Self {
+            max_entries,
+            phantom: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl::corpus::{InMemoryCorpus, Testcase};
+    use libafl::state::StdState;
+    use libafl_bolts::rands::{RomuDuoJrRand, StdRand};
+
+    use super::*;
+    use crate::algebra::test_signature::{setup_simple_trace, TestTrace};
+
+    type TestState =
+        StdState<TestTrace, InMemoryCorpus<TestTrace>, RomuDuoJrRand, InMemoryCorpus<TestTrace>>;
+
+    fn add_entry(state: &mut TestState, trace: TestTrace, edges: Vec<usize>) -> CorpusId {
+        let mut testcase = Testcase::new(trace);
+        testcase.add_metadata(MapIndexesMetadata::new(edges));
+        state.corpus_mut().add(testcase).unwrap()
+    }
+
+    fn trace_with_steps(n: usize) -> TestTrace {
+        let mut trace = setup_simple_trace();
+        let step = trace.steps[0].clone();
+        trace.steps.extend(std::iter::repeat(step).take(n));
+        trace
+    }
+
+    #[test_log::test]
+    fn test_eviction_beyond_cap() {
+        let mut state = StdState::new(
+            StdRand::with_seed(1235),
+            InMemoryCorpus::new(),
+            InMemoryCorpus::new(),
+            &mut (),
+            &mut (),
+        )
+        .unwrap();
+
+        let small = add_entry(&mut state, setup_simple_trace(), vec![1, 2]);
+        let large = add_entry(&mut state, trace_with_steps(1), vec![1, 2]);
+        // Largest entry, but the only one covering edge 3
+        let unique = add_entry(&mut state, trace_with_steps(2), vec![1, 3]);
+
+        assert!(evict_corpus_entries(&mut state, 3).unwrap().is_empty());
+
+        add_entry(&mut state, setup_simple_trace(), vec![1, 2]);
+        let evicted = evict_corpus_entries(&mut state, 3).unwrap();
+
+        assert_eq!(evicted.len(), 1);
+        assert_eq!(evicted[0], large);
+        assert_eq!(state.corpus().count(), 3);
+
+        // Only one of the equal entries can go, the other one then covers edge 2 uniquely
+        let evicted = evict_corpus_entries(&mut state, 1).unwrap();
+
+        assert_eq!(evicted.len(), 1);
+        assert_eq!(evicted[0], small);
+        assert_eq!(state.corpus().count(), 2);
+        assert!(state.corpus().get(unique).is_ok());
+    }
+}
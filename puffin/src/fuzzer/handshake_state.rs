@@ -0,0 +1,299 @@
+//! Feedback on the states which the agents reach during an execution.
+//!
+//! After executing a trace, the harness calls [`record_handshake_state`] with the state of every
+//! agent, as described by [`Put::describe_state`](crate::put::Put::describe_state). The
+//! [`HandshakeStateObserver`] collects the states of an execution and the
+//! [`HandshakeStateFeedback`] annotates the testcases added to the corpus with the states which no
+//! testcase reached so far.
+
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashSet};
+
+use libafl::corpus::Testcase;
+use libafl::events::EventFirer;
+use libafl::executors::ExitKind;
+use libafl::feedbacks::Feedback;
+use libafl::inputs::UsesInput;
+use libafl::observers::{Observer, ObserversTuple};
+use libafl::state::{HasNamedMetadata, State};
+use libafl::Error;
+use libafl_bolts::Named;
+use serde::{Deserialize, Serialize};
+
+use crate::fuzzer::interesting_reason::InterestingReasonsMetadata;
+use crate::fuzzer::stats_stage::CORPUS_NEW_HANDSHAKE_STATES;
+
+pub const HANDSHAKE_STATE_OBSERVER_NAME: &str = "handshake_state_observer";
+
+thread_local! {
+    /// States of the agents of the execution in progress, `None` if nothing is observed
+    static STATES: RefCell<Option<BTreeSet<String>>> = const { RefCell::new(None) };
+}
+
+/// Records that an agent ended the execution in `state`. Does nothing if the execution is not
+/// observed.
+pub fn record_handshake_state(state: String) {
+    STATES.with(|states| {
+        if let Some(states) = states.borrow_mut().as_mut() {
+            states.insert(state);
+        }
+    });
+}
+
+/// An [`Observer`] which collects the states passed to [`record_handshake_state`] during an
+/// execution
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HandshakeStateObserver {
+    name: String,
+    states: BTreeSet<String>,
+}
+
+impl HandshakeStateObserver {
+    #[must_use]
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            states: BTreeSet::new(),
+        }
+    }
+
+    /// States of the last execution
+    #[must_use]
+    pub fn states(&self) -> &BTreeSet<String> {
+        &self.states
+    }
+}
+
+impl Named for HandshakeStateObserver {
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl<S: UsesInput> Observer<S> for HandshakeStateObserver {
+    fn pre_exec(&mut self, _state: &mut S, _input: &S::Input) -> Result<(), Error> {
+        self.states.clear();
+        STATES.with(|states| states.replace(Some(BTreeSet::new())));
+        Ok(())
+    }
+
+    fn post_exec(
+        &mut self,
+        _state: &mut S,
+        _input: &S::Input,
+        _exit_kind: &ExitKind,
+    ) -> Result<(), Error> {
+        self.states = STATES.with(|states| states.take()).unwrap_or_default();
+        Ok(())
+    }
+}
+
+/// States which the testcases of the corpus reached, kept in the fuzzer state such that they
+/// survive restarts of the client
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HandshakeStatesMetadata {
+    pub seen: HashSet<String>,
+}
+
+libafl_bolts::impl_serdeany!(HandshakeStatesMetadata);
+
+/// A [`Feedback`] which records in the [`InterestingReasonsMetadata`] of a testcase when an agent
+/// ends its execution in a state observed by the [`HandshakeStateObserver`] which no testcase of
+/// the corpus reached so far.
+///
+/// It never finds an input interesting on its own, such that the corpus grows as with the baseline
+/// feedbacks and the states only annotate the testcases which they add.
+///
+/// Empty state descriptions are ignored, as they tell nothing about the agent.
+#[derive(Debug)]
+pub struct HandshakeStateFeedback {
+    observer_name: String,
+    new_states: BTreeSet<String>,
+}
+
+impl HandshakeStateFeedback {
+    #[must_use]
+    pub fn new(observer: &HandshakeStateObserver) -> Self {
+        Self {
+            observer_name: observer.name().to_owned(),
+            new_states: BTreeSet::new(),
+        }
+    }
+}
+
+impl Named for HandshakeStateFeedback {
+    fn name(&self) -> &str {
+        "HandshakeStateFeedback"
+    }
+}
+
+impl<S> Feedback<S> for HandshakeStateFeedback
+where
+    S: State + HasNamedMetadata,
+{
+    fn init_state(&mut self, state: &mut S) -> Result<(), Error> {
+        state.add_named_metadata(HandshakeStatesMetadata::default(), self.name());
+        Ok(())
+    }
+
+    fn is_interesting<EM, OT>(
+        &mut self,
+        state: &mut S,
+        _manager: &mut EM,
+        _input: &S::Input,
+        observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error>
+    where
+        EM: EventFirer<State = S>,
+        OT: ObserversTuple<S>,
+    {
+        let observer = observers
+            .match_name::<HandshakeStateObserver>(&self.observer_name)
+            .ok_or_else(|| Error::key_not_found("HandshakeStateObserver not found".to_string()))?;
+        let seen = &state
+            .named_metadata::<HandshakeStatesMetadata>(self.name())?
+            .seen;
+
+        self.new_states = observer
+            .states()
+            .iter()
+            .filter(|description| !description.is_empty() && !seen.contains(*description))
+            .cloned()
+            .collect();
+
+        Ok(false)
+    }
+
+    fn append_metadata<OT>(
+        &mut self,
+        state: &mut S,
+        _observers: &OT,
+        testcase: &mut Testcase<S::Input>,
+    ) -> Result<(), Error>
+    where
+        OT: ObserversTuple<S>,
+    {
+        if self.new_states.is_empty() {
+            return Ok(());
+        }
+
+        CORPUS_NEW_HANDSHAKE_STATES.increment();
+        let seen = &mut state
+            .named_metadata_mut::<HandshakeStatesMetadata>(self.name())?
+            .seen;
+        for description in std::mem::take(&mut self.new_states) {
+            InterestingReasonsMetadata::record(
+                testcase,
+                format!("new handshake state {description}"),
+            );
+            seen.insert(description);
+        }
+
+        Ok(())
+    }
+
+    fn discard_metadata(&mut self, _state: &mut S, _input: &S::Input) -> Result<(), Error> {
+        self.new_states.clear();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl::corpus::InMemoryCorpus;
+    use libafl::events::NopEventManager;
+    use libafl::state::{HasMetadata, StdState};
+    use libafl_bolts::rands::StdRand;
+    use libafl_bolts::tuples::tuple_list;
+
+    use super::*;
+    use crate::algebra::test_signature::{setup_simple_trace, TestTrace};
+
+    type TestState =
+        StdState<TestTrace, InMemoryCorpus<TestTrace>, StdRand, InMemoryCorpus<TestTrace>>;
+
+    /// Executes a trace after which the agents are in `states` and adds it to the corpus, as if
+    /// another feedback found it interesting. Returns the reasons which the feedback recorded.
+    fn execute(
+        feedback: &mut HandshakeStateFeedback,
+        state: &mut TestState,
+        states: &[&str],
+    ) -> Vec<String> {
+        let mut manager = NopEventManager::new();
+        let trace = setup_simple_trace();
+
+        let mut observers = tuple_list!(HandshakeStateObserver::new(HANDSHAKE_STATE_OBSERVER_NAME));
+        observers.pre_exec_all(state, &trace).unwrap();
+        for description in states {
+            record_handshake_state((*description).to_owned());
+        }
+        observers
+            .post_exec_all(state, &trace, &ExitKind::Ok)
+            .unwrap();
+
+        let interesting = feedback
+            .is_interesting(state, &mut manager, &trace, &observers, &ExitKind::Ok)
+            .unwrap();
+        assert!(!interesting, "the states alone must not add to the corpus");
+
+        let mut testcase = Testcase::new(trace);
+        feedback
+            .append_metadata(state, &observers, &mut testcase)
+            .unwrap();
+
+        testcase
+            .metadata::<InterestingReasonsMetadata>()
+            .map(|metadata| metadata.reasons.clone())
+            .unwrap_or_default()
+    }
+
+    fn state(feedback: &mut HandshakeStateFeedback) -> TestState {
+        StdState::new(
+            StdRand::with_seed(1235),
+            InMemoryCorpus::new(),
+            InMemoryCorpus::new(),
+            feedback,
+            &mut (),
+        )
+        .unwrap()
+    }
+
+    #[test_log::test]
+    fn test_new_handshake_state() {
+        let mut feedback = HandshakeStateFeedback::new(&HandshakeStateObserver::new(
+            HANDSHAKE_STATE_OBSERVER_NAME,
+        ));
+        let mut state = state(&mut feedback);
+
+        assert_eq!(
+            execute(&mut feedback, &mut state, &["before SSL initialization"]),
+            ["new handshake state before SSL initialization"]
+        );
+        assert!(execute(&mut feedback, &mut state, &["before SSL initialization"]).is_empty());
+
+        // Only the state which was not reached before is a reason
+        assert_eq!(
+            execute(
+                &mut feedback,
+                &mut state,
+                &[
+                    "before SSL initialization",
+                    "SSL negotiation finished successfully"
+                ]
+            ),
+            ["new handshake state SSL negotiation finished successfully"]
+        );
+    }
+
+    #[test_log::test]
+    fn test_empty_handshake_state_ignored() {
+        let mut feedback = HandshakeStateFeedback::new(&HandshakeStateObserver::new(
+            HANDSHAKE_STATE_OBSERVER_NAME,
+        ));
+        let mut state = state(&mut feedback);
+
+        assert!(execute(&mut feedback, &mut state, &[""]).is_empty());
+        assert!(execute(&mut feedback, &mut state, &[]).is_empty());
+    }
+}
@@ -3,16 +3,21 @@ use rand::Rng;
 
 use crate::algebra::TermType;
 use crate::error::Error;
-use crate::execution::{Runner, TraceRunner};
+use crate::execution::Runner;
+use crate::fuzzer::handshake_state::record_handshake_state;
+use crate::fuzzer::interesting_reason::record_message_types;
 use crate::fuzzer::stats_stage::{
-    AGENT, CODEC, EXTRACTION, FN_ERROR, IO, PUT, STREAM, TERM, TERM_SIZE, TRACE_LENGTH,
+    Counter, AGENT, CODEC, EXECUTION_TIME, EXTRACTION, FN_ERROR, HANDSHAKE_COMPLETE, IO, PUT,
+    STREAM, TERM, TERM_SIZE, TIMEOUT, TRACE_LENGTH,
 };
 use crate::protocol::ProtocolBehavior;
 use crate::put_registry::PutRegistry;
-use crate::trace::{Action, Spawner, Trace};
+use crate::trace::{Action, Spawner, Trace, TraceContext};
 
+/// Executes `input` in `ctx`, such that the agents of previous executions are reused.
 pub fn harness<PB: ProtocolBehavior + 'static>(
     put_registry: &PutRegistry<PB>,
+    ctx: &mut TraceContext<PB>,
     input: &Trace<PB::ProtocolTypes>,
 ) -> ExitKind {
     let runner = Runner::new(put_registry.clone(), Spawner::new(put_registry.clone()));
@@ -28,28 +33,74 @@ pub fn harness<PB: ProtocolBehavior + 'static>(
         }
     }
 
-    if let Err(err) = runner.execute(input) {
-        match &err {
-            Error::Fn(_) => FN_ERROR.increment(),
-            Error::Term(_e) => TERM.increment(),
-            Error::Put(_) => PUT.increment(),
-            Error::Codec(_) => CODEC.increment(),
-            Error::IO(_) => IO.increment(),
-            Error::Agent(_) => AGENT.increment(),
-            Error::Stream(_) => STREAM.increment(),
-            Error::Extraction() => EXTRACTION.increment(),
-            Error::SecurityClaim(msg) => {
-                log::warn!("{}", msg);
-                std::process::abort()
-            }
-        }
+    let span = EXECUTION_TIME.start();
+    let result = runner.execute_in(ctx, input);
+    span.finish();
+
+    if let Some(counter) = completion_counter(ctx) {
+        counter.increment();
+    }
 
+    for state in ctx.describe_agent_states() {
+        record_handshake_state(state);
+    }
+    record_message_types(
+        ctx.sent_message_types()
+            .into_iter()
+            .map(|(_, message_type)| message_type),
+    );
+
+    if let Err(err) = result {
+        count_error(&err);
         log::trace!("{}", err);
     }
 
     ExitKind::Ok
 }
 
+/// Increments the stats counter of the bucket of `err`. Aborts on security violations, such that
+/// they are recorded as crashes.
+fn count_error(err: &Error) {
+    if let Error::SecurityClaim(msg) = err.root() {
+        log::warn!("{}", msg);
+        std::process::abort()
+    }
+
+    if let Some(counter) = error_counter(err) {
+        counter.increment();
+    }
+}
+
+/// The stats counter which records that the execution in `ctx` completed the handshake. A trace
+/// may fail after the handshake, e.g. in a renegotiation, therefore this is independent of its
+/// error.
+#[must_use]
+pub fn completion_counter<PB: ProtocolBehavior>(
+    ctx: &TraceContext<PB>,
+) -> Option<&'static Counter> {
+    ctx.is_handshake_complete().then_some(&HANDSHAKE_COMPLETE)
+}
+
+/// The stats counter of the bucket of `err`, disregarding the step in which it occurred
+fn error_counter(err: &Error) -> Option<&'static Counter> {
+    match err.root() {
+        Error::Fn(_) => Some(&FN_ERROR),
+        Error::Term(_e) => Some(&TERM),
+        Error::Put(_) => Some(&PUT),
+        Error::Codec(_) => Some(&CODEC),
+        Error::IO(_) => Some(&IO),
+        Error::Agent(_) => Some(&AGENT),
+        Error::Stream(_) => Some(&STREAM),
+        Error::Timeout(_) => Some(&TIMEOUT),
+        Error::Extraction() => Some(&EXTRACTION),
+        // Mutations change the messages which are exchanged, therefore a mutated trace which
+        // diverges from its expected flow is no error of the execution
+        Error::Flow(_) => None,
+        Error::SecurityClaim(_) => None,
+        Error::Context { .. } => unreachable!("the root of an error has no context"),
+    }
+}
+
 #[allow(unused)]
 #[must_use]
 pub fn dummy_harness<PB: ProtocolBehavior + 'static>(
@@ -64,3 +115,55 @@ pub fn dummy_harness<PB: ProtocolBehavior + 'static>(
     }
     ExitKind::Ok // Everything other than Ok is recorded in the crash corpus
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{io, ptr};
+
+    use super::*;
+    use crate::agent::AgentName;
+
+    fn is_counted_by(err: &Error, counter: &'static Counter) -> bool {
+        error_counter(err).is_some_and(|bucket| ptr::eq(bucket, counter))
+    }
+
+    #[test_log::test]
+    fn test_count_timeout() {
+        let err = Error::from(io::Error::new(
+            io::ErrorKind::TimedOut,
+            "step took too long",
+        ));
+        assert!(matches!(err, Error::Timeout(_)));
+        assert!(is_counted_by(&err, &TIMEOUT));
+
+        let err = Error::from(io::Error::new(io::ErrorKind::BrokenPipe, "closed"));
+        assert!(matches!(err, Error::IO(_)));
+        assert!(is_counted_by(&err, &IO));
+    }
+
+    #[test_log::test]
+    fn test_count_step_timeout() {
+        // as produced by a step which exceeded `TraceContext::limit_step_duration`
+        let err = Error::Context {
+            step: 1,
+            agent: AgentName::first(),
+            function: None,
+            source: Box::new(Error::Timeout("step took 2s, longer than 1s".to_string())),
+        };
+
+        assert!(is_counted_by(&err, &TIMEOUT));
+    }
+
+    #[test_log::test]
+    fn test_count_error_in_step() {
+        let err = Error::Context {
+            step: 2,
+            agent: AgentName::first(),
+            function: None,
+            source: Box::new(Error::Extraction()),
+        };
+
+        assert!(is_counted_by(&err, &EXTRACTION));
+        assert!(error_counter(&Error::Flow(String::new())).is_none());
+    }
+}
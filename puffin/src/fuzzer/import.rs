@@ -0,0 +1,234 @@
+//! Filtering an external corpus while importing it.
+//!
+//! Traces of an external corpus can be much larger than the traces the mutators produce, which
+//! slows down every execution of the campaign. Before the initial inputs are loaded, an
+//! [`ImportFilter`] rejects files whose trace exceeds the configured limits or which do not
+//! deserialize, e.g. because they reference function symbols unknown to the signature. Rejected
+//! files are logged and skipped.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use libafl::corpus::Corpus;
+use libafl::events::EventFirer;
+use libafl::inputs::Input;
+use libafl::prelude::{Evaluator, UsesState};
+use libafl::state::StdState;
+use libafl::Error;
+use libafl_bolts::rands::Rand;
+use libafl_bolts::HasLen;
+
+use super::eviction::HasTermSize;
+use crate::protocol::ProtocolTypes;
+use crate::trace::{Action, Trace};
+
+/// Inputs whose terms have a depth.
+pub trait HasTermDepth {
+    fn term_depth(&self) -> usize;
+}
+
+impl<PT: ProtocolTypes> HasTermDepth for Trace<PT> {
+    /// Depth of the deepest input recipe
+    fn term_depth(&self) -> usize {
+        self.steps
+            .iter()
+            .map(|step| match &step.action {
+                Action::Input(input) => input.recipe.depth(),
+                Action::Output(_) => 0,
+            })
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// Limits which the traces of an imported corpus have to respect. Traces right at a limit are
+/// accepted.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ImportFilter {
+    /// Maximum number of steps
+    pub max_steps: Option<usize>,
+    /// Maximum sum of the sizes of the input recipes
+    pub max_term_size: Option<usize>,
+    /// Maximum depth of an input recipe
+    pub max_term_depth: Option<usize>,
+}
+
+impl ImportFilter {
+    /// Checks `input` against the limits, and returns the reason for rejecting it otherwise
+    pub fn check<I>(&self, input: &I) -> Result<(), String>
+    where
+        I: HasLen + HasTermSize + HasTermDepth,
+    {
+        let limits = [
+            ("steps", input.len(), self.max_steps),
+            ("term size", input.term_size(), self.max_term_size),
+            ("term depth", input.term_depth(), self.max_term_depth),
+        ];
+
+        for (name, value, limit) in limits {
+            if let Some(limit) = limit {
+                if value > limit {
+                    return Err(format!("{name} {value} exceeds the limit of {limit}"));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Lists the files below `in_dirs` which hold an input that passes the filter
+    pub fn filter_files<I>(&self, in_dirs: &[PathBuf]) -> Result<Vec<PathBuf>, Error>
+    where
+        I: Input + HasLen + HasTermSize + HasTermDepth,
+    {
+        let mut files = Vec::new();
+        for dir in in_dirs {
+            collect_files(dir, &mut files)?;
+        }
+
+        files.retain(|path| {
+            let rejection = match I::from_file(path) {
+                Ok(input) => self.check(&input).err(),
+                Err(err) => Some(format!("failed to load it: {err}")),
+            };
+
+            if let Some(reason) = &rejection {
+                log::warn!("Skipping import of {path:?}: {reason}");
+            }
+
+            rejection.is_none()
+        });
+
+        Ok(files)
+    }
+}
+
+/// Collects the non-empty files below `path`, skipping hidden entries of directories like LibAFL
+/// does
+fn collect_files(path: &Path, files: &mut Vec<PathBuf>) -> Result<(), Error> {
+    let metadata = fs::metadata(path)?;
+    if metadata.is_dir() {
+        let mut entries = fs::read_dir(path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+        entries.sort();
+
+        for entry in entries {
+            let hidden = entry
+                .file_name()
+                .map_or(false, |name| name.to_string_lossy().starts_with('.'));
+            if !hidden {
+                collect_files(&entry, files)?;
+            }
+        }
+    } else if metadata.is_file() && metadata.len() > 0 {
+        files.push(path.to_path_buf());
+    }
+
+    Ok(())
+}
+
+/// Loads the initial inputs below `in_dirs` which pass `filter` into `state`, like
+/// [`StdState::load_initial_inputs`]
+pub fn load_initial_inputs<I, C, R, SC, E, EM, Z>(
+    state: &mut StdState<I, C, R, SC>,
+    fuzzer: &mut Z,
+    executor: &mut E,
+    manager: &mut EM,
+    in_dirs: &[PathBuf],
+    filter: &ImportFilter,
+) -> Result<(), Error>
+where
+    I: Input + HasLen + HasTermSize + HasTermDepth,
+    C: Corpus<Input = I>,
+    R: Rand,
+    SC: Corpus<Input = I>,
+    E: UsesState<State = StdState<I, C, R, SC>>,
+    EM: EventFirer<State = StdState<I, C, R, SC>>,
+    Z: Evaluator<E, EM, State = StdState<I, C, R, SC>>,
+{
+    let files = filter.filter_files::<I>(in_dirs)?;
+    state.load_initial_inputs_by_filenames(fuzzer, executor, manager, &files)
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl::corpus::InMemoryCorpus;
+    use libafl::events::NopEventManager;
+    use libafl::executors::{ExitKind, InProcessExecutor};
+    use libafl::feedbacks::ConstFeedback;
+    use libafl::fuzzer::StdFuzzer;
+    use libafl::schedulers::QueueScheduler;
+    use libafl::state::HasCorpus;
+    use libafl_bolts::rands::StdRand;
+
+    use super::*;
+    use crate::algebra::test_signature::{setup_simple_trace, TestTrace};
+
+    type TestState =
+        StdState<TestTrace, InMemoryCorpus<TestTrace>, StdRand, InMemoryCorpus<TestTrace>>;
+
+    #[test_log::test]
+    fn test_import_filter() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let trace = setup_simple_trace();
+        trace.to_file(dir.path().join("simple.trace")).unwrap();
+
+        let mut oversized = setup_simple_trace();
+        oversized.steps.extend(setup_simple_trace().steps);
+        oversized
+            .to_file(dir.path().join("oversized.trace"))
+            .unwrap();
+
+        fs::write(dir.path().join("unknown.trace"), b"{\"steps\": 42}").unwrap();
+
+        // The simple trace is right at the limits
+        let filter = ImportFilter {
+            max_steps: Some(trace.len()),
+            max_term_size: Some(trace.term_size()),
+            max_term_depth: Some(trace.term_depth()),
+        };
+        assert!(filter.check(&trace).is_ok());
+        assert!(filter.check(&oversized).is_err());
+
+        let files = filter
+            .filter_files::<TestTrace>(&[dir.path().to_path_buf()])
+            .unwrap();
+        assert_eq!(files, vec![dir.path().join("simple.trace")]);
+
+        let mut state: TestState = StdState::new(
+            StdRand::with_seed(1235),
+            InMemoryCorpus::new(),
+            InMemoryCorpus::new(),
+            &mut (),
+            &mut (),
+        )
+        .unwrap();
+        let mut fuzzer = StdFuzzer::new(
+            QueueScheduler::new(),
+            ConstFeedback::True,
+            ConstFeedback::False,
+        );
+        let mut manager = NopEventManager::new();
+        let mut harness = |_input: &TestTrace| ExitKind::Ok;
+        let mut executor =
+            InProcessExecutor::new(&mut harness, (), &mut fuzzer, &mut state, &mut manager)
+                .unwrap();
+
+        load_initial_inputs(
+            &mut state,
+            &mut fuzzer,
+            &mut executor,
+            &mut manager,
+            &[dir.path().to_path_buf()],
+            &filter,
+        )
+        .unwrap();
+
+        assert_eq!(state.corpus().count(), 1);
+        let id = state.corpus().first().unwrap();
+        let imported = state.corpus().cloned_input_for_id(id).unwrap();
+        assert_eq!(imported.len(), trace.len());
+    }
+}
@@ -0,0 +1,452 @@
+//! Annotation of corpus entries with the reasons why they were added.
+//!
+//! When a feedback finds an input interesting, it records a reason like "new edges" in the
+//! [`InterestingReasonsMetadata`] of the testcase which is added to the corpus. If several
+//! feedbacks fire for the same input, all of their reasons are recorded. The on-disk corpus writes
+//! the metadata to a sidecar file next to the trace and every reason is counted in the stats. This
+//! helps to understand what drives the growth of the corpus.
+
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashSet};
+
+use libafl::corpus::Testcase;
+use libafl::events::EventFirer;
+use libafl::executors::ExitKind;
+use libafl::feedbacks::Feedback;
+use libafl::inputs::{Input, UsesInput};
+use libafl::observers::{Observer, ObserversTuple};
+use libafl::state::{HasMetadata, HasNamedMetadata, State};
+use libafl::Error;
+use libafl_bolts::Named;
+use serde::{Deserialize, Serialize};
+
+use crate::fuzzer::stats_stage::{Counter, CORPUS_NEW_MESSAGE_TYPES};
+
+/// Reasons why the feedbacks added a testcase to the corpus
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InterestingReasonsMetadata {
+    pub reasons: Vec<String>,
+}
+
+libafl_bolts::impl_serdeany!(InterestingReasonsMetadata);
+
+impl InterestingReasonsMetadata {
+    /// Appends `reason` to the reasons already recorded for `testcase`
+    pub fn record<I: Input>(testcase: &mut Testcase<I>, reason: String) {
+        match testcase.metadata_map_mut().get_mut::<Self>() {
+            Some(metadata) => metadata.reasons.push(reason),
+            None => testcase.add_metadata(Self {
+                reasons: vec![reason],
+            }),
+        }
+    }
+}
+
+/// Wraps a [`Feedback`] such that the testcases it finds interesting are annotated with `reason`
+/// and counted in `counter`.
+#[derive(Debug)]
+pub struct ReasonFeedback<F> {
+    feedback: F,
+    reason: &'static str,
+    counter: &'static Counter,
+    interesting: bool,
+}
+
+impl<F> ReasonFeedback<F> {
+    pub fn new(feedback: F, reason: &'static str, counter: &'static Counter) -> Self {
+        Self {
+            feedback,
+            reason,
+            counter,
+            interesting: false,
+        }
+    }
+}
+
+impl<F: Named> Named for ReasonFeedback<F> {
+    fn name(&self) -> &str {
+        self.feedback.name()
+    }
+}
+
+impl<S, F> Feedback<S> for ReasonFeedback<F>
+where
+    S: State,
+    F: Feedback<S>,
+{
+    fn init_state(&mut self, state: &mut S) -> Result<(), Error> {
+        self.feedback.init_state(state)
+    }
+
+    fn is_interesting<EM, OT>(
+        &mut self,
+        state: &mut S,
+        manager: &mut EM,
+        input: &S::Input,
+        observers: &OT,
+        exit_kind: &ExitKind,
+    ) -> Result<bool, Error>
+    where
+        EM: EventFirer<State = S>,
+        OT: ObserversTuple<S>,
+    {
+        self.interesting = self
+            .feedback
+            .is_interesting(state, manager, input, observers, exit_kind)?;
+        Ok(self.interesting)
+    }
+
+    fn append_metadata<OT>(
+        &mut self,
+        state: &mut S,
+        observers: &OT,
+        testcase: &mut Testcase<S::Input>,
+    ) -> Result<(), Error>
+    where
+        OT: ObserversTuple<S>,
+    {
+        self.feedback.append_metadata(state, observers, testcase)?;
+
+        if std::mem::take(&mut self.interesting) {
+            self.counter.increment();
+            InterestingReasonsMetadata::record(testcase, self.reason.to_owned());
+        }
+
+        Ok(())
+    }
+
+    fn discard_metadata(&mut self, state: &mut S, input: &S::Input) -> Result<(), Error> {
+        self.interesting = false;
+        self.feedback.discard_metadata(state, input)
+    }
+}
+
+pub const MESSAGE_TYPES_OBSERVER_NAME: &str = "message_types_observer";
+
+thread_local! {
+    /// Types of the messages sent during the execution in progress, `None` if nothing is observed
+    static MESSAGE_TYPES: RefCell<Option<Vec<String>>> = const { RefCell::new(None) };
+}
+
+/// Records the types of the messages which the agents sent during an execution, in the order they
+/// were sent. Does nothing if the execution is not observed.
+pub fn record_message_types<'a>(message_types: impl IntoIterator<Item = &'a str>) {
+    MESSAGE_TYPES.with(|observed| {
+        if let Some(observed) = observed.borrow_mut().as_mut() {
+            observed.extend(message_types.into_iter().map(str::to_owned));
+        }
+    });
+}
+
+/// An [`Observer`] which collects the message types passed to [`record_message_types`] during an
+/// execution
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageTypesObserver {
+    name: String,
+    message_types: Vec<String>,
+}
+
+impl MessageTypesObserver {
+    #[must_use]
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            message_types: vec![],
+        }
+    }
+
+    /// Types of the messages sent in the last execution
+    #[must_use]
+    pub fn message_types(&self) -> &[String] {
+        &self.message_types
+    }
+}
+
+impl Named for MessageTypesObserver {
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl<S: UsesInput> Observer<S> for MessageTypesObserver {
+    fn pre_exec(&mut self, _state: &mut S, _input: &S::Input) -> Result<(), Error> {
+        self.message_types.clear();
+        MESSAGE_TYPES.with(|observed| observed.replace(Some(vec![])));
+        Ok(())
+    }
+
+    fn post_exec(
+        &mut self,
+        _state: &mut S,
+        _input: &S::Input,
+        _exit_kind: &ExitKind,
+    ) -> Result<(), Error> {
+        self.message_types = MESSAGE_TYPES
+            .with(|observed| observed.take())
+            .unwrap_or_default();
+        Ok(())
+    }
+}
+
+/// Pairs of consecutive message types which the testcases of the corpus contain, kept in the
+/// fuzzer state such that they survive restarts of the client
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MessageTypesMetadata {
+    pub seen: HashSet<(String, String)>,
+}
+
+libafl_bolts::impl_serdeany!(MessageTypesMetadata);
+
+/// A [`Feedback`] which records in the [`InterestingReasonsMetadata`] of a testcase when two
+/// consecutive messages sent by the agents form a pair of message types that no testcase of the
+/// corpus contains so far.
+///
+/// It never finds an input interesting on its own, such that the corpus grows as with the baseline
+/// feedbacks and the pairs only annotate the testcases which they add.
+///
+/// The types are named by
+/// [`ProtocolBehavior::message_type_name`](crate::protocol::ProtocolBehavior::message_type_name)
+/// and observed by the [`MessageTypesObserver`].
+#[derive(Debug)]
+pub struct MessageTypesFeedback {
+    observer_name: String,
+    new_pairs: BTreeSet<(String, String)>,
+}
+
+impl MessageTypesFeedback {
+    #[must_use]
+    pub fn new(observer: &MessageTypesObserver) -> Self {
+        Self {
+            observer_name: observer.name().to_owned(),
+            new_pairs: BTreeSet::new(),
+        }
+    }
+}
+
+impl Named for MessageTypesFeedback {
+    fn name(&self) -> &str {
+        "MessageTypesFeedback"
+    }
+}
+
+impl<S> Feedback<S> for MessageTypesFeedback
+where
+    S: State + HasNamedMetadata,
+{
+    fn init_state(&mut self, state: &mut S) -> Result<(), Error> {
+        state.add_named_metadata(MessageTypesMetadata::default(), self.name());
+        Ok(())
+    }
+
+    fn is_interesting<EM, OT>(
+        &mut self,
+        state: &mut S,
+        _manager: &mut EM,
+        _input: &S::Input,
+        observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error>
+    where
+        EM: EventFirer<State = S>,
+        OT: ObserversTuple<S>,
+    {
+        let observer = observers
+            .match_name::<MessageTypesObserver>(&self.observer_name)
+            .ok_or_else(|| Error::key_not_found("MessageTypesObserver not found".to_string()))?;
+        let seen = &state
+            .named_metadata::<MessageTypesMetadata>(self.name())?
+            .seen;
+
+        self.new_pairs = observer
+            .message_types()
+            .windows(2)
+            .map(|pair| (pair[0].clone(), pair[1].clone()))
+            .filter(|pair| !seen.contains(pair))
+            .collect();
+
+        Ok(false)
+    }
+
+    fn append_metadata<OT>(
+        &mut self,
+        state: &mut S,
+        _observers: &OT,
+        testcase: &mut Testcase<<S as UsesInput>::Input>,
+    ) -> Result<(), Error>
+    where
+        OT: ObserversTuple<S>,
+    {
+        if self.new_pairs.is_empty() {
+            return Ok(());
+        }
+
+        CORPUS_NEW_MESSAGE_TYPES.increment();
+        let seen = &mut state
+            .named_metadata_mut::<MessageTypesMetadata>(self.name())?
+            .seen;
+        for (first, second) in std::mem::take(&mut self.new_pairs) {
+            InterestingReasonsMetadata::record(
+                testcase,
+                format!("new message-type pair {first} -> {second}"),
+            );
+            seen.insert((first, second));
+        }
+
+        Ok(())
+    }
+
+    fn discard_metadata(&mut self, _state: &mut S, _input: &S::Input) -> Result<(), Error> {
+        self.new_pairs.clear();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl::corpus::InMemoryCorpus;
+    use libafl::events::NopEventManager;
+    use libafl::feedback_or;
+    use libafl::feedbacks::ConstFeedback;
+    use libafl::state::StdState;
+    use libafl_bolts::rands::StdRand;
+    use libafl_bolts::tuples::tuple_list;
+
+    use super::*;
+    use crate::algebra::test_signature::{setup_simple_trace, TestTrace};
+    use crate::fuzzer::stats_stage::CORPUS_NEW_EDGES;
+
+    type TestState =
+        StdState<TestTrace, InMemoryCorpus<TestTrace>, StdRand, InMemoryCorpus<TestTrace>>;
+
+    fn state<F: Feedback<TestState>>(feedback: &mut F) -> TestState {
+        StdState::new(
+            StdRand::with_seed(1235),
+            InMemoryCorpus::new(),
+            InMemoryCorpus::new(),
+            feedback,
+            &mut (),
+        )
+        .unwrap()
+    }
+
+    fn observer() -> MessageTypesObserver {
+        MessageTypesObserver::new(MESSAGE_TYPES_OBSERVER_NAME)
+    }
+
+    /// Runs the feedback on an execution in which the agents sent messages of `message_types`.
+    /// Returns whether the feedback finds it interesting and, if `added` to the corpus regardless,
+    /// the reasons which are recorded.
+    fn execute<F: Feedback<TestState>>(
+        feedback: &mut F,
+        state: &mut TestState,
+        message_types: &[&str],
+        added: bool,
+    ) -> (bool, Vec<String>) {
+        let mut manager = NopEventManager::new();
+        let trace = setup_simple_trace();
+
+        let mut observers = tuple_list!(observer());
+        observers.pre_exec_all(state, &trace).unwrap();
+        record_message_types(message_types.iter().copied());
+        observers
+            .post_exec_all(state, &trace, &ExitKind::Ok)
+            .unwrap();
+
+        let interesting = feedback
+            .is_interesting(state, &mut manager, &trace, &observers, &ExitKind::Ok)
+            .unwrap();
+        if !added {
+            feedback.discard_metadata(state, &trace).unwrap();
+            return (interesting, vec![]);
+        }
+
+        let mut testcase = Testcase::new(trace);
+        feedback
+            .append_metadata(state, &observers, &mut testcase)
+            .unwrap();
+
+        let reasons = testcase
+            .metadata::<InterestingReasonsMetadata>()
+            .map(|metadata| metadata.reasons.clone())
+            .unwrap_or_default();
+        (interesting, reasons)
+    }
+
+    #[test_log::test]
+    fn test_new_message_types_reason() {
+        let mut feedback = MessageTypesFeedback::new(&observer());
+        let mut state = state(&mut feedback);
+        let sent = ["ServerHello", "Certificate", "ServerHelloDone"];
+
+        // the pairs of an execution which is not added to the corpus stay unknown
+        assert_eq!(
+            execute(&mut feedback, &mut state, &sent, false),
+            (false, vec![])
+        );
+
+        let (interesting, recorded) = execute(&mut feedback, &mut state, &sent, true);
+        assert!(!interesting);
+        assert_eq!(
+            recorded,
+            [
+                "new message-type pair Certificate -> ServerHelloDone",
+                "new message-type pair ServerHello -> Certificate",
+            ]
+        );
+
+        // the pairs are known now that the execution is part of the corpus
+        assert_eq!(
+            execute(&mut feedback, &mut state, &sent, true),
+            (false, vec![])
+        );
+
+        // the pairs are kept in the state, which is restored when a client restarts
+        let mut restarted = MessageTypesFeedback::new(&observer());
+        assert_eq!(
+            execute(&mut restarted, &mut state, &sent, true),
+            (false, vec![])
+        );
+
+        // a single message forms no pair
+        assert_eq!(
+            execute(&mut feedback, &mut state, &["Alert"], true),
+            (false, vec![])
+        );
+    }
+
+    #[test_log::test]
+    fn test_message_types_do_not_add_to_corpus() {
+        let mut feedback = feedback_or!(
+            ReasonFeedback::new(ConstFeedback::False, "new edges", &CORPUS_NEW_EDGES),
+            MessageTypesFeedback::new(&observer())
+        );
+
+        let mut state = state(&mut feedback);
+        let (interesting, _) = execute(
+            &mut feedback,
+            &mut state,
+            &["ClientHello", "ClientKeyExchange", "Finished"],
+            false,
+        );
+        assert!(!interesting);
+    }
+
+    #[test_log::test]
+    fn test_all_reasons_recorded() {
+        let mut feedback = feedback_or!(
+            ReasonFeedback::new(ConstFeedback::True, "new edges", &CORPUS_NEW_EDGES),
+            MessageTypesFeedback::new(&observer())
+        );
+
+        let mut state = state(&mut feedback);
+        let (interesting, recorded) = execute(
+            &mut feedback,
+            &mut state,
+            &["ClientHello", "ClientKeyExchange", "Finished"],
+            true,
+        );
+        assert!(interesting);
+        assert_eq!(recorded.len(), 3);
+        assert_eq!(recorded[0], "new edges");
+    }
+}
@@ -1,26 +1,51 @@
 use core::time::Duration;
 use std::fmt;
 use std::path::PathBuf;
+use std::time::Instant;
 
 use libafl::corpus::ondisk::OnDiskMetadataFormat;
 use libafl::prelude::*;
 use libafl_bolts::prelude::*;
 use log4rs::Handle;
 
+use super::decryption::{DecryptionObserver, DecryptionPatternFeedback, DECRYPTION_OBSERVER_NAME};
+use super::dedup::{DedupMutationalStage, HasStructuralHash};
+use super::eviction::{EvictionStage, HasTermSize};
+use super::handshake_state::{
+    HandshakeStateFeedback, HandshakeStateObserver, HANDSHAKE_STATE_OBSERVER_NAME,
+};
 use super::harness;
+use super::import::{load_initial_inputs, HasTermDepth, ImportFilter};
+use super::interesting_reason::{
+    MessageTypesFeedback, MessageTypesObserver, ReasonFeedback, MESSAGE_TYPES_OBSERVER_NAME,
+};
+use super::objective_stop::StopOnObjectiveStage;
+use super::objective_symbols::ObjectiveSymbolsFeedback;
+use super::snapshot::{client_snapshot_path, CampaignSnapshot, SnapshotStage};
+use super::time_limit::TimeLimitStage;
 use crate::fuzzer::mutations::{trace_mutations, MutationConfig};
 use crate::fuzzer::stats_monitor::StatsMonitor;
+use crate::fuzzer::stats_stage::{CORPUS_NEW_EDGES, OBJECTIVE_CRASH_SITES};
 use crate::log::{config_fuzzing, config_fuzzing_client};
 use crate::protocol::{ProtocolBehavior, ProtocolTypes};
 use crate::put::PutDescriptor;
 use crate::put_registry::PutRegistry;
-use crate::trace::Trace;
+use crate::trace::{Spawner, Trace, TraceContext};
 
 pub const MAP_FEEDBACK_NAME: &str = "edges";
 const EDGES_OBSERVER_NAME: &str = "edges_observer";
+const BACKTRACE_OBSERVER_NAME: &str = "backtrace_observer";
+const CRASH_BACKTRACES_FEEDBACK_NAME: &str = "crash_backtraces";
 
 type ConcreteExecutor<'harness, H, OT, S> = TimeoutExecutor<InProcessExecutor<'harness, H, OT, S>>;
 
+/// Duration of an execution after which the executor aborts it
+const EXECUTION_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Duration of a step after which the execution fails with a timeout error, well below the
+/// [`EXECUTION_TIMEOUT`], such that slow steps are counted apart from aborted executions
+const STEP_TIMEOUT: Duration = Duration::from_secs(1);
+
 type ConcreteState<C, R, SC, I> = StdState<I, C, R, SC>;
 
 #[derive(Clone, Debug)]
@@ -28,6 +53,8 @@ pub struct FuzzerConfig {
     pub initial_corpus_dir: PathBuf,
     pub static_seed: Option<u64>,
     pub max_iters: Option<u64>,
+    /// Wall-clock time after which the campaign stops, regardless of the iterations
+    pub max_time: Option<Duration>,
     pub core_definition: String,
     pub stats_file: PathBuf,
     pub corpus_dir: PathBuf,
@@ -39,6 +66,20 @@ pub struct FuzzerConfig {
     pub tui: bool,
     pub no_launcher: bool,
     pub log_file: PathBuf,
+    /// Base path of the campaign snapshots, each client writes to its own file next to it
+    pub snapshot_file: PathBuf,
+    pub snapshot_interval: Duration,
+    /// Whether to restore the campaign from the last snapshot
+    pub resume: bool,
+    /// Number of corpus entries above which the least valuable entries are evicted
+    pub max_corpus_size: Option<usize>,
+    /// Whether the clients stop once the first objective was found
+    pub stop_on_first_objective: bool,
+    /// Limits for the traces of the initial corpus, exceeding traces are skipped
+    pub import_filter: ImportFilter,
+    /// Whether inputs whose records decrypt in a new pattern are interesting, see
+    /// [`DecryptionPatternFeedback`]
+    pub decryption_patterns: bool,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -77,15 +118,16 @@ where
     objective: Option<OF>,
     initial_inputs: Option<Vec<(I, &'static str)>>,
     mutations: Option<MT>,
+    deadline: Option<Instant>,
 }
 
 impl<'harness, H, C, R, SC, EM, F, OF, OT, CS, MT, I>
     RunClientBuilder<'harness, H, C, R, SC, EM, F, OF, OT, CS, MT, I>
 where
     ConcreteState<C, R, SC, I>: UsesInput<Input = I>,
-    I: Input + HasLen,
+    I: Input + HasLen + HasTermSize + HasTermDepth + HasStructuralHash,
     C: Corpus + UsesInput<Input = I>,
-    R: Rand,
+    R: Rand + Clone,
     SC: Corpus + UsesInput<Input = I>,
     H: FnMut(&I) -> ExitKind,
     CS: Scheduler + UsesState<State = ConcreteState<C, R, SC, I>>,
@@ -122,6 +164,7 @@ where
             objective: None,
             initial_inputs: None,
             mutations: None,
+            deadline: None,
         }
     }
 
@@ -170,10 +213,17 @@ where
         self
     }
 
-    fn run_client(mut self) -> Result<(), Error> {
+    fn with_deadline(mut self, deadline: Option<Instant>) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    fn run_client(mut self, client_id: usize) -> Result<(), Error> {
         let mut feedback = self.feedback.unwrap();
         let mut objective = self.objective.unwrap();
 
+        let is_restarting = self.existing_state.is_some();
+
         // If not restarting, create a State from scratch
         let mut state = self.existing_state.unwrap_or_else(|| {
             StdState::new(
@@ -186,9 +236,24 @@ where
             .unwrap()
         });
 
+        // The counters of a client are lost when it crashes, while the crash sites are kept in the
+        // state
+        if let Ok(crash_sites) =
+            state.named_metadata::<NewHashFeedbackMetadata>(CRASH_BACKTRACES_FEEDBACK_NAME)
+        {
+            OBJECTIVE_CRASH_SITES.set(crash_sites.hash_set.len());
+        }
+
         let FuzzerConfig {
             initial_corpus_dir,
             max_iters,
+            objective_dir,
+            snapshot_file,
+            snapshot_interval,
+            resume,
+            max_corpus_size,
+            stop_on_first_objective,
+            import_filter,
             mutation_stage_config:
                 MutationStageConfig {
                     max_iterations_per_stage: _,
@@ -197,13 +262,21 @@ where
             ..
         } = self.config;
 
+        let snapshot_path = client_snapshot_path(&snapshot_file, client_id);
+
         // FIXME let mutator = PuffinScheduledMutator::new(self.mutations.unwrap(),
         // max_mutations_per_iteration);
         let mutator = StdScheduledMutator::new(self.mutations.unwrap());
         let mut stages = tuple_list!(
+            // Comes first, such that a client which restarted after a crash stops right away
+            StopOnObjectiveStage::new(stop_on_first_objective.then_some(objective_dir)),
             // FIXMEPuffinMutationalStage::new(mutator, max_iterations_per_stage),
-            StdMutationalStage::new(mutator),
+            DedupMutationalStage::new(mutator),
             // FIXME StatsStage::new()
+            EvictionStage::new(max_corpus_size),
+            SnapshotStage::new(snapshot_path.clone(), snapshot_interval),
+            // Comes last, such that the client stops after a complete iteration
+            TimeLimitStage::new(self.deadline),
         );
 
         let mut fuzzer: StdFuzzer<CS, F, OF, OT> =
@@ -219,25 +292,40 @@ where
                 &mut state,
                 &mut self.event_manager,
             )?,
-            Duration::new(5, 0),
+            EXECUTION_TIMEOUT,
         );
 
+        // The restarting manager already handed us the latest state, only resume on a fresh start
+        if resume && !is_restarting {
+            if let Some(snapshot) = CampaignSnapshot::load(&snapshot_path) {
+                for id in snapshot.restore(&mut state)? {
+                    fuzzer.scheduler_mut().on_add(&mut state, id)?;
+                }
+                log::info!(
+                    "Resumed {} inputs from snapshot {:?}.",
+                    state.corpus().count(),
+                    &snapshot_path
+                );
+            }
+        }
+
         // In case the corpus is empty (on first run), reset
         if state.corpus().is_empty() {
             if initial_corpus_dir.exists() {
-                state
-                    .load_initial_inputs(
-                        &mut fuzzer,
-                        &mut executor,
-                        &mut self.event_manager,
-                        &[initial_corpus_dir.clone()],
+                load_initial_inputs(
+                    &mut state,
+                    &mut fuzzer,
+                    &mut executor,
+                    &mut self.event_manager,
+                    &[initial_corpus_dir.clone()],
+                    &import_filter,
+                )
+                .unwrap_or_else(|err| {
+                    panic!(
+                        "Failed to load initial corpus at {:?}: {}",
+                        &initial_corpus_dir, err
                     )
-                    .unwrap_or_else(|err| {
-                        panic!(
-                            "Failed to load initial corpus at {:?}: {}",
-                            &initial_corpus_dir, err
-                        )
-                    });
+                });
                 log::info!("Imported {} inputs from disk.", state.corpus().count());
             } else {
                 log::info!("Initial seed corpus not found. Using embedded seeds.");
@@ -275,18 +363,39 @@ type ConcreteMinimizer<S> = IndexesLenTimeMinimizerScheduler<QueueScheduler<S>>;
 
 type ConcreteObservers<'a> = (
     HitcountsMapObserver<StdMapObserver<'a, u8, false>>,
-    (TimeObserver, ()),
+    (
+        TimeObserver,
+        (
+            MessageTypesObserver,
+            (
+                DecryptionObserver,
+                (HandshakeStateObserver, (BacktraceObserver<'a>, ())),
+            ),
+        ),
+    ),
 );
 
 type ConcreteFeedback<'a, S> = CombinedFeedback<
-    MapFeedback<
-        DifferentIsNovel,
-        HitcountsMapObserver<StdMapObserver<'a, u8, false>>,
-        MaxReducer,
+    ReasonFeedback<
+        MapFeedback<
+            DifferentIsNovel,
+            HitcountsMapObserver<StdMapObserver<'a, u8, false>>,
+            MaxReducer,
+            S,
+            u8,
+        >,
+    >,
+    CombinedFeedback<
+        DecryptionPatternFeedback,
+        CombinedFeedback<
+            MessageTypesFeedback,
+            CombinedFeedback<HandshakeStateFeedback, TimeFeedback, LogicEagerOr, S>,
+            LogicEagerOr,
+            S,
+        >,
+        LogicEagerOr,
         S,
-        u8,
     >,
-    TimeFeedback,
     LogicEagerOr,
     S,
 >;
@@ -308,7 +417,7 @@ impl<'harness, 'a, H, SC, C, R, EM, OF, CS, MT, I>
     >
 where
     ConcreteState<C, R, SC, I>: UsesInput<Input = I>,
-    I: Input + HasLen,
+    I: Input + HasLen + HasTermSize + HasStructuralHash,
     C: Corpus + UsesInput<Input = I> + fmt::Debug,
     R: Rand,
     SC: Corpus + UsesInput<Input = I> + fmt::Debug,
@@ -330,8 +439,11 @@ where
     MT: MutatorsTuple<I, ConcreteState<C, R, SC, I>>,
     <EM as UsesState>::State: HasClientPerfMonitor + HasMetadata + HasExecutions,
 {
+    /// Creates the feedback and the observers of the client. The crash handler of the client
+    /// writes the hash of the backtrace of a crash to `backtrace_hash`.
     fn create_feedback_observers(
         &self,
+        backtrace_hash: &'a mut Option<u64>,
     ) -> (
         ConcreteFeedback<'a, ConcreteState<C, R, SC, I>>,
         ConcreteObservers<'a>,
@@ -360,23 +472,113 @@ where
 
         {
             let time_observer = TimeObserver::new("time");
+            let message_types_observer = MessageTypesObserver::new(MESSAGE_TYPES_OBSERVER_NAME);
+            let decryption_observer = if self.config.decryption_patterns {
+                DecryptionObserver::new(DECRYPTION_OBSERVER_NAME)
+            } else {
+                DecryptionObserver::disabled(DECRYPTION_OBSERVER_NAME)
+            };
+            let handshake_state_observer =
+                HandshakeStateObserver::new(HANDSHAKE_STATE_OBSERVER_NAME);
             let edges_observer =
                 HitcountsMapObserver::new(unsafe { StdMapObserver::new(EDGES_OBSERVER_NAME, map) });
             let feedback = feedback_or!(
                 // New maximization map feedback linked to the edges observer and the feedback
                 // state `track_indexes` needed because of
                 // IndexesLenTimeMinimizerCorpusScheduler
-                map_feedback,
+                ReasonFeedback::new(map_feedback, "new edges", &CORPUS_NEW_EDGES),
+                // Records whose decryption outcomes form a new pattern, never interesting unless
+                // enabled by `FuzzerConfig::decryption_patterns`
+                DecryptionPatternFeedback::new(&decryption_observer),
+                // Never interesting, records new pairs of consecutive message types sent by the
+                // agents
+                MessageTypesFeedback::new(&message_types_observer),
+                // Never interesting, records new states in which an agent ended the execution
+                HandshakeStateFeedback::new(&handshake_state_observer),
                 // Time feedback, this one does not need a feedback state
                 // needed for IndexesLenTimeMinimizerCorpusScheduler
                 TimeFeedback::with_observer(&time_observer)
             );
-            let observers = tuple_list!(edges_observer, time_observer);
+            // The hash of a crash is captured by the crash handler in the client, which is forked
+            // by the restarting manager and therefore shares the address layout of its siblings.
+            // The backtrace is not resolved, such that the crash path does not symbolize.
+            let backtrace_observer = BacktraceObserver::new(
+                BACKTRACE_OBSERVER_NAME,
+                backtrace_hash,
+                HarnessType::InProcess,
+            );
+            let observers = tuple_list!(
+                edges_observer,
+                time_observer,
+                message_types_observer,
+                decryption_observer,
+                handshake_state_observer,
+                backtrace_observer
+            );
             (feedback, observers)
         }
     }
 }
 
+type CrashBacktraceFeedback<'a, S> = CombinedFeedback<
+    MissingBacktraceFeedback,
+    ReasonFeedback<NewHashFeedback<BacktraceObserver<'a>, S>>,
+    LogicFastOr,
+    S,
+>;
+
+/// A [`Feedback`] which finds a crash interesting if the hash of its backtrace, as observed by the
+/// [`BacktraceObserver`], is new. The known hashes are kept in the state, such that they survive
+/// the restart of a client after a crash.
+///
+/// Crashes without a backtrace can not be told apart and are therefore always interesting.
+fn crash_backtrace_feedback<'a, S>() -> CrashBacktraceFeedback<'a, S>
+where
+    S: State + HasNamedMetadata,
+{
+    feedback_or_fast!(
+        MissingBacktraceFeedback,
+        ReasonFeedback::new(
+            NewHashFeedback::with_names(CRASH_BACKTRACES_FEEDBACK_NAME, BACKTRACE_OBSERVER_NAME),
+            "new crash site",
+            &OBJECTIVE_CRASH_SITES
+        )
+    )
+}
+
+/// A [`Feedback`] which finds a crash interesting if the [`BacktraceObserver`] has no backtrace for
+/// it, e.g. if the crash handler did not run the observer or the stack could not be walked
+#[derive(Debug)]
+struct MissingBacktraceFeedback;
+
+impl Named for MissingBacktraceFeedback {
+    fn name(&self) -> &str {
+        "MissingBacktraceFeedback"
+    }
+}
+
+impl<S: State> Feedback<S> for MissingBacktraceFeedback {
+    fn is_interesting<EM, OT>(
+        &mut self,
+        _state: &mut S,
+        _manager: &mut EM,
+        _input: &S::Input,
+        observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error>
+    where
+        EM: EventFirer<State = S>,
+        OT: ObserversTuple<S>,
+    {
+        let observer = observers
+            .match_name::<BacktraceObserver>(BACKTRACE_OBSERVER_NAME)
+            .ok_or_else(|| Error::key_not_found("BacktraceObserver not found".to_string()))?;
+
+        // `collect_backtrace` hashes an empty backtrace to 0
+        Ok(matches!(observer.hash(), None | Some(0)))
+    }
+}
+
 /// Starts the fuzzing loop
 pub fn start<PB>(
     put_registry: &PutRegistry<PB>,
@@ -397,15 +599,8 @@ where
         broker_port,
         tui,
         no_launcher,
-        mutation_config:
-            MutationConfig {
-                fresh_zoo_after,
-                max_trace_length,
-                min_trace_length,
-                term_constraints,
-                with_bit_level,
-                with_dy,
-            },
+        max_time,
+        mutation_config,
         ..
     } = &config;
 
@@ -413,29 +608,33 @@ where
     log::info!("Config: {:?}\n\nlog_handle: {:?}", &config, &log_handle);
     log_handle.set_config(config_fuzzing(log_file));
 
+    // Fixed before the clients are launched, such that restarted clients share the budget
+    let deadline = max_time.map(|max_time| Instant::now() + max_time);
+
     let mut run_client = |state: Option<StdState<Trace<PB::ProtocolTypes>, _, _, _>>,
                           event_manager: LlmpRestartingEventManager<_, StdShMemProvider>,
-                          _core_id: CoreId|
+                          core_id: CoreId|
      -> Result<(), Error> {
         log_handle
             .clone()
             .set_config(config_fuzzing_client(log_file));
 
-        let harness_fn = &mut (|input: &_| harness::harness::<PB>(put_registry, input));
+        // The context is kept across executions to soft reset the agents
+        let mut ctx = TraceContext::new(Spawner::new(put_registry.clone()));
+        ctx.limit_step_duration(STEP_TIMEOUT);
+        // Written by the crash handler of this client, see `BacktraceObserver`
+        let mut backtrace_hash = None;
+        let harness_fn = &mut (|input: &_| harness::harness::<PB>(put_registry, &mut ctx, input));
 
         let mut builder = RunClientBuilder::new(config.clone(), harness_fn, state, event_manager);
         builder = builder
             .with_mutations(trace_mutations::<_, _, PB>(
-                *min_trace_length,
-                *max_trace_length,
-                *term_constraints,
-                *fresh_zoo_after,
-                *with_bit_level,
-                *with_dy,
+                *mutation_config,
                 <PB::ProtocolTypes as ProtocolTypes>::signature(),
                 put_registry,
             ))
             .with_initial_inputs(PB::create_corpus(put.clone()))
+            .with_deadline(deadline)
             .with_rand(StdRand::new())
             .with_corpus(
                 //InMemoryCorpus::new(),
@@ -454,10 +653,15 @@ where
                 )
                 .unwrap(),
             )
-            .with_objective(feedback_or_fast!(
-                // don't execute second if first is conclusive, mimicking https://github.com/AFLplusplus/LibAFL/blob/8445ae54b34a6cea48ae243d40bb1b1b94493898/libafl_sugar/src/inmemory.rs#L164
-                CrashFeedback::new(),
-                TimeoutFeedback::new()
+            .with_objective(feedback_or!(
+                feedback_or_fast!(
+                    // don't execute second if first is conclusive, mimicking https://github.com/AFLplusplus/LibAFL/blob/8445ae54b34a6cea48ae243d40bb1b1b94493898/libafl_sugar/src/inmemory.rs#L164
+                    // crashes with a known backtrace are duplicates, those without are kept
+                    feedback_and_fast!(CrashFeedback::new(), crash_backtrace_feedback()),
+                    TimeoutFeedback::new()
+                ),
+                // never interesting, records the function symbols of objectives for triage
+                ObjectiveSymbolsFeedback::<PB::ProtocolTypes>::new()
             ));
 
         //#[cfg(feature = "sancov")]
@@ -473,14 +677,14 @@ where
         {
             // FIXME
             log::error!("Running without minimizer is unsupported");
-            let (feedback, observer) = builder.create_feedback_observers();
+            let (feedback, observer) = builder.create_feedback_observers(&mut backtrace_hash);
             builder = builder
                 .with_feedback(feedback)
                 .with_observers(observer)
                 .with_scheduler(RandScheduler::new());
         } // TODO:EVAL investigate using QueueScheduler instead (see https://github.com/AFLplusplus/LibAFL/blob/8445ae54b34a6cea48ae243d40bb1b1b94493898/libafl_sugar/src/inmemory.rs#L190)
 
-        builder.run_client()
+        builder.run_client(core_id.0)
     };
 
     if *no_launcher {
@@ -538,3 +742,121 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algebra::test_signature::{setup_simple_trace, TestTrace};
+
+    type TestState =
+        StdState<TestTrace, InMemoryCorpus<TestTrace>, StdRand, InMemoryCorpus<TestTrace>>;
+
+    #[inline(never)]
+    fn crash_site_a(observer: &mut BacktraceObserver, state: &mut TestState) {
+        observer
+            .post_exec(state, &setup_simple_trace(), &ExitKind::Crash)
+            .unwrap();
+    }
+
+    #[inline(never)]
+    fn crash_site_b(observer: &mut BacktraceObserver, state: &mut TestState) {
+        observer
+            .post_exec(state, &setup_simple_trace(), &ExitKind::Crash)
+            .unwrap();
+    }
+
+    #[test_log::test]
+    fn test_crashes_deduplicated_by_backtrace() {
+        let mut feedback = crash_backtrace_feedback();
+        let mut state: TestState = StdState::new(
+            StdRand::with_seed(1235),
+            InMemoryCorpus::new(),
+            InMemoryCorpus::new(),
+            &mut feedback,
+            &mut (),
+        )
+        .unwrap();
+        let mut manager = NopEventManager::new();
+        let trace = setup_simple_trace();
+
+        let mut hash = None;
+        let mut observers = tuple_list!(BacktraceObserver::new(
+            BACKTRACE_OBSERVER_NAME,
+            &mut hash,
+            HarnessType::InProcess,
+        ));
+
+        let sites: [fn(&mut BacktraceObserver, &mut TestState); 3] =
+            [crash_site_a, crash_site_a, crash_site_b];
+        let interesting: Vec<bool> = sites
+            .iter()
+            .map(|site| {
+                site(&mut observers.0, &mut state);
+                feedback
+                    .is_interesting(
+                        &mut state,
+                        &mut manager,
+                        &trace,
+                        &observers,
+                        &ExitKind::Crash,
+                    )
+                    .unwrap()
+            })
+            .collect();
+
+        // the second crash at the first site is a duplicate
+        assert_eq!(interesting, [true, false, true]);
+        assert_eq!(
+            state
+                .named_metadata::<NewHashFeedbackMetadata>(CRASH_BACKTRACES_FEEDBACK_NAME)
+                .unwrap()
+                .hash_set
+                .len(),
+            2
+        );
+    }
+
+    #[test_log::test]
+    fn test_crashes_without_backtrace_interesting() {
+        let mut feedback = crash_backtrace_feedback();
+        let mut state: TestState = StdState::new(
+            StdRand::with_seed(1235),
+            InMemoryCorpus::new(),
+            InMemoryCorpus::new(),
+            &mut feedback,
+            &mut (),
+        )
+        .unwrap();
+        let mut manager = NopEventManager::new();
+        let trace = setup_simple_trace();
+
+        let mut hash = None;
+        let mut observers = tuple_list!(BacktraceObserver::new(
+            BACKTRACE_OBSERVER_NAME,
+            &mut hash,
+            HarnessType::External,
+        ));
+
+        // the crash handler did not run the observer, then the stack could not be walked
+        let hashes = [None, None, Some(0), Some(0)];
+        let interesting: Vec<bool> = hashes
+            .iter()
+            .map(|hash| {
+                if let Some(hash) = hash {
+                    observers.0.fill_external(*hash, &ExitKind::Crash);
+                }
+                feedback
+                    .is_interesting(
+                        &mut state,
+                        &mut manager,
+                        &trace,
+                        &observers,
+                        &ExitKind::Crash,
+                    )
+                    .unwrap()
+            })
+            .collect();
+
+        assert_eq!(interesting, [true; 4]);
+    }
+}
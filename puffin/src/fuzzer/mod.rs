@@ -1,22 +1,35 @@
 //! The fuzzer module setups the fuzzing loop. It also is responsible for gathering feedback from
 //! runs and restarting processes if they crash.
 
+use std::fs;
 use std::hash::{BuildHasher, Hash, Hasher};
+use std::path::Path;
 
 use chrono::Utc;
 use libafl::inputs::Input;
-use libafl_bolts::HasLen;
+use libafl_bolts::fs::write_file_atomic;
+use libafl_bolts::{Error, HasLen};
 
 use crate::protocol::ProtocolTypes;
 use crate::trace::Trace;
 
+pub mod decryption;
+pub mod dedup;
+pub mod eviction;
+pub mod handshake_state;
 pub mod harness;
+pub mod import;
+pub mod interesting_reason;
 mod libafl_setup;
+pub mod objective_stop;
+pub mod objective_symbols;
 pub mod sanitizer;
+pub mod snapshot;
 mod stages;
 mod stats_monitor;
-mod stats_stage;
+pub mod stats_stage;
 pub mod term_zoo;
+pub mod time_limit;
 // Public for benchmarks
 pub mod mutations;
 pub mod utils;
@@ -25,6 +38,24 @@ pub use libafl_setup::{start, FuzzerConfig};
 
 // LibAFL support
 impl<PT: ProtocolTypes> Input for Trace<PT> {
+    fn to_file<P>(&self, path: P) -> Result<(), Error>
+    where
+        P: AsRef<Path>,
+    {
+        let bytes = self
+            .serialize_versioned()
+            .map_err(|err| Error::serialize(err.to_string()))?;
+        write_file_atomic(path, &bytes)
+    }
+
+    fn from_file<P>(path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let bytes = fs::read(path)?;
+        Self::deserialize_any(&bytes).map_err(|err| Error::serialize(err.to_string()))
+    }
+
     fn generate_name(&self, _idx: usize) -> String {
         let now = Utc::now();
         let mut hasher = ahash::RandomState::with_seeds(0, 0, 0, 0).build_hasher();
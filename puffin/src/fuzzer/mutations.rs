@@ -1,18 +1,25 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
 use anyhow::Result;
 use libafl::prelude::*;
 use libafl_bolts::prelude::*;
 
 use super::utils::{
-    choose, choose_iter, choose_term, choose_term_filtered_mut, choose_term_mut,
-    choose_term_path_filtered, find_term_mut, Choosable, TermConstraints,
+    choose, choose_iter, choose_term, choose_term_filtered_mut, choose_term_mut, choose_term_path,
+    choose_term_path_filtered, find_term, find_term_by_term_path, find_term_mut, Choosable,
+    TermConstraints, TracePath,
 };
+use crate::agent::AgentName;
 use crate::algebra::atoms::Function;
+use crate::algebra::dynamic_function::TypeShape;
 use crate::algebra::signature::Signature;
+use crate::algebra::term::LOG_MAX_NODES;
 use crate::algebra::{DYTerm, Subterms, Term, TermType};
 use crate::fuzzer::term_zoo::TermZoo;
 use crate::protocol::{ProtocolBehavior, ProtocolTypes};
 use crate::put_registry::PutRegistry;
-use crate::trace::Trace;
+use crate::trace::{Action, Query, Source, Spawner, Step, Trace, TraceContext};
 
 #[derive(Clone, Copy, Debug)]
 pub struct MutationConfig {
@@ -21,7 +28,8 @@ pub struct MutationConfig {
     pub min_trace_length: usize,
     /// Below this term size we no longer mutate. Note that it is possible to reach
     /// smaller terms by having a mutation which removes all symbols in a single mutation.
-    /// Above this term size we no longer mutate.
+    /// Above this term size we no longer mutate, and mutations which would grow a recipe beyond
+    /// it are skipped.
     pub term_constraints: TermConstraints,
     pub with_bit_level: bool,
     pub with_dy: bool,
@@ -42,12 +50,7 @@ impl Default for MutationConfig {
 }
 
 pub fn trace_mutations<'harness, S, PT: ProtocolTypes, PB>(
-    min_trace_length: usize,
-    max_trace_length: usize,
-    constraints: TermConstraints,
-    fresh_zoo_after: u64,
-    _with_bit_level: bool,
-    with_dy: bool,
+    config: MutationConfig,
     signature: &'static Signature<PT>,
     _put_registry: &'harness PutRegistry<PB>,
 ) -> tuple_list_type!(
@@ -57,12 +60,22 @@ pub fn trace_mutations<'harness, S, PT: ProtocolTypes, PB>(
       ReplaceMatchMutator<S, PT>,
       RemoveAndLiftMutator<S>,
       GenerateMutator<S, PT>,
-      SwapMutator<S>
+      SwapMutator<S>,
+      ReorderStepsMutator<S>
    )
 where
     S: HasCorpus + HasMetadata + HasMaxSize + HasRand,
     PB: ProtocolBehavior,
 {
+    let MutationConfig {
+        fresh_zoo_after,
+        max_trace_length,
+        min_trace_length,
+        term_constraints: constraints,
+        with_bit_level: _,
+        with_dy,
+    } = config;
+
     tuple_list!(
         RepeatMutator::new(max_trace_length, with_dy),
         SkipMutator::new(min_trace_length, with_dy),
@@ -71,9 +84,42 @@ where
         RemoveAndLiftMutator::new(constraints, with_dy),
         GenerateMutator::new(0, fresh_zoo_after, constraints, None, signature, with_dy), /* Refresh zoo after 100000M mutations */
         SwapMutator::new(constraints, with_dy),
+        ReorderStepsMutator::new(with_dy),
     )
 }
 
+/// Whether replacing the sub-term at `trace_path` by `replacement` keeps the size of its recipe
+/// within `max_term_size`, see [`TermConstraints::max_term_size`]
+fn fits_term_size<PT: ProtocolTypes>(
+    trace: &Trace<PT>,
+    trace_path: &TracePath,
+    replacement: &Term<PT>,
+    max_term_size: usize,
+) -> bool {
+    let (step_index, term_path) = trace_path;
+    let Some(Step {
+        action: Action::Input(input),
+        ..
+    }) = trace.steps.get(*step_index)
+    else {
+        return false;
+    };
+    let Some(replaced) = find_term_by_term_path(&input.recipe, term_path) else {
+        return false;
+    };
+
+    let size = input.recipe.size() - replaced.size() + replacement.size();
+    if size > max_term_size {
+        log::debug!(
+            "[Mutation] Skipping mutation which grows the recipe of step #{step_index} to size \
+             {size}"
+        );
+        return false;
+    }
+
+    true
+}
+
 /// SWAP: Swaps a sub-term with a different sub-term which is part of the trace
 
 /// (such that types match).
@@ -124,6 +170,24 @@ where
                 rand,
             ) {
                 let term_a_cloned = term_a.clone();
+
+                // Swapping within a recipe does not change its size
+                if trace_path_a.0 != trace_path_b.0 {
+                    let Some(term_b) = find_term(trace, &trace_path_b) else {
+                        return Ok(MutationResult::Skipped);
+                    };
+                    if !fits_term_size(trace, &trace_path_a, term_b, self.constraints.max_term_size)
+                        || !fits_term_size(
+                            trace,
+                            &trace_path_b,
+                            term_a,
+                            self.constraints.max_term_size,
+                        )
+                    {
+                        return Ok(MutationResult::Skipped);
+                    }
+                }
+
                 if let Some(term_b_mut) = find_term_mut(trace, &trace_path_b) {
                     log::debug!(
                         "[Mutation] Mutate SwapMutator on terms\n{} and\n {}",
@@ -293,12 +357,19 @@ where
         }
         let rand = state.rand_mut();
         if let Some(to_mutate) = choose_term_mut(trace, self.constraints, rand) {
-            log::debug!("[Mutation] ReplaceMatchMutator on term\n{}", to_mutate);
+            log::debug!(
+                "[Mutation] ReplaceMatchMutator on term\n{}",
+                to_mutate.display_truncated(LOG_MAX_NODES)
+            );
             match &mut to_mutate.term {
                 // TODO-bitlevel: maybe also SKIP if not(to_mutate.is_symbolic())
                 DYTerm::Variable(variable) => {
                     if let Some((shape, dynamic_fn)) = self.signature.functions.choose_filtered(
-                        |(shape, _)| variable.typ == shape.return_type && shape.is_constant(),
+                        |(shape, _)| {
+                            variable.typ == shape.return_type
+                                && shape.is_constant()
+                                && !self.signature.is_deprecated(shape.name)
+                        },
                         rand,
                     ) {
                         to_mutate.mutate(Term::from(DYTerm::Application(
@@ -316,6 +387,7 @@ where
                             func_mut.shape() != shape
                                 && func_mut.shape().return_type == shape.return_type
                                 && func_mut.shape().argument_types == shape.argument_types
+                                && !self.signature.is_deprecated(shape.name)
                         },
                         rand,
                     ) {
@@ -383,13 +455,24 @@ where
         }
         let rand = state.rand_mut();
         if let Some(replacement) = choose_term(trace, self.constraints, rand).cloned() {
-            if let Some(to_replace) = choose_term_filtered_mut(
+            if let Some(trace_path) = choose_term_path_filtered(
                 trace,
                 |term: &Term<PT>| term.get_type_shape() == replacement.get_type_shape(),
                 // TODO-bitlevel: maybe also check that both are .is_symbolic()
                 self.constraints,
                 rand,
             ) {
+                if !fits_term_size(
+                    trace,
+                    &trace_path,
+                    &replacement,
+                    self.constraints.max_term_size,
+                ) {
+                    return Ok(MutationResult::Skipped);
+                }
+                let Some(to_replace) = find_term_mut(trace, &trace_path) else {
+                    return Ok(MutationResult::Skipped);
+                };
                 log::debug!(
                     "[Mutation] Mutate ReplaceReuseMutator on terms\n {} and\n{}",
                     to_replace,
@@ -531,16 +614,203 @@ where
     }
 }
 
+/// REORDER: Swaps two adjacent steps, unless the later step depends on knowledge produced by the
+/// earlier one
+pub struct ReorderStepsMutator<S>
+where
+    S: HasRand,
+{
+    phantom_s: std::marker::PhantomData<S>,
+    with_dy: bool,
+}
+
+impl<S> ReorderStepsMutator<S>
+where
+    S: HasRand,
+{
+    #[must_use]
+    pub const fn new(with_dy: bool) -> Self {
+        Self {
+            phantom_s: std::marker::PhantomData,
+            with_dy,
+        }
+    }
+}
+impl<S, PT: ProtocolTypes> Mutator<Trace<PT>, S> for ReorderStepsMutator<S>
+where
+    S: HasRand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        trace: &mut Trace<PT>,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        if !self.with_dy {
+            return Ok(MutationResult::Skipped);
+        }
+        let steps = &mut trace.steps;
+        let independent = (1..steps.len())
+            .filter(|&i| !depends_on(steps, i))
+            .collect::<Vec<_>>();
+        if independent.is_empty() {
+            return Ok(MutationResult::Skipped);
+        }
+        let index = *state.rand_mut().choose(&independent);
+        log::debug!(
+            "[Mutation] Mutate ReorderStepsMutator on steps {} and {index}",
+            index - 1
+        );
+        steps.swap(index - 1, index);
+        Ok(MutationResult::Mutated)
+    }
+}
+impl<S> Named for ReorderStepsMutator<S>
+where
+    S: HasRand,
+{
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+}
+
+/// Whether the steps `index - 1` and `index` can not be swapped without changing the meaning of
+/// the trace, which is the case if
+/// * the later step is an output of the agent which received the earlier input,
+/// * the later step queries knowledge which the earlier step may have produced,
+/// * any step queries the output of one of both steps by its index, see [`Source::Step`], or
+/// * a later step queries knowledge of both steps without a source, as the counter of the query
+///   then depends on their order.
+fn depends_on<PT: ProtocolTypes>(steps: &[Step<PT>], index: usize) -> bool {
+    let (earlier, step) = (&steps[index - 1], &steps[index]);
+
+    if matches!(
+        (&earlier.action, &step.action),
+        (Action::Input(_), Action::Output(_))
+    ) && earlier.agent == step.agent
+    {
+        return true;
+    }
+
+    let earlier_produced = produced_sources(earlier, index - 1);
+    let step_produced = produced_sources(step, index);
+
+    let queried_by_step = queried_sources(step).any(|source| match source {
+        Some(source) => earlier_produced.contains(source),
+        None => !earlier_produced.is_empty(),
+    });
+    let queried_by_index = steps
+        .iter()
+        .flat_map(queried_sources)
+        .any(|source| matches!(source, Some(Source::Step(i)) if *i == index - 1 || *i == index));
+    let queried_by_later = !earlier_produced.is_empty()
+        && !step_produced.is_empty()
+        && steps[index + 1..]
+            .iter()
+            .flat_map(queried_sources)
+            .any(|source| source.is_none());
+
+    queried_by_step || queried_by_index || queried_by_later
+}
+
+/// Sources of the knowledge which the step with the index `index` produces
+fn produced_sources<PT: ProtocolTypes>(step: &Step<PT>, index: usize) -> Vec<Source> {
+    match &step.action {
+        Action::Output(_) => vec![Source::Agent(step.agent), Source::Step(index)],
+        Action::Input(input) => input
+            .precomputations
+            .iter()
+            .map(|precomputation| Source::Label(precomputation.label.clone()))
+            .collect(),
+    }
+}
+
+/// Sources of all queries of the variables in `step`
+fn queried_sources<PT: ProtocolTypes>(step: &Step<PT>) -> impl Iterator<Item = &Option<Source>> {
+    let recipes: Vec<&Term<PT>> = match &step.action {
+        Action::Output(_) => vec![],
+        Action::Input(input) => input
+            .precomputations
+            .iter()
+            .map(|precomputation| &precomputation.recipe)
+            .chain(std::iter::once(&input.recipe))
+            .collect(),
+    };
+
+    recipes
+        .into_iter()
+        .flat_map(|recipe| recipe.into_iter())
+        .filter_map(|term| match &term.term {
+            DYTerm::Variable(variable) => Some(&variable.query.source),
+            DYTerm::Application(_, _) => None,
+        })
+}
+
+/// Term zoo which is generated on first use and regenerated every `refresh_after` uses. Clones
+/// share the same zoo, such that all mutators which generate terms draw from a single zoo.
+pub struct SharedTermZoo<PT: ProtocolTypes> {
+    inner: Rc<RefCell<SharedTermZooInner<PT>>>,
+}
+
+struct SharedTermZooInner<PT: ProtocolTypes> {
+    uses: u64,
+    refresh_after: u64,
+    zoo: Option<TermZoo<PT>>,
+    signature: &'static Signature<PT>,
+}
+
+impl<PT: ProtocolTypes> SharedTermZoo<PT> {
+    #[must_use]
+    pub fn new(
+        uses: u64,
+        refresh_after: u64,
+        zoo: Option<TermZoo<PT>>,
+        signature: &'static Signature<PT>,
+    ) -> Self {
+        Self {
+            inner: Rc::new(RefCell::new(SharedTermZooInner {
+                uses,
+                refresh_after,
+                zoo,
+                signature,
+            })),
+        }
+    }
+
+    /// Chooses a term of the zoo which satisfies `filter`, this counts as one use of the zoo
+    pub fn choose_filtered<P, R: Rand>(&self, filter: P, rand: &mut R) -> Option<Term<PT>>
+    where
+        P: FnMut(&&Term<PT>) -> bool,
+    {
+        let mut inner = self.inner.borrow_mut();
+        inner.uses += 1;
+        let signature = inner.signature;
+        let zoo = if inner.uses % inner.refresh_after == 0 {
+            inner.zoo.insert(TermZoo::generate(signature, rand))
+        } else {
+            inner
+                .zoo
+                .get_or_insert_with(|| TermZoo::generate(signature, rand))
+        };
+        zoo.choose_filtered(filter, rand).cloned()
+    }
+}
+
+impl<PT: ProtocolTypes> Clone for SharedTermZoo<PT> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Rc::clone(&self.inner),
+        }
+    }
+}
+
 /// GENERATE: Generates a previously-unseen term using a term zoo
 pub struct GenerateMutator<S, PT: ProtocolTypes>
 where
     S: HasRand,
 {
-    mutation_counter: u64,
-    refresh_zoo_after: u64,
     constraints: TermConstraints,
-    zoo: Option<TermZoo<PT>>,
-    signature: &'static Signature<PT>,
+    zoo: SharedTermZoo<PT>,
     phantom_s: std::marker::PhantomData<S>,
     with_dy: bool,
 }
@@ -549,7 +819,7 @@ where
     S: HasRand,
 {
     #[must_use]
-    pub const fn new(
+    pub fn new(
         mutation_counter: u64,
         refresh_zoo_after: u64,
         constraints: TermConstraints,
@@ -558,15 +828,19 @@ where
         with_dy: bool,
     ) -> Self {
         Self {
-            mutation_counter,
-            refresh_zoo_after,
             constraints,
-            zoo,
-            signature,
+            zoo: SharedTermZoo::new(mutation_counter, refresh_zoo_after, zoo, signature),
             phantom_s: std::marker::PhantomData,
             with_dy,
         }
     }
+
+    /// The zoo of this mutator, to be shared with other mutators which generate terms, e.g.
+    /// [`SubstituteVariableMutator`]
+    #[must_use]
+    pub fn zoo(&self) -> SharedTermZoo<PT> {
+        self.zoo.clone()
+    }
 }
 impl<S, PT: ProtocolTypes> Mutator<Trace<PT>, S> for GenerateMutator<S, PT>
 where
@@ -582,24 +856,31 @@ where
             return Ok(MutationResult::Skipped);
         }
         let rand = state.rand_mut();
-        if let Some(to_mutate) = choose_term_mut(trace, self.constraints, rand) {
-            log::debug!("[Mutation] Mutate GenerateMutator on term\n{}", to_mutate);
-            self.mutation_counter += 1;
-            let zoo = if self.mutation_counter % self.refresh_zoo_after == 0 {
-                self.zoo.insert(TermZoo::generate(self.signature, rand))
-            } else {
-                self.zoo
-                    .get_or_insert_with(|| TermZoo::generate(self.signature, rand))
-            };
-            if let Some(term) = zoo.choose_filtered(
-                |term| to_mutate.get_type_shape() == term.get_type_shape(),
-                rand,
-            ) {
-                to_mutate.mutate(term.clone());
-                Ok(MutationResult::Mutated)
-            } else {
-                Ok(MutationResult::Skipped)
-            }
+        let Some(trace_path) = choose_term_path(trace, self.constraints, rand) else {
+            return Ok(MutationResult::Skipped);
+        };
+        let Some(to_mutate) = find_term(trace, &trace_path) else {
+            return Ok(MutationResult::Skipped);
+        };
+
+        log::debug!(
+            "[Mutation] Mutate GenerateMutator on term\n{}",
+            to_mutate.display_truncated(LOG_MAX_NODES)
+        );
+        let Some(term) = self.zoo.choose_filtered(
+            |term| to_mutate.get_type_shape() == term.get_type_shape(),
+            rand,
+        ) else {
+            return Ok(MutationResult::Skipped);
+        };
+
+        if !fits_term_size(trace, &trace_path, &term, self.constraints.max_term_size) {
+            return Ok(MutationResult::Skipped);
+        }
+
+        if let Some(to_mutate) = find_term_mut(trace, &trace_path) {
+            to_mutate.mutate(term);
+            Ok(MutationResult::Mutated)
         } else {
             Ok(MutationResult::Skipped)
         }
@@ -615,6 +896,159 @@ where
     }
 }
 
+/// SUBSTITUTE: Replaces a constant sub-term by a variable which queries knowledge of the same type
+/// from an agent, or inlines a variable by generating a term of its type using the zoo of a
+/// [`GenerateMutator`]
+///
+/// The source of a new variable is only an agent which produced knowledge of the type of the
+/// constant before its step. This is checked by executing the trace up to that step, which is why
+/// the mutator is not part of the default [`trace_mutations`].
+pub struct SubstituteVariableMutator<S, PB: ProtocolBehavior>
+where
+    S: HasRand,
+{
+    constraints: TermConstraints,
+    zoo: SharedTermZoo<PB::ProtocolTypes>,
+    put_registry: PutRegistry<PB>,
+    phantom_s: std::marker::PhantomData<S>,
+    with_dy: bool,
+}
+impl<S, PB: ProtocolBehavior> SubstituteVariableMutator<S, PB>
+where
+    S: HasRand,
+{
+    #[must_use]
+    pub fn new(
+        zoo: SharedTermZoo<PB::ProtocolTypes>,
+        constraints: TermConstraints,
+        put_registry: &PutRegistry<PB>,
+        with_dy: bool,
+    ) -> Self {
+        Self {
+            constraints,
+            zoo,
+            put_registry: put_registry.clone(),
+            phantom_s: std::marker::PhantomData,
+            with_dy,
+        }
+    }
+
+    /// Agents which have knowledge of type `typ` when `step_index` of `trace` is executed
+    fn knowledge_sources(
+        &self,
+        trace: &Trace<PB::ProtocolTypes>,
+        step_index: usize,
+        typ: &TypeShape<PB::ProtocolTypes>,
+    ) -> Vec<AgentName> {
+        let mut candidates = vec![];
+        for step in &trace.steps[..step_index] {
+            if matches!(step.action, Action::Output(_)) && !candidates.contains(&step.agent) {
+                candidates.push(step.agent);
+            }
+        }
+        if candidates.is_empty() {
+            return candidates;
+        }
+
+        let mut ctx = TraceContext::new(Spawner::new(self.put_registry.clone()));
+        if let Err(err) = trace.execute_until_step(&mut ctx, step_index) {
+            log::debug!("[Mutation] Prefix of the trace failed, knowledge may be missing: {err}");
+        }
+
+        candidates
+            .into_iter()
+            .filter(|agent| {
+                let query = Query {
+                    source: Some(Source::Agent(*agent)),
+                    matcher: None,
+                    counter: 0,
+                };
+                ctx.find_variable(typ.clone(), &query).is_some()
+            })
+            .collect()
+    }
+}
+impl<S, PB: ProtocolBehavior> Mutator<Trace<PB::ProtocolTypes>, S>
+    for SubstituteVariableMutator<S, PB>
+where
+    S: HasRand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        trace: &mut Trace<PB::ProtocolTypes>,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        if !self.with_dy {
+            return Ok(MutationResult::Skipped);
+        }
+        let rand = state.rand_mut();
+        let Some(trace_path) = choose_term_path_filtered(
+            trace,
+            |term| match &term.term {
+                DYTerm::Variable(_) => true,
+                DYTerm::Application(function, _) => function.is_constant(),
+            },
+            self.constraints,
+            rand,
+        ) else {
+            return Ok(MutationResult::Skipped);
+        };
+
+        let Some(to_mutate) = find_term(trace, &trace_path) else {
+            return Ok(MutationResult::Skipped);
+        };
+
+        let replacement = match &to_mutate.term {
+            DYTerm::Application(_, _) => {
+                let typ = to_mutate.get_type_shape().clone();
+                let sources = self.knowledge_sources(trace, trace_path.0, &typ);
+                if sources.is_empty() {
+                    return Ok(MutationResult::Skipped);
+                }
+                let agent = *rand.choose(&sources);
+                log::debug!(
+                    "[Mutation] Mutate SubstituteVariableMutator on constant {} with a query to \
+                     {agent}",
+                    to_mutate.display_truncated(LOG_MAX_NODES)
+                );
+                let variable = Signature::new_var(typ, Some(Source::Agent(agent)), None, 0);
+                Term::from(DYTerm::Variable(variable))
+            }
+            DYTerm::Variable(variable) => {
+                log::debug!("[Mutation] Mutate SubstituteVariableMutator on variable {variable}");
+                let Some(term) = self
+                    .zoo
+                    .choose_filtered(|term| &variable.typ == term.get_type_shape(), rand)
+                else {
+                    return Ok(MutationResult::Skipped);
+                };
+
+                if !fits_term_size(trace, &trace_path, &term, self.constraints.max_term_size) {
+                    return Ok(MutationResult::Skipped);
+                }
+                term
+            }
+        };
+
+        if let Some(to_mutate) = find_term_mut(trace, &trace_path) {
+            to_mutate.mutate(replacement);
+            Ok(MutationResult::Mutated)
+        } else {
+            Ok(MutationResult::Skipped)
+        }
+    }
+}
+
+impl<S, PB: ProtocolBehavior> Named for SubstituteVariableMutator<S, PB>
+where
+    S: HasRand,
+{
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::{HashMap, HashSet};
@@ -625,16 +1059,17 @@ mod tests {
     use libafl_bolts::rands::{RomuDuoJrRand, StdRand};
 
     use super::*;
-    use crate::agent::AgentName;
     use crate::algebra::dynamic_function::DescribableFunction;
     use crate::algebra::test_signature::{TestTrace, *};
     use crate::algebra::DYTerm;
     use crate::fuzzer::utils::{choose_term_path, TracePath};
-    use crate::trace::{Action, Step};
+    use crate::term;
+    use crate::trace::{InputAction, OutputAction};
 
-    fn create_state(
-    ) -> StdState<TestTrace, InMemoryCorpus<TestTrace>, RomuDuoJrRand, InMemoryCorpus<TestTrace>>
-    {
+    type TestState =
+        StdState<TestTrace, InMemoryCorpus<TestTrace>, RomuDuoJrRand, InMemoryCorpus<TestTrace>>;
+
+    fn create_state() -> TestState {
         let rand = StdRand::with_seed(1235);
         let corpus: InMemoryCorpus<TestTrace> = InMemoryCorpus::new();
         StdState::new(rand, corpus, InMemoryCorpus::new(), &mut (), &mut ()).unwrap()
@@ -814,6 +1249,207 @@ mod tests {
         }
     }
 
+    #[test_log::test]
+    fn test_reorder_steps_mutator() {
+        let mut state = create_state();
+        let mut mutator = ReorderStepsMutator::new(true);
+
+        fn recipe_names(trace: &TestTrace) -> Vec<String> {
+            trace
+                .steps
+                .iter()
+                .map(|step| match &step.action {
+                    Action::Input(input) => input.recipe.name().to_string(),
+                    Action::Output(_) => String::new(),
+                })
+                .collect()
+        }
+
+        let swapped_first = (0..1000).any(|_| {
+            let mut trace = setup_simple_trace();
+            let before = recipe_names(&trace);
+            let result = mutator.mutate(&mut state, &mut trace, 0).unwrap();
+            assert_eq!(result, MutationResult::Mutated);
+
+            let after = recipe_names(&trace);
+            after[0] == before[1] && after[1] == before[0] && after[2] == before[2]
+        });
+        assert!(swapped_first);
+    }
+
+    #[test_log::test]
+    fn test_reorder_steps_mutator_dependent() {
+        let mut state = create_state();
+        let mut mutator = ReorderStepsMutator::new(true);
+        let server = AgentName::first();
+
+        let mut trace = setup_simple_trace();
+        trace.steps = vec![
+            OutputAction::new_step(server),
+            InputAction::new_step(
+                server,
+                term! { fn_encrypt12(((server, 0)/HandshakeMessage), fn_seq_0) },
+            ),
+        ];
+
+        for _ in 0..100 {
+            let result = mutator.mutate(&mut state, &mut trace, 0).unwrap();
+            assert_eq!(result, MutationResult::Skipped);
+            assert!(matches!(trace.steps[0].action, Action::Output(_)));
+        }
+    }
+
+    #[test_log::test]
+    fn test_reorder_steps_mutator_output_after_input() {
+        let mut state = create_state();
+        let mut mutator = ReorderStepsMutator::new(true);
+        let server = AgentName::first();
+
+        // the output of the server is its reaction to the input
+        let mut trace = setup_simple_trace();
+        trace.steps = vec![
+            InputAction::new_step(server, term! { fn_client_key_exchange }),
+            OutputAction::new_step(server),
+        ];
+
+        for _ in 0..100 {
+            let result = mutator.mutate(&mut state, &mut trace, 0).unwrap();
+            assert_eq!(result, MutationResult::Skipped);
+        }
+    }
+
+    #[test_log::test]
+    fn test_reorder_steps_mutator_step_source() {
+        let mut state = create_state();
+        let mut mutator = ReorderStepsMutator::new(true);
+        let (server, client) = (AgentName::first(), AgentName::first().next());
+
+        // the outputs of both agents are independent, but the last step queries the first output
+        // by its index
+        let mut trace = setup_simple_trace();
+        trace.steps = vec![
+            OutputAction::new_step(server),
+            OutputAction::new_step(client),
+            InputAction::new_step(
+                server,
+                Term::from(DYTerm::Variable(Signature::new_var(
+                    TypeShape::of::<Vec<u8>>(),
+                    Some(Source::Step(0)),
+                    None,
+                    0,
+                ))),
+            ),
+        ];
+
+        for _ in 0..100 {
+            let result = mutator.mutate(&mut state, &mut trace, 0).unwrap();
+            assert_eq!(result, MutationResult::Skipped);
+        }
+    }
+
+    #[test_log::test]
+    fn test_substitute_variable_mutator_constant_without_knowledge() {
+        let mut state = create_state();
+        let zoo = SharedTermZoo::new(0, 100, None, &TEST_SIGNATURE);
+        let mut mutator = SubstituteVariableMutator::new(
+            zoo,
+            TermConstraints::default(),
+            &dummy_registry(),
+            true,
+        );
+        let server = AgentName::first();
+        let constant = term! { fn_client_key_exchange };
+
+        let mut trace = setup_simple_trace();
+        trace.steps = vec![InputAction::new_step(server, constant.clone())];
+        let result = mutator.mutate(&mut state, &mut trace, 0).unwrap();
+        assert_eq!(result, MutationResult::Skipped);
+
+        // The server outputs before the constant, but the PUT of the stub has nothing to output
+        trace.steps.insert(0, OutputAction::new_step(server));
+        for _ in 0..10 {
+            let result = mutator.mutate(&mut state, &mut trace, 0).unwrap();
+            assert_eq!(result, MutationResult::Skipped);
+        }
+    }
+
+    #[test_log::test]
+    fn test_substitute_variable_mutator_variable() {
+        let mut state = create_state();
+        let generate = GenerateMutator::<TestState, _>::new(
+            0,
+            100,
+            TermConstraints::default(),
+            None,
+            &TEST_SIGNATURE,
+            true,
+        );
+        let mut mutator = SubstituteVariableMutator::new(
+            generate.zoo(),
+            TermConstraints::default(),
+            &dummy_registry(),
+            true,
+        );
+        let server = AgentName::first();
+        let variable = term! { (server, 0) / HandshakeMessage };
+
+        let mut trace = setup_simple_trace();
+        trace.steps = vec![
+            OutputAction::new_step(server),
+            InputAction::new_step(server, variable.clone()),
+        ];
+        let result = mutator.mutate(&mut state, &mut trace, 0).unwrap();
+        assert_eq!(result, MutationResult::Mutated);
+
+        let Action::Input(input) = &trace.steps[1].action else {
+            panic!("expected an input step");
+        };
+        assert!(matches!(input.recipe.term, DYTerm::Application(_, _)));
+        assert_eq!(input.recipe.get_type_shape(), variable.get_type_shape());
+        // The term was drawn from the zoo of the generate mutator
+        assert!(generate.zoo.inner.borrow().zoo.is_some());
+    }
+
+    #[test_log::test]
+    fn test_generate_mutator_max_term_size() {
+        let mut state = create_state();
+        let server = AgentName::first();
+        // Every term of the zoo which computes an encrypted message is larger than the variable
+        let recipe = term! { (server, 0) / Encrypted };
+        let max_term_size = recipe.size();
+
+        let mut trace = setup_simple_trace();
+        trace.steps = vec![
+            OutputAction::new_step(server),
+            InputAction::new_step(server, recipe.clone()),
+        ];
+
+        let constraints = TermConstraints {
+            max_term_size,
+            ..TermConstraints::default()
+        };
+        let mut mutator = GenerateMutator::new(0, 100, constraints, None, &TEST_SIGNATURE, true);
+        for _ in 0..100 {
+            let result = mutator.mutate(&mut state, &mut trace, 0).unwrap();
+            assert_eq!(result, MutationResult::Skipped);
+        }
+        let Action::Input(input) = &trace.steps[1].action else {
+            panic!("expected an input step");
+        };
+        assert_eq!(input.recipe.to_string(), recipe.to_string());
+
+        let mut mutator = GenerateMutator::new(
+            0,
+            100,
+            TermConstraints::default(),
+            None,
+            &TEST_SIGNATURE,
+            true,
+        );
+        let result = mutator.mutate(&mut state, &mut trace, 0).unwrap();
+        assert_eq!(result, MutationResult::Mutated);
+    }
+
     #[test_log::test]
     fn test_find_term() {
         let mut rand = StdRand::with_seed(45);
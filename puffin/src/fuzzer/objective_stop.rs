@@ -0,0 +1,236 @@
+//! Stopping the campaign at the first objective.
+//!
+//! When reproducing a known crash, there is no point in fuzzing on once an objective was found.
+//! The [`StopOnObjectiveStage`] shuts the client down as soon as its state holds an objective. The
+//! clients of a campaign do not share their objectives through the broker, but they all write them
+//! to the same objective directory. Therefore, a client also stops once a trace appears in this
+//! directory which was not there when it started. As every client tells the broker that it exits,
+//! the broker shuts down once all clients stopped.
+
+use std::collections::{BTreeSet, HashSet};
+use std::fs;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+
+use libafl::corpus::{Corpus, CorpusId};
+use libafl::events::EventRestarter;
+use libafl::prelude::{Evaluator, UsesState};
+use libafl::stages::Stage;
+use libafl::state::HasSolutions;
+use libafl::Error;
+
+use crate::fuzzer::stages::StagePhantom;
+
+/// Traces which are stored in the objective directory `dir`
+pub fn objective_traces(dir: &Path) -> HashSet<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return HashSet::new();
+    };
+
+    entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "trace")
+        })
+        .collect()
+}
+
+/// A [`Stage`] which stops the fuzzing loop once an objective was found by this or another client.
+#[derive(Clone, Debug)]
+pub struct StopOnObjectiveStage<E, EM, Z> {
+    /// Objective directory shared by all clients, `None` if the stage never stops the loop
+    objective_dir: Option<PathBuf>,
+    /// Traces which were in the objective directory before the stage was created
+    known_objectives: HashSet<PathBuf>,
+    phantom: StagePhantom<E, EM, Z>,
+}
+
+impl<E, EM, Z> UsesState for StopOnObjectiveStage<E, EM, Z>
+where
+    E: UsesState<State = Z::State>,
+    EM: UsesState<State = Z::State>,
+    Z: Evaluator<E, EM>,
+{
+    type State = Z::State;
+}
+
+impl<E, EM, Z> Stage<E, EM, Z> for StopOnObjectiveStage<E, EM, Z>
+where
+    E: UsesState<State = Z::State>,
+    EM: UsesState<State = Z::State> + EventRestarter,
+    Z: Evaluator<E, EM>,
+    Z::State: HasSolutions,
+{
+    fn perform(
+        &mut self,
+        _fuzzer: &mut Z,
+        _executor: &mut E,
+        state: &mut Z::State,
+        manager: &mut EM,
+        _corpus_idx: CorpusId,
+    ) -> Result<(), Error> {
+        let Some(objective_dir) = &self.objective_dir else {
+            return Ok(());
+        };
+
+        let solutions = state.solutions();
+        let mut found: BTreeSet<PathBuf> = objective_traces(objective_dir)
+            .difference(&self.known_objectives)
+            .cloned()
+            .collect();
+
+        // A client which restarted after a crash already knows the trace of the crash on disk
+        for id in solutions.ids() {
+            if let Some(path) = solutions.get(id)?.borrow().file_path() {
+                found.insert(path.clone());
+            }
+        }
+
+        if found.is_empty() && solutions.is_empty() {
+            return Ok(());
+        }
+
+        for path in &found {
+            log::warn!("Found objective {:?}, stopping the fuzzer.", path);
+        }
+
+        if found.is_empty() {
+            log::warn!(
+                "Found {} objectives, stopping the fuzzer.",
+                solutions.count()
+            );
+        }
+
+        // The restarting manager does not respawn the client after this
+        manager.send_exiting()?;
+        Err(Error::shutting_down())
+    }
+}
+
+impl<E, EM, Z> StopOnObjectiveStage<E, EM, Z>
+where
+    E: UsesState<State = Z::State>,
+    EM: UsesState<State = Z::State>,
+    Z: Evaluator<E, EM>,
+{
+    /// Creates the stage, which never stops the fuzzing loop if `objective_dir` is `None`.
+    pub fn new(objective_dir: Option<PathBuf>) -> Self {
+        let known_objectives = objective_dir
+            .as_deref()
+            .map(objective_traces)
+            .unwrap_or_default();
+
+        Self {
+            objective_dir,
+            known_objectives,
+            phantom: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl::corpus::InMemoryCorpus;
+    use libafl::events::NopEventManager;
+    use libafl::executors::{ExitKind, InProcessExecutor};
+    use libafl::feedbacks::{ConstFeedback, CrashFeedback};
+    use libafl::fuzzer::{Fuzzer, StdFuzzer};
+    use libafl::schedulers::QueueScheduler;
+    use libafl::state::{HasExecutions, StdState};
+    use libafl_bolts::rands::StdRand;
+    use libafl_bolts::tuples::tuple_list;
+
+    use super::*;
+    use crate::algebra::test_signature::{setup_simple_trace, TestTrace};
+
+    type TestState =
+        StdState<TestTrace, InMemoryCorpus<TestTrace>, StdRand, InMemoryCorpus<TestTrace>>;
+
+    /// A seed on which the harness of [`fuzz`] always crashes
+    fn crashing_seed() -> TestTrace {
+        let mut trace = setup_simple_trace();
+        trace.steps.truncate(1);
+        trace
+    }
+
+    /// Fuzzes `seeds` with a harness which crashes on single-step traces. If `other_objective` is
+    /// set, another client writes an objective to `objective_dir` once the fuzzer started.
+    fn fuzz(
+        objective_dir: &Path,
+        seeds: Vec<TestTrace>,
+        other_objective: Option<&str>,
+    ) -> (Result<(), Error>, TestState) {
+        let mut feedback = ConstFeedback::False;
+        let mut objective = CrashFeedback::new();
+        let mut state: TestState = StdState::new(
+            StdRand::with_seed(1235),
+            InMemoryCorpus::new(),
+            InMemoryCorpus::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+
+        let mut fuzzer = StdFuzzer::new(QueueScheduler::new(), feedback, objective);
+        let mut manager = NopEventManager::new();
+        let mut harness = |input: &TestTrace| {
+            if input.steps.len() == 1 {
+                ExitKind::Crash
+            } else {
+                ExitKind::Ok
+            }
+        };
+        let mut executor =
+            InProcessExecutor::new(&mut harness, (), &mut fuzzer, &mut state, &mut manager)
+                .unwrap();
+        let mut stages = tuple_list!(StopOnObjectiveStage::new(Some(objective_dir.to_path_buf())));
+
+        if let Some(name) = other_objective {
+            fs::write(objective_dir.join(name), b"").unwrap();
+        }
+
+        for seed in seeds {
+            fuzzer
+                .add_input(&mut state, &mut executor, &mut manager, seed)
+                .unwrap();
+        }
+
+        let result = fuzzer.fuzz_loop(&mut stages, &mut executor, &mut state, &mut manager);
+        (result, state)
+    }
+
+    #[test_log::test]
+    fn test_stop_after_first_objective() {
+        let objective_dir = tempfile::tempdir().unwrap();
+
+        let (result, state) = fuzz(
+            objective_dir.path(),
+            vec![setup_simple_trace(), crashing_seed()],
+            None,
+        );
+
+        assert!(matches!(result, Err(Error::ShuttingDown)));
+        assert_eq!(state.solutions().count(), 1);
+        assert_eq!(*state.executions(), 2);
+    }
+
+    #[test_log::test]
+    fn test_stop_on_objective_of_other_client() {
+        let objective_dir = tempfile::tempdir().unwrap();
+        let new_objective = objective_dir.path().join("new.trace");
+
+        let (result, state) = fuzz(
+            objective_dir.path(),
+            vec![setup_simple_trace()],
+            Some("new.trace"),
+        );
+
+        assert!(matches!(result, Err(Error::ShuttingDown)));
+        assert!(state.solutions().is_empty());
+        assert_eq!(
+            objective_traces(objective_dir.path()),
+            HashSet::from([new_objective])
+        );
+    }
+}
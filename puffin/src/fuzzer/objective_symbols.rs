@@ -0,0 +1,191 @@
+//! Recording of the function symbols in the traces of objectives for triage.
+//!
+//! When a trace is added to the objective corpus, the number of applications of each function
+//! symbol across all recipes of the trace is attached to it as [`ObjectiveSymbolsMetadata`]. The
+//! on-disk corpus writes the metadata to a sidecar file next to the trace, e.g.
+//! `.<trace>.metadata`. This allows to spot patterns across crashes, like a function symbol which
+//! is part of every crashing trace.
+
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+
+use libafl::corpus::Testcase;
+use libafl::events::EventFirer;
+use libafl::executors::ExitKind;
+use libafl::feedbacks::Feedback;
+use libafl::inputs::UsesInput;
+use libafl::observers::ObserversTuple;
+use libafl::state::{HasMetadata, State};
+use libafl::Error;
+use libafl_bolts::Named;
+use serde::{Deserialize, Serialize};
+
+use crate::algebra::remove_prefix;
+use crate::protocol::ProtocolTypes;
+use crate::trace::{Action, Trace};
+
+/// Number of applications of each function symbol in the recipes of an objective
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ObjectiveSymbolsMetadata {
+    pub counts: BTreeMap<String, usize>,
+}
+
+libafl_bolts::impl_serdeany!(ObjectiveSymbolsMetadata);
+
+impl ObjectiveSymbolsMetadata {
+    /// Aggregates the function symbols of the recipes and precomputations of all steps
+    #[must_use]
+    pub fn of_trace<PT: ProtocolTypes>(trace: &Trace<PT>) -> Self {
+        let mut counts = BTreeMap::new();
+
+        for step in &trace.steps {
+            let Action::Input(input) = &step.action else {
+                continue;
+            };
+
+            let recipes = input
+                .precomputations
+                .iter()
+                .map(|precomputation| &precomputation.recipe)
+                .chain(std::iter::once(&input.recipe));
+
+            for recipe in recipes {
+                for (name, count) in recipe.count_by_symbol() {
+                    *counts.entry(remove_prefix(name)).or_default() += count;
+                }
+            }
+        }
+
+        Self { counts }
+    }
+}
+
+/// A [`Feedback`] which is never interesting on its own, but attaches the
+/// [`ObjectiveSymbolsMetadata`] to the testcases it is asked for.
+///
+/// It is meant to be combined with the objective feedbacks, such that only objectives get the
+/// metadata.
+#[derive(Debug, Clone)]
+pub struct ObjectiveSymbolsFeedback<PT> {
+    phantom: PhantomData<PT>,
+}
+
+impl<PT: ProtocolTypes> ObjectiveSymbolsFeedback<PT> {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<PT: ProtocolTypes> Default for ObjectiveSymbolsFeedback<PT> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<PT> Named for ObjectiveSymbolsFeedback<PT> {
+    fn name(&self) -> &str {
+        "ObjectiveSymbolsFeedback"
+    }
+}
+
+impl<S, PT> Feedback<S> for ObjectiveSymbolsFeedback<PT>
+where
+    S: State + UsesInput<Input = Trace<PT>>,
+    PT: ProtocolTypes,
+{
+    fn is_interesting<EM, OT>(
+        &mut self,
+        _state: &mut S,
+        _manager: &mut EM,
+        _input: &S::Input,
+        _observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error>
+    where
+        EM: EventFirer<State = S>,
+        OT: ObserversTuple<S>,
+    {
+        Ok(false)
+    }
+
+    fn append_metadata<OT>(
+        &mut self,
+        _state: &mut S,
+        _observers: &OT,
+        testcase: &mut Testcase<S::Input>,
+    ) -> Result<(), Error>
+    where
+        OT: ObserversTuple<S>,
+    {
+        let Some(trace) = testcase.input() else {
+            return Ok(());
+        };
+
+        let metadata = ObjectiveSymbolsMetadata::of_trace(trace);
+        log::info!("Function symbols of objective: {:?}", metadata.counts);
+        testcase.add_metadata(metadata);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl::corpus::ondisk::OnDiskMetadataFormat;
+    use libafl::corpus::{CachedOnDiskCorpus, Corpus, InMemoryCorpus};
+    use libafl::inputs::Input;
+    use libafl::state::{HasSolutions, StdState};
+    use libafl_bolts::rands::StdRand;
+
+    use super::*;
+    use crate::algebra::test_signature::{setup_simple_trace, TestProtocolTypes, TestTrace};
+
+    #[test_log::test]
+    fn test_objective_symbols_sidecar() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut state = StdState::new(
+            StdRand::with_seed(1235),
+            InMemoryCorpus::<TestTrace>::new(),
+            CachedOnDiskCorpus::with_meta_format(
+                dir.path(),
+                4096,
+                Some(OnDiskMetadataFormat::Json),
+            )
+            .unwrap(),
+            &mut (),
+            &mut (),
+        )
+        .unwrap();
+
+        // Two steps apply fn_encrypt12(fn_finished, fn_seq_0)
+        let mut trace = setup_simple_trace();
+        trace.steps.push(trace.steps[2].clone());
+        let name = trace.generate_name(0);
+
+        let mut testcase = Testcase::new(trace);
+        ObjectiveSymbolsFeedback::<TestProtocolTypes>::new()
+            .append_metadata(&mut state, &(), &mut testcase)
+            .unwrap();
+        *testcase.filename_mut() = Some(name.clone());
+        state.solutions_mut().add(testcase).unwrap();
+
+        let sidecar =
+            std::fs::read_to_string(dir.path().join(format!(".{name}.metadata"))).unwrap();
+        let sidecar: serde_json::Value = serde_json::from_str(&sidecar).unwrap();
+        let counts = sidecar["metadata"]["map"]
+            .as_object()
+            .unwrap()
+            .values()
+            .find_map(|entry| entry[1]["counts"].as_object())
+            .expect("no function symbols in sidecar");
+
+        assert_eq!(counts["fn_client_hello"], 1);
+        assert_eq!(counts["fn_client_key_exchange"], 1);
+        assert_eq!(counts["fn_encrypt12"], 2);
+        assert_eq!(counts["fn_finished"], 2);
+        assert_eq!(counts["fn_seq_0"], 2);
+    }
+}
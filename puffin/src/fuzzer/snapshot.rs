@@ -0,0 +1,313 @@
+//! Periodic snapshots of the fuzzing campaign, such that a campaign can be resumed after the
+//! machine restarts.
+//!
+//! The restarting event manager of LibAFL only keeps the state alive across crashes of a client.
+//! A [`CampaignSnapshot`] additionally persists the corpus, including the metadata of its
+//! testcases, the RNG and the number of executions to disk. A missing, partial or corrupt snapshot
+//! is never fatal: the campaign then starts from a fresh state.
+
+use std::fs;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use libafl::corpus::{Corpus, CorpusId, Testcase};
+use libafl::inputs::{Input, UsesInput};
+use libafl::prelude::{Evaluator, UsesState};
+use libafl::stages::Stage;
+use libafl::state::{HasCorpus, HasExecutions, HasMetadata, HasRand};
+use libafl::Error;
+use libafl_bolts::rands::Rand;
+use libafl_bolts::serdeany::SerdeAnyMap;
+use serde::{Deserialize, Serialize};
+
+use crate::fuzzer::stages::StagePhantom;
+
+/// Serializable part of the fuzzer state which is needed to continue a campaign.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(bound = "I: Input, R: Rand")]
+pub struct CampaignSnapshot<I, R> {
+    pub rand: R,
+    pub executions: usize,
+    pub corpus: Vec<SnapshotEntry<I>>,
+}
+
+/// Testcase of the corpus in a [`CampaignSnapshot`]
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(bound = "I: Input")]
+pub struct SnapshotEntry<I> {
+    pub input: I,
+    /// Metadata of the testcase, e.g. the reasons why it was added to the corpus
+    pub metadata: SerdeAnyMap,
+    pub exec_time: Option<Duration>,
+}
+
+impl<I, R> CampaignSnapshot<I, R>
+where
+    I: Input,
+    R: Rand + Clone,
+{
+    /// Captures the corpus with the metadata of its testcases, the RNG and the execution count of
+    /// `state`.
+    pub fn capture<S>(state: &S) -> Result<Self, Error>
+    where
+        S: HasCorpus + HasRand<Rand = R> + HasExecutions + UsesInput<Input = I>,
+    {
+        let corpus = state.corpus();
+        let mut entries = Vec::with_capacity(corpus.count());
+
+        for id in corpus.ids() {
+            let mut testcase = corpus.get(id)?.borrow_mut();
+            let input = testcase.load_input(corpus)?.clone();
+
+            entries.push(SnapshotEntry {
+                input,
+                metadata: testcase.metadata_map().clone(),
+                exec_time: *testcase.exec_time(),
+            });
+        }
+
+        Ok(Self {
+            rand: state.rand().clone(),
+            executions: *state.executions(),
+            corpus: entries,
+        })
+    }
+
+    /// Restores the snapshot into `state` and returns the ids of the restored corpus entries.
+    pub fn restore<S>(self, state: &mut S) -> Result<Vec<CorpusId>, Error>
+    where
+        S: HasCorpus + HasRand<Rand = R> + HasExecutions + UsesInput<Input = I>,
+    {
+        let ids = self
+            .corpus
+            .into_iter()
+            .map(|entry| {
+                let mut testcase = Testcase::new(entry.input);
+                *testcase.metadata_map_mut() = entry.metadata;
+                *testcase.exec_time_mut() = entry.exec_time;
+
+                state.corpus_mut().add(testcase)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        *state.rand_mut() = self.rand;
+        *state.executions_mut() = self.executions;
+
+        Ok(ids)
+    }
+
+    /// Writes the snapshot to `path`.
+    ///
+    /// The snapshot is first written to a temporary file which is then renamed, such that a crash
+    /// while writing never destroys the previous snapshot.
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        let tmp_path = path.with_extension("tmp");
+        let json = serde_json::to_vec(self).map_err(|err| Error::serialize(err.to_string()))?;
+
+        fs::write(&tmp_path, json)?;
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    /// Reads a snapshot from `path`.
+    ///
+    /// Returns `None` if there is no snapshot or if it can not be read, in which case the campaign
+    /// should start from a fresh state.
+    pub fn load(path: &Path) -> Option<Self> {
+        if !path.exists() {
+            log::info!("No snapshot found at {:?}. Starting fresh.", path);
+            return None;
+        }
+
+        let result = fs::read(path)
+            .map_err(|err| err.to_string())
+            .and_then(|data| serde_json::from_slice(&data).map_err(|err| err.to_string()));
+
+        match result {
+            Ok(snapshot) => Some(snapshot),
+            Err(err) => {
+                log::warn!(
+                    "Snapshot at {:?} is corrupt ({}). Starting fresh.",
+                    path,
+                    err
+                );
+                None
+            }
+        }
+    }
+}
+
+/// Path of the snapshot of a single fuzzing client.
+pub fn client_snapshot_path(snapshot_file: &Path, client_id: usize) -> PathBuf {
+    snapshot_file.with_extension(format!("{}.json", client_id))
+}
+
+/// A [`Stage`] which writes a [`CampaignSnapshot`] every `interval`.
+#[derive(Clone, Debug)]
+pub struct SnapshotStage<E, EM, Z> {
+    path: PathBuf,
+    interval: Duration,
+    last_snapshot: Instant,
+    phantom: StagePhantom<E, EM, Z>,
+}
+
+impl<E, EM, Z> UsesState for SnapshotStage<E, EM, Z>
+where
+    E: UsesState<State = Z::State>,
+    EM: UsesState<State = Z::State>,
+    Z: Evaluator<E, EM>,
+{
+    type State = Z::State;
+}
+
+impl<E, EM, Z> Stage<E, EM, Z> for SnapshotStage<E, EM, Z>
+where
+    E: UsesState<State = Z::State>,
+    EM: UsesState<State = Z::State>,
+    Z: Evaluator<E, EM>,
+    Z::State: HasCorpus + HasRand + HasExecutions,
+    <Z::State as HasRand>::Rand: Clone,
+{
+    fn perform(
+        &mut self,
+        _fuzzer: &mut Z,
+        _executor: &mut E,
+        state: &mut Z::State,
+        _manager: &mut EM,
+        _corpus_idx: CorpusId,
+    ) -> Result<(), Error> {
+        if self.last_snapshot.elapsed() < self.interval {
+            return Ok(());
+        }
+
+        self.last_snapshot = Instant::now();
+
+        // A failed snapshot should not stop the campaign
+        if let Err(err) = CampaignSnapshot::capture(state).and_then(|s| s.save(&self.path)) {
+            log::warn!("Failed to write snapshot to {:?}: {}", self.path, err);
+        }
+
+        Ok(())
+    }
+}
+
+impl<E, EM, Z> SnapshotStage<E, EM, Z>
+where
+    E: UsesState<State = Z::State>,
+    EM: UsesState<State = Z::State>,
+    Z: Evaluator<E, EM>,
+{
+    pub fn new(path: PathBuf, interval: Duration) -> Self {
+        Self {
+            path,
+            interval,
+            last_snapshot: Instant::now(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl::corpus::InMemoryCorpus;
+    use libafl::state::StdState;
+    use libafl_bolts::rands::{RomuDuoJrRand, StdRand};
+
+    use super::*;
+    use crate::algebra::test_signature::{setup_simple_trace, TestTrace};
+    use crate::fuzzer::interesting_reason::InterestingReasonsMetadata;
+
+    type TestState =
+        StdState<TestTrace, InMemoryCorpus<TestTrace>, RomuDuoJrRand, InMemoryCorpus<TestTrace>>;
+
+    fn create_state() -> TestState {
+        let rand = StdRand::with_seed(1235);
+        StdState::new(
+            rand,
+            InMemoryCorpus::new(),
+            InMemoryCorpus::new(),
+            &mut (),
+            &mut (),
+        )
+        .unwrap()
+    }
+
+    #[test_log::test]
+    fn test_snapshot_resume() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("snapshot.json");
+
+        let mut state = create_state();
+        for _ in 0..3 {
+            state
+                .corpus_mut()
+                .add(Testcase::new(setup_simple_trace()))
+                .unwrap();
+        }
+        state.rand_mut().next();
+        *state.executions_mut() = 42;
+
+        CampaignSnapshot::capture(&state)
+            .unwrap()
+            .save(&path)
+            .unwrap();
+
+        let mut resumed = create_state();
+        CampaignSnapshot::load(&path)
+            .unwrap()
+            .restore(&mut resumed)
+            .unwrap();
+
+        assert_eq!(resumed.corpus().count(), 3);
+        assert_eq!(*resumed.executions(), 42);
+        assert_eq!(resumed.rand_mut().next(), state.rand_mut().next());
+    }
+
+    #[test_log::test]
+    fn test_snapshot_keeps_testcase_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("snapshot.json");
+
+        let mut state = create_state();
+        let mut testcase = Testcase::new(setup_simple_trace());
+        InterestingReasonsMetadata::record(&mut testcase, "new edges".to_owned());
+        testcase.set_exec_time(Duration::from_millis(7));
+        state.corpus_mut().add(testcase).unwrap();
+
+        CampaignSnapshot::capture(&state)
+            .unwrap()
+            .save(&path)
+            .unwrap();
+
+        let mut resumed = create_state();
+        let ids = CampaignSnapshot::load(&path)
+            .unwrap()
+            .restore(&mut resumed)
+            .unwrap();
+
+        let restored = resumed.corpus().get(ids[0]).unwrap().borrow();
+        assert_eq!(
+            restored
+                .metadata::<InterestingReasonsMetadata>()
+                .unwrap()
+                .reasons,
+            ["new edges"]
+        );
+        assert_eq!(*restored.exec_time(), Some(Duration::from_millis(7)));
+    }
+
+    #[test_log::test]
+    fn test_corrupt_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("snapshot.json");
+
+        fs::write(&path, b"{\"rand\":").unwrap();
+
+        assert!(CampaignSnapshot::<TestTrace, RomuDuoJrRand>::load(&path).is_none());
+        assert!(CampaignSnapshot::<TestTrace, RomuDuoJrRand>::load(
+            &dir.path().join("missing.json")
+        )
+        .is_none());
+    }
+}
@@ -6,12 +6,16 @@ use libafl::prelude::mutational::MutatedTransform;
 use libafl::prelude::*;
 use libafl_bolts::prelude::*;
 
+/// Marker of the executor, event manager and fuzzer with which a stage is used
+pub(crate) type StagePhantom<E, EM, Z> = PhantomData<(E, EM, Z)>;
+/// Marker of the executor, event manager, input and fuzzer with which a mutational stage is used
+pub(crate) type MutationalStagePhantom<E, EM, I, Z> = PhantomData<(E, EM, I, Z)>;
+
 /// The default mutational stage
 #[derive(Clone, Debug)]
 pub struct PuffinMutationalStage<E, EM, I, M, Z> {
     mutator: M,
-    #[allow(clippy::type_complexity)]
-    phantom: PhantomData<(E, EM, I, Z)>,
+    phantom: MutationalStagePhantom<E, EM, I, Z>,
     max_iterations_per_stage: u64,
 }
 
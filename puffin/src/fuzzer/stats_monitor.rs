@@ -120,7 +120,7 @@ impl StatsMonitor {
                 _ => None,
             });
 
-        Statistics::Client(ClientStatistics {
+        Statistics::Client(Box::new(ClientStatistics {
             id: id.0,
             time: SystemTime::now(),
             trace,
@@ -132,7 +132,7 @@ impl StatsMonitor {
             objective_size,
             total_execs,
             exec_per_sec: exec_sec as u64,
-        })
+        }))
     }
 
     fn global(&mut self) -> Statistics {
@@ -184,7 +184,7 @@ impl Monitor for StatsMonitor {
 #[serde(tag = "type")]
 enum Statistics {
     #[serde(rename = "client")]
-    Client(ClientStatistics),
+    Client(Box<ClientStatistics>),
     #[serde(rename = "global")]
     Global(GlobalStatistics),
 }
@@ -299,6 +299,7 @@ struct ErrorStatistics {
     io_error: u64,
     ag_error: u64,
     str_error: u64,
+    timeout_error: u64,
     ext_error: u64,
 }
 
@@ -311,6 +312,10 @@ struct TraceStatistics {
     min_term_size: Option<u64>,
     max_term_size: Option<u64>,
     mean_term_size: Option<u64>,
+
+    min_execution_time_us: Option<u64>,
+    max_execution_time_us: Option<u64>,
+    mean_execution_time_us: Option<u64>,
 }
 
 #[cfg(feature = "introspection")]
@@ -386,6 +391,7 @@ impl ErrorStatistics {
             io_error: 0,
             ag_error: 0,
             str_error: 0,
+            timeout_error: 0,
             ext_error: 0,
         }
     }
@@ -399,6 +405,9 @@ impl ErrorStatistics {
                 RuntimeStats::IOError(c) => self.io_error += get_number(client_stats, c.name),
                 RuntimeStats::AgentError(c) => self.ag_error += get_number(client_stats, c.name),
                 RuntimeStats::StreamError(c) => self.str_error += get_number(client_stats, c.name),
+                RuntimeStats::TimeoutError(c) => {
+                    self.timeout_error += get_number(client_stats, c.name);
+                }
                 RuntimeStats::ExtractionError(c) => {
                     self.ext_error += get_number(client_stats, c.name);
                 }
@@ -428,9 +437,12 @@ impl TraceStatistics {
             min_term_size: None,
             max_term_size: None,
             mean_term_size: None,
+            min_execution_time_us: None,
+            max_execution_time_us: None,
+            mean_execution_time_us: None,
         };
 
-        // Sum for all TraceLength and TermSize
+        // Sum for all TraceLength, TermSize and ExecutionTime
         for stat_definition in &STATS {
             match stat_definition {
                 RuntimeStats::TraceLength(mmm) => {
@@ -449,6 +461,14 @@ impl TraceStatistics {
                     trace_stats.mean_term_size =
                         Some(get_number(user_stats, &(mmm.name.to_owned() + "-mean")));
                 }
+                RuntimeStats::ExecutionTime(timer) => {
+                    trace_stats.min_execution_time_us =
+                        Some(get_number(user_stats, &(timer.name().to_owned() + "-min")));
+                    trace_stats.max_execution_time_us =
+                        Some(get_number(user_stats, &(timer.name().to_owned() + "-max")));
+                    trace_stats.mean_execution_time_us =
+                        Some(get_number(user_stats, &(timer.name().to_owned() + "-mean")));
+                }
                 _ => {}
             }
         }
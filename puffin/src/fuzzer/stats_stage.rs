@@ -1,7 +1,12 @@
 use std::marker::PhantomData;
-use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
 
 use libafl::prelude::*;
+
+use crate::fuzzer::stages::StagePhantom;
+
 pub enum RuntimeStats {
     FnError(&'static Counter),
     TermError(&'static Counter),
@@ -10,9 +15,14 @@ pub enum RuntimeStats {
     IOError(&'static Counter),
     AgentError(&'static Counter),
     StreamError(&'static Counter),
+    TimeoutError(&'static Counter),
     ExtractionError(&'static Counter),
+    CorpusReason(&'static Counter),
+    ObjectiveReason(&'static Counter),
+    HandshakeComplete(&'static Counter),
     TraceLength(&'static MinMaxMean),
     TermSize(&'static MinMaxMean),
+    ExecutionTime(&'static Timer),
 }
 
 impl RuntimeStats {
@@ -28,9 +38,14 @@ impl RuntimeStats {
             Self::IOError(inner) => inner.fire(consume),
             Self::AgentError(inner) => inner.fire(consume),
             Self::StreamError(inner) => inner.fire(consume),
+            Self::TimeoutError(inner) => inner.fire(consume),
             Self::ExtractionError(inner) => inner.fire(consume),
+            Self::CorpusReason(inner) => inner.fire(consume),
+            Self::ObjectiveReason(inner) => inner.fire(consume),
+            Self::HandshakeComplete(inner) => inner.fire(consume),
             Self::TraceLength(inner) => inner.fire(consume),
             Self::TermSize(inner) => inner.fire(consume),
+            Self::ExecutionTime(inner) => inner.fire(consume),
         }
     }
 }
@@ -49,14 +64,36 @@ pub static IO: Counter = Counter::new("io");
 pub static AGENT: Counter = Counter::new("ag");
 // Stream(String),
 pub static STREAM: Counter = Counter::new("str");
+// Timeout(String),
+pub static TIMEOUT: Counter = Counter::new("timeout");
 // Extraction(ContentType),
 pub static EXTRACTION: Counter = Counter::new("extr");
 
+// Inputs added to the corpus because they cover new edges
+pub static CORPUS_NEW_EDGES: Counter = Counter::new("corpus-edges");
+// Inputs added to the corpus which contain a new pair of message types, recorded as a reason only
+pub static CORPUS_NEW_MESSAGE_TYPES: Counter = Counter::new("corpus-msg-types");
+// Inputs added to the corpus because their records decrypt in a new pattern, with
+// `--decryption-patterns` only
+pub static CORPUS_NEW_DECRYPTION_PATTERNS: Counter = Counter::new("corpus-decrypt-patterns");
+// Inputs added to the corpus in which an agent reached a new state, recorded as a reason only
+pub static CORPUS_NEW_HANDSHAKE_STATES: Counter = Counter::new("corpus-handshake-states");
+
+// Crash sites of the objectives, i.e. crashes with a distinct backtrace
+pub static OBJECTIVE_CRASH_SITES: Counter = Counter::new("objective-crash-sites");
+
+// Executions in which both peers finished the handshake
+pub static HANDSHAKE_COMPLETE: Counter = Counter::new("handshake-complete");
+
 pub static TRACE_LENGTH: MinMaxMean = MinMaxMean::new("trace-length");
 
 pub static TERM_SIZE: MinMaxMean = MinMaxMean::new("term-size");
 
-pub static STATS: [RuntimeStats; 10] = [
+pub static EXECUTION_TIME: Timer = Timer::new("exec-time-us", &MONOTONIC_CLOCK);
+
+pub static MONOTONIC_CLOCK: MonotonicClock = MonotonicClock::new();
+
+pub static STATS: [RuntimeStats; 18] = [
     RuntimeStats::FnError(&FN_ERROR),
     RuntimeStats::TermError(&TERM),
     RuntimeStats::CodecError(&TERM),
@@ -64,9 +101,17 @@ pub static STATS: [RuntimeStats; 10] = [
     RuntimeStats::IOError(&IO),
     RuntimeStats::AgentError(&AGENT),
     RuntimeStats::StreamError(&STREAM),
+    RuntimeStats::TimeoutError(&TIMEOUT),
     RuntimeStats::ExtractionError(&EXTRACTION),
+    RuntimeStats::CorpusReason(&CORPUS_NEW_EDGES),
+    RuntimeStats::CorpusReason(&CORPUS_NEW_MESSAGE_TYPES),
+    RuntimeStats::CorpusReason(&CORPUS_NEW_DECRYPTION_PATTERNS),
+    RuntimeStats::CorpusReason(&CORPUS_NEW_HANDSHAKE_STATES),
+    RuntimeStats::ObjectiveReason(&OBJECTIVE_CRASH_SITES),
+    RuntimeStats::HandshakeComplete(&HANDSHAKE_COMPLETE),
     RuntimeStats::TraceLength(&TRACE_LENGTH),
     RuntimeStats::TermSize(&TERM_SIZE),
+    RuntimeStats::ExecutionTime(&EXECUTION_TIME),
 ];
 
 pub trait Fire: Sync {
@@ -76,6 +121,7 @@ pub trait Fire: Sync {
     ) -> Result<(), Error>;
 }
 
+#[derive(Debug)]
 pub struct Counter {
     pub name: &'static str,
     counter: AtomicUsize,
@@ -92,6 +138,15 @@ impl Counter {
     pub fn increment(&self) {
         self.counter.fetch_add(1, Ordering::SeqCst);
     }
+
+    pub fn set(&self, value: usize) {
+        self.counter.store(value, Ordering::SeqCst);
+    }
+
+    #[must_use]
+    pub fn value(&self) -> usize {
+        self.counter.load(Ordering::SeqCst)
+    }
 }
 
 impl Fire for Counter {
@@ -197,10 +252,131 @@ impl Fire for MinMaxMean {
     }
 }
 
+/// Source of time for [`Timer`]s
+pub trait Clock: Sync {
+    /// Time elapsed since some fixed point in time
+    fn now(&self) -> Duration;
+}
+
+/// High-resolution monotonic clock, which starts when it is read the first time
+pub struct MonotonicClock {
+    origin: OnceLock<Instant>,
+}
+
+impl MonotonicClock {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            origin: OnceLock::new(),
+        }
+    }
+}
+
+impl Default for MonotonicClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for MonotonicClock {
+    fn now(&self) -> Duration {
+        self.origin.get_or_init(Instant::now).elapsed()
+    }
+}
+
+/// Clock which only advances when told to. This makes timing stats deterministic in tests.
+#[derive(Default)]
+pub struct FakeClock {
+    nanos: AtomicU64,
+}
+
+impl FakeClock {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            nanos: AtomicU64::new(0),
+        }
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.nanos.fetch_add(by.as_nanos() as u64, Ordering::SeqCst);
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> Duration {
+        Duration::from_nanos(self.nanos.load(Ordering::SeqCst))
+    }
+}
+
+/// Measures the duration of [`TimerSpan`]s in microseconds
+pub struct Timer {
+    clock: &'static dyn Clock,
+    durations: MinMaxMean,
+}
+
+impl Timer {
+    pub const fn new(name: &'static str, clock: &'static dyn Clock) -> Self {
+        Self {
+            clock,
+            durations: MinMaxMean::new(name),
+        }
+    }
+
+    #[must_use]
+    pub const fn name(&self) -> &'static str {
+        self.durations.name
+    }
+
+    /// Starts a span which is measured when it is finished or dropped. Spans can be nested, each
+    /// of them measures the time since its own start.
+    #[must_use]
+    pub fn start(&self) -> TimerSpan<'_> {
+        TimerSpan {
+            timer: self,
+            start: self.clock.now(),
+        }
+    }
+}
+
+impl Fire for Timer {
+    fn fire(
+        &self,
+        consume: &mut dyn FnMut(String, UserStats) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        self.durations.fire(consume)
+    }
+}
+
+pub struct TimerSpan<'a> {
+    timer: &'a Timer,
+    start: Duration,
+}
+
+impl TimerSpan<'_> {
+    /// Time elapsed since the span started
+    #[must_use]
+    pub fn elapsed(&self) -> Duration {
+        self.timer.clock.now().saturating_sub(self.start)
+    }
+
+    /// Records the duration of the span and returns it
+    pub fn finish(self) -> Duration {
+        self.elapsed()
+    }
+}
+
+impl Drop for TimerSpan<'_> {
+    fn drop(&mut self) {
+        self.timer
+            .durations
+            .update(self.elapsed().as_micros() as usize);
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct StatsStage<E, EM, Z> {
-    #[allow(clippy::type_complexity)]
-    phantom: PhantomData<(E, EM, Z)>,
+    phantom: StagePhantom<E, EM, Z>,
 }
 
 impl<E, EM, Z> UsesState for StatsStage<E, EM, Z>
@@ -270,3 +446,37 @@ where
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_log::test]
+    fn test_fake_clock_timer() {
+        static CLOCK: FakeClock = FakeClock::new();
+        static TIMER: Timer = Timer::new("test-time", &CLOCK);
+
+        let outer = TIMER.start();
+        CLOCK.advance(Duration::from_micros(150));
+
+        let inner = TIMER.start();
+        CLOCK.advance(Duration::from_micros(42));
+        assert_eq!(inner.finish(), Duration::from_micros(42));
+
+        CLOCK.advance(Duration::from_micros(8));
+        assert_eq!(outer.finish(), Duration::from_micros(200));
+
+        let mut recorded = vec![];
+        TIMER
+            .fire(&mut |name, stats| {
+                if let UserStatsValue::Number(n) = stats.value() {
+                    recorded.push((name, *n));
+                }
+                Ok(())
+            })
+            .unwrap();
+
+        assert!(recorded.contains(&("test-time-min".to_string(), 42)));
+        assert!(recorded.contains(&("test-time-max".to_string(), 200)));
+    }
+}
@@ -3,22 +3,55 @@
 
 use libafl_bolts::rands::Rand;
 
+use crate::agent::AgentDescriptor;
 use crate::algebra::atoms::Function;
 use crate::algebra::signature::{FunctionDefinition, Signature};
 use crate::algebra::{DYTerm, Term};
+use crate::execution::Outcome;
 use crate::fuzzer::utils::Choosable;
-use crate::protocol::ProtocolTypes;
+use crate::protocol::{ProtocolBehavior, ProtocolTypes};
+use crate::trace::Trace;
 
 const MAX_DEPTH: u16 = 8; // how deep terms we allow max
 const MAX_TRIES: u16 = 100; // How often we want to try to generate before stopping
 
+/// Limits of the generation of a [`TermZoo`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TermZooConfig {
+    /// Maximum depth of a generated term
+    pub max_depth: u16,
+    /// How often the generation of a term for a function symbol is tried before giving up
+    pub max_tries: u16,
+}
+
+impl Default for TermZooConfig {
+    fn default() -> Self {
+        Self {
+            max_depth: MAX_DEPTH,
+            max_tries: MAX_TRIES,
+        }
+    }
+}
+
 pub struct TermZoo<PT: ProtocolTypes> {
     terms: Vec<Term<PT>>,
+    /// Names of the function symbols for which no term could be generated
+    missing_functions: Vec<String>,
 }
 
 impl<PT: ProtocolTypes> TermZoo<PT> {
     pub fn generate<R: Rand>(signature: &Signature<PT>, rand: &mut R) -> Self {
-        Self::generate_many(signature, rand, 1, None)
+        Self::generate_with_config(signature, rand, TermZooConfig::default())
+    }
+
+    /// Generates a term for each function symbol within the limits of `config`. Function symbols
+    /// without a term within these limits are listed in [`TermZoo::missing_functions`].
+    pub fn generate_with_config<R: Rand>(
+        signature: &Signature<PT>,
+        rand: &mut R,
+        config: TermZooConfig,
+    ) -> Self {
+        Self::generate_many_with_config(signature, rand, 1, None, config)
     }
 
     pub fn generate_many<R: Rand>(
@@ -26,10 +59,33 @@ impl<PT: ProtocolTypes> TermZoo<PT> {
         rand: &mut R,
         how_many: usize,
         filter: Option<&FunctionDefinition<PT>>,
+    ) -> Self {
+        Self::generate_many_with_config(signature, rand, how_many, filter, TermZooConfig::default())
+    }
+
+    pub fn generate_many_with_config<R: Rand>(
+        signature: &Signature<PT>,
+        rand: &mut R,
+        how_many: usize,
+        filter: Option<&FunctionDefinition<PT>>,
+        config: TermZooConfig,
     ) -> Self {
         let mut acc = vec![];
-        if let Some(def) = filter {
-            let mut counter = MAX_TRIES as usize * how_many;
+        let mut missing_functions = vec![];
+
+        let definitions: Vec<&FunctionDefinition<PT>> = if let Some(def) = filter {
+            vec![def]
+        } else {
+            // Deprecated symbols are not introduced into new terms
+            signature
+                .functions
+                .iter()
+                .filter(|(shape, _)| !signature.is_deprecated(shape.name))
+                .collect()
+        };
+
+        for def in definitions {
+            let mut counter = config.max_tries as usize * how_many;
             let mut many = 0;
 
             loop {
@@ -39,32 +95,22 @@ impl<PT: ProtocolTypes> TermZoo<PT> {
 
                 counter -= 1;
 
-                if let Some(term) = Self::generate_term(signature, def, MAX_DEPTH, rand) {
+                if let Some(term) = Self::generate_term(signature, def, config.max_depth, rand) {
                     many += 1;
                     acc.push(term);
                 }
             }
-        } else {
-            for def in &signature.functions {
-                let mut counter = MAX_TRIES as usize * how_many;
-                let mut many = 0;
-
-                loop {
-                    if counter == 0 || many >= how_many {
-                        break;
-                    }
 
-                    counter -= 1;
-
-                    if let Some(term) = Self::generate_term(signature, def, MAX_DEPTH, rand) {
-                        many += 1;
-                        acc.push(term);
-                    }
-                }
+            if many == 0 {
+                // Usually an argument type has no function producing it
+                missing_functions.push(def.0.name.to_string());
             }
         }
 
-        Self { terms: acc }
+        Self {
+            terms: acc,
+            missing_functions,
+        }
     }
 
     fn generate_term<R: Rand>(
@@ -84,7 +130,20 @@ impl<PT: ProtocolTypes> TermZoo<PT> {
 
         for typ in required_types {
             if let Some(possibilities) = signature.functions_by_typ.get(typ) {
-                if let Some(possibility) = possibilities.choose(rand) {
+                // Deprecated symbols are redrawn from the other symbols, which keeps the choice
+                // uniform. A type which is only produced by deprecated symbols is not available.
+                let possibility = possibilities.choose(rand).and_then(|possibility| {
+                    if signature.is_deprecated(possibility.0.name) {
+                        possibilities.choose_filtered(
+                            |(shape, _)| !signature.is_deprecated(shape.name),
+                            rand,
+                        )
+                    } else {
+                        Some(possibility)
+                    }
+                });
+
+                if let Some(possibility) = possibility {
                     if let Some(subterm) =
                         Self::generate_term(signature, possibility, depth - 1, rand)
                     {
@@ -109,6 +168,26 @@ impl<PT: ProtocolTypes> TermZoo<PT> {
         )))
     }
 
+    /// Generates traces which violate the protocol, see
+    /// [`ProtocolBehavior::create_negative_corpus`].
+    ///
+    /// Each trace is labeled with the expected outcome [`Outcome::Rejected`], such that a harness
+    /// can assert that the agent spawned from `descriptor` rejects it and flag the traces which are
+    /// accepted.
+    pub fn negative_traces<PB>(descriptor: &AgentDescriptor<PT::PUTConfig>) -> Vec<Trace<PT>>
+    where
+        PB: ProtocolBehavior<ProtocolTypes = PT>,
+    {
+        PB::create_negative_corpus(descriptor)
+            .into_iter()
+            .map(|(mut trace, name)| {
+                log::debug!("Generated negative trace {name}");
+                trace.expected_outcome = Some(Outcome::Rejected);
+                trace
+            })
+            .collect()
+    }
+
     pub fn choose_filtered<P, R: Rand>(&self, filter: P, rand: &mut R) -> Option<&Term<PT>>
     where
         P: FnMut(&&Term<PT>) -> bool,
@@ -120,4 +199,178 @@ impl<PT: ProtocolTypes> TermZoo<PT> {
     pub fn terms(&self) -> &[Term<PT>] {
         &self.terms
     }
+
+    /// Names of the function symbols for which no term could be generated within the limits of the
+    /// [`TermZooConfig`]
+    #[must_use]
+    pub fn missing_functions(&self) -> Vec<String> {
+        self.missing_functions.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl_bolts::rands::StdRand;
+
+    use super::*;
+    use crate::algebra::dynamic_function::{make_dynamic, DescribableFunction, FunctionAttributes};
+    use crate::algebra::error::FnError;
+    use crate::algebra::test_signature::*;
+    use crate::algebra::TermType;
+    use crate::error::Error;
+    use crate::protocol::Extractable;
+    use crate::trace::{Knowledge, Source, Spawner, TraceContext};
+    use crate::{codec, term};
+
+    /// Type of the `N`-th link of a chain of function symbols
+    #[derive(Debug, Clone)]
+    struct Link<const N: usize>;
+
+    impl<const N: usize> codec::CodecP for Link<N> {
+        fn encode(&self, _bytes: &mut Vec<u8>) {}
+
+        fn read(&mut self, _r: &mut codec::Reader) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    impl<const N: usize> Extractable<TestProtocolTypes> for Link<N> {
+        fn extract_knowledge<'a>(
+            &'a self,
+            _knowledges: &mut Vec<Knowledge<'a, TestProtocolTypes>>,
+            _matcher: Option<<TestProtocolTypes as ProtocolTypes>::Matcher>,
+            _source: &'a Source,
+        ) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    fn fn_link_start() -> Result<Link<0>, FnError> {
+        Ok(Link)
+    }
+
+    fn fn_link<const N: usize, const M: usize>(_link: &Link<N>) -> Result<Link<M>, FnError> {
+        Ok(Link)
+    }
+
+    #[test_log::test]
+    fn test_zoo_covers_signature() {
+        let mut rand = StdRand::with_seed(101);
+        let zoo = TermZoo::<TestProtocolTypes>::generate(&TEST_SIGNATURE, &mut rand);
+
+        assert!(zoo.missing_functions().is_empty());
+        assert_eq!(zoo.terms().len(), TEST_SIGNATURE.functions.len());
+    }
+
+    #[test_log::test]
+    fn test_zoo_lists_missing_functions() {
+        // Nothing in the signature produces the `u8` argument of `example_op_c`
+        let definitions = vec![
+            (make_dynamic(&fn_seq_0), Default::default()),
+            (make_dynamic(&example_op_c), Default::default()),
+        ];
+        let signature = Signature::<TestProtocolTypes>::new(definitions);
+
+        let mut rand = StdRand::with_seed(101);
+        let zoo = TermZoo::generate(&signature, &mut rand);
+
+        assert_eq!(
+            zoo.missing_functions(),
+            vec![example_op_c.name().to_string()]
+        );
+        assert_eq!(zoo.terms().len(), 1);
+    }
+
+    #[test_log::test]
+    fn test_zoo_skips_deprecated_functions() {
+        let deprecated = FunctionAttributes {
+            is_deprecated: true,
+            ..FunctionAttributes::default()
+        };
+        let definitions = vec![
+            (make_dynamic(&fn_finished), Default::default()),
+            (make_dynamic(&fn_seq_0), Default::default()),
+            (make_dynamic(&fn_seq_1), deprecated),
+            (make_dynamic(&fn_encrypt12), Default::default()),
+        ];
+        let signature = Signature::<TestProtocolTypes>::new(definitions.clone());
+
+        let mut rand = StdRand::with_seed(101);
+        let zoo = TermZoo::generate_many(&signature, &mut rand, 20, None);
+
+        assert!(zoo.missing_functions().is_empty());
+        assert!(!zoo.terms().is_empty());
+        assert!(zoo
+            .terms()
+            .iter()
+            .all(|term| term.count_functions_by_name(fn_seq_1.name()) == 0));
+
+        // A type which only deprecated symbols produce is not available
+        let without_seq_0 = definitions
+            .into_iter()
+            .filter(|((shape, _), _)| shape.name != fn_seq_0.name())
+            .collect();
+        let signature = Signature::<TestProtocolTypes>::new(without_seq_0);
+        let zoo = TermZoo::generate(&signature, &mut rand);
+
+        assert_eq!(
+            zoo.missing_functions(),
+            vec![fn_encrypt12.name().to_string()]
+        );
+
+        // Existing terms which use deprecated symbols are still evaluated
+        let registry = dummy_registry();
+        let ctx = TraceContext::new(Spawner::new(registry));
+        let term: TestTerm = term! { fn_encrypt12(fn_finished, fn_seq_1) };
+
+        assert!(term.evaluate_dy(&ctx).is_ok());
+    }
+
+    #[test_log::test]
+    fn test_zoo_config_max_depth() {
+        // The only term of `fn_link::<8, 9>` is a chain of depth 10
+        let definitions = vec![
+            (make_dynamic(&fn_link_start), Default::default()),
+            (make_dynamic(&fn_link::<0, 1>), Default::default()),
+            (make_dynamic(&fn_link::<1, 2>), Default::default()),
+            (make_dynamic(&fn_link::<2, 3>), Default::default()),
+            (make_dynamic(&fn_link::<3, 4>), Default::default()),
+            (make_dynamic(&fn_link::<4, 5>), Default::default()),
+            (make_dynamic(&fn_link::<5, 6>), Default::default()),
+            (make_dynamic(&fn_link::<6, 7>), Default::default()),
+            (make_dynamic(&fn_link::<7, 8>), Default::default()),
+            (make_dynamic(&fn_link::<8, 9>), Default::default()),
+        ];
+        let signature = Signature::<TestProtocolTypes>::new(definitions);
+        let deepest = &signature.functions_by_name[fn_link::<8, 9>.name()];
+
+        let shallow = TermZooConfig {
+            max_depth: 8,
+            ..TermZooConfig::default()
+        };
+        let deep = TermZooConfig {
+            max_depth: 12,
+            ..TermZooConfig::default()
+        };
+        let mut rand = StdRand::with_seed(101);
+
+        let zoo =
+            TermZoo::generate_many_with_config(&signature, &mut rand, 1, Some(deepest), shallow);
+        assert!(zoo.terms().is_empty());
+
+        let zoo = TermZoo::generate_many_with_config(&signature, &mut rand, 1, Some(deepest), deep);
+        assert_eq!(zoo.terms().len(), 1);
+        assert_eq!(zoo.terms()[0].depth(), 10);
+
+        // The terms within the limits are kept
+        let zoo = TermZoo::generate_with_config(&signature, &mut rand, shallow);
+        assert_eq!(zoo.terms().len(), 8);
+        assert_eq!(
+            zoo.missing_functions(),
+            vec![
+                fn_link::<7, 8>.name().to_string(),
+                fn_link::<8, 9>.name().to_string()
+            ]
+        );
+    }
 }
@@ -0,0 +1,155 @@
+//! Stopping the campaign after a wall-clock time budget.
+//!
+//! `--max-iters` bounds the number of iterations of a client, which makes the duration of a
+//! campaign hard to predict. For time-boxed runs, e.g. in CI, the [`TimeLimitStage`] stops a client
+//! once a deadline has passed. The deadline is fixed when the campaign starts, such that a client
+//! which restarted after a crash does not get a fresh budget. The stage only runs between
+//! iterations, therefore an iteration which exceeds the budget is completed before the client
+//! stops.
+
+use std::marker::PhantomData;
+use std::time::Instant;
+
+use libafl::corpus::CorpusId;
+use libafl::events::{EventRestarter, ProgressReporter};
+use libafl::prelude::{Evaluator, UsesState};
+use libafl::stages::Stage;
+use libafl::state::{HasExecutions, HasLastReportTime, HasMetadata};
+use libafl::Error;
+
+use crate::fuzzer::stages::StagePhantom;
+
+/// A [`Stage`] which stops the fuzzing loop once the deadline of the campaign has passed.
+#[derive(Clone, Debug)]
+pub struct TimeLimitStage<E, EM, Z> {
+    /// End of the campaign, `None` if the stage never stops the loop
+    deadline: Option<Instant>,
+    phantom: StagePhantom<E, EM, Z>,
+}
+
+impl<E, EM, Z> UsesState for TimeLimitStage<E, EM, Z>
+where
+    E: UsesState<State = Z::State>,
+    EM: UsesState<State = Z::State>,
+    Z: Evaluator<E, EM>,
+{
+    type State = Z::State;
+}
+
+impl<E, EM, Z> Stage<E, EM, Z> for TimeLimitStage<E, EM, Z>
+where
+    E: UsesState<State = Z::State>,
+    EM: UsesState<State = Z::State> + EventRestarter + ProgressReporter,
+    Z: Evaluator<E, EM>,
+    Z::State: HasMetadata + HasExecutions + HasLastReportTime,
+{
+    fn perform(
+        &mut self,
+        _fuzzer: &mut Z,
+        _executor: &mut E,
+        state: &mut Z::State,
+        manager: &mut EM,
+        _corpus_idx: CorpusId,
+    ) -> Result<(), Error> {
+        if self
+            .deadline
+            .map_or(true, |deadline| Instant::now() < deadline)
+        {
+            return Ok(());
+        }
+
+        log::warn!(
+            "Time budget exhausted after {} executions, stopping the fuzzer.",
+            state.executions()
+        );
+
+        // The monitor shows the final stats of this client
+        manager.report_progress(state)?;
+        // The restarting manager does not respawn the client after this
+        manager.send_exiting()?;
+        Err(Error::shutting_down())
+    }
+}
+
+impl<E, EM, Z> TimeLimitStage<E, EM, Z>
+where
+    E: UsesState<State = Z::State>,
+    EM: UsesState<State = Z::State>,
+    Z: Evaluator<E, EM>,
+{
+    /// Creates the stage, which never stops the fuzzing loop if `deadline` is `None`.
+    pub fn new(deadline: Option<Instant>) -> Self {
+        Self {
+            deadline,
+            phantom: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use libafl::corpus::InMemoryCorpus;
+    use libafl::events::NopEventManager;
+    use libafl::executors::{ExitKind, InProcessExecutor};
+    use libafl::feedbacks::ConstFeedback;
+    use libafl::fuzzer::{Fuzzer, StdFuzzer};
+    use libafl::mutators::StdScheduledMutator;
+    use libafl::schedulers::QueueScheduler;
+    use libafl::stages::StdMutationalStage;
+    use libafl::state::StdState;
+    use libafl_bolts::rands::StdRand;
+    use libafl_bolts::tuples::tuple_list;
+
+    use super::*;
+    use crate::algebra::test_signature::{setup_simple_trace, TestTrace};
+    use crate::fuzzer::mutations::RepeatMutator;
+
+    type TestState =
+        StdState<TestTrace, InMemoryCorpus<TestTrace>, StdRand, InMemoryCorpus<TestTrace>>;
+
+    #[test_log::test]
+    fn test_stop_after_time_budget() {
+        let mut state: TestState = StdState::new(
+            StdRand::with_seed(1235),
+            InMemoryCorpus::new(),
+            InMemoryCorpus::new(),
+            &mut (),
+            &mut (),
+        )
+        .unwrap();
+
+        let mut fuzzer = StdFuzzer::new(
+            QueueScheduler::new(),
+            ConstFeedback::False,
+            ConstFeedback::False,
+        );
+        let mut manager = NopEventManager::new();
+        let mut harness = |_input: &TestTrace| ExitKind::Ok;
+        let mut executor =
+            InProcessExecutor::new(&mut harness, (), &mut fuzzer, &mut state, &mut manager)
+                .unwrap();
+
+        fuzzer
+            .add_input(
+                &mut state,
+                &mut executor,
+                &mut manager,
+                setup_simple_trace(),
+            )
+            .unwrap();
+
+        let start = Instant::now();
+        let mutator = StdScheduledMutator::new(tuple_list!(RepeatMutator::new(16, true)));
+        let mut stages = tuple_list!(
+            StdMutationalStage::new(mutator),
+            TimeLimitStage::new(Some(start + Duration::from_millis(100)))
+        );
+
+        let result = fuzzer.fuzz_loop(&mut stages, &mut executor, &mut state, &mut manager);
+
+        assert!(matches!(result, Err(Error::ShuttingDown)));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+}
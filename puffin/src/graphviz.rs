@@ -7,7 +7,9 @@ use std::{fmt, io};
 
 use itertools::Itertools;
 
+use crate::agent::AgentName;
 use crate::algebra::{remove_fn_prefix, remove_prefix, DYTerm, Term, TermType};
+use crate::claims::Claim;
 use crate::protocol::ProtocolTypes;
 use crate::trace::{Action, Trace};
 
@@ -34,6 +36,11 @@ const COLOR_LEAVES_PAYLOAD: &str = "#ff00ff";
 const COLOR_PAYLOAD: &str = "#ff0000";
 const SHOW_LABELS: bool = false;
 
+// Diff theme
+const COLOR_DIFF_COMMON: &str = "white";
+const COLOR_DIFF_ADDED: &str = "green";
+const COLOR_DIFF_REMOVED: &str = "red";
+
 pub fn write_graphviz(output: &str, format: &str, dot_script: &str) -> Result<(), io::Error> {
     let mut child = Command::new("dot")
         .args(["-o", output, "-T", format])
@@ -60,6 +67,193 @@ pub fn write_graphviz(output: &str, format: &str, dot_script: &str) -> Result<()
     Ok(())
 }
 
+/// Renders the ordered `claims` as a state machine of the handshake progression.
+///
+/// Each claim is a state, labeled with the [`Display`](fmt::Display) of the claim. The edge into a
+/// state is labeled with the type of the claim, i.e. the message which triggered it. The claims of
+/// each agent are drawn in a subgraph of their own, such that interleaved claims of clients and
+/// servers form separate chains.
+pub fn claims_to_dot<C: Claim + fmt::Display>(claims: &[(AgentName, C)]) -> String {
+    let states: Vec<(AgentName, String, String)> = claims
+        .iter()
+        .map(|(agent, claim)| (*agent, claim.to_string(), remove_prefix(claim.id().name)))
+        .collect();
+
+    chains_to_dot("Claims", "claim", &states)
+}
+
+/// Renders the types of the messages which the agents sent as a chain of messages for each agent.
+///
+/// The messages are usually those of
+/// [`TraceContext::sent_message_types`](crate::trace::TraceContext::sent_message_types).
+pub fn messages_to_dot(messages: &[(AgentName, &str)]) -> String {
+    let states: Vec<(AgentName, String, String)> = messages
+        .iter()
+        .map(|(agent, message_type)| (*agent, (*message_type).to_owned(), String::new()))
+        .collect();
+
+    chains_to_dot("Messages", "message", &states)
+}
+
+/// Renders the `states` of each agent as a chain, in a subgraph of their own. Each state is a
+/// triple of the agent, the label of the state and the label of the edge into the state.
+fn chains_to_dot(name: &str, node_prefix: &str, states: &[(AgentName, String, String)]) -> String {
+    let agents: Vec<AgentName> = states.iter().map(|(agent, _, _)| *agent).unique().collect();
+
+    let subgraphs: Vec<String> = agents
+        .iter()
+        .map(|agent| {
+            let agent_id = u8::from(*agent);
+            let mut previous = format!("start_{agent_id}");
+            let mut statements = vec![format!("{previous} [label=\"\",shape=\"point\"];")];
+
+            for (i, (_, label, edge_label)) in states
+                .iter()
+                .enumerate()
+                .filter(|(_, (state_agent, _, _))| state_agent == agent)
+            {
+                let node = format!("{node_prefix}_{i}");
+                statements.push(format!(
+                    "{node} [label=\"{}\",shape=\"box\",fontname=\"{FONT}\"];",
+                    label.replace('"', "\\\"")
+                ));
+                statements.push(format!(
+                    "{previous} -> {node} [label=\"{edge_label}\",fontname=\"{FONT}\"];"
+                ));
+                previous = node;
+            }
+
+            format!(
+                "subgraph cluster_agent_{agent_id} \
+            {{ \
+                label=\"{agent}\";\
+                \n{}\n\
+            }}",
+                statements.join("\n"),
+            )
+        })
+        .collect();
+
+    format!(
+        "digraph \"{name}\" \
+        {{ \
+            fontname=\"{FONT}\";\
+            {} \
+        }}",
+        subgraphs.join("\n")
+    )
+}
+
+/// Renders the union of the trees of `before` and `after`, e.g. a term before and after a mutation.
+///
+/// The trees are aligned by structure: Nodes at the same position with the same symbol are common
+/// and drawn neutral, and their arguments are compared pairwise. Otherwise, the subtree of `before`
+/// is drawn as removed in red and the subtree of `after` as added in green. Entirely disjoint terms
+/// are therefore rendered side by side and fully colored.
+pub fn term_diff_to_dot<PT: ProtocolTypes>(before: &Term<PT>, after: &Term<PT>) -> String {
+    let mut statements = Vec::new();
+    collect_diff_statements(before, after, "n", None, &mut statements);
+
+    format!(
+        "digraph \"TermDiff\" \
+        {{ \
+            fontname=\"{FONT}\";\
+            \n{}\n\
+        }}",
+        statements.join("\n")
+    )
+}
+
+fn diff_label<PT: ProtocolTypes>(term: &Term<PT>) -> String {
+    match &term.term {
+        DYTerm::Variable(variable) => variable.to_string(),
+        DYTerm::Application(func, _) => remove_fn_prefix(&remove_prefix(func.name())),
+    }
+}
+
+fn is_same_symbol<PT: ProtocolTypes>(a: &Term<PT>, b: &Term<PT>) -> bool {
+    match (&a.term, &b.term) {
+        (DYTerm::Variable(a), DYTerm::Variable(b)) => a.to_string() == b.to_string(),
+        (DYTerm::Application(func_a, subterms_a), DYTerm::Application(func_b, subterms_b)) => {
+            func_a.name() == func_b.name() && subterms_a.len() == subterms_b.len()
+        }
+        _ => false,
+    }
+}
+
+fn push_diff_node(
+    id: &str,
+    parent: Option<&str>,
+    label: &str,
+    color: &str,
+    statements: &mut Vec<String>,
+) {
+    statements.push(format!(
+        "{id} [label=\"{}\",style=\"filled\",fillcolor=\"{color}\",shape=\"box\",fontname=\"{FONT}\"];",
+        label.replace('"', "\\\"")
+    ));
+    if let Some(parent) = parent {
+        statements.push(format!("{parent} -> {id};"));
+    }
+}
+
+/// Pushes the node `id` for `term` together with all of its subterms, drawn in `color`
+fn collect_colored_statements<PT: ProtocolTypes>(
+    term: &Term<PT>,
+    id: &str,
+    parent: Option<&str>,
+    color: &str,
+    statements: &mut Vec<String>,
+) {
+    push_diff_node(id, parent, &diff_label(term), color, statements);
+
+    if let DYTerm::Application(_, subterms) = &term.term {
+        for (i, subterm) in subterms.iter().enumerate() {
+            collect_colored_statements(subterm, &format!("{id}_{i}"), Some(id), color, statements);
+        }
+    }
+}
+
+fn collect_diff_statements<PT: ProtocolTypes>(
+    before: &Term<PT>,
+    after: &Term<PT>,
+    id: &str,
+    parent: Option<&str>,
+    statements: &mut Vec<String>,
+) {
+    if !is_same_symbol(before, after) {
+        let removed = format!("{id}_removed");
+        collect_colored_statements(before, &removed, parent, COLOR_DIFF_REMOVED, statements);
+        let added = format!("{id}_added");
+        collect_colored_statements(after, &added, parent, COLOR_DIFF_ADDED, statements);
+        return;
+    }
+
+    push_diff_node(
+        id,
+        parent,
+        &diff_label(before),
+        COLOR_DIFF_COMMON,
+        statements,
+    );
+
+    if let (DYTerm::Application(_, subterms_before), DYTerm::Application(_, subterms_after)) =
+        (&before.term, &after.term)
+    {
+        for (i, (subterm_before, subterm_after)) in
+            subterms_before.iter().zip(subterms_after).enumerate()
+        {
+            collect_diff_statements(
+                subterm_before,
+                subterm_after,
+                &format!("{id}_{i}"),
+                Some(id),
+                statements,
+            );
+        }
+    }
+}
+
 impl<PT: ProtocolTypes> Trace<PT> {
     #[must_use]
     pub fn dot_graph(&self, tree_mode: bool) -> String {
@@ -235,7 +429,9 @@ impl<PT: ProtocolTypes> Term<PT> {
 
 #[cfg(test)]
 mod tests {
-    use crate::algebra::test_signature::setup_simple_trace;
+    use super::*;
+    use crate::algebra::test_signature::*;
+    use crate::term;
 
     #[test_log::test]
     fn test_dot_graph() {
@@ -243,4 +439,49 @@ mod tests {
         let _string = trace.dot_graph(true);
         //println!("{}", string);
     }
+
+    fn count_nodes(dot: &str, color: &str) -> usize {
+        dot.matches(&format!("fillcolor=\"{color}\"")).count()
+    }
+
+    #[test_log::test]
+    fn test_term_diff_to_dot() {
+        let before: TestTerm = term! { fn_encrypt12(fn_finished, fn_seq_0) };
+        let after: TestTerm = term! { fn_encrypt12(fn_client_key_exchange, fn_seq_0) };
+
+        let dot = term_diff_to_dot(&before, &after);
+        assert_eq!(count_nodes(&dot, COLOR_DIFF_COMMON), 2);
+        assert_eq!(count_nodes(&dot, COLOR_DIFF_REMOVED), 1);
+        assert_eq!(count_nodes(&dot, COLOR_DIFF_ADDED), 1);
+        assert!(dot.contains("label=\"finished\""));
+        assert!(dot.contains("label=\"client_key_exchange\""));
+    }
+
+    #[test_log::test]
+    fn test_messages_to_dot() {
+        let (client, server) = (AgentName::first(), AgentName::first().next());
+        let dot = messages_to_dot(&[
+            (client, "ClientHello"),
+            (server, "ServerHello"),
+            (server, "ServerHelloDone"),
+        ]);
+
+        assert!(dot.contains("subgraph cluster_agent_0"));
+        assert!(dot.contains("subgraph cluster_agent_1"));
+        assert!(dot.contains("message_0 [label=\"ClientHello\""));
+        assert!(dot.contains("start_0 -> message_0"));
+        assert!(dot.contains("start_1 -> message_1"));
+        assert!(dot.contains("message_1 -> message_2"));
+    }
+
+    #[test_log::test]
+    fn test_term_diff_to_dot_disjoint() {
+        let before: TestTerm = term! { fn_encrypt12(fn_finished, fn_seq_0) };
+        let after: TestTerm = term! { fn_seq_1 };
+
+        let dot = term_diff_to_dot(&before, &after);
+        assert_eq!(count_nodes(&dot, COLOR_DIFF_COMMON), 0);
+        assert_eq!(count_nodes(&dot, COLOR_DIFF_REMOVED), 3);
+        assert_eq!(count_nodes(&dot, COLOR_DIFF_ADDED), 1);
+    }
 }
@@ -19,5 +19,6 @@ pub mod stream;
 pub mod test_utils;
 pub mod trace;
 pub mod trace_helper;
+pub mod trace_template;
 
 pub use {libafl, libafl_bolts};
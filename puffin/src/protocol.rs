@@ -1,11 +1,12 @@
 use std::any::{Any, TypeId};
+use std::cell::RefCell;
 use std::fmt::{Debug, Display};
 use std::hash::Hash;
 
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
-use crate::agent::ProtocolDescriptorConfig;
+use crate::agent::{Agent, AgentDescriptor, ProtocolDescriptorConfig};
 use crate::algebra::signature::Signature;
 use crate::algebra::Matcher;
 use crate::claims::{Claim, SecurityViolationPolicy};
@@ -41,6 +42,56 @@ where
         matcher: Option<PT::Matcher>,
         source: &'a Source,
     ) -> Result<(), Error>;
+
+    /// Extracts as much knowledge as possible. Unlike `extract_knowledge`, an error in one part
+    /// only loses the knowledge of that part: the errors of all malformed parts are returned
+    /// together with the knowledge of the well-formed ones.
+    ///
+    /// Types which consist of independent parts should override this and extract each part on its
+    /// own. By default, the knowledge extracted before the first error is kept.
+    fn extract_knowledge_partial<'a>(
+        &'a self,
+        matcher: Option<PT::Matcher>,
+        source: &'a Source,
+    ) -> ExtractedKnowledge<'a, PT> {
+        let mut knowledges = vec![];
+        let errors = self
+            .extract_knowledge(&mut knowledges, matcher, source)
+            .err()
+            .into_iter()
+            .collect();
+
+        ExtractedKnowledge { knowledges, errors }
+    }
+}
+
+/// Result of [`Extractable::extract_knowledge_partial`]
+#[derive(Debug)]
+pub struct ExtractedKnowledge<'a, PT: ProtocolTypes> {
+    pub knowledges: Vec<Knowledge<'a, PT>>,
+    pub errors: Vec<Error>,
+}
+
+impl<'a, PT: ProtocolTypes> ExtractedKnowledge<'a, PT> {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            knowledges: vec![],
+            errors: vec![],
+        }
+    }
+
+    /// Adds the knowledge and errors of `other`
+    pub fn extend(&mut self, other: Self) {
+        self.knowledges.extend(other.knowledges);
+        self.errors.extend(other.errors);
+    }
+}
+
+impl<PT: ProtocolTypes> Default for ExtractedKnowledge<'_, PT> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 /// `EvaluatedTerm`: have both Codec and a way to extract knowledge out of a Message/OpaqueMessage
@@ -143,6 +194,10 @@ pub trait ProtocolTypes:
 /// queries for [knowledge](crate::trace::Knowledge).
 pub trait ProtocolBehavior: 'static {
     type ProtocolTypes: ProtocolTypes;
+    /// Protocol extension which a PUT may implement, see [`Put::supported_extensions`]
+    ///
+    /// [`Put::supported_extensions`]: crate::put::Put::supported_extensions
+    type Extension: Debug + Clone;
     type Claim: Claim<PT = Self::ProtocolTypes>;
     type SecurityViolationPolicy: SecurityViolationPolicy<C = Self::Claim>;
     type ProtocolMessage: ProtocolMessage<Self::ProtocolTypes, Self::OpaqueProtocolMessage>;
@@ -159,6 +214,53 @@ pub trait ProtocolBehavior: 'static {
     /// Creates a sane initial seed corpus.
     fn create_corpus(put: PutDescriptor) -> Vec<(Trace<Self::ProtocolTypes>, &'static str)>;
 
+    /// A trace which every PUT which is built correctly executes successfully, by default the first
+    /// seed of the corpus of `put`. See [`PutRegistry::self_test`].
+    ///
+    /// [`PutRegistry::self_test`]: crate::put_registry::PutRegistry::self_test
+    fn default_trace(put: PutDescriptor) -> Option<Trace<Self::ProtocolTypes>> {
+        Self::create_corpus(put)
+            .into_iter()
+            .next()
+            .map(|(trace, _)| trace)
+    }
+
+    /// Creates traces which violate the protocol, e.g. through a wrong version, a missing
+    /// mandatory extension or messages out of order. An agent spawned from `descriptor` has to
+    /// reject each of them.
+    fn create_negative_corpus(
+        _descriptor: &AgentDescriptor<<Self::ProtocolTypes as ProtocolTypes>::PUTConfig>,
+    ) -> Vec<(Trace<Self::ProtocolTypes>, &'static str)> {
+        vec![]
+    }
+
+    /// Stable name of the kind of `message`, e.g. "ClientHello", for logging and feedback.
+    ///
+    /// Messages of a kind which the protocol does not know are named "Unknown".
+    fn message_type_name(_message: &Self::ProtocolMessage) -> &'static str {
+        "Unknown"
+    }
+
+    /// Outcome of the decryption of the protected records in `input`, which `agent` just
+    /// processed: `Some(false)` if the PUT failed to decrypt a record, `Some(true)` if it
+    /// decrypted them and `None` if `input` holds no protected records or the PUT can not tell.
+    ///
+    /// `protected` tells whether the records which the agent receives are already protected by
+    /// a previous input, e.g. after a ChangeCipherSpec in TLS 1.2, and is updated for `input`.
+    ///
+    /// The outcome is passed to [`record_decryption`] for each input. Protocols which do not
+    /// protect their records keep the default.
+    fn put_decryption_outcome(
+        _agent: &Agent<Self>,
+        _input: &[u8],
+        _protected: &mut bool,
+    ) -> Option<bool>
+    where
+        Self: Sized,
+    {
+        None
+    }
+
     /// Downcast from `Box<dyn Any>` and encode as bitstring any message as per the PB's internal
     /// structure
     fn any_get_encoding(message: &dyn EvaluatedTerm<Self::ProtocolTypes>) -> Vec<u8> {
@@ -174,6 +276,48 @@ pub trait ProtocolBehavior: 'static {
     ) -> Result<Box<dyn EvaluatedTerm<Self::ProtocolTypes>>, Error>;
 }
 
+/// A [`ProtocolBehavior`] whose PUTs can report the alerts with which they abort connections.
+///
+/// Protocols without alerts do not implement it, see [`Put::last_alert`].
+///
+/// [`Put::last_alert`]: crate::put::Put::last_alert
+pub trait ProtocolAlerts: ProtocolBehavior {
+    /// Alert which a PUT raises when it aborts a connection
+    type Alert: Debug + Clone;
+}
+
+thread_local! {
+    /// Outcomes of the record decryptions since [`start_recording_decryptions`], `None` if they
+    /// are not recorded
+    static DECRYPTIONS: RefCell<Option<Vec<bool>>> = const { RefCell::new(None) };
+}
+
+/// Records whether the decryption of a record succeeded. Function symbols call it for the records
+/// which they decrypt with the keys of the attacker, and the execution of a trace for the records
+/// which a PUT decrypts, see [`ProtocolBehavior::put_decryption_outcome`].
+///
+/// Does nothing if the decryptions are not recorded.
+pub fn record_decryption(success: bool) {
+    DECRYPTIONS.with(|decryptions| {
+        if let Some(outcomes) = decryptions.borrow_mut().as_mut() {
+            outcomes.push(success);
+        }
+    });
+}
+
+/// Starts to record the decryptions, forgetting any outcome recorded before
+pub(crate) fn start_recording_decryptions() {
+    DECRYPTIONS.with(|decryptions| decryptions.replace(Some(vec![])));
+}
+
+/// Stops to record the decryptions and returns the outcomes recorded since
+/// [`start_recording_decryptions`]
+pub(crate) fn finish_recording_decryptions() -> Vec<bool> {
+    DECRYPTIONS
+        .with(|decryptions| decryptions.take())
+        .unwrap_or_default()
+}
+
 // -- Macros --
 #[macro_export]
 macro_rules! dummy_extract_knowledge {
@@ -1,10 +1,12 @@
+use std::cell::RefCell;
 use std::hash::Hash;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use serde::{Deserialize, Serialize};
 
 use crate::agent::{AgentDescriptor, AgentName};
 use crate::error::Error;
-use crate::protocol::{ProtocolBehavior, ProtocolTypes};
+use crate::protocol::{ProtocolAlerts, ProtocolBehavior, ProtocolTypes};
 use crate::stream::Stream;
 
 #[derive(Debug, Clone, Deserialize, Serialize, Eq, PartialEq, Hash, Default)]
@@ -29,6 +31,32 @@ impl PutOptions {
     }
 }
 
+thread_local! {
+    /// Options of the PUT which receives the input that is being evaluated
+    static RECEIVING_PUT_OPTIONS: RefCell<Option<PutOptions>> = const { RefCell::new(None) };
+}
+
+/// Options of the PUT of the agent which receives the input that is being evaluated, `None` outside
+/// of the evaluation of an input step.
+///
+/// Function symbols read them to adapt the messages they build to the receiving PUT, e.g. to
+/// corrupt records for PUTs which are configured to receive corrupted records.
+#[must_use]
+pub fn receiving_put_options() -> Option<PutOptions> {
+    RECEIVING_PUT_OPTIONS.with(|options| options.borrow().clone())
+}
+
+/// Runs `evaluate` with `options` as the [`receiving_put_options`]
+pub(crate) fn with_receiving_put_options<T>(
+    options: PutOptions,
+    evaluate: impl FnOnce() -> T,
+) -> T {
+    let previous = RECEIVING_PUT_OPTIONS.with(|current| current.replace(Some(options)));
+    let result = evaluate();
+    RECEIVING_PUT_OPTIONS.with(|current| current.replace(previous));
+    result
+}
+
 impl<S> From<Vec<(S, S)>> for PutOptions
 where
     S: Into<String>,
@@ -67,15 +95,132 @@ where
     }
 }
 
+/// Session negotiated by a PUT, which another agent of the same PUT can resume, e.g. in a later
+/// trace.
+#[derive(Debug, Clone, Deserialize, Serialize, Eq, PartialEq, Hash)]
+pub struct SessionData {
+    /// Serialization of the session in the format of the PUT, e.g. DER for OpenSSL
+    pub bytes: Vec<u8>,
+    /// Seconds since the Unix epoch after which the session must not be resumed anymore
+    pub expires_at: Option<u64>,
+}
+
+impl SessionData {
+    #[must_use]
+    pub fn is_expired(&self) -> bool {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since_epoch| since_epoch.as_secs());
+
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
 /// Generic trait used to define the interface with a concrete library
 /// implementing the protocol.
 pub trait Put<PB: ProtocolBehavior>: Stream<PB> + 'static {
     /// Process incoming buffer, internal progress, can fill in the output buffer
     fn progress(&mut self) -> Result<(), Error>;
 
+    /// Writes `bytes` unchanged to the inbound channel and lets the PUT process them.
+    ///
+    /// Unlike the recipes of input steps, the bytes are not required to form messages or records,
+    /// such that arbitrary blobs reach the parser of the PUT. Errors of the PUT while processing
+    /// the bytes, e.g. because they are no valid records, are returned.
+    fn feed_raw(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        self.add_to_inbound(&bytes.to_vec());
+        self.progress()
+    }
+
+    /// Advances the PUT until it either needs more input or produced exactly one complete flight,
+    /// which gives traces a finer control over the timing of messages than [`Put::progress`].
+    ///
+    /// PUTs which can not tell where a flight ends fall back to [`Put::progress`].
+    fn progress_one_flight(&mut self) -> Result<(), Error> {
+        self.progress()
+    }
+
+    /// Number of bytes which the PUT produced but which were not yet taken from its outbound
+    /// channel, including output the PUT is still in the middle of writing.
+    ///
+    /// PUTs which can not tell this without consuming their output report 0.
+    fn pending_output_len(&self) -> usize {
+        0
+    }
+
+    /// Returns and clears the bytes which the PUT buffered for sending but which were not yet
+    /// taken from its outbound channel. The executor calls this when a step fails, such that a
+    /// partially written flight is kept for triage.
+    ///
+    /// PUTs which can not access their buffered output return no bytes.
+    fn drain_output(&mut self) -> Vec<u8> {
+        Vec::new()
+    }
+
     /// In-place reset of the state
     fn reset(&mut self, new_name: AgentName) -> Result<(), Error>;
 
+    /// Resets the connection state, but keeps heavy resources like contexts and loaded
+    /// certificates allocated, such that the PUT can be reused for the next execution of a trace.
+    /// All per-connection buffers and sequence numbers have to be cleared.
+    ///
+    /// Returns `false` if soft resets are not supported, in which case the PUT has to be
+    /// instantiated anew.
+    fn reset_soft(&mut self, _new_name: AgentName) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    /// Returns the session which was negotiated by the PUT, such that it can be resumed by another
+    /// agent through [`Put::import_session`].
+    ///
+    /// Returns `None` if no session was negotiated yet or if the PUT does not support exporting
+    /// sessions.
+    fn export_session(&self) -> Option<SessionData> {
+        None
+    }
+
+    /// Sets the session which the PUT offers for resumption in its next handshake.
+    ///
+    /// Before a trace is executed, each agent is offered the session which the agent of the same
+    /// name negotiated in the prior traces, see [`Trace::prior_traces`](crate::trace::Trace).
+    ///
+    /// Fails if the session expired or if the PUT does not support importing sessions.
+    fn import_session(&mut self, _session: &SessionData) -> Result<(), Error> {
+        Err(Error::Put(
+            "PUT does not support importing sessions".to_string(),
+        ))
+    }
+
+    /// Returns the last alert which the PUT sent to its peer.
+    ///
+    /// Returns `None` if the PUT did not raise an alert yet or if it can not observe its alerts.
+    fn last_alert(&self) -> Option<PB::Alert>
+    where
+        PB: ProtocolAlerts,
+    {
+        None
+    }
+
+    /// Forgets the last alert which the PUT sent, such that [`Put::last_alert`] only returns the
+    /// alerts which the PUT raises afterwards. The executor calls this before each input, such
+    /// that an alert is attributed to the input which caused it.
+    fn clear_last_alert(&mut self) {}
+
+    /// Whether [`Put::last_alert`] observes the alerts of the PUT. PUTs which can not observe their
+    /// alerts return `None` from [`Put::last_alert`] even if they raised one.
+    fn observes_alerts(&self) -> bool {
+        false
+    }
+
+    /// Returns the protocol extensions which the PUT implements, such that tooling knows which
+    /// extensions it can exercise and which ones the PUT may legitimately ignore.
+    ///
+    /// Returns no extensions if the PUT can not tell which ones it implements, such that no
+    /// extension is expected to be handled.
+    fn supported_extensions(&self) -> Vec<PB::Extension> {
+        vec![]
+    }
+
     fn descriptor(
         &self,
     ) -> &AgentDescriptor<<<PB as ProtocolBehavior>::ProtocolTypes as ProtocolTypes>::PUTConfig>;
@@ -86,6 +231,16 @@ pub trait Put<PB: ProtocolBehavior>: Stream<PB> + 'static {
     /// Checks whether the Put is in a good state
     fn is_state_successful(&self) -> bool;
 
+    /// Checks whether the PUT finished its handshake, such that it handles further input as
+    /// application data.
+    ///
+    /// A PUT in the middle of a post-handshake handshake, e.g. of post-handshake client
+    /// authentication, has not completed its handshake until it finished that one as well. By
+    /// default, a PUT in a good state completed its handshake.
+    fn is_handshake_complete(&self) -> bool {
+        self.is_state_successful()
+    }
+
     /// Shut down the PUT by consuming it and returning a string that summarizes the execution.
     fn shutdown(&mut self) -> String;
 
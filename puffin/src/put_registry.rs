@@ -1,11 +1,13 @@
 use std::collections::HashMap;
 use std::fmt;
 
-use crate::agent::AgentDescriptor;
+use crate::agent::{AgentDescriptor, AgentName};
 use crate::claims::GlobalClaimList;
 use crate::error::Error;
+use crate::execution::{replay, Runner};
 use crate::protocol::{ProtocolBehavior, ProtocolTypes};
-use crate::put::{Put, PutOptions};
+use crate::put::{Put, PutDescriptor, PutOptions};
+use crate::trace::{Spawner, TraceContext};
 
 // FIXME TCP_PUT should be defined in the tlspuffin package
 //
@@ -15,6 +17,18 @@ use crate::put::{Put, PutOptions};
 //     Once we factor out the `tcp` command, we can move this definition into `tlspuffin`.
 pub const TCP_PUT: &str = "tcp";
 
+/// Outcome of executing the default trace of the protocol with a PUT, see
+/// [`PutRegistry::self_test`].
+#[derive(Debug)]
+pub enum SelfTestResult {
+    /// The agents of the default trace completed it successfully
+    Passed,
+    /// The PUT does not support the capabilities which these agents of the default trace require,
+    /// e.g. because it implements only one of the protocol versions. The trace was not executed.
+    Unsupported(Vec<AgentName>),
+    Failed(Error),
+}
+
 /// Registry for [Factories](Factory). An instance of this is usually defined statically and then
 /// used throughout the fuzzer.
 pub struct PutRegistry<PB> {
@@ -94,12 +108,88 @@ impl<PB: ProtocolBehavior> PutRegistry<PB> {
             .map(|f| f.to_owned().as_ref())
     }
 
+    /// Versions of the components of each PUT, e.g. the harness and the library, as lines
+    /// `<put>: <component> <version>`. The lines are ordered by the names of the PUTs, such that
+    /// campaigns comparing several libraries report which versions they fuzzed.
+    #[must_use]
+    pub fn version_strings(&self) -> Vec<String> {
+        let mut names: Vec<&String> = self.factories.keys().collect();
+        names.sort();
+
+        names
+            .into_iter()
+            .flat_map(|name| {
+                self.factories[name]
+                    .versions()
+                    .into_iter()
+                    .map(move |(component, version)| format!("{name}: {component} {version}"))
+            })
+            .collect()
+    }
+
+    /// Executes the [default trace](ProtocolBehavior::default_trace) with each PUT of the
+    /// registry, such that broken bindings are found before a campaign starts. The results are
+    /// ordered by the names of the PUTs.
+    pub fn self_test(&self) -> Vec<(String, SelfTestResult)> {
+        let mut names: Vec<&String> = self.factories.keys().collect();
+        names.sort();
+
+        names
+            .into_iter()
+            .map(|name| (name.clone(), self.self_test_put(name)))
+            .collect()
+    }
+
+    fn self_test_put(&self, name: &str) -> SelfTestResult {
+        let put = PutDescriptor::from(name);
+        let Some(trace) = PB::default_trace(put.clone()) else {
+            return SelfTestResult::Failed(Error::Put(format!("no default trace for PUT {name}")));
+        };
+
+        // Agents which the PUT does not support are skipped, failures to spawn the others are
+        // errors of the binding
+        let factory = self.factories[name].as_ref();
+        let unsupported: Vec<AgentName> = trace
+            .descriptors
+            .iter()
+            .filter(|descriptor| {
+                !descriptor
+                    .protocol_config
+                    .required_capabilities()
+                    .into_iter()
+                    .all(|capability| factory.supports(capability))
+            })
+            .map(|descriptor| descriptor.name)
+            .collect();
+
+        if !unsupported.is_empty() {
+            return SelfTestResult::Unsupported(unsupported);
+        }
+
+        let mut ctx = TraceContext::new(Spawner::new(self.clone()).with_default(put));
+        match trace.execute(&mut ctx) {
+            Ok(()) if ctx.agents_successful() => SelfTestResult::Passed,
+            Ok(()) => SelfTestResult::Failed(Error::Put(format!(
+                "the agents of PUT {name} did not complete the default trace"
+            ))),
+            Err(err) => SelfTestResult::Failed(err),
+        }
+    }
+
     pub fn determinism_reseed_all_factories(&self) {
         log::debug!("[RNG] reseed all PUT factories");
         for factory in self.factories.values() {
             factory.rng_reseed();
         }
     }
+
+    /// Reseeds all PUTs with `seed` instead of their default seed
+    pub fn determinism_reseed_all_factories_with(&self, seed: u64) {
+        log::debug!("[RNG] reseed all PUT factories with {seed}");
+        for factory in self.factories.values() {
+            factory.rng_reseed_with(seed);
+        }
+    }
 }
 
 impl<PB: ProtocolBehavior> Clone for PutRegistry<PB> {
@@ -134,4 +224,120 @@ pub trait Factory<PB: ProtocolBehavior> {
     fn rng_reseed(&self) {
         log::debug!("[RNG] reseed failed ({}): not supported", self.name());
     }
+
+    /// Executes the [default trace](ProtocolBehavior::default_trace) twice with the PUTs of this
+    /// factory and compares how the executions behaved. The PUTs are reseeded before each
+    /// execution like during fuzzing, so this detects bindings whose reseeding misses a source of
+    /// randomness.
+    ///
+    /// Returns `false` if the executions differ or if the PUT can not self-test, i.e. the
+    /// [self test](PutRegistry::self_test) does not pass for it.
+    fn verify_determinism(&self) -> bool {
+        let name = self.name();
+        let registry = PutRegistry::new([(name.clone(), self.clone_factory())], name.clone());
+        if !matches!(registry.self_test_put(&name), SelfTestResult::Passed) {
+            return false;
+        }
+
+        let put = PutDescriptor::from(name);
+        let Some(trace) = PB::default_trace(put.clone()) else {
+            return false;
+        };
+        let runner = Runner::new(registry.clone(), Spawner::new(registry).with_default(put));
+        replay(&runner, &trace, 2).is_deterministic()
+    }
+
+    /// Reseeds the random number generator of the PUTs with `seed` instead of the default seed
+    fn rng_reseed_with(&self, _seed: u64) {
+        log::debug!("[RNG] reseed failed ({}): not supported", self.name());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algebra::test_signature::{dummy_factory, TestFactory, TestProtocolBehavior};
+
+    /// A binding which implements only the `roles` and fails to spawn agents if it is `broken`
+    fn roles_factory(
+        roles: Vec<&'static str>,
+        broken: bool,
+    ) -> Box<dyn Factory<TestProtocolBehavior>> {
+        Box::new(TestFactory {
+            capabilities: roles,
+            broken,
+            ..TestFactory::default()
+        })
+    }
+
+    #[test_log::test]
+    fn test_version_strings() {
+        let registry = PutRegistry::<TestProtocolBehavior>::new(
+            [
+                ("teststub", dummy_factory()),
+                (
+                    "unversioned",
+                    Box::new(TestFactory {
+                        unversioned: true,
+                        ..TestFactory::default()
+                    }),
+                ),
+            ],
+            "teststub",
+        );
+
+        assert_eq!(
+            registry.version_strings(),
+            [format!(
+                "teststub: harness TESTSTUB_RUST_PUT {}",
+                puffin_build::puffin::full_version()
+            )]
+        );
+    }
+
+    #[test_log::test]
+    fn test_self_test() {
+        let server = AgentName::first().next();
+        let registry = PutRegistry::<TestProtocolBehavior>::new(
+            [
+                ("both", roles_factory(vec!["client", "server"], false)),
+                ("client-only", roles_factory(vec!["client"], false)),
+                ("broken", roles_factory(vec!["client", "server"], true)),
+            ],
+            "both",
+        );
+
+        let results = registry.self_test();
+        let names: Vec<&str> = results.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["both", "broken", "client-only"]);
+
+        assert!(matches!(results[0].1, SelfTestResult::Passed));
+        assert!(matches!(
+            results[1].1,
+            SelfTestResult::Failed(Error::Put(_))
+        ));
+        assert!(matches!(
+            &results[2].1,
+            SelfTestResult::Unsupported(agents) if *agents == [server]
+        ));
+    }
+
+    #[test_log::test]
+    fn test_verify_determinism() {
+        // The self test and the first execution spawn the agents of the default trace, the agents
+        // of the second execution fail
+        let agents = TestProtocolBehavior::default_trace(PutDescriptor::from("flaky"))
+            .unwrap()
+            .descriptors
+            .len();
+        let flaky = TestFactory {
+            capabilities: vec!["client", "server"],
+            healthy: Some(2 * agents),
+            ..TestFactory::default()
+        };
+
+        assert!(roles_factory(vec!["client", "server"], false).verify_determinism());
+        assert!(!roles_factory(vec!["client"], false).verify_determinism());
+        assert!(!flaky.verify_determinism());
+    }
 }
@@ -22,6 +22,8 @@
 
 use std::io::{self, Read, Write};
 
+use libafl_bolts::rands::{Rand, StdRand};
+
 use crate::algebra::ConcreteMessage;
 use crate::codec::Codec;
 use crate::error::Error;
@@ -56,6 +58,16 @@ pub type Channel = io::Cursor<Vec<u8>>;
 pub struct MemoryStream {
     inbound: Channel,
     outbound: Channel,
+    read_chunks: Option<ReadChunks>,
+    hold_reads: bool,
+}
+
+/// Splits the reads from a [`MemoryStream`] into chunks of random length, like a network which
+/// delivers the data in arbitrary pieces.
+#[derive(Debug)]
+struct ReadChunks {
+    max_len: usize,
+    rand: StdRand,
 }
 
 impl MemoryStream {
@@ -64,8 +76,41 @@ impl MemoryStream {
         Self {
             inbound: io::Cursor::new(Vec::new()),
             outbound: io::Cursor::new(Vec::new()),
+            read_chunks: None,
+            hold_reads: false,
         }
     }
+
+    /// Limits each read to a random number of at most `max_len` bytes. The lengths are drawn from
+    /// a generator seeded with `seed`, such that executions stay reproducible.
+    #[must_use]
+    pub fn with_read_chunks(mut self, max_len: usize, seed: u64) -> Self {
+        self.read_chunks = Some(ReadChunks {
+            max_len: max_len.max(1),
+            rand: StdRand::with_seed(seed),
+        });
+        self
+    }
+
+    /// While `hold` is set, reads fail with [`io::ErrorKind::WouldBlock`] as long as bytes are
+    /// pending in the outbound channel. A PUT then stops where its handshake state machine
+    /// switches from writing a flight to reading the next one, see
+    /// [`Put::progress_one_flight`](crate::put::Put::progress_one_flight).
+    pub fn hold_reads_while_output_pending(&mut self, hold: bool) {
+        self.hold_reads = hold;
+    }
+
+    /// Number of bytes written to the outbound channel which were not yet taken
+    #[must_use]
+    pub fn pending_output_len(&self) -> usize {
+        self.outbound.get_ref().len()
+    }
+
+    /// Takes all bytes of the outbound channel, whether or not they form a complete flight
+    pub fn drain_output(&mut self) -> Vec<u8> {
+        self.outbound.set_position(0);
+        std::mem::take(self.outbound.get_mut())
+    }
 }
 
 impl<PB: ProtocolBehavior> Stream<PB> for MemoryStream {
@@ -87,7 +132,20 @@ impl<PB: ProtocolBehavior> Stream<PB> for MemoryStream {
 
 impl Read for MemoryStream {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        let n = self.inbound.read(buf)?;
+        if self.hold_reads && self.pending_output_len() > 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::WouldBlock,
+                "flight was not yet taken",
+            ));
+        }
+
+        let len = match &mut self.read_chunks {
+            Some(chunks) => buf
+                .len()
+                .min(1 + chunks.rand.below(chunks.max_len as u64) as usize),
+            None => buf.len(),
+        };
+        let n = self.inbound.read(&mut buf[..len])?;
 
         // Clear as soon as we read all data
         if self.inbound.position() == self.inbound.get_ref().len() as u64 {
@@ -113,3 +171,47 @@ impl Write for MemoryStream {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_log::test]
+    fn test_read_chunks() {
+        let data = (0..=255).collect::<Vec<u8>>();
+
+        for max_len in [1, 7] {
+            let mut stream = MemoryStream::new().with_read_chunks(max_len, 42);
+            stream.inbound.get_mut().extend_from_slice(&data);
+
+            let mut received = vec![];
+            let mut buf = [0; 64];
+            while let Ok(n) = stream.read(&mut buf) {
+                assert!(n >= 1 && n <= max_len);
+                received.extend_from_slice(&buf[..n]);
+            }
+
+            assert_eq!(received, data);
+        }
+    }
+
+    #[test_log::test]
+    fn test_hold_reads_while_output_pending() {
+        let mut stream = MemoryStream::new();
+        stream.inbound.get_mut().extend_from_slice(&[1, 2, 3]);
+        stream.hold_reads_while_output_pending(true);
+
+        let mut buf = [0; 1];
+        assert_eq!(stream.read(&mut buf).unwrap(), 1);
+
+        stream.write_all(&[4]).unwrap();
+        assert_eq!(
+            stream.read(&mut buf).unwrap_err().kind(),
+            io::ErrorKind::WouldBlock
+        );
+
+        stream.drain_output();
+        assert_eq!(stream.read(&mut buf).unwrap(), 1);
+        assert_eq!(buf, [2]);
+    }
+}
@@ -1,8 +1,8 @@
 use crate::algebra::term::TermType;
 use crate::algebra::{DYTerm, Term};
-use crate::execution::{ExecutionStatus, ForkError};
+use crate::execution::{ExecutionStatus, ForkError, ForkedRunner, TraceRunner};
 use crate::graphviz::write_graphviz;
-use crate::protocol::ProtocolTypes;
+use crate::protocol::{ProtocolBehavior, ProtocolTypes};
 use crate::trace::{Action, Trace};
 
 impl<PT: ProtocolTypes> Trace<PT> {
@@ -29,6 +29,31 @@ impl<PT: ProtocolTypes> Trace<PT> {
             .sum()
     }
 
+    /// Executes the trace and asserts that it has its [`Trace::expected_outcome`]. Traces without
+    /// an expected outcome are not executed.
+    ///
+    /// Some outcomes are flaky, e.g. crashes which depend on the memory layout. The assertion
+    /// therefore holds as soon as one of `retries` executions has the expected outcome.
+    pub fn assert_expected_outcome<R>(&self, runner: &ForkedRunner<R>, retries: usize)
+    where
+        R: TraceRunner + Clone,
+        R::PB: ProtocolBehavior<ProtocolTypes = PT>,
+    {
+        let Some(expected) = self.expected_outcome else {
+            return;
+        };
+
+        let mut statuses = vec![];
+        for _ in 0..retries.max(1) {
+            match runner.execute(self) {
+                Ok(status) if expected.matches(status) => return,
+                status => statuses.push(status),
+            }
+        }
+
+        panic!("expected trace execution to have outcome {expected:?}, but got {statuses:?}");
+    }
+
     pub fn write_plots(&self, i: u16) {
         write_graphviz(
             format!("test_mutation{i}.svg").as_str(),
@@ -147,3 +172,52 @@ macro_rules! supports {
 
 #[allow(unused_imports)]
 pub(crate) use {supports, test_puts};
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::algebra::test_signature::{dummy_registry, TestProtocolBehavior, TestTrace};
+    use crate::execution::{ForkedRunner, Outcome, Runner};
+    use crate::trace::{Spawner, Trace};
+
+    fn empty_trace(expected_outcome: Outcome) -> TestTrace {
+        Trace {
+            descriptors: vec![],
+            steps: vec![],
+            prior_traces: vec![],
+            expected_outcome: Some(expected_outcome),
+            expected_flow: None,
+            seed: None,
+        }
+    }
+
+    fn test_runner() -> Runner<TestProtocolBehavior> {
+        let registry = dummy_registry();
+
+        Runner::new(registry.clone(), Spawner::new(registry))
+    }
+
+    /// Forking while other tests are running can leave the child deadlocked, so the forked runner
+    /// needs a timeout and the assertions some retries
+    fn forked(
+        runner: &Runner<TestProtocolBehavior>,
+    ) -> ForkedRunner<&Runner<TestProtocolBehavior>> {
+        ForkedRunner::new(runner).with_timeout(Duration::from_secs(5))
+    }
+
+    #[test_log::test]
+    fn test_expected_clean_close() {
+        let runner = test_runner();
+
+        empty_trace(Outcome::CleanClose).assert_expected_outcome(&forked(&runner), 3);
+    }
+
+    #[test_log::test]
+    #[should_panic(expected = "expected trace execution to have outcome Crash")]
+    fn test_expected_crash_without_crash() {
+        let runner = test_runner();
+
+        empty_trace(Outcome::Crash).assert_expected_outcome(&forked(&runner), 3);
+    }
+}
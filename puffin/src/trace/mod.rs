@@ -0,0 +1,2890 @@
+//! This module define the execution [`Trace`]s.
+//!
+//! Each [`Trace`]s consist of several [`Step`]s, of which each has either an [`OutputAction`] or
+//! [`InputAction`]. This is a declarative way of modeling communication between [`Agent`]s. The
+//! [`TraceContext`] holds data, also known as [`Knowledge`], which is created by [`Agent`]s
+//! during the concrete execution of the Trace. It also holds the [`Agent`]s with the references to
+//! concrete PUT.
+//!
+//! ### Serializability of Traces
+//!
+//! Each trace is serializable to JSON or even binary data. This helps at reproducing discovered
+//! security vulnerabilities during fuzzing. If a trace triggers a security vulnerability we can
+//! store it on disk and replay it when investigating the case.
+//! As traces depend on concrete implementations as discussed in the next section we need to link
+//! serialized data like strings or numerical IDs to functions implemented in Rust.
+
+pub mod pcap;
+
+use core::fmt;
+use std::any::TypeId;
+use std::cell::{Ref, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::marker::PhantomData;
+use std::ops::Range;
+use std::time::{Duration, Instant};
+use std::vec::IntoIter;
+
+use clap::error::Result;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::agent::{Agent, AgentDescriptor, AgentName};
+use crate::algebra::atoms::Variable;
+use crate::algebra::bitstrings::{MemoKey, Payloads};
+use crate::algebra::dynamic_function::TypeShape;
+use crate::algebra::{remove_prefix, DYTerm, Matcher, Term, TermType};
+use crate::claims::{ClaimList, GlobalClaimList, SecurityViolationPolicy};
+use crate::codec;
+use crate::error::Error;
+use crate::execution::{ExecutionMetrics, Outcome, StepMetrics, Termination};
+use crate::protocol::{record_decryption, EvaluatedTerm, ProtocolBehavior, ProtocolTypes};
+use crate::put::{with_receiving_put_options, PutDescriptor, SessionData};
+use crate::put_registry::PutRegistry;
+use crate::stream::Stream;
+use crate::trace::pcap::{Direction, RecordedRecord};
+use crate::trace::Action::Input;
+
+#[derive(Debug, Deserialize, Serialize, Clone, Hash, Eq, PartialEq)]
+pub struct Query<M> {
+    pub source: Option<Source>,
+    pub matcher: Option<M>,
+    pub counter: u16, // in case an agent sends multiple messages of the same type
+}
+
+impl<M> Query<M> {
+    /// Query for the secret added by [`TraceContext::add_shared_secret`] under `label`
+    #[must_use]
+    pub fn shared_secret(label: impl Into<String>) -> Self {
+        Self {
+            source: Some(Source::Label(Some(label.into()))),
+            matcher: None,
+            counter: 0,
+        }
+    }
+}
+
+impl<M: Matcher> fmt::Display for Query<M> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "({:?}, {})[{:?}]",
+            self.source, self.counter, self.matcher
+        )
+    }
+}
+
+/// Type of a message which an agent is expected to send, see [`Trace::expected_flow`]
+#[derive(Debug, Deserialize, Serialize, Clone, Hash, Eq, PartialEq)]
+pub struct MessageType<M> {
+    /// Matcher of the message, `None` matches any message
+    pub matcher: Option<M>,
+    /// Whether the agent may omit the message, e.g. a CertificateRequest which a server only
+    /// sends if it is configured for client authentication
+    #[serde(default)]
+    pub optional: bool,
+}
+
+impl<M> MessageType<M> {
+    #[must_use]
+    pub const fn required(matcher: Option<M>) -> Self {
+        Self {
+            matcher,
+            optional: false,
+        }
+    }
+
+    #[must_use]
+    pub const fn optional(matcher: Option<M>) -> Self {
+        Self {
+            matcher,
+            optional: true,
+        }
+    }
+}
+
+impl<M: Matcher> MessageType<M> {
+    /// Whether the sequence of message types `sent` conforms to `expected`
+    fn conforms(expected: &[Self], sent: &[Option<M>]) -> bool {
+        match (expected.split_first(), sent.split_first()) {
+            (None, _) => sent.is_empty(),
+            (Some((first, rest)), _) if first.optional && Self::conforms(rest, sent) => true,
+            (Some((first, rest)), Some((message, sent_rest))) => {
+                message.matches(&first.matcher) && Self::conforms(rest, sent_rest)
+            }
+            (Some(_), None) => false,
+        }
+    }
+}
+
+impl<M: fmt::Debug> fmt::Display for MessageType<M> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.matcher {
+            Some(matcher) => write!(f, "{matcher:?}")?,
+            None => write!(f, "_")?,
+        }
+        if self.optional {
+            write!(f, "?")?;
+        }
+        Ok(())
+    }
+}
+
+/// Messages which an agent is expected to send, in this order
+pub type AgentFlow<M> = (AgentName, Vec<MessageType<M>>);
+
+/// [Source] stores the origin of a knowledge, whether the agent name or
+/// the label of the precomputation that produced it
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Deserialize, Serialize)]
+pub enum Source {
+    Agent(AgentName),
+    Label(Option<String>),
+    /// The output of the step with this index in the executed trace, regardless of the agent
+    Step(usize),
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Agent(x) => write!(f, "agent:{x}"),
+            Self::Label(x) => write!(f, "label:{x:?}"),
+            Self::Step(x) => write!(f, "step:{x}"),
+        }
+    }
+}
+
+/// [Knowledge] describes an atomic piece of knowledge inferred by the
+/// [`crate::protocol::Extractable::extract_knowledge`] function
+/// [Knowledge] is made of the data, the source of the output, the
+/// TLS message type and the internal type.
+#[derive(Debug)]
+pub struct Knowledge<'a, PT: ProtocolTypes> {
+    pub source: &'a Source,
+    pub matcher: Option<PT::Matcher>,
+    pub data: &'a dyn EvaluatedTerm<PT>,
+}
+
+/// [`RawKnowledge`] stores
+#[derive(Debug)]
+pub struct RawKnowledge<PT: ProtocolTypes> {
+    pub source: Source,
+    pub matcher: Option<PT::Matcher>,
+    pub associated_term: Option<Term<PT>>,
+    pub data: Box<dyn EvaluatedTerm<PT>>,
+    /// Index of the step during which the knowledge was added, `None` outside of steps
+    pub step: Option<usize>,
+}
+
+impl<PT: ProtocolTypes> Clone for RawKnowledge<PT> {
+    fn clone(&self) -> Self {
+        Self {
+            source: self.source.clone(),
+            matcher: self.matcher.clone(),
+            associated_term: self.associated_term.clone(),
+            data: self.data.boxed(),
+            step: self.step,
+        }
+    }
+}
+
+impl<PT: ProtocolTypes> RawKnowledge<PT> {
+    /// Whether the knowledge is queried by `source`. A [`Source::Step`] only refers to the output
+    /// of agents, not to the precomputations of an input step.
+    #[must_use]
+    pub fn is_from(&self, source: &Source) -> bool {
+        match source {
+            Source::Step(step) => {
+                self.step == Some(*step) && matches!(self.source, Source::Agent(_))
+            }
+            source => self.source == *source,
+        }
+    }
+}
+
+impl<PT: ProtocolTypes> fmt::Display for RawKnowledge<PT> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "({})/{:?}", self.source, self.matcher)
+    }
+}
+
+impl<'a, PT: ProtocolTypes> IntoIterator for &'a RawKnowledge<PT> {
+    type IntoIter = IntoIter<Knowledge<'a, PT>>;
+    type Item = Knowledge<'a, PT>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let extracted = self
+            .data
+            .extract_knowledge_partial(self.matcher.clone(), &self.source);
+
+        for error in &extracted.errors {
+            log::debug!("Failed to extract part of knowledge {}: {}", self, error);
+        }
+
+        extracted.knowledges.into_iter()
+    }
+}
+
+impl<PT: ProtocolTypes> Knowledge<'_, PT> {
+    pub fn specificity(&self) -> u32 {
+        self.matcher.specificity()
+    }
+}
+
+impl<PT: ProtocolTypes> Knowledge<'_, PT> {
+    pub fn debug_print<PB>(&self, ctx: &TraceContext<PB>, source: &Source)
+    where
+        PB: ProtocolBehavior<ProtocolTypes = PT>,
+    {
+        let data_type_id = self.data.type_id();
+        let message_type = self
+            .data
+            .as_any()
+            .downcast_ref::<PB::ProtocolMessage>()
+            .map_or("", PB::message_type_name);
+        log::debug!(
+            "New knowledge {}: {} {}  (counter: {})",
+            &self,
+            remove_prefix(self.data.type_name()),
+            message_type,
+            ctx.number_matching_message_with_source(source.clone(), data_type_id, &self.matcher)
+        );
+        log::debug!("Knowledge data: {:?}", self.data);
+    }
+}
+
+impl<PT: ProtocolTypes> fmt::Display for Knowledge<'_, PT> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "({})/{:?}", self.source, self.matcher)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct KnowledgeStore<PT: ProtocolTypes> {
+    raw_knowledge: Vec<RawKnowledge<PT>>,
+    /// Index of the step which is being executed, see [`RawKnowledge::step`]
+    current_step: Option<usize>,
+}
+
+impl<PT: ProtocolTypes> Clone for KnowledgeStore<PT> {
+    fn clone(&self) -> Self {
+        Self {
+            raw_knowledge: self.raw_knowledge.clone(),
+            current_step: self.current_step,
+        }
+    }
+}
+
+impl<PT: ProtocolTypes> KnowledgeStore<PT> {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            raw_knowledge: vec![],
+            current_step: None,
+        }
+    }
+
+    /// Number of knowledge entries, counting each extracted part of a message
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.raw_knowledge.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.raw_knowledge.is_empty()
+    }
+
+    /// Digest of the knowledge which agents produced, i.e. of the bytes they sent, together with
+    /// the agent which sent them
+    #[must_use]
+    pub fn output_digest(&self) -> u64 {
+        let mut hasher = ahash::RandomState::with_seeds(0, 0, 0, 0).build_hasher();
+        for knowledge in &self.raw_knowledge {
+            if let Source::Agent(agent) = knowledge.source {
+                agent.hash(&mut hasher);
+                knowledge.data.get_encoding().hash(&mut hasher);
+            }
+        }
+        hasher.finish()
+    }
+
+    /// Forgets the steps in which the knowledge so far was learned, such that a [`Source::Step`]
+    /// does not refer to the knowledge of prior traces, which number their steps from 0 as well
+    pub fn forget_steps(&mut self) {
+        for knowledge in &mut self.raw_knowledge {
+            knowledge.step = None;
+        }
+    }
+
+    pub fn add_raw_knowledge<T: EvaluatedTerm<PT> + 'static>(
+        &mut self,
+        data: T,
+        source: Source,
+        term: Option<Term<PT>>,
+    ) {
+        log::trace!("Adding raw knowledge for {:?}", &data);
+
+        self.raw_knowledge.push(RawKnowledge {
+            source,
+            matcher: None,
+            data: Box::new(data),
+            associated_term: term,
+            step: self.current_step,
+        });
+    }
+
+    pub fn add_raw_boxed_knowledge(
+        &mut self,
+        data: Box<dyn EvaluatedTerm<PT>>,
+        source: Source,
+        term: Option<Term<PT>>,
+    ) {
+        log::trace!("Adding raw knowledge : {:?}", &data);
+
+        self.raw_knowledge.push(RawKnowledge {
+            source,
+            matcher: None,
+            data,
+            associated_term: term,
+            step: self.current_step,
+        });
+    }
+
+    pub fn number_matching_message_with_source(
+        &self,
+        source: Source,
+        type_id: TypeId,
+        tls_message_type: &Option<PT::Matcher>,
+    ) -> usize {
+        self.raw_knowledge
+            .iter()
+            .filter(|raw| raw.is_from(&source))
+            .flatten()
+            .filter(|knowledge| {
+                knowledge.matcher == *tls_message_type && knowledge.data.type_id() == type_id
+            })
+            .count()
+    }
+
+    /// Count the number of sub-messages of type `type_id` in the output message.
+    pub fn number_matching_message(
+        &self,
+        type_id: TypeId,
+        tls_message_type: &Option<PT::Matcher>,
+    ) -> usize {
+        self.raw_knowledge
+            .iter()
+            .flatten()
+            .filter(|knowledge| {
+                knowledge.matcher == *tls_message_type && knowledge.data.type_id() == type_id
+            })
+            .count()
+    }
+
+    /// Matchers of the messages of type `type_id` which `agent` sent, in the order they were sent
+    #[must_use]
+    pub fn sent_messages(&self, agent: AgentName, type_id: TypeId) -> Vec<Option<PT::Matcher>> {
+        let source = Source::Agent(agent);
+
+        self.raw_knowledge
+            .iter()
+            .filter(|raw| raw.source == source)
+            .flatten()
+            .filter(|knowledge| knowledge.data.type_id() == type_id)
+            .map(|knowledge| knowledge.matcher)
+            .collect()
+    }
+
+    /// Checks that each agent in `expected` sent the listed messages of type `type_id`, in this
+    /// order and without further messages.
+    pub fn verify_flow(
+        &self,
+        expected: &[AgentFlow<PT::Matcher>],
+        type_id: TypeId,
+    ) -> Result<(), Error> {
+        let mut diff = String::new();
+
+        for (agent, expected) in expected {
+            let sent = self.sent_messages(*agent, type_id);
+
+            if !MessageType::conforms(expected, &sent) {
+                let sent: Vec<_> = sent.into_iter().map(MessageType::required).collect();
+                diff.push_str(&format!(
+                    "  {agent}:\n    expected [{}]\n    sent     [{}]\n",
+                    expected.iter().join(", "),
+                    sent.iter().join(", ")
+                ));
+            }
+        }
+
+        if diff.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::Flow(diff))
+        }
+    }
+
+    /// Returns the variable which matches best -> highest specificity
+    /// If we want a variable with lower specificity, then we can just query less specific
+    pub fn find_variable(
+        &self,
+        query_type_shape: TypeShape<PT>,
+        query: &Query<PT::Matcher>,
+    ) -> Option<&(dyn EvaluatedTerm<PT>)> {
+        log::trace!(
+            "Looking for variable {:?} with query_type_shape {:?} and query {:?}",
+            self,
+            query_type_shape,
+            query
+        );
+        let query_type_id: TypeId = query_type_shape.into();
+
+        let mut possibilities: Vec<Knowledge<PT>> = self
+            .raw_knowledge
+            .iter()
+            .filter(|raw| {
+                query
+                    .source
+                    .as_ref()
+                    .map_or(true, |source| raw.is_from(source))
+            })
+            .flatten()
+            .filter(|knowledge| {
+                query_type_id == knowledge.data.type_id()
+                    && knowledge.matcher.matches(&query.matcher)
+            })
+            .collect();
+
+        possibilities.sort_by_key(Knowledge::specificity);
+
+        possibilities
+            .get(query.counter as usize)
+            .map(|possibility| possibility.data)
+    }
+}
+
+#[derive(Debug)]
+pub struct Spawner<PB: ProtocolBehavior> {
+    registry: PutRegistry<PB>,
+    descriptors: HashMap<AgentName, PutDescriptor>,
+    default: PutDescriptor,
+}
+
+impl<PB: ProtocolBehavior> Spawner<PB> {
+    pub fn new(registry: impl Into<PutRegistry<PB>>) -> Self {
+        let registry = registry.into();
+        Self {
+            default: registry.default().name().into(),
+            registry,
+            descriptors: Default::default(),
+        }
+    }
+
+    #[must_use]
+    pub fn with_mapping(mut self, descriptors: &[(AgentName, PutDescriptor)]) -> Self {
+        self.descriptors.extend(descriptors.iter().cloned());
+        self
+    }
+
+    pub fn with_default(mut self, put: impl Into<PutDescriptor>) -> Self {
+        self.default = put.into();
+        self
+    }
+
+    /// The PUT which is spawned for the agent `name`.
+    #[must_use]
+    pub fn put_descriptor(&self, name: AgentName) -> PutDescriptor {
+        self.descriptors
+            .get(&name)
+            .cloned()
+            .unwrap_or_else(|| self.default.clone())
+    }
+
+    pub fn spawn(
+        &self,
+        claims: &GlobalClaimList<PB::Claim>,
+        descriptor: &AgentDescriptor<<PB::ProtocolTypes as ProtocolTypes>::PUTConfig>,
+    ) -> Result<Agent<PB>, Error> {
+        let put_descriptor = self.put_descriptor(descriptor.name);
+
+        let factory = self
+            .registry
+            .find_by_id(&put_descriptor.factory)
+            .ok_or_else(|| {
+                Error::Agent(format!(
+                    "unable to find PUT {} factory in binary",
+                    &put_descriptor.factory
+                ))
+            })?;
+
+        let put = factory.create(descriptor, claims, &put_descriptor.options)?;
+        Ok(Agent::new(descriptor.clone(), put))
+    }
+}
+
+impl<PB: ProtocolBehavior + PartialEq> PartialEq for Spawner<PB> {
+    fn eq(&self, other: &Self) -> bool {
+        self.registry == other.registry
+            && self.descriptors == other.descriptors
+            && self.default == other.default
+    }
+}
+
+impl<PB: ProtocolBehavior> Clone for Spawner<PB> {
+    fn clone(&self) -> Self {
+        Self {
+            registry: self.registry.clone(),
+            descriptors: self.descriptors.clone(),
+            default: self.default.clone(),
+        }
+    }
+}
+
+/// Maximum number of idle agents which a [`TraceContext`] keeps for reuse
+const MAX_IDLE_AGENTS: usize = 16;
+
+/// Maximum number of evaluations which a [`TraceContext`] memoizes per execution
+const MAX_MEMOIZED_EVALUATIONS: usize = 4096;
+
+/// Evaluated subterms by their [`MemoKey`]
+#[derive(Debug)]
+struct EvalMemo<PT: ProtocolTypes> {
+    entries: HashMap<MemoKey, Box<dyn EvaluatedTerm<PT>>>,
+}
+
+impl<PT: ProtocolTypes> EvalMemo<PT> {
+    fn new() -> Self {
+        Self {
+            entries: HashMap::new(),
+        }
+    }
+
+    fn get(&self, key: MemoKey) -> Option<Box<dyn EvaluatedTerm<PT>>> {
+        self.entries.get(&key).map(|value| value.boxed())
+    }
+
+    /// Memoizes `value` for `key`, unless [`MAX_MEMOIZED_EVALUATIONS`] are memoized already
+    fn insert(&mut self, key: MemoKey, value: &dyn EvaluatedTerm<PT>) {
+        if self.entries.len() >= MAX_MEMOIZED_EVALUATIONS {
+            return;
+        }
+
+        self.entries.insert(key, value.boxed());
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+/// The [`TraceContext`] represents the state of an execution.
+///
+/// The [`TraceContext`] contains a list of [`EvaluatedTerm`], which is known as the knowledge
+/// of the attacker. [`EvaluatedTerm`] can contain data of various types like for example
+/// client and server extensions, cipher suits or session ID It also holds the concrete
+/// references to the [`Agent`]s and the underlying streams, which contain the messages
+/// which have need exchanged and are not yet processed by an output step.
+#[derive(Debug)]
+pub struct TraceContext<PB: ProtocolBehavior> {
+    /// The knowledge of the attacker
+    pub knowledge_store: KnowledgeStore<PB::ProtocolTypes>,
+    agents: Vec<Agent<PB>>,
+    /// Agents of previous executions, which are soft reset instead of spawning new agents. At most
+    /// [`MAX_IDLE_AGENTS`] are kept, the oldest ones are dropped first.
+    idle_agents: Vec<(PutDescriptor, Agent<PB>)>,
+    /// Secrets provisioned out-of-band, e.g. pre-shared keys, by their label
+    shared_secrets: HashMap<String, Box<dyn EvaluatedTerm<PB::ProtocolTypes>>>,
+    claims: GlobalClaimList<PB::Claim>,
+    /// Bytes which agents buffered for sending when a step failed, see
+    /// [`Put::drain_output`](crate::put::Put::drain_output)
+    drained_output: Vec<(AgentName, Vec<u8>)>,
+    metrics: ExecutionMetrics,
+    /// Metrics of the step which is being executed by [`Trace::execute_until_step`], `None` unless
+    /// enabled by [`TraceContext::measure`]
+    current_step: Option<StepMetrics>,
+    /// Evaluations of ground subterms during the current execution, see
+    /// [`TraceContext::memoized_evaluation`]
+    eval_memo: RefCell<EvalMemo<PB::ProtocolTypes>>,
+    /// Messages which crossed the wire, `None` unless enabled by [`TraceContext::record_wire`]
+    wire_records: Option<Vec<RecordedRecord>>,
+    /// Agents whose inbound records are protected since a previous input, see
+    /// [`ProtocolBehavior::put_decryption_outcome`]
+    protected_inbound: HashSet<AgentName>,
+    /// Whether the executions are measured, see [`TraceContext::measure`]
+    measure: bool,
+    /// Duration after which a step fails with [`Error::Timeout`], see
+    /// [`TraceContext::limit_step_duration`]
+    step_timeout: Option<Duration>,
+
+    spawner: Spawner<PB>,
+
+    phantom: PhantomData<PB>,
+}
+
+impl<PB: ProtocolBehavior> fmt::Display for TraceContext<PB> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Knowledge [not displaying other fields] (size={}):",
+            self.knowledge_store.raw_knowledge.len()
+        )?;
+        for k in &self.knowledge_store.raw_knowledge {
+            write!(f, "\n   {k},          --  {k:?}")?;
+        }
+        Ok(())
+    }
+}
+
+impl<PB: ProtocolBehavior + PartialEq> PartialEq for TraceContext<PB> {
+    fn eq(&self, other: &Self) -> bool {
+        self.agents == other.agents
+            && self.spawner == other.spawner
+            && format!("{:?}", self.knowledge_store.raw_knowledge)
+                == format!("{:?}", other.knowledge_store.raw_knowledge)
+            && format!("{:?}", self.claims) == format!("{:?}", other.claims)
+            && format!("{:?}", self.shared_secrets) == format!("{:?}", other.shared_secrets)
+    }
+}
+
+impl<PB: ProtocolBehavior> TraceContext<PB> {
+    #[must_use]
+    pub fn new(spawner: Spawner<PB>) -> Self {
+        // We keep a global list of all claims throughout the execution. Each claim is identified
+        // by the AgentName. A rename of an Agent does not interfere with this.
+        let claims = GlobalClaimList::<PB::Claim>::new();
+
+        Self {
+            knowledge_store: KnowledgeStore::new(),
+            agents: vec![],
+            idle_agents: vec![],
+            shared_secrets: HashMap::new(),
+            claims,
+            drained_output: vec![],
+            metrics: ExecutionMetrics::default(),
+            current_step: None,
+            eval_memo: RefCell::new(EvalMemo::new()),
+            wire_records: None,
+            protected_inbound: HashSet::new(),
+            measure: false,
+            step_timeout: None,
+            spawner,
+            phantom: Default::default(),
+        }
+    }
+
+    /// Copies the knowledge of the attacker into a new context, e.g. to evaluate recipes
+    /// speculatively while [minimizing](crate::execution::minimize) a trace, without executing the
+    /// trace prefix again.
+    ///
+    /// The PUTs can not be copied, therefore the fork has no agents: the agents, their claims and
+    /// their drained output are not kept, and steps which need a PUT have to
+    /// [spawn](TraceContext::spawn) the agents anew. The shared secrets, the metrics and the
+    /// recorded records of the execution so far are kept, as well as whether the fork is
+    /// [measured](TraceContext::measure) and its [step timeout](TraceContext::limit_step_duration).
+    #[must_use]
+    pub fn fork_knowledge(&self) -> Self {
+        Self {
+            knowledge_store: self.knowledge_store.clone(),
+            shared_secrets: self
+                .shared_secrets
+                .iter()
+                .map(|(label, secret)| (label.clone(), secret.boxed()))
+                .collect(),
+            metrics: self.metrics.clone(),
+            wire_records: self.wire_records.clone(),
+            measure: self.measure,
+            step_timeout: self.step_timeout,
+            ..Self::new(self.spawner.clone())
+        }
+    }
+
+    pub fn verify_security_violations(&self) -> Result<(), Error> {
+        let claims = self.claims.deref_borrow();
+        claims.log();
+        if let Some(msg) = PB::SecurityViolationPolicy::check_violation(claims.slice()) {
+            // [TODO] Lucca: versus checking at each step ? Could detect violation earlier, before a
+            // blocking state is reached ? [BENCH] benchmark the efficiency loss of doing so
+            // Max: We only check for Finished claims right now, so its fine to check only at the
+            // end
+            return Err(Error::SecurityClaim(msg));
+        }
+        Ok(())
+    }
+
+    /// Claims which the agents made so far, including those of agents which were renamed or
+    /// reused
+    pub fn claims(&self) -> Ref<'_, ClaimList<PB::Claim>> {
+        self.claims.deref_borrow()
+    }
+
+    /// Bytes which agents had buffered for sending when a step of the execution failed, e.g. a
+    /// partially written flight
+    pub fn drained_output(&self) -> &[(AgentName, Vec<u8>)] {
+        &self.drained_output
+    }
+
+    /// Records the messages which are sent to and taken from the agents in the following
+    /// executions, such that they can be exported with [`pcap::write_pcap`]
+    pub fn record_wire(&mut self) {
+        self.wire_records.get_or_insert_with(Vec::new);
+    }
+
+    /// Collects the [`ExecutionMetrics`] of the following executions, see
+    /// [`TraceContext::metrics`].
+    ///
+    /// This is off by default, because the steps have to be timed and the output flights have to
+    /// be encoded to be counted.
+    pub fn measure(&mut self) {
+        self.measure = true;
+    }
+
+    /// Fails the steps of the following executions which take longer than `timeout` with
+    /// [`Error::Timeout`], such that hangs of the PUT are told apart from other errors.
+    pub fn limit_step_duration(&mut self, timeout: Duration) {
+        self.step_timeout = Some(timeout);
+    }
+
+    /// Messages which crossed the wire since [`TraceContext::record_wire`], in the order in which
+    /// they were sent
+    pub fn wire_records(&self) -> &[RecordedRecord] {
+        self.wire_records.as_deref().unwrap_or_default()
+    }
+
+    fn record_on_wire(&mut self, agent: AgentName, direction: Direction, bytes: &[u8]) {
+        if let Some(records) = &mut self.wire_records {
+            records.push(RecordedRecord {
+                agent,
+                direction,
+                bytes: bytes.to_vec(),
+            });
+        }
+    }
+
+    /// Metrics of the execution so far, empty unless enabled by [`TraceContext::measure`]
+    pub fn metrics(&self) -> &ExecutionMetrics {
+        &self.metrics
+    }
+
+    /// Takes the output which `agent_name` buffered, such that it is not lost when a step fails.
+    /// Agents which buffered nothing are not recorded.
+    fn drain_output_of(&mut self, agent_name: AgentName) {
+        let Ok(agent) = self.find_agent_mut(agent_name) else {
+            return;
+        };
+
+        let output = agent.drain_output();
+        if !output.is_empty() {
+            log::debug!(
+                "{agent_name} buffered {} bytes for sending when the step failed",
+                output.len()
+            );
+            self.drained_output.push((agent_name, output));
+        }
+    }
+
+    /// Whether the claims of the execution show that both peers finished the handshake
+    #[must_use]
+    pub fn is_handshake_complete(&self) -> bool {
+        PB::SecurityViolationPolicy::is_handshake_complete(self.claims.deref_borrow().slice())
+    }
+
+    /// Checks that the agents sent the messages in `expected`, see [`Trace::expected_flow`]
+    pub fn verify_flow(
+        &self,
+        expected: &[AgentFlow<<PB::ProtocolTypes as ProtocolTypes>::Matcher>],
+    ) -> Result<(), Error> {
+        self.knowledge_store
+            .verify_flow(expected, TypeId::of::<PB::ProtocolMessage>())
+    }
+
+    /// Count the number of sub-messages of type `type_id` with the correct source
+    pub fn number_matching_message_with_source(
+        &self,
+        source: Source,
+        type_id: TypeId,
+        tls_message_type: &Option<<PB::ProtocolTypes as ProtocolTypes>::Matcher>,
+    ) -> usize {
+        self.knowledge_store
+            .number_matching_message_with_source(source, type_id, tls_message_type)
+    }
+
+    /// Count the number of sub-messages of type `type_id` in the output message.
+    pub fn number_matching_message(
+        &self,
+        type_id: TypeId,
+        tls_message_type: &Option<<PB::ProtocolTypes as ProtocolTypes>::Matcher>,
+    ) -> usize {
+        self.knowledge_store
+            .number_matching_message(type_id, tls_message_type)
+    }
+
+    #[must_use]
+    pub fn find_claim(
+        &self,
+        agent_name: AgentName,
+        query_type_shape: TypeShape<PB::ProtocolTypes>,
+    ) -> Option<Box<dyn EvaluatedTerm<PB::ProtocolTypes>>> {
+        self.claims
+            .deref_borrow()
+            .find_last_claim(agent_name, query_type_shape)
+            .map(super::claims::Claim::inner)
+    }
+
+    /// Returns the variable which matches best -> highest specificity
+    /// If we want a variable with lower specificity, then we can just query less specific
+    pub fn find_variable(
+        &self,
+        query_type_shape: TypeShape<PB::ProtocolTypes>,
+        query: &Query<<PB::ProtocolTypes as ProtocolTypes>::Matcher>,
+    ) -> Option<&(dyn EvaluatedTerm<PB::ProtocolTypes>)> {
+        log::trace!(
+            "Looking for variable in {:?} with query {:?}",
+            self.knowledge_store,
+            query
+        );
+        self.knowledge_store
+            .find_variable(query_type_shape.clone(), query)
+            .or_else(|| self.find_shared_secret(query_type_shape, query))
+    }
+
+    /// Adds a secret which is known to the attacker without deriving it during the execution, like
+    /// a pre-shared key. Terms reference it through a variable with the source
+    /// `Source::Label(Some(label))`, see [`Query::shared_secret`]. An existing secret with the
+    /// same label is replaced.
+    ///
+    /// Shared secrets are kept when the context is [reset](TraceContext::reset).
+    pub fn add_shared_secret(&mut self, label: String, secret: Vec<u8>)
+    where
+        Vec<u8>: EvaluatedTerm<PB::ProtocolTypes>,
+    {
+        self.shared_secrets.insert(label, Box::new(secret));
+    }
+
+    fn find_shared_secret(
+        &self,
+        query_type_shape: TypeShape<PB::ProtocolTypes>,
+        query: &Query<<PB::ProtocolTypes as ProtocolTypes>::Matcher>,
+    ) -> Option<&(dyn EvaluatedTerm<PB::ProtocolTypes>)> {
+        let Some(Source::Label(Some(label))) = &query.source else {
+            return None;
+        };
+
+        let secret: &dyn EvaluatedTerm<PB::ProtocolTypes> =
+            self.shared_secrets.get(label)?.as_ref();
+        let query_type_id: TypeId = query_type_shape.into();
+
+        (query.counter == 0 && query.matcher.is_none() && secret.type_id() == query_type_id)
+            .then_some(secret)
+    }
+
+    /// Evaluation of the term identified by `key` which was memoized earlier in the current
+    /// execution, see [`TraceContext::memoize_evaluation`]
+    pub(crate) fn memoized_evaluation(
+        &self,
+        key: MemoKey,
+    ) -> Option<Box<dyn EvaluatedTerm<PB::ProtocolTypes>>> {
+        self.eval_memo.borrow().get(key)
+    }
+
+    /// Memoizes the evaluation of the term identified by `key`, such that later occurrences of the
+    /// same subterm in the current execution are not evaluated again. Only terms whose evaluation
+    /// does not depend on the knowledge, i.e. without variables, may be memoized. At most
+    /// [`MAX_MEMOIZED_EVALUATIONS`] are memoized per execution, later ones are evaluated anew.
+    pub(crate) fn memoize_evaluation(
+        &self,
+        key: MemoKey,
+        value: &dyn EvaluatedTerm<PB::ProtocolTypes>,
+    ) {
+        self.eval_memo.borrow_mut().insert(key, value);
+    }
+
+    /// Forgets the memoized evaluations, see [`TraceContext::memoize_evaluation`]
+    pub fn clear_evaluation_memo(&mut self) {
+        self.eval_memo.get_mut().clear();
+    }
+
+    /// Prepares the context for the execution of another trace.
+    ///
+    /// The knowledge, the shared secrets, the claims, the drained output, the memoized
+    /// evaluations, the recorded wire messages and the metrics are cleared. The agents are kept
+    /// and reused by [`TraceContext::spawn`] if their PUT supports soft resets.
+    pub fn reset(&mut self) {
+        self.knowledge_store = KnowledgeStore::new();
+        self.shared_secrets.clear();
+        self.claims.deref_borrow_mut().clear();
+        self.drained_output.clear();
+        if let Some(records) = &mut self.wire_records {
+            records.clear();
+        }
+        self.protected_inbound.clear();
+        self.metrics = ExecutionMetrics::default();
+        self.clear_evaluation_memo();
+
+        for agent in self.agents.drain(..) {
+            let put_descriptor = self.spawner.put_descriptor(agent.name());
+            self.idle_agents.push((put_descriptor, agent));
+        }
+
+        let excess = self.idle_agents.len().saturating_sub(MAX_IDLE_AGENTS);
+        self.idle_agents.drain(..excess);
+    }
+
+    /// Takes an idle agent which can be soft reset to spawn `descriptor`. The PUT of the idle
+    /// agent, including its options, has to be the same as the one of the new agent and the
+    /// protocol configurations have to be compatible.
+    fn take_idle_agent(
+        &mut self,
+        put_descriptor: &PutDescriptor,
+        descriptor: &AgentDescriptor<<PB::ProtocolTypes as ProtocolTypes>::PUTConfig>,
+    ) -> Option<Agent<PB>> {
+        let i = self.idle_agents.iter().position(|(idle_put, idle)| {
+            idle_put == put_descriptor && idle.is_reusable_with(descriptor)
+        })?;
+
+        Some(self.idle_agents.remove(i).1)
+    }
+
+    pub fn spawn(
+        &mut self,
+        descriptor: &AgentDescriptor<<PB::ProtocolTypes as ProtocolTypes>::PUTConfig>,
+    ) -> Result<(), Error> {
+        let put_descriptor = self.spawner.put_descriptor(descriptor.name);
+        self.protected_inbound.remove(&descriptor.name);
+
+        if let Some(mut agent) = self.take_idle_agent(&put_descriptor, descriptor) {
+            // Agents without support for soft resets are dropped and spawned anew
+            if agent.reset_soft(descriptor.name)? {
+                self.agents.push(agent);
+                return Ok(());
+            }
+        }
+
+        let agent = self.spawner.spawn(&self.claims, descriptor)?;
+        self.agents.push(agent);
+
+        Ok(())
+    }
+
+    pub fn find_agent_mut(&mut self, name: AgentName) -> Result<&mut Agent<PB>, Error> {
+        let mut iter = self.agents.iter_mut();
+
+        iter.find(|agent| agent.name() == name).ok_or_else(|| {
+            Error::Agent(format!(
+                "Could not find agent {name}. Did you forget to call spawn_agents?"
+            ))
+        })
+    }
+
+    pub fn find_agent(&self, name: AgentName) -> Result<&Agent<PB>, Error> {
+        let mut iter = self.agents.iter();
+        iter.find(|agent| agent.name() == name).ok_or_else(|| {
+            Error::Agent(format!(
+                "Could not find agent {name}. Did you forget to call spawn_agents?"
+            ))
+        })
+    }
+
+    /// Types of the messages which the agents sent, in the order they were sent, as named by
+    /// [`ProtocolBehavior::message_type_name`]
+    #[must_use]
+    pub fn sent_message_types(&self) -> Vec<(AgentName, &'static str)> {
+        self.knowledge_store
+            .raw_knowledge
+            .iter()
+            .filter(|raw| {
+                raw.data
+                    .as_any()
+                    .downcast_ref::<PB::ProtocolMessageFlight>()
+                    .is_some()
+            })
+            .filter_map(|raw| match raw.source {
+                Source::Agent(agent) => Some((agent, raw)),
+                _ => None,
+            })
+            .flat_map(|(agent, raw)| {
+                raw.into_iter().filter_map(move |knowledge| {
+                    knowledge
+                        .data
+                        .as_any()
+                        .downcast_ref::<PB::ProtocolMessage>()
+                        .map(|message| (agent, PB::message_type_name(message)))
+                })
+            })
+            .collect()
+    }
+
+    /// Descriptions of the states of the agents, see
+    /// [`Put::describe_state`](crate::put::Put::describe_state)
+    #[must_use]
+    pub fn describe_agent_states(&self) -> Vec<String> {
+        self.agents
+            .iter()
+            .map(|agent| agent.put().describe_state())
+            .collect()
+    }
+
+    #[must_use]
+    pub fn agents_successful(&self) -> bool {
+        self.agents
+            .iter()
+            .all(super::agent::Agent::is_state_successful)
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize, Hash)]
+#[serde(bound = "PT: ProtocolTypes")]
+pub struct Trace<PT: ProtocolTypes> {
+    pub descriptors: Vec<AgentDescriptor<PT::PUTConfig>>,
+    pub steps: Vec<Step<PT>>,
+    /// Traces which are executed before this one. The agents of this trace resume the sessions
+    /// which the agents of the same name negotiated in them, see
+    /// [`Put::import_session`](crate::put::Put::import_session).
+    pub prior_traces: Vec<Trace<PT>>,
+    /// Outcome which executing this trace is expected to have, e.g. when it is a regression test
+    /// for a fixed crash or a known vulnerability
+    #[serde(default)]
+    pub expected_outcome: Option<Outcome>,
+    /// Messages which each agent is expected to send, checked after the execution. This turns the
+    /// trace into a conformance test of the protocol.
+    #[serde(default)]
+    pub expected_flow: Option<Vec<AgentFlow<PT::Matcher>>>,
+    /// Seed with which the PUTs are reseeded before the execution, such that it is reproducible
+    /// regardless of how it is run. Without a seed, the PUTs keep the default seed of the
+    /// executor, see [`Factory::rng_reseed`](crate::put_registry::Factory::rng_reseed).
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+/// Version of the format in which [`Trace::serialize_versioned`] stores traces.
+///
+/// Increase it with every change to the serialization of [`Trace`] and its fields, and add a
+/// migration from the previous version to [`Trace::deserialize_versioned`].
+///
+/// * 0: unversioned (legacy) traces, without [`Trace::expected_outcome`] and
+///   [`Trace::expected_flow`]
+/// * 1: the version is stored in front of the trace
+/// * 2: [`Trace::seed`]
+/// * 3: [`OutputAction::one_flight`]
+pub const TRACE_FORMAT_VERSION: u16 = 3;
+
+/// Prefix of a versioned trace. A legacy trace starts with the number of its descriptors instead.
+const TRACE_FORMAT_MAGIC: &[u8] = b"puffin-trace";
+
+/// A [`Trace`] in format version 0
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(Serialize))]
+#[serde(bound = "PT: ProtocolTypes")]
+struct TraceV0<PT: ProtocolTypes> {
+    descriptors: Vec<AgentDescriptor<PT::PUTConfig>>,
+    steps: Vec<StepV2<PT>>,
+    prior_traces: Vec<TraceV0<PT>>,
+}
+
+impl<PT: ProtocolTypes> From<TraceV0<PT>> for Trace<PT> {
+    fn from(trace: TraceV0<PT>) -> Self {
+        Self {
+            descriptors: trace.descriptors,
+            steps: trace.steps.into_iter().map(Step::from).collect(),
+            prior_traces: trace.prior_traces.into_iter().map(Self::from).collect(),
+            expected_outcome: None,
+            expected_flow: None,
+            seed: None,
+        }
+    }
+}
+
+/// A [`Trace`] in format version 1
+#[derive(Deserialize)]
+#[serde(bound = "PT: ProtocolTypes")]
+struct TraceV1<PT: ProtocolTypes> {
+    descriptors: Vec<AgentDescriptor<PT::PUTConfig>>,
+    steps: Vec<StepV2<PT>>,
+    prior_traces: Vec<TraceV1<PT>>,
+    expected_outcome: Option<Outcome>,
+    expected_flow: Option<Vec<AgentFlow<PT::Matcher>>>,
+}
+
+impl<PT: ProtocolTypes> From<TraceV1<PT>> for Trace<PT> {
+    fn from(trace: TraceV1<PT>) -> Self {
+        Self {
+            descriptors: trace.descriptors,
+            steps: trace.steps.into_iter().map(Step::from).collect(),
+            prior_traces: trace.prior_traces.into_iter().map(Self::from).collect(),
+            expected_outcome: trace.expected_outcome,
+            expected_flow: trace.expected_flow,
+            seed: None,
+        }
+    }
+}
+
+/// A [`Trace`] in format version 2
+#[derive(Deserialize)]
+#[serde(bound = "PT: ProtocolTypes")]
+struct TraceV2<PT: ProtocolTypes> {
+    descriptors: Vec<AgentDescriptor<PT::PUTConfig>>,
+    steps: Vec<StepV2<PT>>,
+    prior_traces: Vec<TraceV2<PT>>,
+    expected_outcome: Option<Outcome>,
+    expected_flow: Option<Vec<AgentFlow<PT::Matcher>>>,
+    seed: Option<u64>,
+}
+
+impl<PT: ProtocolTypes> From<TraceV2<PT>> for Trace<PT> {
+    fn from(trace: TraceV2<PT>) -> Self {
+        Self {
+            descriptors: trace.descriptors,
+            steps: trace.steps.into_iter().map(Step::from).collect(),
+            prior_traces: trace.prior_traces.into_iter().map(Self::from).collect(),
+            expected_outcome: trace.expected_outcome,
+            expected_flow: trace.expected_flow,
+            seed: trace.seed,
+        }
+    }
+}
+
+/// A [`Step`] in format versions 0 to 2, in which an [`OutputAction`] had no options
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(Serialize))]
+#[serde(bound = "PT: ProtocolTypes")]
+struct StepV2<PT: ProtocolTypes> {
+    agent: AgentName,
+    action: ActionV2<PT>,
+}
+
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(Serialize))]
+#[serde(bound = "PT: ProtocolTypes")]
+enum ActionV2<PT: ProtocolTypes> {
+    Input(InputAction<PT>),
+    Output(PhantomData<PT>),
+}
+
+impl<PT: ProtocolTypes> From<StepV2<PT>> for Step<PT> {
+    fn from(step: StepV2<PT>) -> Self {
+        match step.action {
+            ActionV2::Input(input) => Self {
+                agent: step.agent,
+                action: Action::Input(input),
+            },
+            ActionV2::Output(_) => OutputAction::new_step(step.agent),
+        }
+    }
+}
+
+/// A [`Trace`] consists of several [`Step`]s. Each has either a [`OutputAction`] or an
+/// [`InputAction`]. Each [`Step`]s references an [`Agent`] by name. Furthermore, a trace also has a
+/// list of *`AgentDescriptors`* which act like a blueprint to spawn [`Agent`]s with a corresponding
+/// server or client role and a specific TLs version. Essentially they are an [`Agent`] without a
+/// stream.
+impl<PT: ProtocolTypes> Trace<PT> {
+    pub fn spawn_agents<PB: ProtocolBehavior>(
+        &self,
+        ctx: &mut TraceContext<PB>,
+    ) -> Result<(), Error>
+    where
+        PB: ProtocolBehavior<ProtocolTypes = PT>,
+    {
+        for descriptor in &self.descriptors {
+            if let Some(reusable) = ctx
+                .agents
+                .iter_mut()
+                .find(|existing| existing.is_reusable_with(descriptor))
+            {
+                // rename if it already exists and we want to reuse
+                reusable.reset(descriptor.name)?;
+            } else {
+                // only spawn completely new if not yet existing
+                ctx.spawn(descriptor)?;
+            };
+        }
+
+        Ok(())
+    }
+
+    pub fn execute_until_step<PB>(
+        &self,
+        ctx: &mut TraceContext<PB>,
+        nb_steps: usize,
+    ) -> Result<(), Error>
+    where
+        PB: ProtocolBehavior<ProtocolTypes = PT>,
+    {
+        if let Some(seed) = self.seed {
+            ctx.spawner
+                .registry
+                .determinism_reseed_all_factories_with(seed);
+        }
+        ctx.clear_evaluation_memo();
+
+        for trace in &self.prior_traces {
+            trace.execute(ctx)?;
+        }
+        ctx.knowledge_store.forget_steps();
+
+        // Sessions negotiated in the prior traces are offered for resumption by the agents of the
+        // same name in this trace
+        let sessions: Vec<(AgentName, SessionData)> = ctx
+            .agents
+            .iter()
+            .filter_map(|agent| Some((agent.name(), agent.export_session()?)))
+            .collect();
+
+        self.spawn_agents(ctx)?;
+
+        for (name, session) in &sessions {
+            let Ok(agent) = ctx.find_agent_mut(*name) else {
+                continue;
+            };
+
+            if let Err(err) = agent.import_session(session) {
+                log::debug!("Agent {name} does not resume the session of a prior trace: {err}");
+            }
+        }
+        self.execute_steps(ctx, 0..nb_steps)
+    }
+
+    /// Executes the steps in `range` with the agents which are already spawned in `ctx`, e.g. by
+    /// executing the steps before `range` with [`Trace::execute_until_step`].
+    pub(crate) fn execute_steps<PB>(
+        &self,
+        ctx: &mut TraceContext<PB>,
+        range: Range<usize>,
+    ) -> Result<(), Error>
+    where
+        PB: ProtocolBehavior<ProtocolTypes = PT>,
+    {
+        for (i, step) in self.steps[range.clone()].iter().enumerate() {
+            let i = range.start + i;
+            log::debug!("Executing step #{}", i);
+            // Steps are only timed if they are measured or their duration is limited
+            let start = (ctx.measure || ctx.step_timeout.is_some()).then(Instant::now);
+            ctx.current_step = ctx.measure.then(|| StepMetrics::new(step.agent));
+            ctx.knowledge_store.current_step = Some(i);
+
+            let result = step
+                .execute(ctx)
+                .and_then(|()| ctx.verify_security_violations());
+            ctx.knowledge_store.current_step = None;
+
+            let duration = start.map(|start| start.elapsed());
+            if let (Some(mut metrics), Some(duration)) = (ctx.current_step.take(), duration) {
+                metrics.duration = duration;
+                ctx.metrics.steps.push(metrics);
+            }
+
+            let result = result.and_then(|()| match (ctx.step_timeout, duration) {
+                (Some(timeout), Some(duration)) if duration > timeout => Err(Error::Timeout(
+                    format!("step took {duration:?}, longer than {timeout:?}"),
+                )),
+                _ => Ok(()),
+            });
+
+            if let Err(err) = result {
+                ctx.drain_output_of(step.agent);
+                return Err(step.error_context(i, err));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn execute<PB>(&self, ctx: &mut TraceContext<PB>) -> Result<(), Error>
+    where
+        PB: ProtocolBehavior<ProtocolTypes = PT>,
+    {
+        let result = self
+            .execute_until_step(ctx, self.steps.len())
+            .and_then(|()| match &self.expected_flow {
+                Some(expected_flow) => ctx.verify_flow(expected_flow),
+                None => Ok(()),
+            });
+
+        if ctx.measure {
+            ctx.metrics.termination = Some(Termination::of(&result));
+        }
+        result
+    }
+
+    pub fn serialize_postcard(&self) -> Result<Vec<u8>, postcard::Error> {
+        postcard::to_allocvec(&self)
+    }
+
+    pub fn deserialize_postcard(slice: &[u8]) -> Result<Self, postcard::Error> {
+        postcard::from_bytes::<Self>(slice)
+    }
+
+    /// Serializes the trace together with the [`TRACE_FORMAT_VERSION`], such that later versions
+    /// of the fuzzer can migrate it.
+    pub fn serialize_versioned(&self) -> Result<Vec<u8>, Error> {
+        let serialized = postcard::to_allocvec(self)
+            .map_err(|err| Error::Codec(format!("failed to serialize trace: {err}")))?;
+
+        let mut bytes = TRACE_FORMAT_MAGIC.to_vec();
+        bytes.extend_from_slice(&TRACE_FORMAT_VERSION.to_le_bytes());
+        bytes.extend(serialized);
+        Ok(bytes)
+    }
+
+    /// Deserializes a trace stored by [`Trace::serialize_versioned`] and migrates it to the current
+    /// [`TRACE_FORMAT_VERSION`]. Bytes without a version are read as a legacy trace of version 0.
+    pub fn deserialize_versioned(bytes: &[u8]) -> Result<Self, Error> {
+        Self::take_versioned(bytes).map(|(trace, _rest)| trace)
+    }
+
+    /// Encodes the trace in the compact binary format of [`Trace::serialize_versioned`], including
+    /// its descriptors and prior traces.
+    #[must_use]
+    pub fn encode_codec(&self) -> Vec<u8> {
+        // postcard only fails for types which serde can not describe upfront, unlike traces
+        self.serialize_versioned()
+            .expect("failed to serialize trace")
+    }
+
+    /// Reads a trace written by [`Trace::encode_codec`] from the front of `reader`. Bytes after the
+    /// trace are left in the reader.
+    pub fn read_codec(reader: &mut codec::Reader) -> Option<Self> {
+        let bytes = reader.peek(reader.left())?;
+        let (trace, rest) = Self::take_versioned(bytes)
+            .map_err(|err| log::debug!("Failed to read trace: {err}"))
+            .ok()?;
+
+        reader.take(bytes.len() - rest.len())?;
+        Some(trace)
+    }
+
+    /// Deserializes a corpus file, which holds either the binary format of
+    /// [`Trace::serialize_versioned`] or the JSON of the serde representation, e.g. of a trace
+    /// which was written by hand.
+    pub fn deserialize_any(bytes: &[u8]) -> Result<Self, Error> {
+        let is_json = bytes
+            .iter()
+            .find(|byte| !byte.is_ascii_whitespace())
+            .is_some_and(|byte| *byte == b'{');
+
+        // A legacy trace with 123 descriptors would start like JSON, but is not valid JSON
+        if is_json && !bytes.starts_with(TRACE_FORMAT_MAGIC) {
+            if let Ok(trace) = serde_json::from_slice(bytes) {
+                return Ok(trace);
+            }
+        }
+
+        Self::deserialize_versioned(bytes)
+    }
+
+    fn take_versioned(bytes: &[u8]) -> Result<(Self, &[u8]), Error> {
+        let (version, serialized) = match bytes.strip_prefix(TRACE_FORMAT_MAGIC) {
+            Some([low, high, serialized @ ..]) => (u16::from_le_bytes([*low, *high]), serialized),
+            Some(_) => return Err(Error::Codec("trace is truncated".to_owned())),
+            None => (0, bytes),
+        };
+
+        let trace = match version {
+            0 => postcard::take_from_bytes::<TraceV0<PT>>(serialized)
+                .map(|(trace, rest)| (Self::from(trace), rest)),
+            1 => postcard::take_from_bytes::<TraceV1<PT>>(serialized)
+                .map(|(trace, rest)| (Self::from(trace), rest)),
+            2 => postcard::take_from_bytes::<TraceV2<PT>>(serialized)
+                .map(|(trace, rest)| (Self::from(trace), rest)),
+            TRACE_FORMAT_VERSION => postcard::take_from_bytes::<Self>(serialized),
+            _ => {
+                return Err(Error::Codec(format!(
+                    "trace has format version {version}, but this build only reads versions up \
+                     to {TRACE_FORMAT_VERSION}. Load it with the build which wrote it or a newer \
+                     one."
+                )))
+            }
+        };
+
+        trace.map_err(|err| {
+            Error::Codec(format!(
+                "failed to deserialize trace of format version {version}: {err}"
+            ))
+        })
+    }
+
+    #[must_use]
+    pub fn all_payloads(&self) -> Vec<&Payloads> {
+        self.steps
+            .iter()
+            .filter_map(|e| match &e.action {
+                Input(r) => Some(&r.recipe),
+                _ => None,
+            })
+            .flat_map(|t| t.all_payloads())
+            .collect()
+    }
+
+    pub fn all_payloads_mut(&mut self) -> Vec<&mut Payloads> {
+        self.steps
+            .iter_mut()
+            .filter_map(|e| match &mut e.action {
+                Input(r) => Some(&mut r.recipe),
+                _ => None,
+            })
+            .flat_map(|t| t.all_payloads_mut())
+            .collect()
+    }
+
+    #[must_use]
+    pub fn is_symbolic(&self) -> bool {
+        self.steps.iter().all(|e| match &e.action {
+            Input(r) => r.recipe.is_symbolic(),
+            _ => true,
+        })
+    }
+
+    /// Variables referenced by the recipes and precomputations of this trace and of its prior
+    /// traces, without duplicates and in order of first occurrence.
+    ///
+    /// These are the knowledge the trace assumes. A self-contained trace produces each of them in
+    /// an earlier output step, a precomputation or a prior trace.
+    #[must_use]
+    pub fn required_variables(&self) -> Vec<&Variable<PT>> {
+        let mut variables = vec![];
+        self.collect_required_variables(&mut HashSet::new(), &mut variables);
+        variables
+    }
+
+    fn collect_required_variables<'a>(
+        &'a self,
+        seen: &mut HashSet<&'a Variable<PT>>,
+        variables: &mut Vec<&'a Variable<PT>>,
+    ) {
+        for trace in &self.prior_traces {
+            trace.collect_required_variables(seen, variables);
+        }
+
+        for step in &self.steps {
+            let Input(input) = &step.action else {
+                continue;
+            };
+
+            let recipes = input
+                .precomputations
+                .iter()
+                .map(|precomputation| &precomputation.recipe)
+                .chain(std::iter::once(&input.recipe));
+
+            for term in recipes.flatten() {
+                if let DYTerm::Variable(variable) = &term.term {
+                    if seen.insert(variable) {
+                        variables.push(variable);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Hash over the structure of the steps, cheap enough to recognize duplicate inputs in the hot
+    /// loop of the fuzzer.
+    ///
+    /// Unlike the [`Hash`] implementation, this ignores the argument types of the function
+    /// symbols, such that traces which differ only there collide. The only allocation is the stack
+    /// for traversing the recipes.
+    #[must_use]
+    pub fn fast_structural_hash(&self) -> u64 {
+        let mut hasher = ahash::RandomState::with_seeds(0, 0, 0, 0).build_hasher();
+        self.hash_structure(&mut hasher, &mut Vec::new());
+        hasher.finish()
+    }
+
+    fn hash_structure<'a, H: Hasher>(&'a self, hasher: &mut H, stack: &mut Vec<&'a Term<PT>>) {
+        self.descriptors.hash(hasher);
+
+        hasher.write_usize(self.prior_traces.len());
+        for trace in &self.prior_traces {
+            trace.hash_structure(hasher, stack);
+        }
+
+        hasher.write_usize(self.steps.len());
+
+        for step in &self.steps {
+            step.agent.hash(hasher);
+
+            let input = match &step.action {
+                Input(input) => input,
+                Action::Output(output) => {
+                    hasher.write_u8(if output.one_flight { 2 } else { 0 });
+                    continue;
+                }
+            };
+
+            hasher.write_u8(1);
+            hasher.write_usize(input.precomputations.len());
+
+            let recipes = input
+                .precomputations
+                .iter()
+                .map(|precomputation| &precomputation.recipe)
+                .chain(std::iter::once(&input.recipe));
+
+            for recipe in recipes {
+                stack.push(recipe);
+
+                while let Some(term) = stack.pop() {
+                    term.payloads.hash(hasher);
+
+                    match &term.term {
+                        DYTerm::Variable(variable) => variable.hash(hasher),
+                        DYTerm::Application(func, args) => {
+                            func.name().hash(hasher);
+                            hasher.write_usize(args.len());
+                            stack.extend(args.iter().rev());
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Renumber the ids of all symbols in a deterministic pre-order traversal over the prior
+    /// traces, precomputations and recipes. Structurally equal traces end up with identical ids.
+    ///
+    /// Afterwards, `unique_id`s are only unique within this trace.
+    pub fn normalize_ids(&mut self) {
+        let mut next_id = 0;
+        self.normalize_ids_from(&mut next_id);
+    }
+
+    fn normalize_ids_from(&mut self, next_id: &mut u32) {
+        for trace in &mut self.prior_traces {
+            trace.normalize_ids_from(next_id);
+        }
+
+        for step in &mut self.steps {
+            if let Input(input) = &mut step.action {
+                for precomputation in &mut input.precomputations {
+                    precomputation.recipe.normalize_ids(next_id);
+                }
+                input.recipe.normalize_ids(next_id);
+            }
+        }
+    }
+}
+
+impl<PT: ProtocolTypes> fmt::Debug for Trace<PT> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Trace with {} steps", self.steps.len())
+    }
+}
+
+impl<PT: ProtocolTypes> fmt::Display for Trace<PT> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Trace:")?;
+        for step in &self.steps {
+            write!(f, "\n{} -> {}", step.agent, step.action)?;
+        }
+        Ok(())
+    }
+}
+
+impl<PT: ProtocolTypes> AsRef<Self> for Trace<PT> {
+    fn as_ref(&self) -> &Self {
+        self
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Hash)]
+#[serde(bound = "PT: ProtocolTypes")]
+pub struct Step<PT: ProtocolTypes> {
+    pub agent: AgentName,
+    pub action: Action<PT>,
+}
+
+impl<PT: ProtocolTypes> Step<PT> {
+    pub fn execute<PB>(&self, ctx: &mut TraceContext<PB>) -> Result<(), Error>
+    where
+        PB: ProtocolBehavior<ProtocolTypes = PT>,
+    {
+        match &self.action {
+            Action::Input(input) => input.execute(self.agent, ctx).and_then(|()| {
+                // NOTE force output after each InputAction step
+                (OutputAction {
+                    one_flight: false,
+                    phantom: Default::default(),
+                })
+                .execute(self.agent, ctx)
+            }),
+            Action::Output(output) => output.execute(self.agent, ctx),
+        }
+    }
+
+    /// Wraps `err` with the index and the agent of this step, which is the step at `index` of its
+    /// trace, such that the error tells where it occurred.
+    fn error_context(&self, index: usize, err: Error) -> Error {
+        let function = match &self.action {
+            Action::Input(input) => match &input.recipe.term {
+                DYTerm::Application(func, _) => Some(remove_prefix(func.name())),
+                DYTerm::Variable(_) => None,
+            },
+            Action::Output(_) => None,
+        };
+
+        Error::Context {
+            step: index,
+            agent: self.agent,
+            function,
+            source: Box::new(err),
+        }
+    }
+}
+
+/// There are two action types [`OutputAction`] and [`InputAction`].
+///
+/// Both actions drive the internal state machine of an [`Agent`] forward by calling `progress()`.
+/// The [`OutputAction`] first forwards the state machine and then extracts knowledge from the
+/// TLS messages produced by the underlying stream by calling  `take_message_from_outbound(...)`.
+/// The [`InputAction`] evaluates the recipe term and injects the newly produced message
+/// into the *inbound channel* of the [`Agent`] referenced through the corresponding [`Step`]s
+/// by calling `add_to_inbound(...)` and then drives the state machine forward.
+/// Therefore, the difference is that one step *increases* the knowledge of the attacker,
+/// whereas the other action *uses* the available knowledge.
+#[derive(Serialize, Deserialize, Clone, Debug, Hash)]
+#[serde(bound = "PT: ProtocolTypes")]
+pub enum Action<PT: ProtocolTypes> {
+    Input(InputAction<PT>),
+    Output(OutputAction<PT>),
+}
+
+impl<PT: ProtocolTypes> fmt::Display for Action<PT> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Input(input) => write!(f, "{input}"),
+            Self::Output(output) => write!(f, "{output}"),
+        }
+    }
+}
+
+/// Maximum number of times an [`OutputAction`] progresses an [`Agent`] which has pending output
+const MAX_OUTPUT_ROUNDS: usize = 16;
+
+/// Advance the [`Agent`]'s state and process the produced output.
+///
+/// The [`OutputAction`] first forwards the state machine and then extracts knowledge from the TLS
+/// messages produced by the underlying stream by calling  `take_message_from_outbound(...)`. An
+/// output action is automatically called after each input step.
+#[derive(Serialize, Deserialize, Clone, Debug, Hash)]
+pub struct OutputAction<PT> {
+    /// Advance the [`Agent`] by at most one flight, see
+    /// [`Put::progress_one_flight`](crate::put::Put::progress_one_flight). Input which the agent
+    /// did not yet process stays in its inbound channel for the following steps.
+    #[serde(default)]
+    pub one_flight: bool,
+    phantom: PhantomData<PT>,
+}
+
+impl<PT: ProtocolTypes> OutputAction<PT> {
+    #[must_use]
+    pub fn new_step(agent: AgentName) -> Step<PT> {
+        Step {
+            agent,
+            action: Action::Output(Self {
+                one_flight: false,
+                phantom: Default::default(),
+            }),
+        }
+    }
+
+    /// Creates a step which takes a single flight of `agent`, see [`OutputAction::one_flight`]
+    #[must_use]
+    pub fn new_flight_step(agent: AgentName) -> Step<PT> {
+        Step {
+            agent,
+            action: Action::Output(Self {
+                one_flight: true,
+                phantom: Default::default(),
+            }),
+        }
+    }
+
+    fn execute<PB>(&self, agent_name: AgentName, ctx: &mut TraceContext<PB>) -> Result<(), Error>
+    where
+        PB: ProtocolBehavior<ProtocolTypes = PT>,
+    {
+        let source = Source::Agent(agent_name);
+
+        // A PUT can be in the middle of writing a flight, so we keep taking its output until it is
+        // drained. PUTs which never finish writing are given up on after a few rounds.
+        for _ in 0..MAX_OUTPUT_ROUNDS {
+            let agent = ctx.find_agent_mut(agent_name)?;
+            if self.one_flight {
+                agent.progress_one_flight()?;
+            } else {
+                agent.progress()?;
+            }
+
+            let output = agent.take_message_from_outbound()?;
+            let pending = agent.pending_output_len();
+
+            if let Some(opaque_flight) = output {
+                // Flights are only encoded when they are measured or recorded
+                if ctx.measure || ctx.wire_records.is_some() {
+                    let encoding = codec::Codec::get_encoding(&opaque_flight);
+                    if let Some(metrics) = &mut ctx.current_step {
+                        metrics.bytes_from_agent += encoding.len();
+                    }
+                    ctx.record_on_wire(agent_name, Direction::FromAgent, &encoding);
+                }
+
+                ctx.knowledge_store
+                    .add_raw_knowledge(opaque_flight.clone(), source.clone(), None);
+
+                if let Ok(flight) = TryInto::<PB::ProtocolMessageFlight>::try_into(opaque_flight) {
+                    ctx.knowledge_store
+                        .add_raw_knowledge(flight, source.clone(), None);
+
+                    if let (Some(metrics), Some(flight)) = (
+                        &mut ctx.current_step,
+                        ctx.knowledge_store.raw_knowledge.last(),
+                    ) {
+                        let message_type = TypeId::of::<PB::ProtocolMessage>();
+                        metrics.messages_from_agent += flight
+                            .into_iter()
+                            .filter(|knowledge| knowledge.data.type_id() == message_type)
+                            .count();
+                    }
+                }
+            }
+
+            if pending == 0 || self.one_flight {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<PT: ProtocolTypes> fmt::Display for OutputAction<PT> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.one_flight {
+            write!(f, "OutputAction (one flight)")
+        } else {
+            write!(f, "OutputAction")
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Hash)]
+#[serde(bound = "PT: ProtocolTypes")]
+pub struct Precomputation<PT: ProtocolTypes> {
+    pub label: Option<String>,
+    pub recipe: Term<PT>,
+}
+
+/// Provide inputs to the [`Agent`].
+///
+/// The [`InputAction`] evaluates the recipe term and injects the newly produced message
+/// into the *inbound channel* of the [`Agent`] referenced through the corresponding [`Step`]s
+/// by calling `add_to_inbound(...)` and then drives the state machine forward.
+#[derive(Serialize, Deserialize, Clone, Debug, Hash)]
+#[serde(bound = "PT: ProtocolTypes")]
+pub struct InputAction<PT: ProtocolTypes> {
+    pub precomputations: Vec<Precomputation<PT>>,
+    pub recipe: Term<PT>,
+}
+
+/// Processes messages in the inbound channel. Uses the recipe field to evaluate to a rustls Message
+/// or a `MultiMessage`.
+impl<PT: ProtocolTypes> InputAction<PT> {
+    pub const fn new_step(agent: AgentName, recipe: Term<PT>) -> Step<PT> {
+        Step {
+            agent,
+            action: Action::Input(Self {
+                recipe,
+                precomputations: vec![],
+            }),
+        }
+    }
+
+    fn execute<PB>(&self, agent_name: AgentName, ctx: &mut TraceContext<PB>) -> Result<(), Error>
+    where
+        PB: ProtocolBehavior<ProtocolTypes = PT>,
+    {
+        let message = self.evaluate(agent_name, ctx)?;
+        if let Some(metrics) = &mut ctx.current_step {
+            metrics.bytes_to_agent += message.len();
+        }
+        ctx.record_on_wire(agent_name, Direction::ToAgent, &message);
+
+        let mut protected = ctx.protected_inbound.contains(&agent_name);
+        let agent = ctx.find_agent_mut(agent_name)?;
+
+        agent.clear_last_alert();
+        agent.add_to_inbound(&message);
+        let result = agent.progress();
+
+        let outcome = PB::put_decryption_outcome(agent, &message, &mut protected);
+        if protected {
+            ctx.protected_inbound.insert(agent_name);
+        }
+        if let Some(success) = outcome {
+            record_decryption(success);
+        }
+
+        result
+    }
+
+    /// Evaluates the precomputations and the recipe of the step for the agent `agent_name`. This
+    /// uses only the knowledge in `ctx`, no agent is required.
+    pub(crate) fn evaluate<PB>(
+        &self,
+        agent_name: AgentName,
+        ctx: &mut TraceContext<PB>,
+    ) -> Result<Vec<u8>, Error>
+    where
+        PB: ProtocolBehavior<ProtocolTypes = PT>,
+    {
+        // The recipes are evaluated for the PUT of the agent, see `receiving_put_options`
+        let put_options = ctx.spawner.put_descriptor(agent_name).options;
+        with_receiving_put_options(put_options, || -> Result<_, Error> {
+            for precomputation in &self.precomputations {
+                let eval = precomputation.recipe.evaluate_dy(ctx)?; // We do not accept payloads in precomputation recipes
+                ctx.knowledge_store.add_raw_boxed_knowledge(
+                    eval,
+                    Source::Label(precomputation.label.clone()),
+                    Some(precomputation.recipe.clone()),
+                );
+            }
+
+            self.recipe.evaluate(ctx)
+        })
+    }
+}
+
+impl<PT: ProtocolTypes> fmt::Display for InputAction<PT> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "InputAction:\n{}", self.recipe)
+    }
+}
+
+/// This macro defines the precomputation syntax to add precomputations to an input action step
+///
+/// Example of precomputation with TLS
+///
+/// ```ignore
+/// input_action! {
+///     // Here we are precomputing a decryption of TLS extension and using it in the following term
+///     "decrypted_extensions" = term!{fn_decrypt_handshake_flight(
+///         ((server, 0)/MessageFlight),
+///         (@server_hello_transcript),
+///         (fn_get_server_key_share(((server, 0)[Some(TlsQueryMatcher::Handshake(Some(HandshakeType::ServerHello)))]))),
+///         fn_no_psk,
+///         fn_named_group_secp384r1,
+///         fn_true,
+///         fn_seq_0  // sequence 0
+///     )}
+///     =>
+///     // This term will be sent to the PUT by the input action
+///     term!{fn_append_transcript(
+///         (@server_hello_transcript),
+///         (
+///             // We can query our precomputation
+///             (!"decrypted_extensions", 0)[
+///                 Some(TlsQueryMatcher::Handshake(Some(HandshakeType::EncryptedExtensions)))
+///             ] / Message
+///         )
+///     )}
+/// };
+/// ```
+///
+/// The following syntaxes are accepted :
+/// ```ignore
+/// # use puffin::input_action;
+/// # use puffin::term;
+/// # use puffin::trace::Precomputation;
+/// # use puffin::trace::InputAction;
+///
+/// input_action!{term!{fn_msg()}};
+/// input_action!{term!{fn_precomputation()} => term!{fn_msg()}};
+/// input_action!{"this_is_a_label" = term!{fn_precomputation()} => term!{fn_msg()}};
+/// input_action!{
+///     "this_is_a_label" = term!{fn_precomputation_1()} =>
+///         term!{fn_precomputation_2()} =>
+///             term!{fn_msg()}
+/// };
+/// // the latter is equivalent to
+/// input_action!{
+///     "this_is_a_label" = term!{fn_precomputation_1()}, term!{fn_precomputation_2()} =>
+///         term!{fn_msg()}
+/// };
+/// ```
+///
+/// All the previous examples respectively produce
+/// ```ignore
+/// # use puffin::trace::Precomputation;
+/// # use puffin::trace::InputAction;
+/// # use puffin::term;
+/// # use crate::algebra::test_signature::fn_msg;
+///
+/// InputAction {
+///     recipe: term!{fn_msg()},
+///     precomputations: vec![],
+/// };
+/// InputAction {
+///     recipe: term!{fn_msg()},
+///     precomputations: vec![Precomputation{label: "".into(), recipe: term!{fn_precomputation()}}],
+/// };
+/// InputAction {
+///     recipe: term!{fn_msg()},
+///     precomputations: vec![Precomputation{label: "this_is_a_label".into(), recipe:
+/// term!{fn_precomputation()}}], };
+/// InputAction {
+///     recipe: term!{fn_msg()},
+///     precomputations: vec![
+///         Precomputation{label: "this_is_a_label".into(), recipe: term!{fn_precomputation_1()}},
+///         Precomputation{label: "".into(), recipe: term!{fn_precomputation_2()}}
+///     ],
+/// };
+/// ```
+#[macro_export]
+macro_rules! input_action {
+    (@internal [$($label:expr, $precomp:expr);+] $recipe:expr) => {
+        InputAction {
+            recipe: $recipe,
+            precomputations: vec![$(Precomputation{label: $label, recipe: $precomp}),*],
+        }
+    };
+
+    (@internal [$($precomps:tt)+] $other_name:literal = $other_precomp:expr => $($tail:tt)+) => {
+        input_action!{@internal [$($precomps)+; Some($other_name.into()), $other_precomp] $($tail)+ }
+    };
+
+    (@internal [$($precomps:tt)+] $other_name:literal = $other_precomp:expr, $($tail:tt)+) => {
+        input_action!{@internal [$($precomps)+; Some($other_name.into()), $other_precomp] $($tail)+ }
+    };
+
+    (@internal [$($precomps:tt)+] $other_precomp:expr => $($tail:tt)+) => {
+        input_action!{@internal [$($precomps)+; None, $other_precomp] $($tail)+ }
+    };
+
+    (@internal [$($precomps:tt)+] $other_precomp:expr, $($tail:tt)+) => {
+        input_action!{@internal [$($precomps)+; None, $other_precomp] $($tail)+ }
+    };
+
+    ($precomp_name:literal = $precomp:expr => $($tail:tt)+) => {
+        input_action!{@internal [Some($precomp_name.into()), $precomp] $($tail)+ }
+    };
+
+    ($precomp_name:literal = $precomp:expr , $($tail:tt)+) => {
+        input_action!{@internal [Some($precomp_name.into()), $precomp] $($tail)+ }
+    };
+
+    ($precomp:expr => $($tail:tt)+) => {
+        input_action!{@internal [None, $precomp] $($tail)+ }
+    };
+
+    ($precomp:expr, $($tail:tt)+) => {
+        input_action!{@internal [None, $precomp] $($tail)+ }
+    };
+
+    ($recipe:expr) => {
+        InputAction {
+            recipe: $recipe,
+            precomputations: vec![],
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::algebra::signature::Signature;
+    use crate::algebra::test_signature::{
+        dummy_registry, fn_encrypt12, fn_finished, fn_new_random, fn_seq_0, fn_seq_1,
+        setup_simple_trace, test_registry, TestFactory, TestPUTConfig, TestProtocolBehavior,
+        TestProtocolTypes, TestPut, TestPutBehavior,
+    };
+    use crate::execution::{minimize, Runner};
+    use crate::protocol::{Extractable, ExtractedKnowledge};
+    use crate::put::SessionData;
+    use crate::trace::{InputAction, Precomputation};
+    use crate::{codec, term};
+
+    /// Trace without descriptors or expectations which executes `steps`
+    fn trace_with_steps(steps: Vec<Step<TestProtocolTypes>>) -> Trace<TestProtocolTypes> {
+        Trace {
+            descriptors: vec![],
+            steps,
+            prior_traces: vec![],
+            expected_outcome: None,
+            expected_flow: None,
+            seed: None,
+        }
+    }
+
+    /// Three flights of 100 bytes, of which the PUT writes `chunk` bytes per call to `progress`
+    fn three_flights(chunk: usize) -> TestPutBehavior {
+        TestPutBehavior {
+            flights: vec![vec![0; 100]; 3],
+            chunk: Some(chunk),
+            ..TestPutBehavior::default()
+        }
+    }
+
+    fn execute_output(behavior: TestPutBehavior) -> TraceContext<TestProtocolBehavior> {
+        let registry = dummy_registry();
+        let mut ctx = TraceContext::new(Spawner::new(registry));
+
+        let descriptor = AgentDescriptor::from_name(AgentName::first());
+        let put = TestPut::new(descriptor.clone(), behavior);
+        ctx.agents.push(Agent::new(descriptor, Box::new(put)));
+
+        OutputAction::new_step(AgentName::first())
+            .execute(&mut ctx)
+            .unwrap();
+        ctx
+    }
+
+    #[test]
+    fn test_output_action_drains_pending_output() {
+        let ctx = execute_output(three_flights(100));
+
+        let agent = ctx.find_agent(AgentName::first()).unwrap();
+        assert_eq!(agent.pending_output_len(), 0);
+        // Each of the three flights is stored as opaque and as parsed flight
+        assert_eq!(ctx.knowledge_store.raw_knowledge.len(), 6);
+    }
+
+    #[test]
+    fn test_output_action_stuck_mid_write() {
+        let ctx = execute_output(three_flights(0));
+
+        let agent = ctx.find_agent(AgentName::first()).unwrap();
+        assert_eq!(agent.pending_output_len(), 300);
+        assert!(ctx.knowledge_store.raw_knowledge.is_empty());
+    }
+
+    fn idle_agent(name: AgentName) -> Agent<TestProtocolBehavior> {
+        let descriptor = AgentDescriptor::from_config(
+            name,
+            TestPUTConfig {
+                reusable: true,
+                ..TestPUTConfig::default()
+            },
+        );
+        let put = TestPut::new(descriptor.clone(), TestPutBehavior::default());
+        Agent::new(descriptor, Box::new(put))
+    }
+
+    #[test]
+    fn test_idle_agent_reused_with_same_put_options() {
+        let mut ctx = TraceContext::new(Spawner::new(dummy_registry()));
+        let descriptor = AgentDescriptor::from_config(
+            AgentName::first(),
+            TestPUTConfig {
+                reusable: true,
+                ..TestPUTConfig::default()
+            },
+        );
+        let put = PutDescriptor::new("teststub", vec![("read_chunk", "1")]);
+        ctx.idle_agents
+            .push((put.clone(), idle_agent(AgentName::first())));
+
+        let other_options = PutDescriptor::new("teststub", vec![("read_chunk", "2")]);
+        assert!(ctx.take_idle_agent(&other_options, &descriptor).is_none());
+        assert!(ctx
+            .take_idle_agent(&"teststub".into(), &descriptor)
+            .is_none());
+        assert!(ctx.take_idle_agent(&put, &descriptor).is_some());
+        assert!(ctx.idle_agents.is_empty());
+    }
+
+    #[test]
+    fn test_reset_clears_shared_secrets() {
+        let mut ctx = TraceContext::new(Spawner::new(dummy_registry()));
+        ctx.add_shared_secret("psk".to_string(), vec![1, 2, 3]);
+
+        ctx.reset();
+
+        assert!(ctx
+            .find_shared_secret(TypeShape::of::<Vec<u8>>(), &Query::shared_secret("psk"))
+            .is_none());
+    }
+
+    #[test]
+    fn test_idle_agents_bounded() {
+        let mut ctx = TraceContext::new(Spawner::new(dummy_registry()));
+        let mut name = AgentName::first();
+        let mut names = vec![];
+        for _ in 0..MAX_IDLE_AGENTS + 4 {
+            ctx.agents.push(idle_agent(name));
+            names.push(name);
+            name = name.next();
+        }
+
+        ctx.reset();
+
+        // The oldest agents are dropped
+        let idle: Vec<_> = ctx
+            .idle_agents
+            .iter()
+            .map(|(_, agent)| agent.name())
+            .collect();
+        assert_eq!(idle, names[4..]);
+    }
+
+    #[test]
+    fn test_progress_one_flight() {
+        let descriptor = AgentDescriptor::from_name(AgentName::first());
+        // e.g. a server which sends its ServerHello flight and waits for the Finished of the client
+        let behavior = TestPutBehavior {
+            flights: vec![b"ServerHello".to_vec(), b"NewSessionTicket".to_vec()],
+            flight_boundaries: true,
+            ..TestPutBehavior::default()
+        };
+        let put = TestPut::new(descriptor.clone(), behavior);
+        let mut agent = Agent::new(descriptor, Box::new(put));
+
+        agent.progress_one_flight().unwrap();
+        assert!(agent.take_message_from_outbound().unwrap().is_some());
+        assert!(agent.take_message_from_outbound().unwrap().is_none());
+
+        agent.progress_one_flight().unwrap();
+        assert!(agent.take_message_from_outbound().unwrap().is_some());
+
+        // The PUT waits for input now
+        agent.progress_one_flight().unwrap();
+        assert!(agent.take_message_from_outbound().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_progress_one_flight_fallback() {
+        let descriptor = AgentDescriptor::from_name(AgentName::first());
+        let put = TestPut::new(descriptor.clone(), three_flights(100));
+        let mut agent = Agent::new(descriptor, Box::new(put));
+
+        // PUTs without flight boundaries make the same progress as with `progress`
+        agent.progress_one_flight().unwrap();
+        assert_eq!(agent.pending_output_len(), 300);
+        assert!(agent.take_message_from_outbound().unwrap().is_some());
+        assert_eq!(agent.pending_output_len(), 200);
+    }
+
+    #[test]
+    fn test_error_context() {
+        let mut ctx = execute_output(TestPutBehavior::default());
+        let client = AgentName::first();
+        let trace = trace_with_steps(vec![
+            OutputAction::new_step(client),
+            // the client did not output a sequence number
+            InputAction::new_step(client, term! {fn_encrypt12(fn_finished,((client, 0)))}),
+        ]);
+
+        ctx.measure();
+        let err = trace.execute(&mut ctx).unwrap_err();
+
+        assert!(matches!(err, Error::Context { step: 1, .. }));
+        assert!(!matches!(err.root(), Error::Context { .. }));
+        assert!(err
+            .to_string()
+            .starts_with("step #1 of client with input fn_encrypt12: "));
+
+        // The failing step is measured as well
+        assert_eq!(ctx.metrics().steps.len(), 2);
+        assert_eq!(ctx.metrics().termination, Some(Termination::Failed));
+    }
+
+    #[test]
+    fn test_evaluation_memo_is_bounded() {
+        let ctx = TraceContext::new(Spawner::new(dummy_registry()));
+        let keys: Vec<MemoKey> = (0..=MAX_MEMOIZED_EVALUATIONS as u32)
+            .map(|resistant_id| MemoKey {
+                resistant_id,
+                structure: 0,
+            })
+            .collect();
+
+        for key in &keys {
+            ctx.memoize_evaluation(*key, &vec![0u8]);
+        }
+
+        assert!(ctx.memoized_evaluation(keys[0]).is_some());
+        assert!(ctx
+            .memoized_evaluation(keys[MAX_MEMOIZED_EVALUATIONS])
+            .is_none());
+    }
+
+    #[test]
+    fn test_fork_knowledge() {
+        let descriptor = AgentDescriptor::from_name(AgentName::first());
+        let mut ctx = TraceContext::new(Spawner::new(dummy_registry()));
+        ctx.spawn(&descriptor).unwrap();
+        ctx.knowledge_store
+            .add_raw_knowledge(vec![1_u8], Source::Agent(AgentName::first()), None);
+        ctx.add_shared_secret("psk".to_string(), vec![1, 2, 3]);
+        ctx.measure();
+        ctx.limit_step_duration(Duration::from_secs(1));
+
+        let mut fork = ctx.fork_knowledge();
+        assert_eq!(
+            format!("{:?}", fork.knowledge_store.raw_knowledge),
+            format!("{:?}", ctx.knowledge_store.raw_knowledge)
+        );
+        assert!(fork
+            .find_shared_secret(TypeShape::of::<Vec<u8>>(), &Query::shared_secret("psk"))
+            .is_some());
+        assert!(fork.measure);
+        assert_eq!(fork.step_timeout, Some(Duration::from_secs(1)));
+
+        // The agent of the original context is not copied, it has to be spawned anew
+        assert!(ctx.find_agent(AgentName::first()).is_ok());
+        assert!(fork.find_agent(AgentName::first()).is_err());
+        fork.spawn(&descriptor).unwrap();
+        assert!(fork.find_agent(AgentName::first()).is_ok());
+
+        // Knowledge which the fork learns is not shared with the original context
+        fork.knowledge_store
+            .add_raw_knowledge(vec![2_u8], Source::Label(None), None);
+        assert_eq!(fork.knowledge_store.len(), ctx.knowledge_store.len() + 1);
+    }
+
+    #[test]
+    fn test_minimize() {
+        let client = AgentName::first();
+        let server = client.next();
+        let registry = test_registry(TestFactory {
+            behavior: three_flights(100),
+            ..TestFactory::default()
+        });
+        let runner = Runner::new(registry.clone(), Spawner::new(registry));
+        let trace = Trace {
+            descriptors: vec![
+                AgentDescriptor::from_name(client),
+                AgentDescriptor::from_name(server),
+            ],
+            ..trace_with_steps(vec![
+                OutputAction::new_step(client),
+                OutputAction::new_step(server),
+                InputAction::new_step(client, term! {fn_seq_1}),
+            ])
+        };
+
+        // Only the output of the server is needed
+        let minimized = minimize(&runner, &trace, |ctx, result| {
+            result.is_ok()
+                && ctx
+                    .knowledge_store
+                    .raw_knowledge
+                    .iter()
+                    .any(|knowledge| knowledge.source == Source::Agent(server))
+        });
+
+        assert_eq!(minimized.steps.len(), 1);
+        assert_eq!(minimized.steps[0].agent, server);
+        assert_eq!(minimized.descriptors.len(), 2);
+    }
+
+    #[test]
+    fn test_execution_metrics() {
+        let mut ctx = execute_output(TestPutBehavior::default());
+        let client = AgentName::first();
+        let trace = trace_with_steps(vec![
+            // the client has nothing to output
+            OutputAction::new_step(client),
+            InputAction::new_step(client, term! {fn_seq_1}),
+        ]);
+
+        ctx.measure();
+        trace.execute(&mut ctx).unwrap();
+        let metrics = ctx.metrics();
+
+        assert_eq!(metrics.steps.len(), trace.steps.len());
+        assert_eq!(metrics.termination, Some(Termination::Completed));
+        assert!(metrics
+            .steps
+            .iter()
+            .all(|step| step.agent == client && step.duration >= Duration::ZERO));
+        assert_eq!(
+            metrics.total_duration(),
+            metrics.steps.iter().map(|step| step.duration).sum()
+        );
+
+        assert_eq!(
+            metrics.steps[0],
+            StepMetrics {
+                duration: metrics.steps[0].duration,
+                ..StepMetrics::new(client)
+            }
+        );
+        assert_eq!(metrics.steps[1].bytes_to_agent, 4);
+        assert_eq!(metrics.bytes_exchanged(), 4);
+    }
+
+    #[test]
+    fn test_step_timeout() {
+        let mut ctx = execute_output(TestPutBehavior::default());
+        let client = AgentName::first();
+        let trace = trace_with_steps(vec![InputAction::new_step(client, term! {fn_seq_1})]);
+
+        trace.execute(&mut ctx).unwrap();
+
+        ctx.limit_step_duration(Duration::ZERO);
+        let err = trace.execute(&mut ctx).unwrap_err();
+
+        assert!(matches!(err, Error::Context { step: 0, .. }));
+        assert!(matches!(err.root(), Error::Timeout(_)));
+    }
+
+    #[test]
+    fn test_metrics_are_opt_in() {
+        let client = AgentName::first();
+        let trace = trace_with_steps(vec![
+            OutputAction::new_step(client),
+            InputAction::new_step(client, term! {fn_seq_1}),
+        ]);
+        let execute = |measure: bool| {
+            let descriptor = AgentDescriptor::from_name(client);
+            let mut ctx = TraceContext::new(Spawner::new(dummy_registry()));
+            let put = TestPut::new(descriptor.clone(), three_flights(100));
+            ctx.agents.push(Agent::new(descriptor, Box::new(put)));
+            if measure {
+                ctx.measure();
+            }
+            trace.execute(&mut ctx).unwrap();
+            ctx
+        };
+
+        let ctx = execute(false);
+        assert_eq!(ctx.metrics(), &ExecutionMetrics::default());
+        assert_eq!(ctx.knowledge_store.raw_knowledge.len(), 6);
+
+        // The three flights of 100 bytes are taken from the client, and 4 bytes are sent to it
+        let ctx = execute(true);
+        let metrics = ctx.metrics();
+        assert_eq!(metrics.steps[0].bytes_from_agent, 300);
+        assert_eq!(metrics.steps[0].bytes_to_agent, 0);
+        assert_eq!(metrics.steps[1].bytes_from_agent, 0);
+        assert_eq!(metrics.steps[1].bytes_to_agent, 4);
+        assert_eq!(metrics.bytes_exchanged(), 304);
+        assert_eq!(metrics.termination, Some(Termination::Completed));
+    }
+
+    #[test]
+    fn test_record_wire() {
+        let mut ctx = execute_output(TestPutBehavior::default());
+        let client = AgentName::first();
+        let trace = trace_with_steps(vec![
+            InputAction::new_step(client, term! {fn_seq_1}),
+            OutputAction::new_step(client),
+            InputAction::new_step(client, term! {fn_seq_1}),
+        ]);
+
+        // Nothing is recorded unless enabled
+        trace.execute(&mut ctx).unwrap();
+        assert!(ctx.wire_records().is_empty());
+
+        let mut ctx = execute_output(TestPutBehavior::default());
+        ctx.record_wire();
+        trace.execute(&mut ctx).unwrap();
+        let records = ctx.wire_records();
+        assert_eq!(records.len(), 2);
+        assert!(records
+            .iter()
+            .all(|record| record.agent == client && record.direction == Direction::ToAgent));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("trace.pcap");
+        pcap::write_pcap(&path, records).unwrap();
+        let file = std::fs::read(&path).unwrap();
+        assert_eq!(file[..4], pcap::PCAP_MAGIC.to_le_bytes());
+    }
+
+    #[test]
+    fn test_query_step_output() {
+        let mut ctx = TraceContext::new(Spawner::new(dummy_registry()));
+        let server = AgentName::first().next();
+
+        // The server answers in steps 0 and 1, step 0 also has a precomputation
+        ctx.knowledge_store.current_step = Some(0);
+        ctx.knowledge_store
+            .add_raw_knowledge(vec![0_u8], Source::Agent(server), None);
+        ctx.knowledge_store
+            .add_raw_knowledge(vec![2_u8], Source::Label(None), None);
+        ctx.knowledge_store.current_step = Some(1);
+        ctx.knowledge_store
+            .add_raw_knowledge(vec![1_u8], Source::Agent(server), None);
+        ctx.knowledge_store.current_step = None;
+
+        let evaluate = |step, counter| {
+            let variable: Variable<TestProtocolTypes> = Signature::new_var(
+                TypeShape::of::<Vec<u8>>(),
+                Some(Source::Step(step)),
+                None,
+                counter,
+            );
+            Term::from(DYTerm::Variable(variable))
+                .evaluate_dy(&ctx)
+                .map(|data| data.as_any().downcast_ref::<Vec<u8>>().unwrap().clone())
+        };
+
+        assert_eq!(evaluate(0, 0).unwrap(), vec![0]);
+        assert_eq!(evaluate(1, 0).unwrap(), vec![1]);
+        // The precomputation is not part of the output of step 0
+        assert!(evaluate(0, 1).is_err());
+        // No output of the requested type in step 2
+        assert!(evaluate(2, 0).is_err());
+    }
+
+    #[test]
+    fn test_step_output_of_prior_trace() {
+        let client = AgentName::first();
+        let descriptor = AgentDescriptor::from_name(client);
+        let mut ctx = TraceContext::new(Spawner::new(dummy_registry()));
+        let put = TestPut::new(descriptor.clone(), three_flights(100));
+        ctx.agents.push(Agent::new(descriptor, Box::new(put)));
+
+        let prior = trace_with_steps(vec![OutputAction::new_step(client)]);
+        let trace = Trace {
+            prior_traces: vec![prior],
+            ..trace_with_steps(vec![])
+        };
+        trace.execute(&mut ctx).unwrap();
+
+        // The output of the prior trace is known, but not as the output of step 0 of the trace
+        let knowledge = &ctx.knowledge_store.raw_knowledge;
+        assert!(knowledge.iter().any(|k| k.is_from(&Source::Agent(client))));
+        assert!(!knowledge.iter().any(|k| k.is_from(&Source::Step(0))));
+    }
+
+    fn execute_failing(partial: Vec<u8>) -> TraceContext<TestProtocolBehavior> {
+        let mut ctx = execute_output(TestPutBehavior::default());
+        let server = AgentName::first().next();
+        let descriptor = AgentDescriptor::from_name(server);
+        // The PUT writes `partial` bytes of a flight and then fails
+        let behavior = TestPutBehavior {
+            flights: vec![[partial.as_slice(), &[0x2a]].concat()],
+            chunk: Some(partial.len()),
+            fail: true,
+            ..TestPutBehavior::default()
+        };
+        let put = TestPut::new(descriptor.clone(), behavior);
+        ctx.agents.push(Agent::new(descriptor, Box::new(put)));
+
+        let trace = trace_with_steps(vec![
+            OutputAction::new_step(AgentName::first()),
+            OutputAction::new_step(server),
+        ]);
+
+        let err = trace.execute(&mut ctx).unwrap_err();
+        assert!(matches!(err, Error::Context { step: 1, .. }));
+        ctx
+    }
+
+    #[test]
+    fn test_drain_output_on_error() {
+        let server = AgentName::first().next();
+        let mut ctx = execute_failing(vec![0x16, 0x03, 0x03, 0x00]);
+
+        assert_eq!(
+            ctx.drained_output(),
+            &[(server, vec![0x16, 0x03, 0x03, 0x00])]
+        );
+
+        // The buffer is cleared by draining it
+        let agent = ctx.find_agent_mut(server).unwrap();
+        assert_eq!(agent.pending_output_len(), 0);
+        assert!(agent.drain_output().is_empty());
+
+        ctx.reset();
+        assert!(ctx.drained_output().is_empty());
+    }
+
+    #[test]
+    fn test_drain_output_empty_buffer() {
+        let ctx = execute_failing(vec![]);
+
+        assert!(ctx.drained_output().is_empty());
+    }
+
+    #[test]
+    fn test_session_export_unsupported() {
+        let mut ctx = execute_output(TestPutBehavior::default());
+        let agent = ctx.find_agent_mut(AgentName::first()).unwrap();
+
+        assert!(agent.export_session().is_none());
+
+        let session = SessionData {
+            bytes: vec![1, 2, 3],
+            expires_at: None,
+        };
+        assert!(!session.is_expired());
+        assert!(matches!(agent.import_session(&session), Err(Error::Put(_))));
+    }
+
+    #[test]
+    fn test_input_action_macro() {
+        let action0 = input_action! {term!{fn_seq_0()}};
+        assert_eq!(action0.precomputations.len(), 0);
+
+        let action1 = input_action! {
+            term!{fn_new_random()} =>
+                "a" = term!{fn_new_random()} =>
+                    term!{
+                        fn_encrypt12(fn_finished,fn_seq_0)
+                    }
+        };
+        assert_eq!(action1.precomputations.len(), 2);
+        assert_eq!(action1.precomputations[0].label, None);
+        assert_eq!(action1.precomputations[1].label, Some("a".into()));
+
+        let action2 = input_action! {
+            "a" = term!{fn_new_random()}, "b" = term!{fn_finished()} =>
+                term!{
+                    fn_encrypt12(fn_finished,fn_seq_0)
+                }
+        };
+        assert_eq!(action2.precomputations.len(), 2);
+        assert_eq!(action2.precomputations[0].label, Some("a".into()));
+        assert_eq!(action2.precomputations[1].label, Some("b".into()));
+
+        let action3 = input_action! {
+            "a" = term!{fn_new_random()} => term!{fn_finished()} =>
+                term!{
+                    fn_encrypt12(fn_finished,fn_seq_0)
+                }
+        };
+        assert_eq!(action3.precomputations.len(), 2);
+        assert_eq!(action3.precomputations[0].label, Some("a".into()));
+        assert_eq!(action3.precomputations[1].label, None);
+
+        let action4 = input_action! {
+            term!{fn_finished()}, "a" = term!{fn_new_random()} =>
+                term!{
+                    fn_encrypt12(fn_finished,fn_seq_0)
+                }
+        };
+        assert_eq!(action4.precomputations.len(), 2);
+        assert_eq!(action4.precomputations[0].label, None);
+        assert_eq!(action4.precomputations[1].label, Some("a".into()));
+
+        let action5 = input_action! {
+            term!{fn_finished()}, "a" = term!{fn_new_random()} =>
+                "b" = term!{fn_seq_0()} =>
+                    term!{fn_seq_1()} =>
+                        "c" = term!{fn_seq_0()} =>
+                            term!{fn_seq_0()}, "d" = term!{fn_seq_0()}, "e" = term!{fn_seq_0()} =>
+                                term!{
+                                    fn_encrypt12(fn_finished,fn_seq_0)
+                                }
+        };
+        assert_eq!(action5.precomputations.len(), 8);
+        assert_eq!(action5.precomputations[0].label, None);
+        assert_eq!(action5.precomputations[1].label, Some("a".into()));
+        assert_eq!(action5.precomputations[2].label, Some("b".into()));
+        assert_eq!(action5.precomputations[3].label, None);
+        assert_eq!(action5.precomputations[4].label, Some("c".into()));
+        assert_eq!(action5.precomputations[5].label, None);
+        assert_eq!(action5.precomputations[6].label, Some("d".into()));
+        assert_eq!(action5.precomputations[7].label, Some("e".into()));
+    }
+
+    #[test]
+    fn test_migrate_legacy_trace() {
+        fn legacy(trace: Trace<TestProtocolTypes>) -> TraceV0<TestProtocolTypes> {
+            let steps = trace
+                .steps
+                .into_iter()
+                .map(|step| StepV2 {
+                    agent: step.agent,
+                    action: match step.action {
+                        Action::Input(input) => ActionV2::Input(input),
+                        Action::Output(_) => ActionV2::Output(PhantomData),
+                    },
+                })
+                .collect();
+
+            TraceV0 {
+                descriptors: trace.descriptors,
+                steps,
+                prior_traces: trace.prior_traces.into_iter().map(legacy).collect(),
+            }
+        }
+
+        let mut trace = setup_simple_trace();
+        trace.prior_traces.push(setup_simple_trace());
+        let serialized = postcard::to_allocvec(&legacy(trace.clone())).unwrap();
+
+        // ids of terms are not preserved by the serialization
+        trace.normalize_ids();
+        let mut migrated = Trace::<TestProtocolTypes>::deserialize_versioned(&serialized).unwrap();
+        migrated.normalize_ids();
+        assert_eq!(
+            serde_json::to_string(&migrated).unwrap(),
+            serde_json::to_string(&trace).unwrap()
+        );
+
+        // the migrated trace is stored in the current version
+        let serialized = migrated.serialize_versioned().unwrap();
+        assert_eq!(
+            serialized[TRACE_FORMAT_MAGIC.len()..][..2],
+            TRACE_FORMAT_VERSION.to_le_bytes()
+        );
+        let mut reloaded = Trace::<TestProtocolTypes>::deserialize_versioned(&serialized).unwrap();
+        reloaded.normalize_ids();
+        assert_eq!(
+            serde_json::to_string(&reloaded).unwrap(),
+            serde_json::to_string(&trace).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_trace_codec() {
+        let mut trace = setup_simple_trace();
+        trace.prior_traces.push(setup_simple_trace());
+        trace.normalize_ids();
+
+        let mut bytes = trace.encode_codec();
+        let len = bytes.len();
+        bytes.extend_from_slice(b"rest");
+
+        let mut reader = codec::Reader::init(&bytes);
+        let mut read = Trace::<TestProtocolTypes>::read_codec(&mut reader).unwrap();
+        assert_eq!(reader.used(), len);
+        assert_eq!(reader.rest(), b"rest");
+
+        read.normalize_ids();
+        assert_eq!(
+            serde_json::to_string(&read).unwrap(),
+            serde_json::to_string(&trace).unwrap()
+        );
+
+        let mut reader = codec::Reader::init(&bytes[..len - 1]);
+        assert!(Trace::<TestProtocolTypes>::read_codec(&mut reader).is_none());
+    }
+
+    #[test]
+    fn test_deserialize_any_format() {
+        let mut trace = setup_simple_trace();
+        trace.normalize_ids();
+        let expected = serde_json::to_string(&trace).unwrap();
+
+        let json = format!("\n  {expected}");
+        let mut from_json = Trace::<TestProtocolTypes>::deserialize_any(json.as_bytes()).unwrap();
+        from_json.normalize_ids();
+        assert_eq!(serde_json::to_string(&from_json).unwrap(), expected);
+
+        let binary = trace.encode_codec();
+        let mut from_binary = Trace::<TestProtocolTypes>::deserialize_any(&binary).unwrap();
+        from_binary.normalize_ids();
+        assert_eq!(serde_json::to_string(&from_binary).unwrap(), expected);
+
+        assert!(Trace::<TestProtocolTypes>::deserialize_any(b"{ not a trace").is_err());
+    }
+
+    #[test]
+    fn test_reject_newer_trace_format() {
+        let mut serialized = setup_simple_trace().serialize_versioned().unwrap();
+        serialized[TRACE_FORMAT_MAGIC.len()..][..2]
+            .copy_from_slice(&(TRACE_FORMAT_VERSION + 1).to_le_bytes());
+
+        let err = Trace::<TestProtocolTypes>::deserialize_versioned(&serialized).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains(&format!("format version {}", TRACE_FORMAT_VERSION + 1)));
+    }
+
+    #[test]
+    fn test_seeded_trace_reseeds_puts() {
+        let factory = TestFactory::default();
+        let seeds = factory.seeds.clone();
+        let registry = test_registry(factory);
+
+        let mut trace = setup_simple_trace();
+        trace.descriptors.clear();
+        trace.steps.clear();
+        trace
+            .execute(&mut TraceContext::new(Spawner::new(registry.clone())))
+            .unwrap();
+        assert!(seeds.lock().unwrap().is_empty());
+
+        // every execution of a seeded trace starts from the same seed
+        trace.seed = Some(1337);
+        for _ in 0..2 {
+            trace
+                .execute(&mut TraceContext::new(Spawner::new(registry.clone())))
+                .unwrap();
+        }
+        assert_eq!(*seeds.lock().unwrap(), [1337, 1337]);
+
+        // the seed survives the serialization
+        let serialized = trace.serialize_versioned().unwrap();
+        let reloaded = Trace::<TestProtocolTypes>::deserialize_versioned(&serialized).unwrap();
+        assert_eq!(reloaded.seed, Some(1337));
+    }
+
+    #[test]
+    fn test_normalize_ids() {
+        let mut a = setup_simple_trace();
+        let mut b = setup_simple_trace();
+        assert_ne!(
+            serde_json::to_string(&a).unwrap(),
+            serde_json::to_string(&b).unwrap()
+        );
+
+        a.normalize_ids();
+        b.normalize_ids();
+        assert_eq!(
+            serde_json::to_string(&a).unwrap(),
+            serde_json::to_string(&b).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_required_variables() {
+        let server = AgentName::first();
+        let mut trace = setup_simple_trace();
+        assert!(trace.required_variables().is_empty());
+
+        // the finished message of the server is not produced by any step of the trace
+        let recipe: Term<TestProtocolTypes> = term! { fn_encrypt12(((server, 0)), fn_seq_0) };
+        trace.steps[2] = InputAction::new_step(server, recipe.clone());
+        trace.steps.push(InputAction::new_step(server, recipe));
+
+        let variables = trace.required_variables();
+        assert_eq!(variables.len(), 1);
+        assert_eq!(variables[0].query.source, Some(Source::Agent(server)));
+        assert_eq!(variables[0].query.counter, 0);
+
+        // variables of prior traces are required as well
+        let mut with_prior = setup_simple_trace();
+        with_prior.prior_traces.push(trace);
+        assert_eq!(with_prior.required_variables().len(), 1);
+    }
+
+    #[test]
+    fn test_fast_structural_hash() {
+        let trace = setup_simple_trace();
+        let hash = trace.fast_structural_hash();
+
+        // ids differ between clones and separately built traces, the structure does not
+        assert_eq!(trace.clone().fast_structural_hash(), hash);
+        assert_eq!(setup_simple_trace().fast_structural_hash(), hash);
+
+        let mut swapped = setup_simple_trace();
+        swapped.steps.swap(0, 2);
+        assert_ne!(swapped.fast_structural_hash(), hash);
+
+        let mut truncated = setup_simple_trace();
+        truncated.steps.pop();
+        assert_ne!(truncated.fast_structural_hash(), hash);
+
+        let mut described = setup_simple_trace();
+        described
+            .descriptors
+            .push(AgentDescriptor::from_name(AgentName::first()));
+        assert_ne!(described.fast_structural_hash(), hash);
+
+        let mut with_prior = setup_simple_trace();
+        with_prior.prior_traces.push(setup_simple_trace());
+        assert_ne!(with_prior.fast_structural_hash(), hash);
+
+        // The steps of a prior trace are not those of the trace itself
+        let mut split = setup_simple_trace();
+        let mut prior = setup_simple_trace();
+        prior.steps = split.steps.drain(..1).collect();
+        split.prior_traces.push(prior);
+        assert_ne!(split.fast_structural_hash(), hash);
+    }
+
+    /// A handshake field, which is malformed if it has no value
+    #[derive(Debug, Clone)]
+    struct TestField(Option<u8>);
+
+    #[derive(Debug, Clone)]
+    struct TestHandshake(Vec<TestField>);
+
+    crate::dummy_codec!(TestProtocolTypes, TestField);
+    crate::dummy_codec!(TestProtocolTypes, TestHandshake);
+
+    impl Extractable<TestProtocolTypes> for TestField {
+        fn extract_knowledge<'a>(
+            &'a self,
+            knowledges: &mut Vec<Knowledge<'a, TestProtocolTypes>>,
+            matcher: Option<<TestProtocolTypes as ProtocolTypes>::Matcher>,
+            source: &'a Source,
+        ) -> Result<(), Error> {
+            if self.0.is_none() {
+                return Err(Error::Extraction());
+            }
+
+            knowledges.push(Knowledge {
+                source,
+                matcher,
+                data: self,
+            });
+            Ok(())
+        }
+    }
+
+    impl Extractable<TestProtocolTypes> for TestHandshake {
+        fn extract_knowledge<'a>(
+            &'a self,
+            knowledges: &mut Vec<Knowledge<'a, TestProtocolTypes>>,
+            matcher: Option<<TestProtocolTypes as ProtocolTypes>::Matcher>,
+            source: &'a Source,
+        ) -> Result<(), Error> {
+            knowledges.push(Knowledge {
+                source,
+                matcher: matcher.clone(),
+                data: self,
+            });
+            for field in &self.0 {
+                field.extract_knowledge(knowledges, matcher.clone(), source)?;
+            }
+            Ok(())
+        }
+
+        fn extract_knowledge_partial<'a>(
+            &'a self,
+            matcher: Option<<TestProtocolTypes as ProtocolTypes>::Matcher>,
+            source: &'a Source,
+        ) -> ExtractedKnowledge<'a, TestProtocolTypes> {
+            let mut extracted = ExtractedKnowledge::new();
+            extracted.knowledges.push(Knowledge {
+                source,
+                matcher: matcher.clone(),
+                data: self,
+            });
+            for field in &self.0 {
+                extracted.extend(field.extract_knowledge_partial(matcher.clone(), source));
+            }
+            extracted
+        }
+    }
+
+    #[test]
+    fn test_extract_partially_malformed_handshake() {
+        let source = Source::Agent(AgentName::first());
+        let handshake = TestHandshake(vec![
+            TestField(Some(1)),
+            TestField(None),
+            TestField(Some(3)),
+        ]);
+
+        let extracted = handshake.extract_knowledge_partial(None, &source);
+
+        assert_eq!(extracted.errors, vec![Error::Extraction()]);
+        let fields: Vec<_> = extracted
+            .knowledges
+            .iter()
+            .filter_map(|knowledge| knowledge.data.as_any().downcast_ref::<TestField>())
+            .map(|field| field.0)
+            .collect();
+        assert_eq!(fields, vec![Some(1), Some(3)]);
+    }
+
+    #[test]
+    fn test_extract_garbage() {
+        let source = Source::Agent(AgentName::first());
+
+        let extracted = TestField(None).extract_knowledge_partial(None, &source);
+
+        assert!(extracted.knowledges.is_empty());
+        assert_eq!(extracted.errors, vec![Error::Extraction()]);
+    }
+}
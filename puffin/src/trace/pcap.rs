@@ -0,0 +1,306 @@
+//! Exporting executed [`Trace`](crate::trace::Trace)s as packet captures.
+//!
+//! During an execution with [`TraceContext::record_wire`](crate::trace::TraceContext::record_wire)
+//! enabled, every message which the attacker sends to or receives from an agent is kept as a
+//! [`RecordedRecord`]. [`write_pcap`] wraps these in synthesized Ethernet, IPv4 and TCP headers,
+//! such that the session can be inspected with Wireshark or tcpdump.
+//!
+//! Each agent gets its own TCP connection with the attacker. The agent listens on port 443 and the
+//! attacker connects from an ephemeral port, such that Wireshark decodes the payloads as TLS
+//! without further configuration, regardless of whether the agent is a client or a server.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::agent::AgentName;
+use crate::error::Error;
+
+/// Magic number of the classic pcap format with timestamps in microseconds
+pub const PCAP_MAGIC: u32 = 0xa1b2_c3d4;
+
+const LINKTYPE_ETHERNET: u32 = 1;
+const SNAPLEN: u32 = 262_144;
+
+const ETHERNET_HEADER_LEN: usize = 14;
+const IPV4_HEADER_LEN: usize = 20;
+const TCP_HEADER_LEN: usize = 20;
+/// Largest payload which fits into a single IPv4 packet
+const MAX_SEGMENT_LEN: usize = u16::MAX as usize - IPV4_HEADER_LEN - TCP_HEADER_LEN;
+
+const ATTACKER_ADDR: [u8; 4] = [10, 0, 0, 1];
+const AGENT_PORT: u16 = 443;
+const ATTACKER_BASE_PORT: u16 = 49152;
+/// Initial sequence number of both directions of each connection
+const INITIAL_SEQ: u32 = 1;
+
+const TCP_FLAG_PSH: u8 = 0x08;
+const TCP_FLAG_ACK: u8 = 0x10;
+
+/// Whether a [`RecordedRecord`] was sent to or received from its agent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// Sent by the attacker to the agent, i.e. an [`InputAction`](crate::trace::InputAction)
+    ToAgent,
+    /// Taken from the agent by an [`OutputAction`](crate::trace::OutputAction)
+    FromAgent,
+}
+
+/// Bytes which crossed the wire between the attacker and an agent during an execution
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedRecord {
+    pub agent: AgentName,
+    pub direction: Direction,
+    pub bytes: Vec<u8>,
+}
+
+/// Writes the `records` as a pcap file to `path`.
+///
+/// Each record becomes one packet. Records larger than a TCP segment are split over several
+/// packets.
+pub fn write_pcap<P: AsRef<Path>>(path: P, records: &[RecordedRecord]) -> Result<(), Error> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_pcap_to(&mut writer, records)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Writes the `records` in the pcap format to `writer`, see [`write_pcap`]
+pub fn write_pcap_to<W: Write>(writer: &mut W, records: &[RecordedRecord]) -> io::Result<()> {
+    writer.write_all(&PCAP_MAGIC.to_le_bytes())?;
+    writer.write_all(&2u16.to_le_bytes())?; // major version
+    writer.write_all(&4u16.to_le_bytes())?; // minor version
+    writer.write_all(&0i32.to_le_bytes())?; // offset to UTC
+    writer.write_all(&0u32.to_le_bytes())?; // accuracy of the timestamps
+    writer.write_all(&SNAPLEN.to_le_bytes())?;
+    writer.write_all(&LINKTYPE_ETHERNET.to_le_bytes())?;
+
+    let mut connections: Vec<Connection> = vec![];
+    let mut packet_index: u32 = 0;
+
+    for record in records {
+        let connection = match connections
+            .iter_mut()
+            .position(|connection| connection.agent == record.agent)
+        {
+            Some(i) => &mut connections[i],
+            None => {
+                connections.push(Connection::new(record.agent));
+                connections.last_mut().unwrap()
+            }
+        };
+
+        // Empty records still get a packet, such that the capture has one packet per record
+        let segments: Vec<&[u8]> = if record.bytes.is_empty() {
+            vec![record.bytes.as_slice()]
+        } else {
+            record.bytes.chunks(MAX_SEGMENT_LEN).collect()
+        };
+
+        for segment in segments {
+            let packet = connection.packet(record.direction, segment);
+
+            // Packets are a millisecond apart, such that captures are reproducible
+            let timestamp_ms = packet_index;
+            writer.write_all(&(timestamp_ms / 1000).to_le_bytes())?;
+            writer.write_all(&((timestamp_ms % 1000) * 1000).to_le_bytes())?;
+            writer.write_all(&(packet.len() as u32).to_le_bytes())?; // captured length
+            writer.write_all(&(packet.len() as u32).to_le_bytes())?; // original length
+            writer.write_all(&packet)?;
+
+            packet_index += 1;
+        }
+    }
+
+    Ok(())
+}
+
+/// TCP connection between the attacker and an agent
+struct Connection {
+    agent: AgentName,
+    agent_addr: [u8; 4],
+    attacker_port: u16,
+    /// Next sequence number of the attacker
+    to_agent_seq: u32,
+    /// Next sequence number of the agent
+    from_agent_seq: u32,
+}
+
+impl Connection {
+    fn new(agent: AgentName) -> Self {
+        let index = u8::from(agent);
+
+        Self {
+            agent,
+            agent_addr: [10, 1, index, 1],
+            attacker_port: ATTACKER_BASE_PORT + u16::from(index),
+            to_agent_seq: INITIAL_SEQ,
+            from_agent_seq: INITIAL_SEQ,
+        }
+    }
+
+    /// Frames `payload` as the next segment in `direction` and advances the sequence numbers
+    fn packet(&mut self, direction: Direction, payload: &[u8]) -> Vec<u8> {
+        let (src_addr, src_port, dst_addr, dst_port, seq, ack) = match direction {
+            Direction::ToAgent => (
+                ATTACKER_ADDR,
+                self.attacker_port,
+                self.agent_addr,
+                AGENT_PORT,
+                &mut self.to_agent_seq,
+                self.from_agent_seq,
+            ),
+            Direction::FromAgent => (
+                self.agent_addr,
+                AGENT_PORT,
+                ATTACKER_ADDR,
+                self.attacker_port,
+                &mut self.from_agent_seq,
+                self.to_agent_seq,
+            ),
+        };
+
+        let tcp_len = TCP_HEADER_LEN + payload.len();
+        let ip_len = IPV4_HEADER_LEN + tcp_len;
+        let mut packet = Vec::with_capacity(ETHERNET_HEADER_LEN + ip_len);
+
+        // Ethernet, with locally administered MAC addresses derived from the IPv4 addresses
+        packet.extend_from_slice(&mac_address(dst_addr));
+        packet.extend_from_slice(&mac_address(src_addr));
+        packet.extend_from_slice(&0x0800u16.to_be_bytes()); // IPv4
+
+        // IPv4
+        let ip_start = packet.len();
+        packet.push(0x45); // version 4, header of 5 words
+        packet.push(0x00); // type of service
+        packet.extend_from_slice(&(ip_len as u16).to_be_bytes());
+        packet.extend_from_slice(&0u16.to_be_bytes()); // identification
+        packet.extend_from_slice(&0x4000u16.to_be_bytes()); // don't fragment
+        packet.push(64); // time to live
+        packet.push(6); // TCP
+        packet.extend_from_slice(&0u16.to_be_bytes()); // checksum, filled in below
+        packet.extend_from_slice(&src_addr);
+        packet.extend_from_slice(&dst_addr);
+        let ip_checksum = checksum(&packet[ip_start..], 0);
+        packet[ip_start + 10..ip_start + 12].copy_from_slice(&ip_checksum.to_be_bytes());
+
+        // TCP
+        let tcp_start = packet.len();
+        packet.extend_from_slice(&src_port.to_be_bytes());
+        packet.extend_from_slice(&dst_port.to_be_bytes());
+        packet.extend_from_slice(&seq.to_be_bytes());
+        packet.extend_from_slice(&ack.to_be_bytes());
+        packet.push(((TCP_HEADER_LEN / 4) as u8) << 4);
+        packet.push(TCP_FLAG_PSH | TCP_FLAG_ACK);
+        packet.extend_from_slice(&u16::MAX.to_be_bytes()); // window
+        packet.extend_from_slice(&0u16.to_be_bytes()); // checksum, filled in below
+        packet.extend_from_slice(&0u16.to_be_bytes()); // urgent pointer
+        packet.extend_from_slice(payload);
+
+        let mut pseudo_header = Vec::with_capacity(12);
+        pseudo_header.extend_from_slice(&src_addr);
+        pseudo_header.extend_from_slice(&dst_addr);
+        pseudo_header.extend_from_slice(&6u16.to_be_bytes());
+        pseudo_header.extend_from_slice(&(tcp_len as u16).to_be_bytes());
+        let tcp_checksum = checksum(&packet[tcp_start..], sum_words(&pseudo_header));
+        packet[tcp_start + 16..tcp_start + 18].copy_from_slice(&tcp_checksum.to_be_bytes());
+
+        *seq = seq.wrapping_add(payload.len() as u32);
+        packet
+    }
+}
+
+fn mac_address(addr: [u8; 4]) -> [u8; 6] {
+    [0x02, 0x00, addr[0], addr[1], addr[2], addr[3]]
+}
+
+/// Sum of the big-endian 16-bit words of `bytes`, padded with a zero byte if needed
+fn sum_words(bytes: &[u8]) -> u32 {
+    bytes
+        .chunks(2)
+        .map(|word| u32::from(u16::from_be_bytes([word[0], *word.get(1).unwrap_or(&0)])))
+        .fold(0u32, u32::wrapping_add)
+}
+
+/// Internet checksum (RFC 1071) of `bytes`, starting from the partial sum `initial`
+fn checksum(bytes: &[u8], initial: u32) -> u16 {
+    let mut sum = u64::from(initial) + u64::from(sum_words(bytes));
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Number of packets in the pcap `file`
+    fn count_packets(file: &[u8]) -> usize {
+        let mut offset = 24;
+        let mut packets = 0;
+        while offset < file.len() {
+            let captured_len =
+                u32::from_le_bytes(file[offset + 8..offset + 12].try_into().unwrap()) as usize;
+            offset += 16 + captured_len;
+            packets += 1;
+        }
+        assert_eq!(offset, file.len());
+        packets
+    }
+
+    #[test]
+    fn test_write_pcap() {
+        let client = AgentName::first();
+        let server = client.next();
+        let records = vec![
+            RecordedRecord {
+                agent: client,
+                direction: Direction::FromAgent,
+                bytes: vec![0x16, 0x03, 0x01, 0x00, 0x01, 0x01],
+            },
+            RecordedRecord {
+                agent: server,
+                direction: Direction::ToAgent,
+                bytes: vec![0x16, 0x03, 0x01, 0x00, 0x01, 0x01],
+            },
+            RecordedRecord {
+                agent: server,
+                direction: Direction::FromAgent,
+                bytes: vec![0x15, 0x03, 0x03, 0x00, 0x02, 0x02, 0x28],
+            },
+        ];
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("trace.pcap");
+        write_pcap(&path, &records).unwrap();
+        let file = std::fs::read(&path).unwrap();
+
+        assert_eq!(file[..4], PCAP_MAGIC.to_le_bytes());
+        assert_eq!(count_packets(&file), records.len());
+    }
+
+    #[test]
+    fn test_packet_framing() {
+        let mut connection = Connection::new(AgentName::first());
+        let payload = [0x17, 0x03, 0x03, 0x00, 0x00];
+
+        let first = connection.packet(Direction::ToAgent, &payload);
+        let ip = &first[ETHERNET_HEADER_LEN..];
+        // A valid checksum sums to zero
+        assert_eq!(checksum(&ip[..IPV4_HEADER_LEN], 0), 0);
+        assert_eq!(
+            &ip[IPV4_HEADER_LEN + 2..IPV4_HEADER_LEN + 4],
+            &443u16.to_be_bytes()
+        );
+        assert_eq!(&first[first.len() - payload.len()..], &payload);
+
+        // The sequence number advances by the length of the payload
+        let second = connection.packet(Direction::ToAgent, &payload);
+        let seq = |packet: &[u8]| {
+            let tcp = ETHERNET_HEADER_LEN + IPV4_HEADER_LEN;
+            u32::from_be_bytes(packet[tcp + 4..tcp + 8].try_into().unwrap())
+        };
+        assert_eq!(seq(&second), seq(&first) + payload.len() as u32);
+    }
+}
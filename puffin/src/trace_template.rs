@@ -0,0 +1,145 @@
+//! Templates for generating families of [`Trace`]s.
+//!
+//! A [`TraceTemplate`] is a trace with named holes, e.g. for the cipher suite or the group of a
+//! handshake. A hole is a variable of the form `((!"name", 0)/Type)` in a recipe of the trace,
+//! whose name was declared as a hole of the template. Instantiating the template with concrete
+//! terms for the holes yields one trace of the family. This allows to explore a parameter space
+//! systematically.
+
+use std::collections::{BTreeSet, HashMap};
+
+use crate::algebra::{DYTerm, Term, TermType};
+use crate::error::Error;
+use crate::protocol::ProtocolTypes;
+use crate::trace::{Action, Source, Trace};
+
+/// A [`Trace`] with named holes which are filled by [`TraceTemplate::instantiate`]
+#[derive(Clone, Debug)]
+pub struct TraceTemplate<PT: ProtocolTypes> {
+    pub trace: Trace<PT>,
+    pub holes: BTreeSet<String>,
+}
+
+impl<PT: ProtocolTypes> TraceTemplate<PT> {
+    pub fn new<S: Into<String>>(trace: Trace<PT>, holes: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            trace,
+            holes: holes.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Returns a copy of the template's trace in which every hole is replaced by the term of the
+    /// same name in `params`.
+    ///
+    /// Fails if a hole of the template has no term in `params` or if the type of the term does not
+    /// match the type of the hole.
+    pub fn instantiate(&self, params: &HashMap<String, Term<PT>>) -> Result<Trace<PT>, Error> {
+        if let Some(hole) = self.holes.iter().find(|hole| !params.contains_key(*hole)) {
+            return Err(Error::Term(format!(
+                "Hole {hole} of the template is not filled"
+            )));
+        }
+
+        let mut trace = self.trace.clone();
+
+        for step in &mut trace.steps {
+            let Action::Input(input) = &mut step.action else {
+                continue;
+            };
+
+            for precomputation in &mut input.precomputations {
+                self.fill(&mut precomputation.recipe, params)?;
+            }
+
+            self.fill(&mut input.recipe, params)?;
+        }
+
+        Ok(trace)
+    }
+
+    fn fill(&self, term: &mut Term<PT>, params: &HashMap<String, Term<PT>>) -> Result<(), Error> {
+        match &mut term.term {
+            DYTerm::Variable(variable) => {
+                let Some(Source::Label(Some(label))) = &variable.query.source else {
+                    return Ok(());
+                };
+
+                if !self.holes.contains(label)
+                    || variable.query.counter != 0
+                    || variable.query.matcher.is_some()
+                {
+                    return Ok(());
+                }
+
+                let param = &params[label];
+
+                if param.get_type_shape() != &variable.typ {
+                    return Err(Error::Term(format!(
+                        "Hole {label} of type {} can not be filled with a term of type {}",
+                        variable.typ,
+                        param.get_type_shape()
+                    )));
+                }
+
+                *term = param.clone();
+            }
+            DYTerm::Application(_, args) => {
+                for arg in args {
+                    self.fill(arg, params)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algebra::test_signature::*;
+    use crate::term;
+    use crate::trace::InputAction;
+
+    fn template() -> TraceTemplate<TestProtocolTypes> {
+        let mut trace = setup_simple_trace();
+        trace.steps[0] = InputAction::new_step(
+            trace.steps[0].agent,
+            term! {
+                fn_append_cipher_suite(
+                    (fn_new_cipher_suites()),
+                    ((!"cipher_suite", 0)/CipherSuite)
+                )
+            },
+        );
+
+        TraceTemplate::new(trace, ["cipher_suite"])
+    }
+
+    #[test_log::test]
+    fn test_instantiate_fills_holes() {
+        let suite: TestTerm = term! { fn_cipher_suite12 };
+        let params = HashMap::from([("cipher_suite".to_owned(), suite.clone())]);
+
+        let trace = template().instantiate(&params).unwrap();
+        let Action::Input(input) = &trace.steps[0].action else {
+            panic!("expected an input step");
+        };
+        let DYTerm::Application(_, args) = &input.recipe.term else {
+            panic!("expected an application");
+        };
+
+        assert_eq!(args[1].to_string(), suite.to_string());
+        assert!(args[1].is_symbolic());
+    }
+
+    #[test_log::test]
+    fn test_instantiate_unfilled_hole() {
+        assert!(template().instantiate(&HashMap::new()).is_err());
+
+        let wrong_type: TestTerm = term! { fn_new_cipher_suites };
+        let params = HashMap::from([("cipher_suite".to_owned(), wrong_type)]);
+
+        assert!(template().instantiate(&params).is_err());
+    }
+}
@@ -285,6 +285,10 @@ impl Put<SshProtocolBehavior> for LibSSL {
         self.session.session_state() == SessionState::SSH_SESSION_STATE_AUTHENTICATED
     }
 
+    fn is_handshake_complete(&self) -> bool {
+        matches!(self.state, PutState::Done)
+    }
+
     fn version() -> String
     where
         Self: Sized,
@@ -1,4 +1,5 @@
 use std::any::TypeId;
+use std::convert::Infallible;
 
 use puffin::agent::ProtocolDescriptorConfig;
 use puffin::algebra::signature::Signature;
@@ -231,6 +232,7 @@ pub struct SshProtocolBehavior {}
 
 impl ProtocolBehavior for SshProtocolBehavior {
     type Claim = SshClaim;
+    type Extension = Infallible;
     type OpaqueProtocolMessage = RawSshMessage;
     type OpaqueProtocolMessageFlight = RawSshMessageFlight;
     type ProtocolMessage = SshMessage;
@@ -242,6 +244,15 @@ impl ProtocolBehavior for SshProtocolBehavior {
         vec![] // TODO
     }
 
+    fn message_type_name(message: &SshMessage) -> &'static str {
+        match message {
+            SshMessage::KexInit(_) => "KexInit",
+            SshMessage::KexEcdhInit(_) => "KexEcdhInit",
+            SshMessage::KexEcdhReply(_) => "KexEcdhReply",
+            SshMessage::NewKeys => "NewKeys",
+        }
+    }
+
     fn try_read_bytes(
         _bitstring: &[u8],
         _ty: TypeId,
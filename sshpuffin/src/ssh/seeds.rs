@@ -9,6 +9,9 @@ use crate::ssh::message::*;
 pub fn seed_successful(client: AgentName, server: AgentName) -> Trace<SshProtocolTypes> {
     Trace {
         prior_traces: vec![],
+        expected_outcome: None,
+        expected_flow: None,
+        seed: None,
         descriptors: vec![
             AgentDescriptor::from_config(
                 client,
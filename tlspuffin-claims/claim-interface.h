@@ -157,6 +157,10 @@ typedef struct Claim
 
     // TLS 1.2
     ClaimSecret master_secret_12;
+    // whether master_secret_12 is an extended master secret (RFC 7627)
+    int extended_master_secret_12;
+    // whether the handshake resumed the session of session_id instead of establishing a new session
+    int session_resumed_12;
 
     // Ciphers
     ClaimCiphers available_ciphers;
@@ -164,6 +168,9 @@ typedef struct Claim
 
     // Transcript
     ClaimTranscript transcript;
+
+    // TLS 1.3: whether the signature of a processed CertificateVerify was valid
+    int certificate_verify_ok;
 } Claim;
 
 typedef void (*claim_t)(Claim claim, void *ctx);
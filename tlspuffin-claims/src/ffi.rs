@@ -46,6 +46,8 @@ impl fmt::Display for Claim {
             exporter_master_secret: {},\
             early_exporter_master_secret: {},\
             master_secret_12: {},\
+            extended_master_secret_12: {},\
+            session_resumed_12: {},\
             available_ciphers: {},\
             chosen_cipher: {},\
             transcript: {},\
@@ -78,6 +80,8 @@ impl fmt::Display for Claim {
             self.exporter_master_secret,
             self.early_exporter_master_secret,
             self.master_secret_12,
+            self.extended_master_secret_12,
+            self.session_resumed_12,
             self.available_ciphers,
             self.chosen_cipher,
             self.transcript,
@@ -1,8 +1,12 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
 use criterion::{criterion_group, criterion_main, Criterion};
 use puffin::algebra::dynamic_function::make_dynamic;
 use puffin::algebra::error::FnError;
-use puffin::algebra::Term;
+use puffin::algebra::{Term, TermType};
 use puffin::execution::{Runner, TraceRunner};
+use puffin::fuzzer::dedup::BloomFilter;
 use puffin::fuzzer::mutations::ReplaceReuseMutator;
 use puffin::fuzzer::utils::TermConstraints;
 use puffin::libafl::corpus::InMemoryCorpus;
@@ -11,7 +15,7 @@ use puffin::libafl::state::StdState;
 use puffin::libafl_bolts::rands::{RomuDuoJrRand, StdRand};
 use puffin::protocol::EvaluatedTerm;
 use puffin::term;
-use puffin::trace::{Spawner, Trace};
+use puffin::trace::{Spawner, Trace, TraceContext};
 use puffin::trace_helper::TraceHelper;
 use tlspuffin::protocol::TLSProtocolTypes;
 use tlspuffin::put_registry::tls_registry;
@@ -65,6 +69,7 @@ fn benchmark_mutations(c: &mut Criterion) {
                 weighted_depth: false,
                 ..TermConstraints::default()
             },
+            usize::MAX,
             true,
         );
         let mut trace = seed_client_attacker12.build_trace();
@@ -75,47 +80,103 @@ fn benchmark_mutations(c: &mut Criterion) {
     });
 }
 
-fn benchmark_trace(c: &mut Criterion) {
-    let mut group = c.benchmark_group("trace");
-
-    group.bench_function("term clone", |b| {
-        let client_hello: Term<TLSProtocolTypes> = term! {
-              fn_client_hello(
-                fn_protocol_version12,
-                fn_new_random,
-                fn_new_session_id,
-                (fn_append_cipher_suite(
-                    (fn_new_cipher_suites()),
-                    // force TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256
-                    fn_cipher_suite12
-                )),
-                fn_compressions,
+fn client_hello() -> Term<TLSProtocolTypes> {
+    term! {
+          fn_client_hello(
+            fn_protocol_version12,
+            fn_new_random,
+            fn_new_session_id,
+            (fn_append_cipher_suite(
+                (fn_new_cipher_suites()),
+                // force TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256
+                fn_cipher_suite12
+            )),
+            fn_compressions,
+            (fn_client_extensions_append(
                 (fn_client_extensions_append(
                     (fn_client_extensions_append(
                         (fn_client_extensions_append(
                             (fn_client_extensions_append(
                                 (fn_client_extensions_append(
-                                    (fn_client_extensions_append(
-                                        fn_client_extensions_new,
-                                        (fn_support_group_extension(fn_named_group_secp384r1))
-                                    )),
-                                    fn_signature_algorithm_extension
+                                    fn_client_extensions_new,
+                                    (fn_support_group_extension(fn_named_group_secp384r1))
                                 )),
-                                fn_ec_point_formats_extension
+                                fn_signature_algorithm_extension
                             )),
-                            fn_signed_certificate_timestamp_extension
+                            (fn_ec_point_formats_extension(fn_ec_point_formats_uncompressed))
                         )),
-                         // Enable Renegotiation
-                        (fn_renegotiation_info_extension((fn_payload_u8(fn_empty_bytes_vec))))
+                        fn_signed_certificate_timestamp_extension
                     )),
-                    // Add signature cert extension
-                    fn_signature_algorithm_cert_extension
-                ))
-            )
-        };
+                     // Enable Renegotiation
+                    (fn_renegotiation_info_extension((fn_payload_u8(fn_empty_bytes_vec))))
+                )),
+                // Add signature cert extension
+                fn_signature_algorithm_cert_extension
+            ))
+        )
+    }
+}
+
+fn benchmark_trace(c: &mut Criterion) {
+    let mut group = c.benchmark_group("trace");
+
+    group.bench_function("term clone", |b| {
+        let client_hello = client_hello();
 
         b.iter(|| client_hello.clone())
     });
+
+    // The memo is cleared before each execution, such that only repeated subterms within an
+    // execution are reused
+    group.bench_function("term evaluate", |b| {
+        let mut ctx = TraceContext::new(Spawner::new(tls_registry()));
+        let client_hello = client_hello();
+
+        b.iter(|| {
+            ctx.clear_evaluation_memo();
+            client_hello.evaluate(&ctx).unwrap()
+        })
+    });
+
+    group.bench_function("term evaluate memoized", |b| {
+        let ctx = TraceContext::new(Spawner::new(tls_registry()));
+        let client_hello = client_hello();
+        client_hello.evaluate(&ctx).unwrap();
+
+        b.iter(|| client_hello.evaluate(&ctx).unwrap())
+    });
+
+    group.finish()
+}
+
+fn benchmark_dedup(c: &mut Criterion) {
+    let mut group = c.benchmark_group("dedup");
+    let trace = seed_client_attacker_full.build_trace();
+
+    group.bench_function("fast_structural_hash", |b| {
+        b.iter(|| trace.fast_structural_hash())
+    });
+
+    // The hash over the full serialization which the structural hash replaces
+    group.bench_function("serialize_postcard hash", |b| {
+        b.iter(|| {
+            let mut hasher = DefaultHasher::new();
+            trace.serialize_postcard().unwrap().hash(&mut hasher);
+            hasher.finish()
+        })
+    });
+
+    group.bench_function("BloomFilter insert", |b| {
+        let mut filter = BloomFilter::new(1 << 20);
+        let mut hash = trace.fast_structural_hash();
+
+        b.iter(|| {
+            hash = hash.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            filter.insert(hash)
+        })
+    });
+
+    group.finish()
 }
 
 fn benchmark_seeds(c: &mut Criterion) {
@@ -167,6 +228,7 @@ criterion_group!(
     benchmark_dynamic,
     benchmark_trace,
     benchmark_mutations,
+    benchmark_dedup,
     benchmark_seeds,
 );
 criterion_main!(benches);
@@ -138,6 +138,11 @@ SSL_CTX *set_cert(SSL_CTX *ssl_ctx, const PEM *pem_cert)
 
 SSL_CTX *set_pkey(SSL_CTX *ssl_ctx, const PEM *pem_pkey)
 {
+    if (ssl_ctx == NULL)
+    {
+        return NULL;
+    }
+
     EVP_PKEY *pkey = load_inmem_pkey(pem_pkey);
     if (pkey == NULL)
     {
@@ -145,7 +150,7 @@ SSL_CTX *set_pkey(SSL_CTX *ssl_ctx, const PEM *pem_pkey)
         return NULL;
     }
 
-    if (SSL_CTX_use_PrivateKey(ssl_ctx, pkey) != 1)
+    if (SSL_CTX_use_PrivateKey(ssl_ctx, pkey) != 1 || SSL_CTX_check_private_key(ssl_ctx) != 1)
     {
         EVP_PKEY_free(pkey);
         SSL_CTX_free(ssl_ctx);
@@ -48,6 +48,14 @@ static RESULT_CODE result_code(AGENT agent, int retcode);
 
 static AGENT make_agent(SSL_CTX *ssl_ctx, const TLS_AGENT_DESCRIPTOR *descriptor);
 
+/*
+ * Verification callback which accepts any certificate, see `skip_cert_verify`
+ */
+static int accept_any_certificate(int preverify_ok, X509_STORE_CTX *store)
+{
+    return 1;
+}
+
 static void default_claimer_notify(void *context, Claim *claim)
 {
     _log(PUFFIN.trace, "call to default claimer `notify`");
@@ -286,7 +294,9 @@ AGENT openssl_create_client(const TLS_AGENT_DESCRIPTOR *descriptor)
 
     if (descriptor->server_authentication)
     {
-        SSL_CTX_set_verify(ssl_ctx, SSL_VERIFY_PEER | SSL_VERIFY_FAIL_IF_NO_PEER_CERT, NULL);
+        SSL_CTX_set_verify(ssl_ctx,
+                           SSL_VERIFY_PEER | SSL_VERIFY_FAIL_IF_NO_PEER_CERT,
+                           descriptor->skip_cert_verify ? accept_any_certificate : NULL);
 
         ssl_ctx = set_store(ssl_ctx, descriptor->store, descriptor->store_length);
         if (ssl_ctx == NULL)
@@ -344,7 +354,9 @@ AGENT openssl_create_server(const TLS_AGENT_DESCRIPTOR *descriptor)
 
     if (descriptor->client_authentication)
     {
-        SSL_CTX_set_verify(ssl_ctx, SSL_VERIFY_PEER | SSL_VERIFY_FAIL_IF_NO_PEER_CERT, NULL);
+        SSL_CTX_set_verify(ssl_ctx,
+                           SSL_VERIFY_PEER | SSL_VERIFY_FAIL_IF_NO_PEER_CERT,
+                           descriptor->skip_cert_verify ? accept_any_certificate : NULL);
 
         ssl_ctx = set_store(ssl_ctx, descriptor->store, descriptor->store_length);
         if (ssl_ctx == NULL)
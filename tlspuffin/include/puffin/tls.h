@@ -36,6 +36,11 @@ extern "C"
         TLS_VERSION tls_version;
         bool client_authentication;
         bool server_authentication;
+        /*
+         * Accept any certificate of the peer instead of verifying it against the
+         * <store>. Only meant for testing.
+         */
+        bool skip_cert_verify;
         const char *cipher_string;
 
         const PEM *cert;
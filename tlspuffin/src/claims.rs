@@ -1,3 +1,4 @@
+use std::fmt;
 use std::fmt::Debug;
 
 use puffin::agent::AgentName;
@@ -10,6 +11,10 @@ use puffin::{codec, dummy_codec, dummy_extract_knowledge, dummy_extract_knowledg
 use smallvec::SmallVec;
 
 use crate::protocol::{AgentType, TLSProtocolTypes, TLSVersion};
+use crate::tls::rustls::conn::ConnectionRandoms;
+use crate::tls::rustls::msgs::enums::CipherSuite;
+use crate::tls::rustls::suites::ALL_CIPHER_SUITES;
+use crate::tls::rustls::tls12::ConnectionSecrets;
 
 #[derive(Debug, Clone)]
 pub struct TlsTranscript(pub [u8; 64], pub i32);
@@ -156,18 +161,34 @@ pub trait Transcript {
     fn as_slice(&self) -> &[u8];
 }
 
+/// Cipher suites offered in a ClientHello, as sent or received by the agent.
 #[derive(Debug, Clone)]
-pub struct ClientHello;
+pub struct ClientHello {
+    pub outbound: bool,
+    pub available_ciphers: SmallVec<[u16; 20]>,
+}
 // We do not expect to encode/read claims!
 dummy_extract_knowledge_codec!(TLSProtocolTypes, ClientHello);
+/// Cipher suite selected in a ServerHello, as sent or received by the agent.
 #[derive(Debug, Clone)]
-pub struct ServerHello;
+pub struct ServerHello {
+    pub outbound: bool,
+    pub chosen_cipher: u16,
+}
 dummy_extract_knowledge_codec!(TLSProtocolTypes, ServerHello);
 #[derive(Debug, Clone)]
 pub struct Certificate;
 dummy_extract_knowledge_codec!(TLSProtocolTypes, Certificate);
+/// Signature of a TLS 1.3 CertificateVerify, as sent or received by the agent.
 #[derive(Debug, Clone)]
-pub struct CertificateVerify;
+pub struct CertificateVerify {
+    pub outbound: bool,
+    /// Signature scheme which the message claims for its signature
+    pub signature_algorithm: i32,
+    /// Whether the agent accepted the signature over the transcript. Always `true` for outbound
+    /// messages.
+    pub verified: bool,
+}
 dummy_extract_knowledge_codec!(TLSProtocolTypes, CertificateVerify);
 #[derive(Debug, Clone)]
 pub struct Finished {
@@ -184,6 +205,19 @@ pub struct Finished {
     pub peer_certificate: SmallVec<[u8; 32]>,
 
     pub master_secret: SmallVec<[u8; 32]>,
+    /// Resumption master secret of a TLS 1.3 handshake (RFC 8446, Section 7.1), empty for TLS 1.2.
+    /// PUTs may pad it, see [`fn_resumption_master_secret`].
+    ///
+    /// [`fn_resumption_master_secret`]: crate::tls::fn_impl::fn_resumption_master_secret
+    pub resumption_master_secret: SmallVec<[u8; 32]>,
+    /// Exporter master secret of a TLS 1.3 handshake (RFC 8446, Section 7.5), empty for TLS 1.2.
+    /// PUTs may pad it like the resumption master secret.
+    pub exporter_master_secret: SmallVec<[u8; 32]>,
+    /// Whether the master secret of a TLS 1.2 handshake is an extended master secret (RFC 7627)
+    pub extended_master_secret: bool,
+    /// Whether the TLS 1.2 handshake resumed the session of `session_id` instead of establishing
+    /// a new session, as claimed by the PUT
+    pub session_resumed: bool,
 
     pub chosen_cipher: u16,
     pub available_ciphers: SmallVec<[u16; 20]>,
@@ -217,6 +251,65 @@ pub struct Finished {
 }
 dummy_extract_knowledge_codec!(TLSProtocolTypes, Finished);
 
+/// TLS 1.2 keying material of an agent, e.g. to export it to a key log or to compare the keys of
+/// both peers.
+///
+/// It is derived from the [`Finished`] claims of TLS 1.2 handshakes, see
+/// [`MasterSecret12::from_claim`].
+#[derive(Debug, Clone)]
+pub struct MasterSecret12 {
+    pub client_random: SmallVec<[u8; 32]>,
+    /// Whether the master secret is an extended master secret (RFC 7627)
+    pub extended: bool,
+    pub master_secret: SmallVec<[u8; 32]>,
+    /// Key block derived from the master secret. `None` if the cipher suite is not supported.
+    pub key_block: Option<Vec<u8>>,
+}
+
+impl MasterSecret12 {
+    /// Keying material of the Finished claim of a TLS 1.2 handshake, `None` for other claims.
+    #[must_use]
+    pub fn from_claim(claim: &TlsClaim) -> Option<Self> {
+        let ClaimData::Message(ClaimDataMessage::Finished(finished)) = &claim.data else {
+            return None;
+        };
+        if claim.protocol_version != TLSVersion::V1_2 {
+            return None;
+        }
+
+        let master_secret = finished.master_secret.get(..48)?;
+        let randoms = ConnectionRandoms {
+            client: finished.client_random.as_slice().try_into().ok()?,
+            server: finished.server_random.as_slice().try_into().ok()?,
+        };
+
+        let key_block = ALL_CIPHER_SUITES
+            .iter()
+            .find(|suite| suite.suite() == CipherSuite::from(finished.chosen_cipher))
+            .and_then(|suite| suite.tls12())
+            .map(|suite| {
+                ConnectionSecrets::new_resume(randoms, suite, master_secret).make_key_block()
+            });
+
+        Some(Self {
+            client_random: finished.client_random.clone(),
+            extended: finished.extended_master_secret,
+            master_secret: SmallVec::from_slice(master_secret),
+            key_block,
+        })
+    }
+
+    /// Line of the NSS key log format, which is for example understood by Wireshark.
+    #[must_use]
+    pub fn keylog_line(&self) -> String {
+        format!(
+            "CLIENT_RANDOM {} {}",
+            hex::encode(&self.client_random),
+            hex::encode(&self.master_secret)
+        )
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum ClaimDataTranscript {
     ClientHello(TranscriptClientHello),
@@ -305,6 +398,49 @@ impl Claim for TlsClaim {
     }
 }
 
+/// Short description of the claim with the negotiated version and ciphers, e.g. for
+/// [`claims_to_dot`](puffin::graphviz::claims_to_dot)
+impl fmt::Display for TlsClaim {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let version = match self.protocol_version {
+            TLSVersion::V1_3 => "TLS 1.3",
+            TLSVersion::V1_2 => "TLS 1.2",
+        };
+        write!(f, "{:?} {version}", self.origin)?;
+
+        match &self.data {
+            ClaimData::Message(ClaimDataMessage::ClientHello(claim)) => write!(
+                f,
+                ", ciphers {}",
+                claim
+                    .available_ciphers
+                    .iter()
+                    .map(|cipher| format!("{cipher:#06x}"))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ),
+            ClaimData::Message(ClaimDataMessage::ServerHello(ServerHello {
+                chosen_cipher,
+                ..
+            }))
+            | ClaimData::Message(ClaimDataMessage::Finished(Finished { chosen_cipher, .. })) => {
+                write!(f, ", cipher {chosen_cipher:#06x}")
+            }
+            ClaimData::Message(ClaimDataMessage::CertificateVerify(claim)) => write!(
+                f,
+                ", signature {:#06x} {}",
+                claim.signature_algorithm,
+                if claim.verified {
+                    "accepted"
+                } else {
+                    "rejected"
+                }
+            ),
+            _ => Ok(()),
+        }
+    }
+}
+
 impl Extractable<TLSProtocolTypes> for TlsClaim {
     fn extract_knowledge(
         &self,
@@ -320,15 +456,54 @@ pub mod claims_helpers {
     use smallvec::SmallVec;
 
     use crate::claims::{
-        ClaimData, ClaimDataMessage, ClaimDataTranscript, Finished, TlsTranscript,
-        TranscriptCertificate, TranscriptClientFinished, TranscriptClientHello,
-        TranscriptPartialClientHello, TranscriptServerFinished, TranscriptServerHello,
+        CertificateVerify, ClaimData, ClaimDataMessage, ClaimDataTranscript, ClientHello, Finished,
+        ServerHello, TlsTranscript, TranscriptCertificate, TranscriptClientFinished,
+        TranscriptClientHello, TranscriptPartialClientHello, TranscriptServerFinished,
+        TranscriptServerHello,
     };
     use crate::protocol::TLSVersion;
 
     pub fn to_claim_data(
         protocol_version: TLSVersion,
         claim: security_claims::Claim,
+    ) -> Vec<ClaimData> {
+        let mut data: Vec<ClaimData> = to_single_claim_data(protocol_version, claim)
+            .into_iter()
+            .collect();
+
+        if claim.typ == security_claims::ClaimType::CLAIM_CERTIFICATE_VERIFY {
+            data.push(ClaimData::Message(ClaimDataMessage::CertificateVerify(
+                to_certificate_verify(claim),
+            )));
+        }
+
+        data
+    }
+
+    fn to_certificate_verify(claim: security_claims::Claim) -> CertificateVerify {
+        let outbound = claim.write > 0;
+
+        CertificateVerify {
+            outbound,
+            signature_algorithm: if outbound {
+                claim.signature_algorithm
+            } else {
+                claim.peer_signature_algorithm
+            },
+            verified: outbound || claim.certificate_verify_ok != 0,
+        }
+    }
+
+    fn to_available_ciphers(claim: &security_claims::Claim) -> SmallVec<[u16; 20]> {
+        claim.available_ciphers.ciphers[..claim.available_ciphers.length as usize]
+            .iter()
+            .map(|cipher| cipher.data)
+            .collect()
+    }
+
+    fn to_single_claim_data(
+        protocol_version: TLSVersion,
+        claim: security_claims::Claim,
     ) -> Option<ClaimData> {
         match claim.typ {
             // Transcripts
@@ -388,17 +563,34 @@ pub mod claims_helpers {
                         TLSVersion::V1_3 => SmallVec::from_slice(&claim.master_secret.secret),
                         TLSVersion::V1_2 => SmallVec::from_slice(&claim.master_secret_12.secret),
                     },
+                    resumption_master_secret: match protocol_version {
+                        TLSVersion::V1_3 => {
+                            SmallVec::from_slice(&claim.resumption_master_secret.secret)
+                        }
+                        TLSVersion::V1_2 => SmallVec::new(),
+                    },
+                    exporter_master_secret: match protocol_version {
+                        TLSVersion::V1_3 => {
+                            SmallVec::from_slice(&claim.exporter_master_secret.secret)
+                        }
+                        TLSVersion::V1_2 => SmallVec::new(),
+                    },
+                    extended_master_secret: protocol_version == TLSVersion::V1_2
+                        && claim.extended_master_secret_12 != 0,
+                    session_resumed: protocol_version == TLSVersion::V1_2
+                        && claim.session_resumed_12 != 0,
                     chosen_cipher: claim.chosen_cipher.data,
-                    available_ciphers: SmallVec::from_iter(
-                        claim.available_ciphers.ciphers[..claim.available_ciphers.length as usize]
-                            .iter()
-                            .map(|cipher| cipher.data),
-                    ),
+                    available_ciphers: to_available_ciphers(&claim),
                     signature_algorithm: claim.signature_algorithm,
                     peer_signature_algorithm: claim.peer_signature_algorithm,
                 })))
             }
-            security_claims::ClaimType::CLAIM_CLIENT_HELLO => None,
+            security_claims::ClaimType::CLAIM_CLIENT_HELLO => Some(ClaimData::Message(
+                ClaimDataMessage::ClientHello(ClientHello {
+                    outbound: claim.write > 0,
+                    available_ciphers: to_available_ciphers(&claim),
+                }),
+            )),
             security_claims::ClaimType::CLAIM_CCS => None,
             security_claims::ClaimType::CLAIM_END_OF_EARLY_DATA => None,
             security_claims::ClaimType::CLAIM_CERTIFICATE => None,
@@ -418,7 +610,12 @@ pub mod claims_helpers {
             }
             security_claims::ClaimType::CLAIM_KEY_UPDATE => None,
             security_claims::ClaimType::CLAIM_HELLO_REQUEST => None,
-            security_claims::ClaimType::CLAIM_SERVER_HELLO => None,
+            security_claims::ClaimType::CLAIM_SERVER_HELLO => Some(ClaimData::Message(
+                ClaimDataMessage::ServerHello(ServerHello {
+                    outbound: claim.write > 0,
+                    chosen_cipher: claim.chosen_cipher.data,
+                }),
+            )),
             security_claims::ClaimType::CLAIM_CERTIFICATE_REQUEST => None,
             security_claims::ClaimType::CLAIM_SERVER_DONE => None,
             security_claims::ClaimType::CLAIM_SESSION_TICKET => None,
@@ -429,3 +626,371 @@ pub mod claims_helpers {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use puffin::claims::SecurityViolationPolicy;
+    use ring::hmac::HMAC_SHA256;
+
+    use super::claims_helpers::to_claim_data;
+    use super::*;
+    use crate::tls::rustls::tls12::prf;
+    use crate::tls::violation::{
+        find_resumed_cipher_mismatch, find_resumed_sessions12, find_unoffered_cipher,
+        TlsSecurityViolationPolicy,
+    };
+
+    fn finished_claim12() -> security_claims::Claim {
+        let mut claim = security_claims::Claim {
+            typ: security_claims::ClaimType::CLAIM_FINISHED,
+            ..Default::default()
+        };
+        claim.master_secret_12.secret[..48].copy_from_slice(&[0x42; 48]);
+        claim.client_random.data = [0x01; 32];
+        claim.server_random.data = [0x02; 32];
+        // TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256
+        claim.chosen_cipher.data = 0xc02f;
+        claim
+    }
+
+    fn find_master_secret12(protocol_version: TLSVersion) -> Option<MasterSecret12> {
+        to_claim_data(protocol_version, finished_claim12())
+            .into_iter()
+            .find_map(|data| {
+                MasterSecret12::from_claim(&TlsClaim {
+                    agent_name: AgentName::first(),
+                    origin: AgentType::Client,
+                    protocol_version,
+                    data,
+                })
+            })
+    }
+
+    #[test_log::test]
+    fn test_master_secret12() {
+        let secret = find_master_secret12(TLSVersion::V1_2).expect("no master secret");
+        assert!(!secret.extended);
+        assert_eq!(secret.master_secret.as_slice(), &[0x42; 48]);
+
+        let mut expected_key_block = vec![0; 48];
+        prf::prf(
+            &mut expected_key_block,
+            HMAC_SHA256,
+            &[0x42; 48],
+            b"key expansion",
+            &[[0x02; 32], [0x01; 32]].concat(),
+        );
+        assert_eq!(secret.key_block, Some(expected_key_block));
+
+        assert_eq!(
+            secret.keylog_line(),
+            format!("CLIENT_RANDOM {} {}", "01".repeat(32), "42".repeat(48))
+        );
+    }
+
+    #[test_log::test]
+    fn test_no_master_secret12_in_tls13() {
+        assert!(find_master_secret12(TLSVersion::V1_3).is_none());
+    }
+
+    fn certificate_verify_claim(write: bool, ok: bool) -> CertificateVerify {
+        let claim = security_claims::Claim {
+            typ: security_claims::ClaimType::CLAIM_CERTIFICATE_VERIFY,
+            write: write.into(),
+            // rsa_pss_rsae_sha256 is announced, but rsa_pkcs1_sha256 was used
+            signature_algorithm: 0x0401,
+            peer_signature_algorithm: 0x0804,
+            certificate_verify_ok: ok.into(),
+            ..Default::default()
+        };
+
+        to_claim_data(TLSVersion::V1_3, claim)
+            .into_iter()
+            .find_map(|data| match data {
+                ClaimData::Message(ClaimDataMessage::CertificateVerify(verify)) => Some(verify),
+                _ => None,
+            })
+            .expect("no CertificateVerify claim")
+    }
+
+    #[test_log::test]
+    fn test_certificate_verify_claim() {
+        let rejected = certificate_verify_claim(false, false);
+        assert!(!rejected.outbound);
+        assert!(!rejected.verified);
+        assert_eq!(rejected.signature_algorithm, 0x0804);
+
+        let accepted = certificate_verify_claim(false, true);
+        assert!(accepted.verified);
+
+        let sent = certificate_verify_claim(true, false);
+        assert!(sent.outbound);
+        assert!(sent.verified);
+        assert_eq!(sent.signature_algorithm, 0x0401);
+
+        let claim = TlsClaim {
+            agent_name: AgentName::first(),
+            origin: AgentType::Client,
+            protocol_version: TLSVersion::V1_3,
+            data: ClaimData::Message(ClaimDataMessage::CertificateVerify(rejected)),
+        };
+        assert_eq!(
+            claim.to_string(),
+            "Client TLS 1.3, signature 0x0804 rejected"
+        );
+    }
+
+    fn hello_claims(offered: &[u16], selected: u16) -> Vec<TlsClaim> {
+        let mut client_hello = security_claims::Claim {
+            typ: security_claims::ClaimType::CLAIM_CLIENT_HELLO,
+            server: 1,
+            ..Default::default()
+        };
+        for (claimed, cipher) in client_hello
+            .available_ciphers
+            .ciphers
+            .iter_mut()
+            .zip(offered)
+        {
+            claimed.data = *cipher;
+        }
+        client_hello.available_ciphers.length = offered.len() as _;
+
+        let mut server_hello = security_claims::Claim {
+            typ: security_claims::ClaimType::CLAIM_SERVER_HELLO,
+            server: 1,
+            write: 1,
+            ..Default::default()
+        };
+        server_hello.chosen_cipher.data = selected;
+
+        [client_hello, server_hello]
+            .into_iter()
+            .flat_map(|claim| to_claim_data(TLSVersion::V1_3, claim))
+            .map(|data| TlsClaim {
+                agent_name: AgentName::first(),
+                origin: AgentType::Server,
+                protocol_version: TLSVersion::V1_3,
+                data,
+            })
+            .collect()
+    }
+
+    #[test_log::test]
+    fn test_claims_to_dot() {
+        let client = AgentName::first();
+        let server = client.next();
+
+        // Interleave the ClientHello and ServerHello claims of both agents
+        let mut claims = hello_claims(&[0x1301, 0x1302], 0x1302);
+        for claim in &mut claims {
+            claim.agent_name = server;
+        }
+        let claims: Vec<_> = hello_claims(&[0x1303], 0x1303)
+            .into_iter()
+            .zip(claims)
+            .flat_map(|(client_claim, server_claim)| [client_claim, server_claim])
+            .map(|claim| (claim.agent_name, claim))
+            .collect();
+
+        let dot = puffin::graphviz::claims_to_dot(&claims);
+
+        for i in 0..claims.len() {
+            assert_eq!(dot.matches(&format!("\nclaim_{i} [label=")).count(), 1);
+        }
+        assert_eq!(dot.matches("shape=\"box\"").count(), claims.len());
+        assert_eq!(dot.matches("subgraph cluster_agent_").count(), 2);
+        assert!(dot.contains("Server TLS 1.3, ciphers 0x1301 0x1302"));
+        assert!(dot.contains("Server TLS 1.3, cipher 0x1302"));
+        assert!(dot.contains("-> claim_3 [label=\"ServerHello\""));
+    }
+
+    #[test_log::test]
+    fn test_offered_and_selected_cipher_claims() {
+        let claims = hello_claims(&[0x1301, 0x1302], 0x1302);
+
+        assert!(claims.iter().any(|claim| matches!(
+            &claim.data,
+            ClaimData::Message(ClaimDataMessage::ClientHello(hello))
+                if !hello.outbound && hello.available_ciphers.as_slice() == [0x1301, 0x1302]
+        )));
+        assert!(claims.iter().any(|claim| matches!(
+            &claim.data,
+            ClaimData::Message(ClaimDataMessage::ServerHello(hello))
+                if hello.outbound && hello.chosen_cipher == 0x1302
+        )));
+        assert_eq!(find_unoffered_cipher(&claims), None);
+    }
+
+    /// Claims of the Finished messages which `origins` received
+    fn finished_claims(origins: &[AgentType]) -> Vec<TlsClaim> {
+        origins
+            .iter()
+            .flat_map(|origin| {
+                let agent_name = match origin {
+                    AgentType::Client => AgentName::first(),
+                    AgentType::Server => AgentName::first().next(),
+                };
+
+                to_claim_data(TLSVersion::V1_2, finished_claim12())
+                    .into_iter()
+                    .map(move |data| TlsClaim {
+                        agent_name,
+                        origin: *origin,
+                        protocol_version: TLSVersion::V1_2,
+                        data,
+                    })
+            })
+            .collect()
+    }
+
+    #[test_log::test]
+    fn test_handshake_complete() {
+        assert!(TlsSecurityViolationPolicy::is_handshake_complete(
+            &finished_claims(&[AgentType::Client, AgentType::Server])
+        ));
+
+        // only one of the peers finished
+        assert!(!TlsSecurityViolationPolicy::is_handshake_complete(
+            &finished_claims(&[AgentType::Server])
+        ));
+        assert!(!TlsSecurityViolationPolicy::is_handshake_complete(&[]));
+    }
+
+    #[test_log::test]
+    fn test_unoffered_cipher_selection() {
+        let claims = hello_claims(&[0x1301, 0x1302], 0x1303);
+
+        assert_eq!(
+            find_unoffered_cipher(&claims),
+            Some((AgentName::first(), 0x1303))
+        );
+        assert_eq!(
+            TlsSecurityViolationPolicy::check_violation(&claims),
+            Some("Selected cipher was not offered")
+        );
+    }
+
+    #[test_log::test]
+    fn test_unoffered_cipher_after_second_client_hello() {
+        // e.g. after a HelloRetryRequest or a renegotiation, the ServerHello answers the second
+        // ClientHello only
+        let mut claims = hello_claims(&[0x1301], 0x1301);
+        claims.extend(hello_claims(&[0x1302], 0x1302));
+        assert_eq!(find_unoffered_cipher(&claims), None);
+
+        let mut claims = hello_claims(&[0x1301], 0x1301);
+        claims.extend(hello_claims(&[0x1302], 0x1301));
+        assert_eq!(
+            find_unoffered_cipher(&claims),
+            Some((AgentName::first(), 0x1301))
+        );
+    }
+
+    #[test_log::test]
+    fn test_unoffered_cipher_received_by_client() {
+        // only the ServerHellos which servers send are checked
+        let claims: Vec<_> = hello_claims(&[0x1301], 0x1302)
+            .into_iter()
+            .map(|claim| TlsClaim {
+                origin: AgentType::Client,
+                ..claim
+            })
+            .collect();
+
+        assert_eq!(find_unoffered_cipher(&claims), None);
+    }
+
+    /// Claims of a server which established a session with cipher 0xc02f and resumed it with
+    /// `resumed_cipher`
+    fn resumption_claims12(resumed_cipher: u16) -> Vec<TlsClaim> {
+        let server = AgentName::first();
+        let mut established = finished_claim12();
+        established.session_id.length = 32;
+        established.session_id.data = [0x03; 32];
+
+        // the abbreviated handshake has fresh randoms
+        let mut resumed = established;
+        resumed.client_random.data = [0x04; 32];
+        resumed.server_random.data = [0x05; 32];
+        resumed.chosen_cipher.data = resumed_cipher;
+        resumed.session_resumed_12 = 1;
+
+        [established, resumed]
+            .into_iter()
+            .flat_map(|claim| to_claim_data(TLSVersion::V1_2, claim))
+            .map(|data| TlsClaim {
+                agent_name: server,
+                origin: AgentType::Server,
+                protocol_version: TLSVersion::V1_2,
+                data,
+            })
+            .collect()
+    }
+
+    #[test_log::test]
+    fn test_session_resumption12() {
+        let claims = resumption_claims12(0xc02f);
+        let resumed = find_resumed_sessions12(&claims);
+
+        assert_eq!(resumed.len(), 1);
+        let ((_, resumed), established) = resumed[0];
+        assert_eq!(resumed.client_random.as_slice(), &[0x04; 32]);
+        assert_eq!(established.client_random.as_slice(), &[0x01; 32]);
+        assert_eq!(find_resumed_cipher_mismatch(&claims), None);
+
+        // a single handshake does not resume a session
+        assert!(find_resumed_sessions12(&claims[..1]).is_empty());
+    }
+
+    #[test_log::test]
+    fn test_session_resumption12_requires_claim() {
+        // a full handshake which happens to finish with the session id of an earlier one
+        let claims: Vec<_> = resumption_claims12(0xc030)
+            .into_iter()
+            .map(|mut claim| {
+                if let ClaimData::Message(ClaimDataMessage::Finished(finished)) = &mut claim.data {
+                    finished.session_resumed = false;
+                }
+                claim
+            })
+            .collect();
+
+        assert!(find_resumed_sessions12(&claims).is_empty());
+        assert_eq!(find_resumed_cipher_mismatch(&claims), None);
+    }
+
+    #[test_log::test]
+    fn test_session_resumption12_of_same_agent() {
+        // the session was established by another agent
+        let mut claims = resumption_claims12(0xc030);
+        claims[0].agent_name = AgentName::first().next();
+        assert!(find_resumed_sessions12(&claims).is_empty());
+
+        // a full handshake with other randoms happened in between
+        let mut claims = resumption_claims12(0xc030);
+        let mut intermediate = claims[0].clone();
+        if let ClaimData::Message(ClaimDataMessage::Finished(finished)) = &mut intermediate.data {
+            finished.client_random = SmallVec::from([0x06; 32]);
+            finished.session_id = SmallVec::from([0x07; 32]);
+        }
+        claims.insert(1, intermediate);
+        assert!(find_resumed_sessions12(&claims).is_empty());
+        assert_eq!(find_resumed_cipher_mismatch(&claims), None);
+    }
+
+    #[test_log::test]
+    fn test_resumed_cipher_mismatch() {
+        // TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384
+        let claims = resumption_claims12(0xc030);
+
+        assert_eq!(
+            find_resumed_cipher_mismatch(&claims),
+            Some((AgentName::first(), 0xc030))
+        );
+        assert_eq!(
+            TlsSecurityViolationPolicy::check_violation(&claims),
+            Some("Resumed session with a different cipher")
+        );
+    }
+}
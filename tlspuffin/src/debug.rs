@@ -57,6 +57,12 @@ pub fn debug_message_with_info(info: &str, message: &Message) {
             format!("{:?}", payload.payload)
         }
         MessagePayload::TLS12EncryptedHandshake(_) => "TLS12EncryptedHandshake".to_string(),
+        MessagePayload::CoalescedHandshake(payloads) => {
+            format!(
+                "{:?}",
+                payloads.iter().map(|p| &p.payload).collect::<Vec<_>>()
+            )
+        }
         MessagePayload::ChangeCipherSpec(_) => "ChangeCipherSpec".to_string(),
         MessagePayload::ApplicationData(_) => "ApplicationData".to_string(),
         MessagePayload::Heartbeat(_) => "Heartbeat".to_string(),
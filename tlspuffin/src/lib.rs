@@ -33,6 +33,9 @@
 //!
 //! let trace = Trace::<TLSProtocolTypes> {
 //!     prior_traces: vec![],
+//!     expected_outcome: None,
+//!     expected_flow: None,
+//!     seed: None,
 //!     descriptors: vec![
 //!         TLSDescriptorConfig::new_client(client, V1_3),
 //!         TLSDescriptorConfig::new_server(server, V1_3),
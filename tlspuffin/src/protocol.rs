@@ -1,13 +1,13 @@
 use core::any::TypeId;
 
-use puffin::agent::{AgentDescriptor, AgentName, ProtocolDescriptorConfig};
+use puffin::agent::{Agent, AgentDescriptor, AgentName, ProtocolDescriptorConfig};
 use puffin::algebra::signature::Signature;
 use puffin::algebra::Matcher;
 use puffin::error::Error;
 use puffin::protocol::{
-    EvaluatedTerm, Extractable, OpaqueProtocolMessage, OpaqueProtocolMessageFlight,
-    ProtocolBehavior, ProtocolMessage, ProtocolMessageDeframer, ProtocolMessageFlight,
-    ProtocolTypes,
+    EvaluatedTerm, Extractable, ExtractedKnowledge, OpaqueProtocolMessage,
+    OpaqueProtocolMessageFlight, ProtocolAlerts, ProtocolBehavior, ProtocolMessage,
+    ProtocolMessageDeframer, ProtocolMessageFlight, ProtocolTypes,
 };
 use puffin::put::PutDescriptor;
 use puffin::trace::{Knowledge, Source, Trace};
@@ -25,8 +25,8 @@ use crate::tls::rustls::msgs::base::Payload;
 use crate::tls::rustls::msgs::ccs::ChangeCipherSpecPayload;
 use crate::tls::rustls::msgs::deframer::MessageDeframer;
 use crate::tls::rustls::msgs::enums::{
-    AlertDescription, AlertLevel, CipherSuite, Compression, HandshakeType, KeyUpdateRequest,
-    NamedGroup, ProtocolVersion, SignatureScheme,
+    AlertDescription, AlertLevel, CipherSuite, Compression, ContentType, ExtensionType,
+    HandshakeType, KeyUpdateRequest, NamedGroup, ProtocolVersion, SignatureScheme,
 };
 use crate::tls::rustls::msgs::handshake::{
     CertReqExtension, CertificateEntry, CertificateExtension, CertificatePayload,
@@ -194,6 +194,26 @@ impl Extractable<TLSProtocolTypes> for MessageFlight {
         }
         Ok(())
     }
+
+    /// Extracts the knowledge of each message on its own, such that a malformed message does not
+    /// discard the knowledge of the others.
+    fn extract_knowledge_partial<'a>(
+        &'a self,
+        matcher: Option<TlsQueryMatcher>,
+        source: &'a Source,
+    ) -> ExtractedKnowledge<'a, TLSProtocolTypes> {
+        let mut extracted = ExtractedKnowledge::new();
+        extracted.knowledges.push(Knowledge {
+            source,
+            matcher,
+            data: self,
+        });
+
+        for msg in &self.messages {
+            extracted.extend(msg.extract_knowledge_partial(matcher, source));
+        }
+        extracted
+    }
 }
 
 impl Extractable<TLSProtocolTypes> for OpaqueMessageFlight {
@@ -213,6 +233,26 @@ impl Extractable<TLSProtocolTypes> for OpaqueMessageFlight {
         }
         Ok(())
     }
+
+    /// Extracts the knowledge of each message on its own, such that a malformed message does not
+    /// discard the knowledge of the others.
+    fn extract_knowledge_partial<'a>(
+        &'a self,
+        matcher: Option<TlsQueryMatcher>,
+        source: &'a Source,
+    ) -> ExtractedKnowledge<'a, TLSProtocolTypes> {
+        let mut extracted = ExtractedKnowledge::new();
+        extracted.knowledges.push(Knowledge {
+            source,
+            matcher,
+            data: self,
+        });
+
+        for msg in &self.messages {
+            extracted.extend(msg.extract_knowledge_partial(matcher, source));
+        }
+        extracted
+    }
 }
 
 impl Extractable<TLSProtocolTypes> for Message {
@@ -234,6 +274,7 @@ impl Extractable<TLSProtocolTypes> for Message {
             MessagePayload::ApplicationData(_) => Some(TlsQueryMatcher::ApplicationData),
             MessagePayload::Heartbeat(_) => Some(TlsQueryMatcher::Heartbeat),
             MessagePayload::TLS12EncryptedHandshake(_) => Some(TlsQueryMatcher::Handshake(None)),
+            MessagePayload::CoalescedHandshake(_) => Some(TlsQueryMatcher::Handshake(None)),
         };
 
         knowledges.push(Knowledge {
@@ -273,6 +314,12 @@ impl Extractable<TLSProtocolTypes> for MessagePayload {
             MessagePayload::TLS12EncryptedHandshake(tls12encrypted) => {
                 tls12encrypted.extract_knowledge(knowledges, matcher, source)?
             }
+            MessagePayload::CoalescedHandshake(messages) => {
+                for hs in messages {
+                    let matcher = Some(TlsQueryMatcher::Handshake(Some(hs.typ)));
+                    hs.extract_knowledge(knowledges, matcher, source)?
+                }
+            }
         }
         Ok(())
     }
@@ -316,6 +363,27 @@ impl Extractable<TLSProtocolTypes> for HeartbeatPayload {
     }
 }
 
+impl Extractable<TLSProtocolTypes> for CertificateStatus {
+    fn extract_knowledge<'a>(
+        &'a self,
+        knowledges: &mut Vec<Knowledge<'a, TLSProtocolTypes>>,
+        matcher: Option<TlsQueryMatcher>,
+        source: &'a Source,
+    ) -> Result<(), Error> {
+        knowledges.push(Knowledge {
+            source,
+            matcher,
+            data: self,
+        });
+        knowledges.push(Knowledge {
+            source,
+            matcher,
+            data: &self.ocsp_response.0,
+        });
+        Ok(())
+    }
+}
+
 impl Extractable<TLSProtocolTypes> for AlertMessagePayload {
     fn extract_knowledge<'a>(
         &'a self,
@@ -379,7 +447,7 @@ impl Extractable<TLSProtocolTypes> for HandshakePayload {
         });
         match &self {
             HandshakePayload::HelloRequest | HandshakePayload::HelloRetryRequest(_) => {}
-            HandshakePayload::ClientHello(ch) => {
+            HandshakePayload::ClientHello(ch) | HandshakePayload::PartialClientHello(ch, _) => {
                 ch.extract_knowledge(knowledges, matcher, source)?;
             }
             HandshakePayload::ServerHello(sh) => {
@@ -729,7 +797,6 @@ atom_extract_knowledge!(TLSProtocolTypes, CertificateExtension);
 atom_extract_knowledge!(TLSProtocolTypes, CertificatePayloadTLS13);
 atom_extract_knowledge!(TLSProtocolTypes, CertificateRequestPayload);
 atom_extract_knowledge!(TLSProtocolTypes, CertificateRequestPayloadTLS13);
-atom_extract_knowledge!(TLSProtocolTypes, CertificateStatus);
 atom_extract_knowledge!(TLSProtocolTypes, CipherSuite);
 atom_extract_knowledge!(TLSProtocolTypes, ClientExtension);
 atom_extract_knowledge!(TLSProtocolTypes, Compression);
@@ -747,6 +814,7 @@ atom_extract_knowledge!(TLSProtocolTypes, Random);
 atom_extract_knowledge!(TLSProtocolTypes, ServerExtension);
 atom_extract_knowledge!(TLSProtocolTypes, SessionID);
 atom_extract_knowledge!(TLSProtocolTypes, SignatureScheme);
+atom_extract_knowledge!(TLSProtocolTypes, u16);
 atom_extract_knowledge!(TLSProtocolTypes, u32);
 atom_extract_knowledge!(TLSProtocolTypes, u64);
 atom_extract_knowledge!(TLSProtocolTypes, u8);
@@ -846,6 +914,30 @@ pub struct TLSDescriptorConfig {
     pub try_reuse: bool,
     /// List of available TLS ciphers
     pub cipher_string: String,
+    /// If agent is a server:
+    ///   Present this certificate and private key instead of the static certificate of Alice.
+    /// If agent is a client:
+    ///   No effect.
+    ///
+    /// Default: None
+    #[serde(default)]
+    pub server_certificate: Option<CertificateKeyPair>,
+}
+
+/// A PEM encoded certificate together with its PEM encoded private key
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Hash)]
+pub struct CertificateKeyPair {
+    pub cert: String,
+    pub key: String,
+}
+
+impl CertificateKeyPair {
+    pub fn new(cert: impl Into<String>, key: impl Into<String>) -> Self {
+        Self {
+            cert: cert.into(),
+            key: key.into(),
+        }
+    }
 }
 
 impl TLSDescriptorConfig {
@@ -878,9 +970,16 @@ impl TLSDescriptorConfig {
 
 impl ProtocolDescriptorConfig for TLSDescriptorConfig {
     fn is_reusable_with(&self, other: &Self) -> bool {
-        self.typ == other.typ
-            && self.tls_version == other.tls_version
-            && self.cipher_string == other.cipher_string
+        // Every field ends up in the context or the stream of the PUT, e.g. the authentication
+        // settings in the verification mode, hence only equal configurations are compatible
+        self == other
+    }
+
+    fn required_capabilities(&self) -> Vec<&str> {
+        match self.tls_version {
+            TLSVersion::V1_3 => vec!["tls13"],
+            TLSVersion::V1_2 => vec!["tls12"],
+        }
     }
 }
 
@@ -893,6 +992,7 @@ impl Default for TLSDescriptorConfig {
             try_reuse: false,
             typ: AgentType::Server,
             cipher_string: String::from("ALL:!EXPORT:!LOW:!aNULL:!eNULL:!SSLv2"),
+            server_certificate: None,
         }
     }
 }
@@ -918,8 +1018,13 @@ impl std::fmt::Display for TLSProtocolTypes {
 #[derive(Clone, Debug, PartialEq)]
 pub struct TLSProtocolBehavior;
 
+impl ProtocolAlerts for TLSProtocolBehavior {
+    type Alert = (AlertLevel, AlertDescription);
+}
+
 impl ProtocolBehavior for TLSProtocolBehavior {
     type Claim = TlsClaim;
+    type Extension = ExtensionType;
     type OpaqueProtocolMessage = OpaqueMessage;
     type OpaqueProtocolMessageFlight = OpaqueMessageFlight;
     type ProtocolMessage = Message;
@@ -935,6 +1040,43 @@ impl ProtocolBehavior for TLSProtocolBehavior {
         )
     }
 
+    fn create_negative_corpus(
+        descriptor: &AgentDescriptor<TLSDescriptorConfig>,
+    ) -> Vec<(Trace<Self::ProtocolTypes>, &'static str)> {
+        crate::tls::seeds::create_negative_corpus(descriptor)
+    }
+
+    fn message_type_name(message: &Message) -> &'static str {
+        match &message.payload {
+            MessagePayload::Handshake(handshake) => handshake.typ.as_str().unwrap_or("Unknown"),
+            MessagePayload::TLS12EncryptedHandshake(_) => "EncryptedHandshake",
+            MessagePayload::CoalescedHandshake(_) => "CoalescedHandshake",
+            MessagePayload::Alert(_) => "Alert",
+            MessagePayload::ChangeCipherSpec(_) => "ChangeCipherSpec",
+            MessagePayload::ApplicationData(_) => "ApplicationData",
+            MessagePayload::Heartbeat(_) => "Heartbeat",
+        }
+    }
+
+    /// A PUT failed to decrypt a record if it raised a `bad_record_mac` or `decryption_failed`
+    /// alert while processing `input`. PUTs which can not observe their alerts have no outcome.
+    fn put_decryption_outcome(
+        agent: &Agent<Self>,
+        input: &[u8],
+        protected: &mut bool,
+    ) -> Option<bool> {
+        let has_protected_records = has_protected_records(input, protected);
+        (has_protected_records && agent.observes_alerts()).then(|| {
+            !matches!(
+                agent.last_alert(),
+                Some((
+                    _,
+                    AlertDescription::BadRecordMac | AlertDescription::DecryptionFailed
+                ))
+            )
+        })
+    }
+
     fn try_read_bytes(
         bitstring: &[u8],
         ty: TypeId,
@@ -942,3 +1084,188 @@ impl ProtocolBehavior for TLSProtocolBehavior {
         try_read_bytes(bitstring, ty)
     }
 }
+
+/// Whether `input` holds records which are protected by the record layer, i.e. application data
+/// records or records which follow a ChangeCipherSpec. `cipher_changed` tells whether a
+/// ChangeCipherSpec was received in a previous input, and is set if `input` holds one.
+fn has_protected_records(input: &[u8], cipher_changed: &mut bool) -> bool {
+    let mut reader = codec::Reader::init(input);
+    let mut protected = false;
+
+    while let Ok(record) = OpaqueMessage::read(&mut reader) {
+        match record.typ {
+            ContentType::ApplicationData => protected = true,
+            ContentType::ChangeCipherSpec => *cipher_changed = true,
+            _ if *cipher_changed => protected = true,
+            _ => {}
+        }
+    }
+
+    protected
+}
+
+#[cfg(test)]
+mod tests {
+    use puffin::codec::Codec;
+    use puffin::trace::{AgentFlow, KnowledgeStore, MessageType};
+
+    use super::*;
+    use crate::tls::fn_impl::{
+        fn_alert_close_notify, fn_certificate, fn_certificate_request, fn_client_hello,
+        fn_server_hello_done,
+    };
+    use crate::tls::rustls::msgs::handshake::{CipherSuites, ClientExtensions, Compressions};
+
+    /// Knowledge of a server which sent `messages` in a single flight
+    fn server_flight(messages: Vec<Message>) -> KnowledgeStore<TLSProtocolTypes> {
+        let mut store = KnowledgeStore::new();
+        store.add_raw_knowledge(
+            MessageFlight { messages },
+            Source::Agent(AgentName::first().next()),
+            None,
+        );
+        store
+    }
+
+    /// A TLS 1.2 server flight after the ServerHello, with an optional CertificateRequest
+    fn expected_flow() -> Vec<AgentFlow<TlsQueryMatcher>> {
+        let handshake = |typ| Some(TlsQueryMatcher::Handshake(Some(typ)));
+
+        vec![
+            (AgentName::first(), vec![]),
+            (
+                AgentName::first().next(),
+                vec![
+                    MessageType::required(handshake(HandshakeType::Certificate)),
+                    MessageType::optional(handshake(HandshakeType::CertificateRequest)),
+                    MessageType::required(handshake(HandshakeType::ServerHelloDone)),
+                ],
+            ),
+        ]
+    }
+
+    #[test_log::test]
+    fn test_flow_conforms() {
+        let with_request = server_flight(vec![
+            fn_certificate(&vec![]).unwrap(),
+            fn_certificate_request().unwrap(),
+            fn_server_hello_done().unwrap(),
+        ]);
+        let without_request = server_flight(vec![
+            fn_certificate(&vec![]).unwrap(),
+            fn_server_hello_done().unwrap(),
+        ]);
+
+        for store in [with_request, without_request] {
+            assert_eq!(
+                store.verify_flow(&expected_flow(), TypeId::of::<Message>()),
+                Ok(())
+            );
+        }
+    }
+
+    #[test_log::test]
+    fn test_flow_diverges() {
+        let store = server_flight(vec![
+            fn_certificate(&vec![]).unwrap(),
+            fn_server_hello_done().unwrap(),
+            fn_alert_close_notify().unwrap(),
+        ]);
+
+        let Err(Error::Flow(diff)) = store.verify_flow(&expected_flow(), TypeId::of::<Message>())
+        else {
+            panic!("the unexpected alert was not detected");
+        };
+        assert_eq!(
+            diff,
+            "  server:\n    expected [Handshake(Some(Certificate)), \
+             Handshake(Some(CertificateRequest))?, Handshake(Some(ServerHelloDone))]\n    sent     \
+             [Handshake(Some(Certificate)), Handshake(Some(ServerHelloDone)), Alert]\n"
+        );
+    }
+
+    #[test_log::test]
+    fn test_message_type_name() {
+        let client_hello = fn_client_hello(
+            &ProtocolVersion::TLSv1_2,
+            &Random::from([1; 32]),
+            &SessionID::empty(),
+            &CipherSuites(vec![CipherSuite::TLS13_AES_128_GCM_SHA256]),
+            &Compressions(vec![Compression::Null]),
+            &ClientExtensions(vec![]),
+        )
+        .unwrap();
+        assert_eq!(
+            TLSProtocolBehavior::message_type_name(&client_hello),
+            "ClientHello"
+        );
+
+        assert_eq!(
+            TLSProtocolBehavior::message_type_name(&fn_server_hello_done().unwrap()),
+            "ServerHelloDone"
+        );
+        assert_eq!(
+            TLSProtocolBehavior::message_type_name(&fn_alert_close_notify().unwrap()),
+            "Alert"
+        );
+
+        let unknown = Message {
+            version: ProtocolVersion::TLSv1_2,
+            payload: MessagePayload::Handshake(HandshakeMessagePayload {
+                typ: HandshakeType::Unknown(0x42),
+                payload: HandshakePayload::Unknown(Payload::empty()),
+            }),
+        };
+        assert_eq!(TLSProtocolBehavior::message_type_name(&unknown), "Unknown");
+    }
+
+    fn records(types: &[ContentType]) -> Vec<u8> {
+        let mut bytes = vec![];
+        for typ in types {
+            OpaqueMessage {
+                typ: *typ,
+                version: ProtocolVersion::TLSv1_2,
+                payload: Payload::new(vec![1, 2, 3]),
+            }
+            .encode(&mut bytes);
+        }
+        bytes
+    }
+
+    #[test_log::test]
+    fn test_has_protected_records() {
+        let protected = |types: &[ContentType]| has_protected_records(&records(types), &mut false);
+
+        assert!(!protected(&[ContentType::Handshake]));
+        assert!(!protected(&[
+            ContentType::Handshake,
+            ContentType::ChangeCipherSpec
+        ]));
+        assert!(protected(&[
+            ContentType::Handshake,
+            ContentType::ChangeCipherSpec,
+            ContentType::Handshake
+        ]));
+        assert!(protected(&[ContentType::ApplicationData]));
+        assert!(!has_protected_records(&[0xff; 3], &mut false));
+    }
+
+    #[test_log::test]
+    fn test_has_protected_records_after_earlier_change_cipher_spec() {
+        let mut cipher_changed = false;
+
+        // The ChangeCipherSpec and the encrypted Finished of TLS 1.2 are sent in separate inputs
+        let ccs = records(&[ContentType::ChangeCipherSpec]);
+        assert!(!has_protected_records(&ccs, &mut cipher_changed));
+        assert!(cipher_changed);
+
+        let finished = records(&[ContentType::Handshake]);
+        assert!(has_protected_records(&finished, &mut cipher_changed));
+        assert!(!has_protected_records(&finished, &mut false));
+    }
+
+    #[test_log::test]
+    fn test_signature_valid() {
+        assert_eq!(TLS_SIGNATURE.validate(), Ok(()));
+    }
+}
@@ -10,12 +10,12 @@ use puffin::claims::GlobalClaimList;
 use puffin::error::Error;
 use puffin::harness::{to_string, CError};
 use puffin::protocol::{OpaqueProtocolMessageFlight, ProtocolBehavior, ProtocolMessageDeframer};
-use puffin::put::{Put, PutOptions};
+use puffin::put::{Put, PutOptions, SessionData};
 use puffin::put_registry::Factory;
-use puffin::stream::Stream;
+use puffin::stream::{MemoryStream, Stream};
 use security_claims::Claim;
 
-use crate::claims::TlsClaim;
+use crate::claims::{Finished, TlsClaim};
 use crate::protocol::{
     AgentType, OpaqueMessageFlight, TLSDescriptorConfig, TLSProtocolBehavior, TLSProtocolTypes,
     TLSVersion,
@@ -24,8 +24,28 @@ use crate::put_registry::bindings::{
     AGENT, CLAIMER_CB, PEM, TLS_AGENT_DESCRIPTOR, TLS_AGENT_ROLE, TLS_PUT_INTERFACE, TLS_VERSION,
 };
 use crate::static_certs::{ALICE_CERT, ALICE_PRIVATE_KEY, BOB_CERT, BOB_PRIVATE_KEY, EVE_CERT};
+use crate::tls::rustls::cipher::corrupt_every_nth_record;
 use crate::tls::rustls::msgs::deframer::MessageDeframer;
 
+/// PUT option which makes an agent accept any certificate of its peer, e.g. `skip_cert_verify=true`
+///
+/// This speeds up handshakes, but hides bugs in the certificate verification, therefore it is
+/// meant for tests only. As PUT options are set per agent, a trace can still verify the
+/// certificates of other agents. Agents are only reused by agents with the same PUT options, hence
+/// an agent which skips the verification never replaces one which verifies.
+pub const SKIP_CERT_VERIFY_OPTION: &str = "skip_cert_verify";
+
+/// PUT option which splits the reads of an agent into chunks of at most the given number of bytes,
+/// e.g. `read_chunk=1`
+///
+/// This simulates a network which delivers records in pieces and exercises the reassembly of
+/// records in the PUT. Only the PUTs which read from a [`MemoryStream`] support this option, the
+/// C PUTs reject it. The value has to be a positive number.
+pub const READ_CHUNK_OPTION: &str = "read_chunk";
+
+/// Seed of the lengths of the chunks, see [`READ_CHUNK_OPTION`]
+const READ_CHUNK_SEED: u64 = 42;
+
 /// Static configuration for creating a new agent state for the PUT
 #[derive(Clone, Debug)]
 pub struct TlsPutConfig {
@@ -33,7 +53,14 @@ pub struct TlsPutConfig {
     pub claims: GlobalClaimList<TlsClaim>,
     pub authenticate_peer: bool,
     pub extract_deferred: Rc<RefCell<Option<TypeShape<TLSProtocolTypes>>>>,
+    /// Finished claim of a TLS 1.3 handshake, which is claimed at the end of the message flight
+    /// once the resumption master secret is derived
+    pub claim_deferred: Rc<RefCell<Option<Finished>>>,
     pub use_clear: bool,
+    /// Accept any certificate of the peer, see [`SKIP_CERT_VERIFY_OPTION`]
+    pub skip_cert_verify: bool,
+    /// Maximum length of a read, see [`READ_CHUNK_OPTION`]
+    pub read_chunk: Option<usize>,
 }
 
 impl TlsPutConfig {
@@ -41,13 +68,36 @@ impl TlsPutConfig {
         agent_descriptor: &AgentDescriptor<TLSDescriptorConfig>,
         claims: &GlobalClaimList<<TLSProtocolBehavior as ProtocolBehavior>::Claim>,
         options: &PutOptions,
-    ) -> TlsPutConfig {
+    ) -> Result<TlsPutConfig, Error> {
         let use_clear = options
             .get_option("use_clear")
             .map(|value| value.parse().unwrap_or(false))
             .unwrap_or(false);
-
-        TlsPutConfig {
+        let skip_cert_verify = options
+            .get_option(SKIP_CERT_VERIFY_OPTION)
+            .map(|value| {
+                value.parse().map_err(|_| {
+                    Error::Put(format!(
+                        "invalid value {value:?} of the PUT option {SKIP_CERT_VERIFY_OPTION}"
+                    ))
+                })
+            })
+            .transpose()?
+            .unwrap_or(false);
+        let read_chunk = options
+            .get_option(READ_CHUNK_OPTION)
+            .map(|value| match value.parse::<usize>() {
+                Ok(max_len) if max_len > 0 => Ok(max_len),
+                _ => Err(Error::Put(format!(
+                    "invalid value {value:?} of the PUT option {READ_CHUNK_OPTION}"
+                ))),
+            })
+            .transpose()?;
+        // The option is read while evaluating the recipes of the inputs, but invalid values are
+        // rejected when the agent is created
+        corrupt_every_nth_record(options)?;
+
+        Ok(TlsPutConfig {
             descriptor: agent_descriptor.clone(),
             claims: claims.clone(),
             authenticate_peer: agent_descriptor.protocol_config.typ == AgentType::Client
@@ -55,7 +105,20 @@ impl TlsPutConfig {
                 || agent_descriptor.protocol_config.typ == AgentType::Server
                     && agent_descriptor.protocol_config.client_authentication,
             extract_deferred: Rc::new(RefCell::new(None)),
+            claim_deferred: Rc::new(RefCell::new(None)),
             use_clear,
+            skip_cert_verify,
+            read_chunk,
+        })
+    }
+
+    /// Creates the in-memory stream of the agent, which splits its reads if [`READ_CHUNK_OPTION`]
+    /// is set
+    #[must_use]
+    pub fn new_memory_stream(&self) -> MemoryStream {
+        match self.read_chunk {
+            Some(max_len) => MemoryStream::new().with_read_chunks(max_len, READ_CHUNK_SEED),
+            None => MemoryStream::new(),
         }
     }
 }
@@ -94,10 +157,15 @@ impl Factory<TLSProtocolBehavior> for CPut {
         claims: &GlobalClaimList<<TLSProtocolBehavior as ProtocolBehavior>::Claim>,
         options: &PutOptions,
     ) -> Result<Box<dyn Put<TLSProtocolBehavior>>, Error> {
-        Ok(Box::new(CAgent::new(
-            self,
-            TlsPutConfig::new(agent_descriptor, claims, options),
-        )?))
+        let config = TlsPutConfig::new(agent_descriptor, claims, options)?;
+        if config.read_chunk.is_some() {
+            return Err(Error::Put(format!(
+                "the C PUT {} does not support the PUT option {READ_CHUNK_OPTION}",
+                self.name
+            )));
+        }
+
+        Ok(Box::new(CAgent::new(self, config)?))
     }
 
     fn name(&self) -> String {
@@ -125,6 +193,20 @@ impl Factory<TLSProtocolBehavior> for CPut {
         }
     }
 
+    fn rng_reseed_with(&self, seed: u64) {
+        if self.interface.rng_reseed.is_none() {
+            log::debug!("[RNG] reseed failed ({}): not supported", self.name());
+            return;
+        }
+
+        let seed = seed.to_le_bytes();
+
+        log::debug!("[RNG] reseed with seed ({})", self.name());
+        unsafe {
+            (self.interface.rng_reseed.unwrap())(seed.as_ptr(), seed.len());
+        }
+    }
+
     fn supports(&self, capability: &str) -> bool {
         self.capabilities.contains(capability)
     }
@@ -142,10 +224,10 @@ pub struct CAgent {
 }
 
 macro_rules! pem {
-    ($pemder:expr) => {
+    ($bytes:expr) => {
         PEM {
-            bytes: $pemder.0.as_ptr(),
-            length: $pemder.0.len(),
+            bytes: $bytes.as_ptr(),
+            length: $bytes.len(),
         }
     };
 }
@@ -176,11 +258,20 @@ macro_rules! r_ccall {
 
 impl CAgent {
     fn new(put: &CPut, config: TlsPutConfig) -> Result<Self, Error> {
-        let server_cert = pem!(ALICE_CERT);
-        let server_pkey = pem!(ALICE_PRIVATE_KEY);
-        let client_cert = pem!(BOB_CERT);
-        let client_pkey = pem!(BOB_PRIVATE_KEY);
-        let other_cert = pem!(EVE_CERT);
+        let (server_cert, server_pkey) = match &config.descriptor.protocol_config.server_certificate
+        {
+            Some(server_certificate) => (
+                pem!(server_certificate.cert.as_bytes()),
+                pem!(server_certificate.key.as_bytes()),
+            ),
+            None => (
+                pem!(ALICE_CERT.0.as_bytes()),
+                pem!(ALICE_PRIVATE_KEY.0.as_bytes()),
+            ),
+        };
+        let client_cert = pem!(BOB_CERT.0.as_bytes());
+        let client_pkey = pem!(BOB_PRIVATE_KEY.0.as_bytes());
+        let other_cert = pem!(EVE_CERT.0.as_bytes());
 
         let server_store = [&client_cert as *const _, &other_cert];
         let client_store = [&server_cert as *const _, &other_cert];
@@ -223,7 +314,7 @@ impl CAgent {
             let agent_name = self.config.descriptor.name;
 
             let claimer = make_claimer(move |claim: Claim| {
-                if let Some(data) = claims_helpers::to_claim_data(protocol_version, claim) {
+                for data in claims_helpers::to_claim_data(protocol_version, claim) {
                     claims.deref_borrow_mut().claim_sized(TlsClaim {
                         agent_name,
                         origin,
@@ -268,6 +359,12 @@ impl Put<TLSProtocolBehavior> for CAgent {
         unsafe { ccall!(self.put, is_state_successful, self.c_agent) }
     }
 
+    fn import_session(&mut self, _session: &SessionData) -> Result<(), Error> {
+        Err(Error::Put(
+            "C PUT interface does not support importing sessions".to_string(),
+        ))
+    }
+
     fn shutdown(&mut self) -> String {
         todo!()
     }
@@ -366,6 +463,7 @@ fn make_descriptor(
         },
         client_authentication: config.descriptor.protocol_config.client_authentication,
         server_authentication: config.descriptor.protocol_config.server_authentication,
+        skip_cert_verify: config.skip_cert_verify,
         cipher_string: ciphers.as_ptr(),
 
         cert,
@@ -159,5 +159,11 @@ mod tests {
         assert!(version.expect("missing version string").contains("5.4.0"));
         #[cfg(feature = "wolfssl430")]
         assert!(version.expect("missing version string").contains("4.3.0"));
+
+        #[cfg(feature = "boringssl-binding")]
+        assert!(registry
+            .version_strings()
+            .iter()
+            .any(|line| line.to_lowercase().contains("boringssl")));
     }
 }
@@ -9,6 +9,11 @@ use crate::tls::rustls::msgs::enums::HandshakeType;
 pub enum TlsQueryMatcher {
     ChangeCipherSpec,
     Alert,
+    /// Knowledge extracted from a handshake message of the given type, e.g. the [`Random`] of
+    /// the ServerHello rather than the one of the ClientHello. `None` matches all handshake
+    /// messages, including encrypted ones of which the type is unknown, and is less specific.
+    ///
+    /// [`Random`]: crate::tls::rustls::msgs::handshake::Random
     Handshake(Option<HandshakeType>),
     ApplicationData,
     Heartbeat,
@@ -42,3 +47,84 @@ impl Matcher for TlsQueryMatcher {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use puffin::algebra::dynamic_function::TypeShape;
+    use puffin::algebra::Matcher;
+    use puffin::trace::{Query, Source, Spawner, TraceContext};
+
+    use super::TlsQueryMatcher;
+    use crate::put_registry::tls_registry;
+    use crate::tls::fn_impl::*;
+    use crate::tls::rustls::msgs::enums::{CipherSuite, HandshakeType};
+    use crate::tls::rustls::msgs::handshake::Random;
+
+    #[test_log::test]
+    fn test_handshake_type_specificity() {
+        let server_hello = Some(TlsQueryMatcher::Handshake(Some(HandshakeType::ServerHello)));
+        let any_handshake = Some(TlsQueryMatcher::Handshake(None));
+        let type_only: Option<TlsQueryMatcher> = None;
+
+        assert!(server_hello.specificity() > any_handshake.specificity());
+        assert!(any_handshake.specificity() > type_only.specificity());
+    }
+
+    #[test_log::test]
+    fn test_find_random_by_handshake_type() {
+        let client_random = Random([0x01; 32]);
+        let server_random = Random([0x02; 32]);
+
+        let client_hello = fn_client_hello(
+            &fn_protocol_version12().unwrap(),
+            &client_random,
+            &fn_new_session_id().unwrap(),
+            &fn_cipher_suites_make(&vec![CipherSuite::TLS13_AES_128_GCM_SHA256]).unwrap(),
+            &fn_compressions().unwrap(),
+            &fn_client_extensions_make(&fn_client_extensions_new().unwrap()).unwrap(),
+        )
+        .unwrap();
+        let server_hello = fn_server_hello(
+            &fn_protocol_version12().unwrap(),
+            &server_random,
+            &fn_new_session_id().unwrap(),
+            &CipherSuite::TLS13_AES_128_GCM_SHA256,
+            &fn_compression().unwrap(),
+            &fn_server_extensions_make(&fn_server_extensions_new().unwrap()).unwrap(),
+        )
+        .unwrap();
+
+        let mut ctx = TraceContext::new(Spawner::new(tls_registry()));
+        let source = Source::Label(None);
+        for message in [client_hello, server_hello] {
+            ctx.knowledge_store
+                .add_raw_knowledge(message, source.clone(), None);
+        }
+
+        let find_random = |matcher: Option<TlsQueryMatcher>| {
+            let query = Query {
+                source: Some(source.clone()),
+                matcher,
+                counter: 0,
+            };
+            ctx.find_variable(TypeShape::of::<Random>(), &query)
+                .and_then(|random| random.as_any().downcast_ref::<Random>())
+                .map(|random| random.0)
+        };
+
+        // both randoms match a query for the type only
+        assert_eq!(find_random(None), Some(client_random.0));
+        assert_eq!(
+            find_random(Some(TlsQueryMatcher::Handshake(Some(
+                HandshakeType::ServerHello
+            )))),
+            Some(server_random.0)
+        );
+        assert_eq!(
+            find_random(Some(TlsQueryMatcher::Handshake(Some(
+                HandshakeType::Certificate
+            )))),
+            None
+        );
+    }
+}
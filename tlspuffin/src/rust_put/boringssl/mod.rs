@@ -3,16 +3,18 @@ use std::io::ErrorKind;
 
 use boring::error::ErrorStack;
 use boring::ex_data::Index;
+use boring::pkey::PKey;
 use boring::ssl::{Ssl, SslContext, SslMethod, SslRef, SslStream, SslVerifyMode};
 use boring::x509::store::X509StoreBuilder;
 use boring::x509::X509;
 use boringssl_sys::ssl_st;
 use foreign_types::ForeignTypeRef;
+use once_cell::sync::Lazy;
 use puffin::agent::{AgentDescriptor, AgentName};
 use puffin::error::Error;
-use puffin::put::Put;
+use puffin::put::{Put, SessionData};
 use puffin::stream::{MemoryStream, Stream};
-use util::{set_max_protocol_version, static_rsa_cert};
+use util::{set_max_protocol_version, set_verify, static_rsa_cert};
 
 use crate::claims::{
     ClaimData, ClaimDataTranscript, TlsClaim, TranscriptCertificate, TranscriptClientFinished,
@@ -21,6 +23,7 @@ use crate::claims::{
 use crate::protocol::{AgentType, OpaqueMessageFlight, TLSDescriptorConfig, TLSProtocolBehavior};
 use crate::put::TlsPutConfig;
 use crate::static_certs::{ALICE_CERT, ALICE_PRIVATE_KEY, BOB_CERT, BOB_PRIVATE_KEY, EVE_CERT};
+use crate::tls::rustls::msgs::enums::{AlertDescription, AlertLevel};
 
 mod transcript;
 mod util;
@@ -30,6 +33,12 @@ use std::ops::Deref;
 use puffin::algebra::ConcreteMessage;
 use transcript::extract_current_transcript;
 
+const SSL_CB_WRITE_ALERT: i32 = 0x4008;
+
+/// Slot in the ex_data of an `SSL` for the last alert it sent
+static LAST_ALERT: Lazy<Index<Ssl, Option<(AlertLevel, AlertDescription)>>> =
+    Lazy::new(|| Ssl::new_ex_index().expect("Failed to allocate ex_data index for alerts"));
+
 pub struct RustPut {
     stream: SslStream<MemoryStream>,
     config: TlsPutConfig,
@@ -55,7 +64,7 @@ impl Stream<TLSProtocolBehavior> for RustPut {
 
 impl Put<TLSProtocolBehavior> for RustPut {
     fn progress(&mut self) -> Result<(), Error> {
-        let result = if self.is_state_successful() {
+        let result = if self.is_handshake_complete() {
             // Trigger another read
             let mut vec: Vec<u8> = Vec::from([1; 128]);
             let maybe_error: MaybeError = self.stream.ssl_read(&mut vec).into();
@@ -68,6 +77,34 @@ impl Put<TLSProtocolBehavior> for RustPut {
         result
     }
 
+    fn progress_one_flight(&mut self) -> Result<(), Error> {
+        self.stream.get_mut().hold_reads_while_output_pending(true);
+        let result = self.progress();
+        self.stream.get_mut().hold_reads_while_output_pending(false);
+
+        result
+    }
+
+    fn import_session(&mut self, _session: &SessionData) -> Result<(), Error> {
+        Err(Error::Put(
+            "BoringSSL PUT does not support importing sessions".to_string(),
+        ))
+    }
+
+    fn last_alert(&self) -> Option<(AlertLevel, AlertDescription)> {
+        self.stream.ssl().ex_data(*LAST_ALERT).copied().flatten()
+    }
+
+    fn clear_last_alert(&mut self) {
+        if let Some(last_alert) = self.stream.ssl_mut().ex_data_mut(*LAST_ALERT) {
+            *last_alert = None;
+        }
+    }
+
+    fn observes_alerts(&self) -> bool {
+        true
+    }
+
     fn descriptor(&self) -> &AgentDescriptor<TLSDescriptorConfig> {
         &self.config.descriptor
     }
@@ -77,6 +114,7 @@ impl Put<TLSProtocolBehavior> for RustPut {
         self.deregister_claimer();
         self.stream.ssl_mut().clear();
         self.register_claimer();
+        self.record_alerts();
         Ok(())
     }
 
@@ -89,6 +127,14 @@ impl Put<TLSProtocolBehavior> for RustPut {
         self.stream.ssl().state_string_long().to_owned()
     }
 
+    fn pending_output_len(&self) -> usize {
+        self.stream.get_ref().pending_output_len()
+    }
+
+    fn drain_output(&mut self) -> Vec<u8> {
+        self.stream.get_mut().drain_output()
+    }
+
     fn is_state_successful(&self) -> bool {
         self.describe_state()
             .contains("SSL negotiation finished successfully")
@@ -117,23 +163,34 @@ impl RustPut {
     fn new_agent(config: TlsPutConfig) -> Result<Self, ErrorStack> {
         let agent_descriptor = &config.descriptor;
         let ssl = match agent_descriptor.protocol_config.typ {
-            AgentType::Server => Self::create_server(agent_descriptor)?,
-            AgentType::Client => Self::create_client(agent_descriptor)?,
+            AgentType::Server => Self::create_server(agent_descriptor, config.skip_cert_verify)?,
+            AgentType::Client => Self::create_client(agent_descriptor, config.skip_cert_verify)?,
         };
 
-        let stream = SslStream::new(ssl, MemoryStream::new())?;
+        let stream = SslStream::new(ssl, config.new_memory_stream())?;
         let mut boringssl = RustPut { config, stream };
 
         boringssl.register_claimer();
+        boringssl.record_alerts();
         Ok(boringssl)
     }
 
-    fn create_server(descriptor: &AgentDescriptor<TLSDescriptorConfig>) -> Result<Ssl, ErrorStack> {
+    fn create_server(
+        descriptor: &AgentDescriptor<TLSDescriptorConfig>,
+        skip_cert_verify: bool,
+    ) -> Result<Ssl, ErrorStack> {
         let mut ctx_builder = SslContext::builder(SslMethod::tls())?;
 
-        let (cert, key) = static_rsa_cert(ALICE_PRIVATE_KEY.0.as_bytes(), ALICE_CERT.0.as_bytes())?;
+        let (cert, key) = match &descriptor.protocol_config.server_certificate {
+            Some(server_certificate) => (
+                X509::from_pem(server_certificate.cert.as_bytes())?,
+                PKey::private_key_from_pem(server_certificate.key.as_bytes())?,
+            ),
+            None => static_rsa_cert(ALICE_PRIVATE_KEY.0.as_bytes(), ALICE_CERT.0.as_bytes())?,
+        };
         ctx_builder.set_certificate(&cert)?;
         ctx_builder.set_private_key(&key)?;
+        ctx_builder.check_private_key()?;
 
         if descriptor.protocol_config.client_authentication {
             let mut store = X509StoreBuilder::new()?;
@@ -141,7 +198,11 @@ impl RustPut {
             store.add_cert(X509::from_pem(EVE_CERT.0.as_bytes())?)?;
             let store = store.build();
 
-            ctx_builder.set_verify(SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT);
+            set_verify(
+                &mut ctx_builder,
+                SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT,
+                skip_cert_verify,
+            );
             ctx_builder.set_cert_store(store);
         } else {
             ctx_builder.set_verify(SslVerifyMode::NONE);
@@ -158,7 +219,10 @@ impl RustPut {
         Ok(ssl)
     }
 
-    fn create_client(descriptor: &AgentDescriptor<TLSDescriptorConfig>) -> Result<Ssl, ErrorStack> {
+    fn create_client(
+        descriptor: &AgentDescriptor<TLSDescriptorConfig>,
+        skip_cert_verify: bool,
+    ) -> Result<Ssl, ErrorStack> {
         let mut ctx_builder = SslContext::builder(SslMethod::tls())?;
         set_max_protocol_version(&mut ctx_builder, descriptor.protocol_config.tls_version)?;
 
@@ -174,7 +238,11 @@ impl RustPut {
         }
 
         if descriptor.protocol_config.server_authentication {
-            ctx_builder.set_verify(SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT);
+            set_verify(
+                &mut ctx_builder,
+                SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT,
+                skip_cert_verify,
+            );
 
             let mut store = X509StoreBuilder::new()?;
             store.add_cert(X509::from_pem(ALICE_CERT.0.as_bytes())?)?;
@@ -197,6 +265,14 @@ impl RustPut {
             .expect("Failed to set msg_callback to extract transcript");
     }
 
+    /// Records the alerts which BoringSSL sends through its info_callback, such that the last one
+    /// can be retrieved through [`Put::last_alert`]. Any alert recorded before is forgotten.
+    fn record_alerts(&mut self) {
+        let ssl = self.stream.ssl_mut();
+        ssl.set_ex_data(*LAST_ALERT, None);
+        unsafe { boringssl_sys::SSL_set_info_callback(ssl.as_ptr(), Some(boring_info_callback)) }
+    }
+
     fn deregister_claimer(&mut self) {
         // TODO implement deregister_claimer for BoringSSL
     }
@@ -310,6 +386,21 @@ pub unsafe extern "C" fn boring_msg_callback<F>(
     (*callback)(ssl, content_type);
 }
 
+/// This callback stores the alerts which BoringSSL sends in the ex_data of the `SSL`
+pub unsafe extern "C" fn boring_info_callback(ssl: *const ssl_st, type_: i32, value: i32) {
+    if type_ & SSL_CB_WRITE_ALERT != SSL_CB_WRITE_ALERT {
+        return;
+    }
+
+    let ssl = SslRef::from_ptr_mut(ssl as *mut ssl_st);
+    if let Some(last_alert) = ssl.ex_data_mut(*LAST_ALERT) {
+        *last_alert = Some((
+            AlertLevel::from((value >> 8) as u8),
+            AlertDescription::from(value as u8),
+        ));
+    }
+}
+
 impl<T> From<Result<T, boring::ssl::Error>> for MaybeError {
     fn from(result: Result<T, boring::ssl::Error>) -> Self {
         if let Err(ssl_error) = result {
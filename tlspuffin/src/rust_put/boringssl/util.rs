@@ -1,6 +1,6 @@
 use boring::error::ErrorStack;
 use boring::pkey::{PKey, Private};
-use boring::ssl::{SslContextBuilder, SslVersion};
+use boring::ssl::{SslContextBuilder, SslVerifyMode, SslVersion};
 use boring::x509::X509;
 
 use crate::protocol::TLSVersion;
@@ -30,3 +30,17 @@ pub fn set_max_protocol_version(
 
     Ok(())
 }
+
+/// Sets the verification `mode`, accepting any certificate of the peer if `skip_cert_verify` is
+/// set, see [`SKIP_CERT_VERIFY_OPTION`](crate::put::SKIP_CERT_VERIFY_OPTION)
+pub fn set_verify(
+    ctx_builder: &mut SslContextBuilder,
+    mode: SslVerifyMode,
+    skip_cert_verify: bool,
+) {
+    if skip_cert_verify {
+        ctx_builder.set_verify_callback(mode, |_preverify_ok, _store| true);
+    } else {
+        ctx_builder.set_verify(mode);
+    }
+}
@@ -1,16 +1,79 @@
+use std::ptr;
+
 use foreign_types_openssl::ForeignTypeRef;
-use libc::c_int;
-use openssl::ssl::SslRef;
-use openssl_sys::SSL;
+use libc::{c_int, c_long, c_void};
+use once_cell::sync::Lazy;
+use openssl::ex_data::Index;
+use openssl::ssl::{Ssl, SslContextRef, SslRef};
+use openssl_sys::{SSL, SSL_CTX};
+
+use crate::tls::rustls::msgs::enums::{AlertDescription, AlertLevel};
+
+const SSL_CB_WRITE_ALERT: c_int = 0x4008;
+const SSL_CTRL_GET_EXTMS_SUPPORT: c_int = 122;
 
 extern "C" {
     fn SSL_clear(ssl: *mut SSL) -> c_int;
+    fn SSL_CTX_flush_sessions(ctx: *mut SSL_CTX, tm: c_long);
+    fn SSL_ctrl(ssl: *mut SSL, cmd: c_int, larg: c_long, parg: *mut c_void) -> c_long;
+    fn SSL_set_info_callback(
+        ssl: *mut SSL,
+        cb: Option<unsafe extern "C" fn(ssl: *const SSL, type_: c_int, val: c_int)>,
+    );
+}
+
+/// Slot in the ex_data of an `SSL` for the last alert it sent
+static LAST_ALERT: Lazy<Index<Ssl, Option<(AlertLevel, AlertDescription)>>> =
+    Lazy::new(|| Ssl::new_ex_index().expect("Failed to allocate ex_data index for alerts"));
+
+unsafe extern "C" fn alert_info_callback(ssl: *const SSL, type_: c_int, val: c_int) {
+    if type_ & SSL_CB_WRITE_ALERT != SSL_CB_WRITE_ALERT {
+        return;
+    }
+
+    let ssl = SslRef::from_ptr_mut(ssl as *mut SSL);
+    if let Some(last_alert) = ssl.ex_data_mut(*LAST_ALERT) {
+        *last_alert = Some((
+            AlertLevel::from((val >> 8) as u8),
+            AlertDescription::from(val as u8),
+        ));
+    }
+}
+
+/// Records the alerts which `ssl` sends, such that the last one can be retrieved through
+/// [`last_alert`]. Any alert recorded before is forgotten.
+pub fn record_alerts(ssl: &mut SslRef) {
+    ssl.set_ex_data(*LAST_ALERT, None);
+    unsafe { SSL_set_info_callback(ssl.as_ptr(), Some(alert_info_callback)) }
+}
+
+/// Returns the last alert which `ssl` sent since [`record_alerts`] was called
+pub fn last_alert(ssl: &SslRef) -> Option<(AlertLevel, AlertDescription)> {
+    ssl.ex_data(*LAST_ALERT).copied().flatten()
+}
+
+/// Forgets the last alert which `ssl` sent, while its alerts are still recorded
+pub fn clear_last_alert(ssl: &mut SslRef) {
+    if let Some(last_alert) = ssl.ex_data_mut(*LAST_ALERT) {
+        *last_alert = None;
+    }
 }
 
 pub fn clear(ssl: &SslRef) -> u32 {
     unsafe { SSL_clear(ssl.as_ptr()) as u32 }
 }
 
+/// Removes all sessions from the session cache of `ctx`
+pub fn flush_sessions(ctx: &SslContextRef) {
+    unsafe { SSL_CTX_flush_sessions(ctx.as_ptr(), 0) }
+}
+
+/// Whether the session of `ssl` uses an extended master secret (RFC 7627). Libraries without
+/// support for extended master secrets do not know the control and always return `false`.
+pub fn extended_master_secret(ssl: &SslRef) -> bool {
+    unsafe { SSL_ctrl(ssl.as_ptr(), SSL_CTRL_GET_EXTMS_SUPPORT, 0, ptr::null_mut()) == 1 }
+}
+
 mod version_specific_bindings {
     #[cfg(all(
         any(feature = "openssl101-binding", feature = "openssl102-binding"),
@@ -44,4 +44,37 @@ mod tests {
 
         assert_ne!(bytes_seed1, bytes_seed2);
     }
+
+    #[test_log::test]
+    fn test_openssl_verify_determinism() {
+        crate::rust_put::rand::rng_init();
+        crate::rust_put::rand::rng_reseed();
+
+        let registry = crate::put_registry::tls_registry();
+        assert!(registry.default().verify_determinism());
+    }
+
+    #[test_log::test]
+    fn test_openssl_seeded_trace_is_reproducible() {
+        use puffin::execution::replay;
+        use puffin::trace_helper::TraceHelper;
+
+        use crate::tls::seeds::seed_successful12;
+
+        crate::rust_put::rand::rng_init();
+
+        let registry = crate::put_registry::tls_registry();
+        let runner = crate::test_utils::default_runner_for(registry.default_put_name());
+        let mut trace = seed_successful12.build_trace();
+
+        trace.seed = Some(789);
+        let seeded = replay(&runner, &trace, 2);
+        assert!(seeded.runs[0].error.is_none());
+        assert!(seeded.is_deterministic());
+
+        // the seed replaces the default seed of the runner
+        trace.seed = Some(987);
+        let reseeded = replay(&runner, &trace, 1);
+        assert_ne!(reseeded.runs[0].output_digest, seeded.runs[0].output_digest);
+    }
 }
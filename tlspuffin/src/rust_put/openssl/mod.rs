@@ -1,19 +1,23 @@
 use std::io::ErrorKind;
 
 use openssl::error::ErrorStack;
-use openssl::ssl::{Ssl, SslContext, SslContextRef, SslMethod, SslStream, SslVerifyMode};
+use openssl::pkey::PKey;
+use openssl::ssl::{
+    Ssl, SslContext, SslContextRef, SslMethod, SslRef, SslSession, SslStream, SslVerifyMode,
+};
 use openssl::x509::store::X509StoreBuilder;
-use openssl::x509::X509;
+use openssl::x509::{X509VerifyResult, X509};
 use puffin::agent::{AgentDescriptor, AgentName};
 use puffin::algebra::ConcreteMessage;
 use puffin::error::Error;
-use puffin::put::Put;
+use puffin::put::{Put, SessionData};
 use puffin::stream::{MemoryStream, Stream};
-use util::{set_max_protocol_version, static_rsa_cert};
+use util::{set_max_protocol_version, set_verify, static_rsa_cert, supported_extensions};
 
 use crate::protocol::{AgentType, OpaqueMessageFlight, TLSDescriptorConfig, TLSProtocolBehavior};
 use crate::put::TlsPutConfig;
 use crate::static_certs::{ALICE_CERT, ALICE_PRIVATE_KEY, BOB_CERT, BOB_PRIVATE_KEY, EVE_CERT};
+use crate::tls::rustls::msgs::enums::{AlertDescription, AlertLevel, ExtensionType};
 
 mod bindings;
 mod deterministic;
@@ -46,7 +50,7 @@ impl Stream<TLSProtocolBehavior> for RustPut {
 
 impl Put<TLSProtocolBehavior> for RustPut {
     fn progress(&mut self) -> Result<(), Error> {
-        let result = if self.is_state_successful() {
+        let result = if self.is_handshake_complete() {
             // Trigger another read
             let mut vec: Vec<u8> = Vec::from([1; 128]);
             let maybe_error: MaybeError = self.stream.ssl_read(&mut vec).into();
@@ -59,6 +63,16 @@ impl Put<TLSProtocolBehavior> for RustPut {
         result
     }
 
+    fn progress_one_flight(&mut self) -> Result<(), Error> {
+        // The handshake state machine only reads the next flight once it wrote its own. Holding
+        // back the reads until the written flight is taken stops it at this flight boundary.
+        self.stream.get_mut().hold_reads_while_output_pending(true);
+        let result = self.progress();
+        self.stream.get_mut().hold_reads_while_output_pending(false);
+
+        result
+    }
+
     fn reset(&mut self, new_name: AgentName) -> Result<(), Error> {
         self.config.descriptor.name = new_name;
 
@@ -66,6 +80,7 @@ impl Put<TLSProtocolBehavior> for RustPut {
 
         if self.config.use_clear {
             bindings::clear(self.stream.ssl());
+            bindings::record_alerts(self.stream.ssl_mut());
         } else {
             self.stream = Self::new_stream(&self.ctx, &self.config).map_err(|err| {
                 Error::Put(format!("OpenSSL error during stream creation: {}", err))
@@ -77,6 +92,86 @@ impl Put<TLSProtocolBehavior> for RustPut {
         Ok(())
     }
 
+    fn reset_soft(&mut self, new_name: AgentName) -> Result<bool, Error> {
+        self.config.descriptor.name = new_name;
+
+        self.deregister_claimer();
+
+        // Sessions of previous executions must not be resumable from the session cache. The keys
+        // of the session tickets are kept, as drawing new ones would change the random stream of
+        // the following handshakes.
+        bindings::flush_sessions(&self.ctx);
+        // A new stream clears all buffers and sequence numbers, the context is kept
+        self.stream = Self::new_stream(&self.ctx, &self.config)
+            .map_err(|err| Error::Put(format!("OpenSSL error during stream creation: {}", err)))?;
+
+        self.register_claimer();
+
+        Ok(true)
+    }
+
+    fn export_session(&self) -> Option<SessionData> {
+        let session = self.stream.ssl().session()?;
+        let bytes = session.to_der().ok()?;
+        #[allow(clippy::useless_conversion)]
+        let expires_at = i64::from(session.time()).checked_add(session.timeout());
+
+        Some(SessionData {
+            bytes,
+            expires_at: expires_at.and_then(|expires_at| u64::try_from(expires_at).ok()),
+        })
+    }
+
+    fn import_session(&mut self, session: &SessionData) -> Result<(), Error> {
+        if self.config.descriptor.protocol_config.typ != AgentType::Client {
+            return Err(Error::Put(
+                "Only clients can resume an imported session".to_string(),
+            ));
+        }
+
+        if session.is_expired() {
+            return Err(Error::Put("Imported session expired".to_string()));
+        }
+
+        let imported = SslSession::from_der(&session.bytes)
+            .map_err(|err| Error::Put(format!("Failed to parse imported session: {}", err)))?;
+
+        self.deregister_claimer();
+
+        // The session has to be set before the handshake starts, so the stream is created anew
+        let result = Self::create_client(&self.ctx)
+            .and_then(|mut ssl| {
+                // SAFETY: `set_session` requires the session to originate from an `SSL_CTX` of the
+                // same library and protocol method. `imported` was parsed by this OpenSSL build
+                // from a DER session which `export_session` of an OpenSSL client produced, and
+                // clients of all versions are created from `SslMethod::tls()`.
+                unsafe { ssl.set_session(&imported)? };
+                SslStream::new(ssl, self.config.new_memory_stream())
+            })
+            .map(|stream| self.stream = stream)
+            .map_err(|err| Error::Put(format!("OpenSSL error during stream creation: {}", err)));
+
+        self.register_claimer();
+
+        result
+    }
+
+    fn last_alert(&self) -> Option<(AlertLevel, AlertDescription)> {
+        bindings::last_alert(self.stream.ssl())
+    }
+
+    fn clear_last_alert(&mut self) {
+        bindings::clear_last_alert(self.stream.ssl_mut());
+    }
+
+    fn observes_alerts(&self) -> bool {
+        true
+    }
+
+    fn supported_extensions(&self) -> Vec<ExtensionType> {
+        supported_extensions(self.config.descriptor.protocol_config.tls_version)
+    }
+
     fn descriptor(&self) -> &AgentDescriptor<TLSDescriptorConfig> {
         &self.config.descriptor
     }
@@ -90,6 +185,14 @@ impl Put<TLSProtocolBehavior> for RustPut {
         self.stream.ssl().state_string_long().to_owned()
     }
 
+    fn pending_output_len(&self) -> usize {
+        self.stream.get_ref().pending_output_len()
+    }
+
+    fn drain_output(&mut self) -> Vec<u8> {
+        self.stream.get_mut().drain_output()
+    }
+
     fn is_state_successful(&self) -> bool {
         self.describe_state()
             .contains("SSL negotiation finished successfully")
@@ -114,8 +217,12 @@ impl RustPut {
         let agent_descriptor = &config.descriptor;
         #[allow(unused_mut)]
         let mut ctx = match agent_descriptor.protocol_config.typ {
-            AgentType::Server => Self::create_server_ctx(agent_descriptor)?,
-            AgentType::Client => Self::create_client_ctx(agent_descriptor)?,
+            AgentType::Server => {
+                Self::create_server_ctx(agent_descriptor, config.skip_cert_verify)?
+            }
+            AgentType::Client => {
+                Self::create_client_ctx(agent_descriptor, config.skip_cert_verify)?
+            }
         };
 
         let stream = Self::new_stream(&ctx, &config)?;
@@ -141,17 +248,25 @@ impl RustPut {
             AgentType::Client => Self::create_client(ctx)?,
         };
 
-        Ok(SslStream::new(ssl, MemoryStream::new())?)
+        Ok(SslStream::new(ssl, config.new_memory_stream())?)
     }
 
     fn create_server_ctx(
         descriptor: &AgentDescriptor<TLSDescriptorConfig>,
+        skip_cert_verify: bool,
     ) -> Result<SslContext, ErrorStack> {
         let mut ctx_builder = SslContext::builder(SslMethod::tls())?;
 
-        let (cert, key) = static_rsa_cert(ALICE_PRIVATE_KEY.0.as_bytes(), ALICE_CERT.0.as_bytes())?;
+        let (cert, key) = match &descriptor.protocol_config.server_certificate {
+            Some(server_certificate) => (
+                X509::from_pem(server_certificate.cert.as_bytes())?,
+                PKey::private_key_from_pem(server_certificate.key.as_bytes())?,
+            ),
+            None => static_rsa_cert(ALICE_PRIVATE_KEY.0.as_bytes(), ALICE_CERT.0.as_bytes())?,
+        };
         ctx_builder.set_certificate(&cert)?;
         ctx_builder.set_private_key(&key)?;
+        ctx_builder.check_private_key()?;
 
         if descriptor.protocol_config.client_authentication {
             let mut store = X509StoreBuilder::new()?;
@@ -159,7 +274,11 @@ impl RustPut {
             store.add_cert(X509::from_pem(EVE_CERT.0.as_bytes())?)?;
             let store = store.build();
 
-            ctx_builder.set_verify(SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT);
+            set_verify(
+                &mut ctx_builder,
+                SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT,
+                skip_cert_verify,
+            );
             ctx_builder.set_cert_store(store);
         } else {
             ctx_builder.set_verify(SslVerifyMode::NONE);
@@ -192,11 +311,13 @@ impl RustPut {
         let mut ssl = Ssl::new(&ctx)?;
 
         ssl.set_accept_state();
+        bindings::record_alerts(&mut ssl);
         Ok(ssl)
     }
 
     fn create_client_ctx(
         descriptor: &AgentDescriptor<TLSDescriptorConfig>,
+        skip_cert_verify: bool,
     ) -> Result<SslContext, ErrorStack> {
         let mut ctx_builder = SslContext::builder(SslMethod::tls())?;
         // Not sure whether we want this disabled or enabled: https://github.com/tlspuffin/tlspuffin/issues/67
@@ -220,7 +341,11 @@ impl RustPut {
         }
 
         if descriptor.protocol_config.server_authentication {
-            ctx_builder.set_verify(SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT);
+            set_verify(
+                &mut ctx_builder,
+                SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT,
+                skip_cert_verify,
+            );
 
             let mut store = X509StoreBuilder::new()?;
             store.add_cert(X509::from_pem(ALICE_CERT.0.as_bytes())?)?;
@@ -238,6 +363,7 @@ impl RustPut {
     pub fn create_client(ctx: &SslContextRef) -> Result<Ssl, ErrorStack> {
         let mut ssl: Ssl = Ssl::new(&ctx)?;
         ssl.set_connect_state();
+        bindings::record_alerts(&mut ssl);
 
         Ok(ssl)
     }
@@ -252,11 +378,30 @@ impl RustPut {
             let claims = self.config.claims.clone();
             let protocol_version = self.config.descriptor.protocol_config.tls_version;
             let origin = self.config.descriptor.protocol_config.typ;
+            let ssl = self.stream.ssl().as_ptr();
 
             security_claims::register_claimer(
-                self.stream.ssl().as_ptr().cast(),
-                move |claim: security_claims::Claim| {
-                    if let Some(data) = claims_helpers::to_claim_data(protocol_version, claim) {
+                ssl.cast(),
+                move |mut claim: security_claims::Claim| {
+                    // The claimer is deregistered before `ssl` is freed
+                    let ssl = SslRef::from_ptr(ssl);
+
+                    match claim.typ {
+                        security_claims::ClaimType::CLAIM_FINISHED => {
+                            claim.extended_master_secret_12 =
+                                bindings::extended_master_secret(ssl).into();
+                            claim.session_resumed_12 = ssl.session_reused().into();
+                        }
+                        // The signature is checked against the certificate of the peer, therefore
+                        // it is only accepted if the certificate verified
+                        security_claims::ClaimType::CLAIM_CERTIFICATE_VERIFY => {
+                            claim.certificate_verify_ok =
+                                (ssl.verify_result() == X509VerifyResult::OK).into();
+                        }
+                        _ => {}
+                    }
+
+                    for data in claims_helpers::to_claim_data(protocol_version, claim) {
                         claims
                             .deref_borrow_mut()
                             .claim_sized(crate::claims::TlsClaim {
@@ -1,9 +1,10 @@
 use openssl::error::ErrorStack;
 use openssl::pkey::{PKey, Private};
-use openssl::ssl::SslContextBuilder;
+use openssl::ssl::{SslContextBuilder, SslVerifyMode};
 use openssl::x509::X509;
 
 use crate::protocol::TLSVersion;
+use crate::tls::rustls::msgs::enums::ExtensionType;
 
 pub fn static_rsa_cert(key: &[u8], cert: &[u8]) -> Result<(X509, PKey<Private>), ErrorStack> {
     let rsa = openssl::rsa::Rsa::private_key_from_pem(key)?;
@@ -34,3 +35,87 @@ pub fn set_max_protocol_version(
 
     Ok(())
 }
+
+/// Extensions which the linked OpenSSL implements for agents of `tls_version`
+pub fn supported_extensions(tls_version: TLSVersion) -> Vec<ExtensionType> {
+    let mut extensions = vec![
+        ExtensionType::ServerName,
+        ExtensionType::StatusRequest,
+        ExtensionType::EllipticCurves,
+        ExtensionType::ECPointFormats,
+        ExtensionType::SignatureAlgorithms,
+        ExtensionType::ALProtocolNegotiation,
+        ExtensionType::SessionTicket,
+        ExtensionType::RenegotiationInfo,
+    ];
+    extensions.extend(binding_extensions(tls_version));
+    extensions
+}
+
+/// Heartbeats were removed in OpenSSL 1.1.0
+#[cfg(any(feature = "openssl101-binding", feature = "openssl102-binding"))]
+fn binding_extensions(_tls_version: TLSVersion) -> Vec<ExtensionType> {
+    vec![ExtensionType::Heartbeat]
+}
+
+#[cfg(feature = "openssl111-binding")]
+fn binding_extensions(tls_version: TLSVersion) -> Vec<ExtensionType> {
+    let mut extensions = vec![
+        ExtensionType::MaxFragmentLength,
+        ExtensionType::SCT,
+        ExtensionType::ExtendedMasterSecret,
+    ];
+
+    if tls_version == TLSVersion::V1_3 {
+        extensions.extend([
+            ExtensionType::SupportedVersions,
+            ExtensionType::KeyShare,
+            ExtensionType::PreSharedKey,
+            ExtensionType::PSKKeyExchangeModes,
+            ExtensionType::EarlyData,
+            ExtensionType::Cookie,
+            ExtensionType::CertificateAuthorities,
+            ExtensionType::PostHandshakeAuth,
+            ExtensionType::SignatureAlgorithmsCert,
+        ]);
+    }
+
+    extensions
+}
+
+/// LibreSSL implements TLS 1.3 without resumption and early data
+#[cfg(feature = "libressl333")]
+fn binding_extensions(tls_version: TLSVersion) -> Vec<ExtensionType> {
+    match tls_version {
+        TLSVersion::V1_3 => vec![
+            ExtensionType::SupportedVersions,
+            ExtensionType::KeyShare,
+            ExtensionType::Cookie,
+        ],
+        TLSVersion::V1_2 => vec![],
+    }
+}
+
+#[cfg(not(any(
+    feature = "openssl101-binding",
+    feature = "openssl102-binding",
+    feature = "openssl111-binding",
+    feature = "libressl333"
+)))]
+fn binding_extensions(_tls_version: TLSVersion) -> Vec<ExtensionType> {
+    vec![]
+}
+
+/// Sets the verification `mode`, accepting any certificate of the peer if `skip_cert_verify` is
+/// set, see [`SKIP_CERT_VERIFY_OPTION`](crate::put::SKIP_CERT_VERIFY_OPTION)
+pub fn set_verify(
+    ctx_builder: &mut SslContextBuilder,
+    mode: SslVerifyMode,
+    skip_cert_verify: bool,
+) {
+    if skip_cert_verify {
+        ctx_builder.set_verify_callback(mode, |_preverify_ok, _store| true);
+    } else {
+        ctx_builder.set_verify(mode);
+    }
+}
@@ -43,7 +43,7 @@ impl Factory<TLSProtocolBehavior> for RustFactory {
             agent_descriptor,
             claims,
             options,
-        ))?))
+        )?)?))
     }
 
     fn name(&self) -> String {
@@ -97,6 +97,11 @@ impl Factory<TLSProtocolBehavior> for RustFactory {
         crate::rust_put::rand::rng_reseed();
     }
 
+    fn rng_reseed_with(&self, seed: u64) {
+        log::debug!("[RNG] reseed with seed ({})", self.name());
+        crate::rust_put::rand::rng_reseed_with(&seed.to_le_bytes());
+    }
+
     fn clone_factory(&self) -> Box<dyn Factory<TLSProtocolBehavior>> {
         Box::new(self.clone())
     }
@@ -0,0 +1,48 @@
+#[cfg(test)]
+mod tests {
+    /// Random bytes of wolfSSL, e.g. for an ephemeral key
+    fn random_key() -> [u8; 32] {
+        let mut key = [0; 32];
+        let ret = unsafe { wolfssl_sys::wolfSSL_RAND_bytes(key.as_mut_ptr(), key.len() as i32) };
+        assert_eq!(ret, 1);
+        key
+    }
+
+    #[test_log::test]
+    fn test_wolfssl_no_randomness_simple() {
+        crate::rust_put::rand::rng_init();
+
+        crate::rust_put::rand::rng_reseed();
+        let key1 = random_key();
+
+        crate::rust_put::rand::rng_reseed();
+        let key2 = random_key();
+
+        assert_eq!(key1, key2);
+    }
+
+    #[test_log::test]
+    fn test_wolfssl_rng_reseed_with_different_seeds_are_different() {
+        const SEED1: [u8; 8] = 123u64.to_le().to_ne_bytes();
+        const SEED2: [u8; 8] = 321u64.to_le().to_ne_bytes();
+
+        crate::rust_put::rand::rng_init();
+
+        crate::rust_put::rand::rng_reseed_with(&SEED1);
+        let key1 = random_key();
+
+        crate::rust_put::rand::rng_reseed_with(&SEED2);
+        let key2 = random_key();
+
+        assert_ne!(key1, key2);
+    }
+
+    #[test_log::test]
+    fn test_wolfssl_verify_determinism() {
+        crate::rust_put::rand::rng_init();
+        crate::rust_put::rand::rng_reseed();
+
+        let registry = crate::put_registry::tls_registry();
+        assert!(registry.default().verify_determinism());
+    }
+}
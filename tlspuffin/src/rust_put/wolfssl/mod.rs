@@ -3,12 +3,12 @@
 use std::io::ErrorKind;
 use std::ops::Deref;
 
-use foreign_types::ForeignType;
+use foreign_types::{ForeignType, ForeignTypeRef};
 use puffin::agent::{AgentDescriptor, AgentName};
 use puffin::algebra::dynamic_function::TypeShape;
 use puffin::algebra::ConcreteMessage;
 use puffin::error::Error;
-use puffin::put::Put;
+use puffin::put::{Put, SessionData};
 use puffin::stream::{MemoryStream, Stream};
 use smallvec::SmallVec;
 use transcript::extract_current_transcript;
@@ -27,8 +27,10 @@ use crate::protocol::{
 };
 use crate::put::TlsPutConfig;
 use crate::static_certs::{ALICE_CERT, ALICE_PRIVATE_KEY, BOB_CERT, BOB_PRIVATE_KEY, EVE_CERT};
-use crate::tls::rustls::msgs::enums::HandshakeType;
+use crate::tls::rustls::msgs::enums::{AlertDescription, AlertLevel, HandshakeType};
 
+#[cfg(feature = "deterministic")]
+mod deterministic;
 mod transcript;
 
 pub struct WolfSSLErrorStack(pub ErrorStack);
@@ -78,8 +80,12 @@ impl RustPut {
         let agent_descriptor = &config.descriptor;
         #[allow(unused_mut)]
         let mut ctx = match agent_descriptor.protocol_config.typ {
-            AgentType::Server => Self::create_server_ctx(agent_descriptor)?,
-            AgentType::Client => Self::create_client_ctx(agent_descriptor)?,
+            AgentType::Server => {
+                Self::create_server_ctx(agent_descriptor, config.skip_cert_verify)?
+            }
+            AgentType::Client => {
+                Self::create_client_ctx(agent_descriptor, config.skip_cert_verify)?
+            }
         };
 
         let stream = Self::new_stream(&mut ctx, &config)?;
@@ -109,7 +115,7 @@ impl RustPut {
         };
 
         #[allow(unused_mut)]
-        let mut stream = SslStream::new(ssl, MemoryStream::new())?;
+        let mut stream = SslStream::new(ssl, config.new_memory_stream())?;
 
         #[cfg(feature = "wolfssl430")]
         stream
@@ -123,7 +129,7 @@ impl RustPut {
 
 impl Put<TLSProtocolBehavior> for RustPut {
     fn progress(&mut self) -> Result<(), Error> {
-        let result = if self.is_state_successful() {
+        let result = if self.is_handshake_complete() {
             // Trigger another read
             let mut vec: Vec<u8> = Vec::from([1; 128]);
             let maybe_error: MaybeError = self.stream.ssl_read(&mut vec).into();
@@ -133,11 +139,22 @@ impl Put<TLSProtocolBehavior> for RustPut {
             maybe_error.into()
         };
 
+        self.deferred_claims();
         self.deferred_transcript_extraction();
 
         result
     }
 
+    fn progress_one_flight(&mut self) -> Result<(), Error> {
+        // wolfSSL reads the next flight right after flushing its own one, so it stops at the
+        // boundary when its reads are held back
+        self.stream.get_mut().hold_reads_while_output_pending(true);
+        let result = self.progress();
+        self.stream.get_mut().hold_reads_while_output_pending(false);
+
+        result
+    }
+
     fn reset(&mut self, new_name: AgentName) -> Result<(), Error> {
         self.config.descriptor.name = new_name;
         self.deregister_claimer();
@@ -153,6 +170,41 @@ impl Put<TLSProtocolBehavior> for RustPut {
         Ok(())
     }
 
+    fn reset_soft(&mut self, new_name: AgentName) -> Result<bool, Error> {
+        self.config.descriptor.name = new_name;
+        self.deregister_claimer();
+
+        // The context is kept: its session cache is disabled and a new context would draw new
+        // keys for the session tickets from the random stream. A new stream clears all buffers
+        // and sequence numbers.
+        self.stream = Self::new_stream(&mut self.ctx, &self.config)?;
+
+        self.register_claimer();
+
+        Ok(true)
+    }
+
+    fn import_session(&mut self, _session: &SessionData) -> Result<(), Error> {
+        Err(Error::Put(
+            "wolfSSL PUT does not support importing sessions".to_string(),
+        ))
+    }
+
+    fn last_alert(&self) -> Option<(AlertLevel, AlertDescription)> {
+        self.stream
+            .ssl()
+            .last_sent_alert()
+            .map(|(level, description)| (level.into(), description.into()))
+    }
+
+    fn clear_last_alert(&mut self) {
+        self.stream.ssl_mut().clear_alert_history();
+    }
+
+    fn observes_alerts(&self) -> bool {
+        true
+    }
+
     fn describe_state(&self) -> String {
         // Very useful for nonblocking according to docs:
         // https://www.openssl.org/docs/manmaster/man3/SSL_state_string.html
@@ -162,6 +214,14 @@ impl Put<TLSProtocolBehavior> for RustPut {
         self.stream.state_string_long().to_owned()
     }
 
+    fn pending_output_len(&self) -> usize {
+        self.stream.get_ref().pending_output_len()
+    }
+
+    fn drain_output(&mut self) -> Vec<u8> {
+        self.stream.get_mut().drain_output()
+    }
+
     fn is_state_successful(&self) -> bool {
         self.stream.is_handshake_done()
     }
@@ -182,6 +242,7 @@ impl Put<TLSProtocolBehavior> for RustPut {
 impl RustPut {
     pub fn create_client_ctx(
         descriptor: &AgentDescriptor<TLSDescriptorConfig>,
+        skip_cert_verify: bool,
     ) -> Result<SslContext, WolfSSLErrorStack> {
         let mut ctx = match descriptor.protocol_config.tls_version {
             TLSVersion::V1_3 => SslContext::new(SslMethod::tls_client_13())?,
@@ -207,7 +268,12 @@ impl RustPut {
         }
 
         if descriptor.protocol_config.server_authentication {
-            ctx.set_verify(SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT);
+            let mode = SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT;
+            if skip_cert_verify {
+                ctx.set_verify_accept_any(mode);
+            } else {
+                ctx.set_verify(mode);
+            }
             ctx.load_verify_buffer(ALICE_CERT.0.as_bytes())?;
             ctx.load_verify_buffer(EVE_CERT.0.as_bytes())?;
         } else {
@@ -233,6 +299,7 @@ impl RustPut {
 
     pub fn create_server_ctx(
         descriptor: &AgentDescriptor<TLSDescriptorConfig>,
+        skip_cert_verify: bool,
     ) -> Result<SslContext, WolfSSLErrorStack> {
         let mut ctx = match descriptor.protocol_config.tls_version {
             TLSVersion::V1_3 => SslContext::new(SslMethod::tls_server_13())?,
@@ -246,22 +313,36 @@ impl RustPut {
         // Disallow EXPORT in server
         ctx.set_cipher_list(&descriptor.protocol_config.cipher_string)?;
 
-        let cert = X509::from_pem(ALICE_CERT.0.as_bytes())?;
+        let (cert, key) = match &descriptor.protocol_config.server_certificate {
+            Some(server_certificate) => (
+                server_certificate.cert.as_bytes(),
+                server_certificate.key.as_bytes(),
+            ),
+            None => (ALICE_CERT.0.as_bytes(), ALICE_PRIVATE_KEY.0.as_bytes()),
+        };
+
+        let cert = X509::from_pem(cert)?;
         ctx.set_certificate(cert.as_ref())?;
 
+        // Only RSA keys are supported
         #[cfg(not(feature = "wolfssl430"))]
         {
-            let rsa = wolfssl::rsa::Rsa::private_key_from_pem(ALICE_PRIVATE_KEY.0.as_bytes())?;
+            let rsa = wolfssl::rsa::Rsa::private_key_from_pem(key)?;
             let pkey = wolfssl::pkey::PKey::from_rsa(rsa)?;
             ctx.set_private_key(pkey.as_ref())?;
         }
         #[cfg(feature = "wolfssl430")]
         {
-            ctx.set_private_key_pem(ALICE_PRIVATE_KEY.0.as_bytes())?;
+            ctx.set_private_key_pem(key)?;
         }
 
         if descriptor.protocol_config.client_authentication {
-            ctx.set_verify(SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT);
+            let mode = SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT;
+            if skip_cert_verify {
+                ctx.set_verify_accept_any(mode);
+            } else {
+                ctx.set_verify(mode);
+            }
             ctx.load_verify_buffer(BOB_CERT.0.as_bytes())?;
             ctx.load_verify_buffer(EVE_CERT.0.as_bytes())?;
         } else {
@@ -282,6 +363,22 @@ impl RustPut {
         Ok(ssl)
     }
 
+    /// Claims the Finished of a TLS 1.3 handshake at the end of the message flight, when wolfSSL
+    /// derived the resumption master secret of the session
+    fn deferred_claims(&self) {
+        let config = &self.config;
+        if let Some(mut finished) = config.claim_deferred.deref().borrow_mut().take() {
+            finished.resumption_master_secret = self.stream.ssl().session_master_key().into();
+
+            config.claims.deref_borrow_mut().claim_sized(TlsClaim {
+                agent_name: config.descriptor.name,
+                origin: config.descriptor.protocol_config.typ,
+                protocol_version: config.descriptor.protocol_config.tls_version,
+                data: ClaimData::Message(ClaimDataMessage::Finished(finished)),
+            });
+        }
+    }
+
     fn deferred_transcript_extraction(&self) {
         let config = &self.config;
         if let Some(type_shape) = self.config.extract_deferred.deref().borrow_mut().take() {
@@ -329,11 +426,20 @@ impl RustPut {
             let claims = self.config.claims.clone();
             let protocol_version = self.config.descriptor.protocol_config.tls_version;
             let origin = self.config.descriptor.protocol_config.typ;
+            let ssl = self.stream.ssl().as_ptr();
 
             security_claims::register_claimer(
-                self.stream.ssl().as_ptr().cast(),
-                move |claim: security_claims::Claim| {
-                    if let Some(data) = claims_helpers::to_claim_data(protocol_version, claim) {
+                ssl.cast(),
+                move |mut claim: security_claims::Claim| {
+                    // The signature is checked against the certificate of the peer, therefore it is
+                    // only accepted if the certificate verified
+                    if claim.typ == security_claims::ClaimType::CLAIM_CERTIFICATE_VERIFY {
+                        // The claimer is deregistered before `ssl` is freed
+                        let ssl = SslRef::from_ptr(ssl);
+                        claim.certificate_verify_ok = (ssl.verify_result() == 0).into();
+                    }
+
+                    for data in claims_helpers::to_claim_data(protocol_version, claim) {
                         claims.deref_borrow_mut().claim_sized(TlsClaim {
                             agent_name,
                             origin,
@@ -360,6 +466,7 @@ impl RustPut {
         let protocol_version = config.descriptor.protocol_config.tls_version;
         let claims = config.claims.clone();
         let extract_transcript = config.extract_deferred.clone();
+        let claim_finished = config.claim_deferred.clone();
         let authenticate_peer = config.authenticate_peer;
 
         move |context: &mut SslRef, content_type: i32, first_byte: u8, outbound: bool| {
@@ -384,27 +491,39 @@ impl RustPut {
                             Some(TypeShape::of::<TranscriptServerFinished>());
                     }
                     HandshakeType::Finished => {
-                        claims.deref_borrow_mut().claim_sized(TlsClaim {
-                            agent_name,
-                            origin,
-                            protocol_version,
-                            data: ClaimData::Message(ClaimDataMessage::Finished(Finished {
-                                outbound,
-                                client_random: context.client_random().into(),
-                                server_random: context.server_random().into(),
-                                session_id: Default::default(), // TODO
-                                authenticate_peer,
-                                peer_certificate: context
-                                    .get_peer_certificate()
-                                    .map(|cert| SmallVec::from_vec(cert))
-                                    .unwrap_or_else(|| SmallVec::new()),
-                                master_secret: Default::default(), // TODO
-                                chosen_cipher: context.current_cipher() as u16,
-                                available_ciphers: Default::default(), // TODO
-                                signature_algorithm: 0,                // TODO
-                                peer_signature_algorithm: 0,           // TODO
-                            })),
-                        });
+                        let finished = Finished {
+                            outbound,
+                            client_random: context.client_random().into(),
+                            server_random: context.server_random().into(),
+                            session_id: Default::default(), // TODO
+                            authenticate_peer,
+                            peer_certificate: context
+                                .get_peer_certificate()
+                                .map(|cert| SmallVec::from_vec(cert))
+                                .unwrap_or_else(|| SmallVec::new()),
+                            master_secret: Default::default(), // TODO
+                            // derived after the Finished of the client, see `deferred_claims`
+                            resumption_master_secret: Default::default(),
+                            exporter_master_secret: Default::default(), // TODO
+                            extended_master_secret: false,              // TODO
+                            session_resumed: protocol_version == TLSVersion::V1_2
+                                && context.session_reused(),
+                            chosen_cipher: context.current_cipher() as u16,
+                            available_ciphers: Default::default(), // TODO
+                            signature_algorithm: 0,                // TODO
+                            peer_signature_algorithm: 0,           // TODO
+                        };
+
+                        if protocol_version == TLSVersion::V1_3 {
+                            *claim_finished.deref().borrow_mut() = Some(finished);
+                        } else {
+                            claims.deref_borrow_mut().claim_sized(TlsClaim {
+                                agent_name,
+                                origin,
+                                protocol_version,
+                                data: ClaimData::Message(ClaimDataMessage::Finished(finished)),
+                            });
+                        }
 
                         // Extract ClientHello..ClientFinished transcript
                         // at the end of the message flight
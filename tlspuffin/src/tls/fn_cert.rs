@@ -183,6 +183,11 @@ pub fn fn_rsa_pkcs1_signature_algorithm() -> Result<SignatureScheme, FnError> {
     Ok(SignatureScheme::RSA_PKCS1_SHA256)
 }
 
+/// Legacy scheme which TLS 1.3 forbids in `signature_algorithms`
+pub fn fn_rsa_pkcs1_sha1_signature_algorithm() -> Result<SignatureScheme, FnError> {
+    Ok(SignatureScheme::RSA_PKCS1_SHA1)
+}
+
 pub fn fn_invalid_signature_algorithm() -> Result<SignatureScheme, FnError> {
     Ok(SignatureScheme::Unknown(0x0100))
 }
@@ -7,6 +7,7 @@
 //! In the source code all IDs are available, but implementations are missing.
 
 use puffin::algebra::error::FnError;
+use puffin::codec::Codec;
 use webpki::DnsNameRef;
 
 use crate::nyi_fn;
@@ -39,6 +40,25 @@ pub fn fn_client_extensions_make(
     Ok(ClientExtensions(extensions.clone()))
 }
 
+/// Extensions block of a ClientHello whose 2-byte length prefix declares `declared_len` bytes.
+///
+/// The declared length is independent of the length of the encoded `extensions`, e.g. it may point
+/// past the end of the message. Use [`fn_client_hello_extensions_block`] to send the block.
+///
+/// [`fn_client_hello_extensions_block`]: crate::tls::fn_messages::fn_client_hello_extensions_block
+pub fn fn_extensions_block_with_length(
+    extensions: &ClientExtensions,
+    declared_len: &u16,
+) -> Result<Vec<u8>, FnError> {
+    let mut block = Vec::new();
+    declared_len.encode(&mut block);
+    for extension in &extensions.0 {
+        extension.encode(&mut block);
+    }
+
+    Ok(block)
+}
+
 pub fn fn_server_extensions_new() -> Result<Vec<ServerExtension>, FnError> {
     Ok(vec![])
 }
@@ -168,17 +188,11 @@ nyi_fn! {
     /// TruncatedHMAC => 0x0004,
 }
 /// StatusRequest => 0x0005,
-pub fn fn_status_request_extension(
-    responder_ids: &VecU16OfPayloadU16,
-    extensions: &PayloadU16,
-) -> Result<ClientExtension, FnError> {
-    // todo unclear where the arguments come from here, needs manual trace implementation
-    //      https://github.com/tlspuffin/tlspuffin/issues/155
+///
+/// Requests OCSP stapling without responder ids and request extensions.
+pub fn fn_status_request_extension() -> Result<ClientExtension, FnError> {
     Ok(ClientExtension::CertificateStatusRequest(
-        CertificateStatusRequest::OCSP(OCSPCertificateStatusRequest {
-            responder_ids: responder_ids.clone(),
-            extensions: extensions.clone(),
-        }),
+        CertificateStatusRequest::build_ocsp(),
     ))
 }
 pub fn fn_status_request_server_extension() -> Result<ServerExtension, FnError> {
@@ -210,11 +224,35 @@ nyi_fn! {
 pub fn fn_support_group_extension(group: &NamedGroup) -> Result<ClientExtension, FnError> {
     Ok(ClientExtension::NamedGroups(NamedGroups(vec![*group])))
 }
+/// Lists `groups` as they are, including duplicates and unknown groups. An empty list is
+/// malformed according to RFC 8422.
+pub fn fn_supported_groups_extension(groups: &Vec<NamedGroup>) -> Result<ClientExtension, FnError> {
+    Ok(ClientExtension::NamedGroups(NamedGroups(groups.clone())))
+}
+pub fn fn_named_groups_new() -> Result<Vec<NamedGroup>, FnError> {
+    Ok(vec![])
+}
+pub fn fn_named_groups_append(
+    groups: &Vec<NamedGroup>,
+    group: &NamedGroup,
+) -> Result<Vec<NamedGroup>, FnError> {
+    let mut new_groups = groups.clone();
+    new_groups.push(*group);
+    Ok(new_groups)
+}
 // ECPointFormats => 0x000b,
-pub fn fn_ec_point_formats_extension() -> Result<ClientExtension, FnError> {
-    Ok(ClientExtension::ECPointFormats(ECPointFormatList(vec![
-        ECPointFormat::Uncompressed,
-    ])))
+/// Lists `formats` as they are, including unknown formats. An empty list is prohibited by
+/// RFC 8422.
+pub fn fn_ec_point_formats_extension(formats: &Vec<u8>) -> Result<ClientExtension, FnError> {
+    Ok(ClientExtension::ECPointFormats(ECPointFormatList(
+        formats
+            .iter()
+            .map(|format| ECPointFormat::from(*format))
+            .collect(),
+    )))
+}
+pub fn fn_ec_point_formats_uncompressed() -> Result<Vec<u8>, FnError> {
+    Ok(vec![ECPointFormat::Uncompressed.get_u8()])
 }
 pub fn fn_ec_point_formats_server_extension() -> Result<ServerExtension, FnError> {
     Ok(ServerExtension::ECPointFormats(ECPointFormatList(vec![
@@ -233,6 +271,26 @@ pub fn fn_signature_algorithm_extension() -> Result<ClientExtension, FnError> {
         ]),
     ))
 }
+/// Lists `schemes` as they are, including duplicates, legacy and unknown schemes. An empty list is
+/// malformed according to RFC 8446 and leaves no scheme to negotiate.
+pub fn fn_signature_algorithms_extension(
+    schemes: &Vec<SignatureScheme>,
+) -> Result<ClientExtension, FnError> {
+    Ok(ClientExtension::SignatureAlgorithms(
+        SupportedSignatureSchemes(schemes.clone()),
+    ))
+}
+pub fn fn_signature_schemes_new() -> Result<Vec<SignatureScheme>, FnError> {
+    Ok(vec![])
+}
+pub fn fn_signature_schemes_append(
+    schemes: &Vec<SignatureScheme>,
+    scheme: &SignatureScheme,
+) -> Result<Vec<SignatureScheme>, FnError> {
+    let mut new_schemes = schemes.clone();
+    new_schemes.push(*scheme);
+    Ok(new_schemes)
+}
 pub fn fn_signature_algorithm_cert_req_extension() -> Result<CertReqExtension, FnError> {
     Ok(CertReqExtension::SignatureAlgorithms(
         SupportedSignatureSchemes(vec![
@@ -244,8 +302,20 @@ pub fn fn_signature_algorithm_cert_req_extension() -> Result<CertReqExtension, F
 nyi_fn! {
     /// UseSRTP => 0x000e,
 }
-nyi_fn! {
-    /// Heartbeat => 0x000f,
+/// Heartbeat => 0x000f,
+///
+/// The `mode` is not restricted to the values of [`HeartbeatMode`].
+pub fn fn_heartbeat_extension(mode: &u8) -> Result<ClientExtension, FnError> {
+    Ok(ClientExtension::Unknown(UnknownExtension {
+        typ: ExtensionType::Heartbeat,
+        payload: Payload::new(vec![*mode]),
+    }))
+}
+pub fn fn_heartbeat_mode_peer_allowed_to_send() -> Result<u8, FnError> {
+    Ok(HeartbeatMode::PeerAllowedToSend.get_u8())
+}
+pub fn fn_heartbeat_mode_peer_not_allowed_to_send() -> Result<u8, FnError> {
+    Ok(HeartbeatMode::PeerNotAllowedToSend.get_u8())
 }
 /// ALProtocolNegotiation => 0x0010,
 pub fn fn_empty_vec_of_vec() -> Result<Vec<Vec<u8>>, FnError> {
@@ -412,6 +482,29 @@ pub fn fn_preshared_keys_extension_empty_binder(
     )))
 }
 
+/// Pre-shared key extension which offers `identities` together with the `binders` of their PSKs.
+///
+/// The extension must be the last one of the ClientHello. As the binders are computed over the
+/// ClientHello up to the binders themselves, they are usually placeholders of the length of the
+/// hash which [`fn_fill_binder`](crate::tls::fn_impl::fn_fill_binder) replaces afterwards. The
+/// numbers of identities and binders are not checked, such that a mismatch yields a malformed
+/// offer.
+pub fn fn_pre_shared_key_extension(
+    identities: &Vec<PresharedKeyIdentity>,
+    binders: &Vec<Vec<u8>>,
+) -> Result<ClientExtension, FnError> {
+    Ok(ClientExtension::PresharedKey(PresharedKeyOffer {
+        identities: PresharedKeyIdentities(identities.clone()),
+        binders: VecU16OfPayloadU8(
+            binders
+                .iter()
+                .cloned()
+                .map(PresharedKeyBinder::new)
+                .collect(),
+        ),
+    }))
+}
+
 pub fn fn_preshared_keys_server_extension(identities: &u64) -> Result<ServerExtension, FnError> {
     Ok(ServerExtension::PresharedKey(*identities as u16))
 }
@@ -657,3 +750,194 @@ pub fn fn_unknown_certificate_extension() -> Result<CertificateExtension, FnErro
         payload: Payload::new([42; 7000]),
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tls::fn_impl::{
+        fn_client_hello, fn_fill_binder, fn_invalid_signature_algorithm, fn_named_group_grease,
+        fn_named_group_secp384r1, fn_named_group_x25519, fn_rsa_pkcs1_sha1_signature_algorithm,
+        fn_rsa_pss_signature_algorithm,
+    };
+
+    #[test_log::test]
+    fn test_supported_groups_extension() {
+        let mut groups = fn_named_groups_new().unwrap();
+        for group in [
+            fn_named_group_x25519(),
+            fn_named_group_grease(),
+            fn_named_group_secp384r1(),
+            fn_named_group_x25519(),
+        ] {
+            groups = fn_named_groups_append(&groups, &group.unwrap()).unwrap();
+        }
+
+        let extension = fn_supported_groups_extension(&groups).unwrap();
+
+        assert_eq!(
+            extension.get_encoding(),
+            vec![
+                0x00, 0x0a, 0x00, 0x0a, // extension header
+                0x00, 0x08, // list length
+                0x00, 0x1d, // x25519
+                0x0a, 0x0a, // GREASE
+                0x00, 0x18, // secp384r1
+                0x00, 0x1d, // x25519
+            ]
+        );
+    }
+
+    #[test_log::test]
+    fn test_empty_supported_groups_extension() {
+        let extension = fn_supported_groups_extension(&fn_named_groups_new().unwrap()).unwrap();
+
+        assert_eq!(
+            extension.get_encoding(),
+            vec![0x00, 0x0a, 0x00, 0x02, 0x00, 0x00]
+        );
+    }
+
+    #[test_log::test]
+    fn test_signature_algorithms_extension() {
+        let mut schemes = fn_signature_schemes_new().unwrap();
+        for scheme in [
+            fn_rsa_pss_signature_algorithm(),
+            fn_rsa_pkcs1_sha1_signature_algorithm(),
+            fn_invalid_signature_algorithm(),
+            fn_rsa_pss_signature_algorithm(),
+        ] {
+            schemes = fn_signature_schemes_append(&schemes, &scheme.unwrap()).unwrap();
+        }
+
+        let extension = fn_signature_algorithms_extension(&schemes).unwrap();
+
+        assert_eq!(
+            extension.get_encoding(),
+            vec![
+                0x00, 0x0d, 0x00, 0x0a, // extension header
+                0x00, 0x08, // list length
+                0x08, 0x04, // rsa_pss_rsae_sha256
+                0x02, 0x01, // rsa_pkcs1_sha1
+                0x01, 0x00, // unknown
+                0x08, 0x04, // rsa_pss_rsae_sha256
+            ]
+        );
+    }
+
+    #[test_log::test]
+    fn test_empty_signature_algorithms_extension() {
+        let extension =
+            fn_signature_algorithms_extension(&fn_signature_schemes_new().unwrap()).unwrap();
+
+        assert_eq!(
+            extension.get_encoding(),
+            vec![0x00, 0x0d, 0x00, 0x02, 0x00, 0x00]
+        );
+    }
+
+    #[test_log::test]
+    fn test_ec_point_formats_extension() {
+        let extension = fn_ec_point_formats_extension(&vec![
+            ECPointFormat::ANSIX962CompressedPrime.get_u8(),
+            0x42, // unknown format
+            fn_ec_point_formats_uncompressed().unwrap()[0],
+        ])
+        .unwrap();
+
+        assert_eq!(
+            extension.get_encoding(),
+            vec![
+                0x00, 0x0b, 0x00, 0x04, // extension header
+                0x03, // list length
+                0x01, // ansiX962_compressed_prime
+                0x42, // unknown
+                0x00, // uncompressed
+            ]
+        );
+    }
+
+    #[test_log::test]
+    fn test_heartbeat_extension() {
+        let extension =
+            fn_heartbeat_extension(&fn_heartbeat_mode_peer_allowed_to_send().unwrap()).unwrap();
+
+        assert_eq!(extension.get_encoding(), vec![0x00, 0x0f, 0x00, 0x01, 0x01]);
+    }
+
+    #[test_log::test]
+    fn test_empty_ec_point_formats_extension() {
+        let extension = fn_ec_point_formats_extension(&vec![]).unwrap();
+
+        assert_eq!(extension.get_encoding(), vec![0x00, 0x0b, 0x00, 0x01, 0x00]);
+    }
+
+    #[test_log::test]
+    fn test_extensions_block_with_length() {
+        let extensions = fn_client_extensions_make(&vec![fn_heartbeat_extension(
+            &fn_heartbeat_mode_peer_allowed_to_send().unwrap(),
+        )
+        .unwrap()])
+        .unwrap();
+
+        // declares more bytes than the block and the message contain
+        let block = fn_extensions_block_with_length(&extensions, &0x4000).unwrap();
+
+        assert_eq!(block, vec![0x40, 0x00, 0x00, 0x0f, 0x00, 0x01, 0x01]);
+    }
+
+    #[test_log::test]
+    fn test_pre_shared_key_extension() {
+        let identity = fn_new_preshared_key_identity(&PayloadU16::new(b"ab".to_vec())).unwrap();
+        let identities = fn_append_preshared_keys_identity(
+            &fn_empty_preshared_keys_identity_vec().unwrap(),
+            &identity,
+        )
+        .unwrap();
+
+        let extension =
+            fn_pre_shared_key_extension(&identities, &vec![vec![0x11, 0x22, 0x33]]).unwrap();
+
+        assert_eq!(
+            extension.get_encoding(),
+            vec![
+                0x00, 0x29, 0x00, 0x10, // extension header
+                0x00, 0x08, // identities length
+                0x00, 0x02, b'a', b'b', // identity
+                0x00, 0x00, 0x00, 0x0a, // obfuscated ticket age
+                0x00, 0x04, // binders length
+                0x03, 0x11, 0x22, 0x33, // binder
+            ]
+        );
+    }
+
+    #[test_log::test]
+    fn test_pre_shared_key_extension_without_binders() {
+        let identity = fn_new_preshared_key_identity(&PayloadU16::new(b"ab".to_vec())).unwrap();
+        let extension = fn_pre_shared_key_extension(&vec![identity], &vec![]).unwrap();
+
+        assert_eq!(
+            extension.get_encoding(),
+            vec![
+                0x00, 0x29, 0x00, 0x0c, // extension header
+                0x00, 0x08, // identities length
+                0x00, 0x02, b'a', b'b', // identity
+                0x00, 0x00, 0x00, 0x0a, // obfuscated ticket age
+                0x00, 0x00, // binders length
+            ]
+        );
+
+        // There is no binder to fill in the malformed offer
+        let client_hello = fn_client_hello(
+            &ProtocolVersion::TLSv1_2,
+            &Random::from([1; 32]),
+            &SessionID::empty(),
+            &CipherSuites(vec![CipherSuite::TLS13_AES_128_GCM_SHA256]),
+            &Compressions(vec![Compression::Null]),
+            &ClientExtensions(vec![extension]),
+        )
+        .unwrap();
+        let filled = fn_fill_binder(&client_hello, &vec![0x11; 32]).unwrap();
+
+        assert_eq!(filled.get_encoding(), client_hello.get_encoding());
+    }
+}
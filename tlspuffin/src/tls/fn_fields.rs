@@ -185,7 +185,27 @@ pub fn fn_sign_transcript(
     transcript: &HandshakeHash,
     group: &NamedGroup,
 ) -> Result<Vec<u8>, FnError> {
-    let secrets = tls12_new_secrets(server_random, server_ecdh_pubkey, group)?;
+    let secrets = tls12_new_secrets(server_random, server_ecdh_pubkey, group, None)?;
+
+    let vh = transcript.get_current_hash();
+    Ok(secrets.client_verify_data(&vh))
+}
+
+/// Like [`fn_sign_transcript`], but derives the master secret from `session_hash` if it is some,
+/// as with the `extended_master_secret` extension (RFC 7627).
+pub fn fn_sign_transcript_ems(
+    server_random: &Random,
+    server_ecdh_pubkey: &Vec<u8>,
+    transcript: &HandshakeHash,
+    group: &NamedGroup,
+    session_hash: &Option<Vec<u8>>,
+) -> Result<Vec<u8>, FnError> {
+    let secrets = tls12_new_secrets(
+        server_random,
+        server_ecdh_pubkey,
+        group,
+        session_hash.as_deref(),
+    )?;
 
     let vh = transcript.get_current_hash();
     Ok(secrets.client_verify_data(&vh))
@@ -224,6 +244,10 @@ pub fn fn_cipher_suite12() -> Result<CipherSuite, FnError> {
     )
 }
 
+pub fn fn_cipher_suite12_aes_256_gcm_sha384() -> Result<CipherSuite, FnError> {
+    Ok(CipherSuite::TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384)
+}
+
 pub fn fn_cipher_suite13_aes_128_gcm_sha256() -> Result<CipherSuite, FnError> {
     Ok(CipherSuite::TLS13_AES_128_GCM_SHA256)
 }
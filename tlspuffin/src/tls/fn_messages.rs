@@ -8,6 +8,7 @@
 //! Return type is `Message`
 
 use puffin::algebra::error::FnError;
+use puffin::codec::Codec;
 
 use crate::nyi_fn;
 use crate::tls::rustls::key;
@@ -63,6 +64,18 @@ pub fn fn_application_data(data: &Vec<u8>) -> Result<Message, FnError> {
     })
 }
 
+/// ApplicationData without any data.
+///
+/// Once encrypted, e.g. with
+/// [`fn_encrypt_application`](crate::tls::fn_impl::fn_encrypt_application), the plaintext of the
+/// record consists of the content type only, which is legal but often mishandled.
+pub fn fn_empty_application_data() -> Result<Message, FnError> {
+    Ok(Message {
+        version: ProtocolVersion::TLSv1_2,
+        payload: MessagePayload::ApplicationData(Payload::empty()),
+    })
+}
+
 // ----
 // Heartbeats Message constructors
 // https://www.iana.org/assignments/tls-parameters/tls-parameters.xhtml#heartbeat-message-types
@@ -118,6 +131,32 @@ pub fn fn_heartbeat(payload: &PayloadU16) -> Result<Message, FnError> {
     fn_heartbeat_fake_length(payload, &(payload.0.len() as u64))
 }
 
+/// Heartbeat request which declares `declared_len` bytes of payload.
+///
+/// The declared length is independent of the actual length of `payload`. A declared length beyond
+/// the payload makes the peer over-read if it does not validate the length, as with Heartbleed.
+pub fn fn_heartbeat_request(payload: &Vec<u8>, declared_len: &u16) -> Result<Message, FnError> {
+    Ok(Message {
+        version: ProtocolVersion::TLSv1_2,
+        payload: MessagePayload::Heartbeat(HeartbeatPayload {
+            typ: HeartbeatMessageType::Request,
+            payload: PayloadU16::new(payload.clone()),
+            fake_length: Some(*declared_len),
+        }),
+    })
+}
+
+/// Actual length of a heartbeat `payload`
+pub fn fn_heartbeat_payload_length(payload: &Vec<u8>) -> Result<u16, FnError> {
+    u16::try_from(payload.len())
+        .map_err(|_| FnError::Malformed("Heartbeat payload exceeds 2^16-1 bytes".to_owned()))
+}
+
+/// Maximum length of a plaintext record
+pub fn fn_heartbeat_large_length() -> Result<u16, FnError> {
+    Ok(0x4000)
+}
+
 // ----
 // Handshake Message constructors
 // ----
@@ -156,6 +195,36 @@ pub fn fn_client_hello(
         }),
     })
 }
+
+/// ClientHello whose extensions are the raw `extensions_block`, including its length prefix.
+///
+/// This allows to send an extensions block whose length prefix disagrees with its content, see
+/// [`fn_extensions_block_with_length`](crate::tls::fn_extensions::fn_extensions_block_with_length).
+pub fn fn_client_hello_extensions_block(
+    client_version: &ProtocolVersion,
+    random: &Random,
+    session_id: &SessionID,
+    cipher_suites: &CipherSuites,
+    compression_methods: &Compressions,
+    extensions_block: &Vec<u8>,
+) -> Result<Message, FnError> {
+    let mut body = Vec::new();
+    client_version.encode(&mut body);
+    random.encode(&mut body);
+    session_id.encode(&mut body);
+    cipher_suites.encode(&mut body);
+    compression_methods.encode(&mut body);
+    body.extend_from_slice(extensions_block);
+
+    Ok(Message {
+        version: ProtocolVersion::TLSv1_2,
+        payload: MessagePayload::Handshake(HandshakeMessagePayload {
+            typ: HandshakeType::ClientHello,
+            payload: HandshakePayload::Unknown(Payload::new(body)),
+        }),
+    })
+}
+
 /// ServerHello => 0x02,
 pub fn fn_server_hello(
     legacy_version: &ProtocolVersion,
@@ -418,9 +487,12 @@ pub fn fn_make_payload_u8_vec_u16(vec: &Vec<PayloadU8>) -> Result<VecU16OfPayloa
 }
 
 /// CertificateVerify => 0x0f,
+///
+/// The `scheme` is not checked against the `signature`, which allows to send valid signatures with
+/// a wrong scheme id or signatures over a wrong transcript.
 pub fn fn_certificate_verify(
     scheme: &SignatureScheme,
-    signature: &PayloadU16,
+    signature: &Vec<u8>,
 ) -> Result<Message, FnError> {
     // todo unclear where the arguments come from here, needs manual trace implementation
     //      https://github.com/tlspuffin/tlspuffin/issues/155
@@ -430,7 +502,7 @@ pub fn fn_certificate_verify(
             typ: HandshakeType::CertificateVerify,
             payload: HandshakePayload::CertificateVerify(DigitallySignedStruct {
                 scheme: *scheme,
-                sig: signature.clone(),
+                sig: PayloadU16::new(signature.clone()),
             }),
         }),
     })
@@ -459,14 +531,16 @@ nyi_fn! {
     /// CertificateURL => 0x15,
 }
 /// CertificateStatus => 0x16,
-pub fn fn_certificate_status(ocsp_response: &PayloadU24) -> Result<Message, FnError> {
+///
+/// The stapled OCSP response may be empty, and may be sent without a prior status_request.
+pub fn fn_certificate_status(ocsp_response: &Vec<u8>) -> Result<Message, FnError> {
     Ok(Message {
         version: ProtocolVersion::TLSv1_2,
         payload: MessagePayload::Handshake(HandshakeMessagePayload {
             typ: HandshakeType::CertificateStatus,
-            payload: HandshakePayload::CertificateStatus(CertificateStatus {
-                ocsp_response: ocsp_response.clone(),
-            }),
+            payload: HandshakePayload::CertificateStatus(CertificateStatus::new(
+                ocsp_response.clone(),
+            )),
         }),
     })
 }
@@ -489,6 +563,51 @@ pub fn fn_key_update_not_requested() -> Result<Message, FnError> {
         }),
     })
 }
+/// KeyUpdate which does not end at a record boundary, because the handshake message `next`
+/// follows it in the same record. Peers have to reject this (RFC 8446, Section 5.1).
+pub fn fn_key_update_coalesced(next: &Message) -> Result<Message, FnError> {
+    let MessagePayload::Handshake(next) = &next.payload else {
+        return Err(FnError::Malformed(
+            "Only handshake messages can follow a KeyUpdate in a record".to_owned(),
+        ));
+    };
+
+    let key_update = HandshakeMessagePayload {
+        typ: HandshakeType::KeyUpdate,
+        payload: HandshakePayload::KeyUpdate(KeyUpdateRequest::UpdateRequested),
+    };
+
+    Ok(Message {
+        version: ProtocolVersion::TLSv1_2,
+        payload: MessagePayload::CoalescedHandshake(vec![key_update, next.clone()]),
+    })
+}
+/// Handshake message with the type byte `msg_type` and the raw `body`, bypassing the typed
+/// constructors above.
+///
+/// This allows to send messages of unknown types, or messages which are unexpected in the current
+/// state of the handshake, e.g. a Finished before the ServerHello.
+pub fn fn_handshake_raw(msg_type: &u8, body: &Vec<u8>) -> Result<Message, FnError> {
+    if body.len() > 0xff_ffff {
+        return Err(FnError::Malformed(
+            "Handshake message body does not fit into a u24 length".to_owned(),
+        ));
+    }
+
+    // HelloRetryRequest is encoded with the ServerHello type, so its type byte is kept as unknown
+    let typ = match HandshakeType::from(*msg_type) {
+        HandshakeType::HelloRetryRequest => HandshakeType::Unknown(*msg_type),
+        typ => typ,
+    };
+
+    Ok(Message {
+        version: ProtocolVersion::TLSv1_2,
+        payload: MessagePayload::Handshake(HandshakeMessagePayload {
+            typ,
+            payload: HandshakePayload::Unknown(Payload::new(body.clone())),
+        }),
+    })
+}
 nyi_fn! {
     /// compressed_certificate => 0x019,
 }
@@ -507,3 +626,226 @@ pub fn fn_message_hash(hash: &Vec<u8>) -> Result<Message, FnError> {
         }),
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use puffin::codec::Codec;
+    use puffin::protocol::Extractable;
+    use puffin::trace::Source;
+
+    use super::*;
+    use crate::tls::fn_impl::{fn_rsa_pss_signature_algorithm, fn_status_request_extension};
+    use crate::tls::rustls::msgs::message::PlainMessage;
+
+    fn encode_message(message: Message) -> Vec<u8> {
+        PlainMessage::from(message)
+            .into_unencrypted_opaque()
+            .get_encoding()
+    }
+
+    #[test_log::test]
+    fn test_status_request_extension() {
+        let extension = fn_status_request_extension().unwrap();
+
+        // type, length, OCSP, empty responder ids, empty extensions
+        assert_eq!(
+            extension.get_encoding(),
+            vec![0x00, 0x05, 0x00, 0x05, 0x01, 0x00, 0x00, 0x00, 0x00]
+        );
+    }
+
+    #[test_log::test]
+    fn test_certificate_status() {
+        let message = fn_certificate_status(&vec![0xaa, 0xbb]).unwrap();
+
+        assert_eq!(
+            encode_message(message.clone()),
+            vec![
+                0x16, 0x03, 0x03, 0x00, 0x0a, // record header
+                0x16, 0x00, 0x00, 0x06, // handshake header
+                0x01, 0x00, 0x00, 0x02, 0xaa, 0xbb, // OCSP response
+            ]
+        );
+
+        let source = Source::Agent(puffin::agent::AgentName::first());
+        let mut knowledges = vec![];
+        message
+            .extract_knowledge(&mut knowledges, None, &source)
+            .unwrap();
+        assert!(knowledges.iter().any(|knowledge| knowledge
+            .data
+            .as_any()
+            .downcast_ref::<Vec<u8>>()
+            .map_or(false, |ocsp| *ocsp == vec![0xaa, 0xbb])));
+    }
+
+    #[test_log::test]
+    fn test_client_hello_knowledge() {
+        let cipher_suites = vec![
+            CipherSuite::TLS13_AES_128_GCM_SHA256,
+            CipherSuite::TLS13_CHACHA20_POLY1305_SHA256,
+        ];
+        let client_hello = |key_share: Vec<u8>| {
+            let message = fn_client_hello(
+                &ProtocolVersion::TLSv1_2,
+                &Random::from([1; 32]),
+                &SessionID::empty(),
+                &CipherSuites(cipher_suites.clone()),
+                &Compressions(vec![Compression::Null]),
+                &ClientExtensions(vec![
+                    ClientExtension::ExtendedMasterSecretRequest,
+                    ClientExtension::Unknown(UnknownExtension {
+                        typ: ExtensionType::KeyShare,
+                        payload: Payload::new(key_share),
+                    }),
+                ]),
+            )
+            .unwrap();
+            // the message as received by the server
+            let opaque = OpaqueMessage::read_bytes(&encode_message(message)).unwrap();
+            Message::try_from(opaque).unwrap()
+        };
+
+        // a well-formed and a malformed key share
+        for key_share in [vec![0x00, 0x00], vec![0x00, 0x05, 0x00]] {
+            let message = client_hello(key_share);
+
+            let source = Source::Agent(puffin::agent::AgentName::first());
+            let mut knowledges = vec![];
+            message
+                .extract_knowledge(&mut knowledges, None, &source)
+                .unwrap();
+
+            assert!(knowledges.iter().any(|knowledge| knowledge
+                .data
+                .as_any()
+                .downcast_ref::<Vec<CipherSuite>>()
+                .map_or(false, |suites| *suites == cipher_suites)));
+            assert!(knowledges.iter().any(|knowledge| knowledge
+                .data
+                .as_any()
+                .downcast_ref::<Random>()
+                .map_or(false, |random| random.0 == [1; 32])));
+            assert!(knowledges.iter().any(|knowledge| matches!(
+                knowledge.data.as_any().downcast_ref::<ClientExtension>(),
+                Some(ClientExtension::ExtendedMasterSecretRequest)
+            )));
+        }
+    }
+
+    #[test_log::test]
+    fn test_partial_client_hello_round_trip() {
+        let message = fn_client_hello(
+            &ProtocolVersion::TLSv1_2,
+            &Random::from([1; 32]),
+            &SessionID::empty(),
+            &CipherSuites(vec![CipherSuite::TLS13_AES_128_GCM_SHA256]),
+            &Compressions(vec![Compression::Null]),
+            &ClientExtensions(vec![ClientExtension::ExtendedMasterSecretRequest]),
+        )
+        .unwrap();
+        let MessagePayload::Handshake(handshake) = message.payload else {
+            panic!("not a handshake message");
+        };
+
+        // the extension block is followed by a truncated extension
+        let mut body = handshake.payload.get_encoding();
+        body.extend_from_slice(&[0x00, 0x17, 0x00]);
+        let mut bytes = vec![];
+        HandshakeType::ClientHello.encode(&mut bytes);
+        puffin::codec::u24(body.len() as u32).encode(&mut bytes);
+        bytes.extend_from_slice(&body);
+
+        assert!(HandshakeMessagePayload::read_bytes(&bytes).is_none());
+        let partial = HandshakeMessagePayload::read_partial_client_hello(&bytes).unwrap();
+        let HandshakePayload::PartialClientHello(client_hello, _) = &partial.payload else {
+            panic!("not a partial ClientHello");
+        };
+        assert_eq!(
+            client_hello.extensions.0.len(),
+            1,
+            "the extension before the truncated one is read"
+        );
+        assert_eq!(partial.get_encoding(), bytes);
+    }
+
+    #[test_log::test]
+    fn test_certificate_verify() {
+        let signature = vec![0xaa; 256];
+        // A PKCS#1 signature which announces the PSS scheme
+        let message =
+            fn_certificate_verify(&fn_rsa_pss_signature_algorithm().unwrap(), &signature).unwrap();
+
+        let encoding = encode_message(message);
+        assert_eq!(
+            encoding[..13],
+            [
+                0x16, 0x03, 0x03, 0x01, 0x08, // record header
+                0x0f, 0x00, 0x01, 0x04, // handshake header
+                0x08, 0x04, // rsa_pss_rsae_sha256
+                0x01, 0x00, // signature length
+            ]
+        );
+        assert_eq!(encoding[13..], signature);
+    }
+
+    #[test_log::test]
+    fn test_key_update_coalesced() {
+        let message = fn_key_update_coalesced(&fn_key_update_not_requested().unwrap()).unwrap();
+
+        assert_eq!(
+            encode_message(message),
+            vec![
+                0x16, 0x03, 0x03, 0x00, 0x0a, // record header
+                0x18, 0x00, 0x00, 0x01, 0x01, // KeyUpdate, update requested
+                0x18, 0x00, 0x00, 0x01, 0x00, // KeyUpdate, update not requested
+            ]
+        );
+
+        assert!(fn_key_update_coalesced(&fn_application_data(&vec![0xaa]).unwrap()).is_err());
+    }
+
+    #[test_log::test]
+    fn test_handshake_raw_unknown_type() {
+        let message = fn_handshake_raw(&0xfe, &vec![0xaa, 0xbb]).unwrap();
+
+        assert_eq!(
+            encode_message(message),
+            vec![
+                0x16, 0x03, 0x03, 0x00, 0x06, // record header
+                0xfe, 0x00, 0x00, 0x02, // handshake header
+                0xaa, 0xbb, // body
+            ]
+        );
+
+        // the type byte is kept as is, even for types with a typed constructor
+        let message = fn_handshake_raw(&0x06, &vec![]).unwrap();
+        assert_eq!(encode_message(message)[5..], [0x06, 0x00, 0x00, 0x00]);
+    }
+
+    #[test_log::test]
+    fn test_heartbeat_request_beyond_payload() {
+        let message =
+            fn_heartbeat_request(&vec![0xaa, 0xbb], &fn_heartbeat_large_length().unwrap()).unwrap();
+
+        assert_eq!(
+            encode_message(message),
+            vec![
+                0x18, 0x03, 0x03, 0x00, 0x05, // record header
+                0x01, // request
+                0x40, 0x00, // declared length
+                0xaa, 0xbb, // payload
+            ]
+        );
+    }
+
+    #[test_log::test]
+    fn test_empty_certificate_status() {
+        let message = fn_certificate_status(&vec![]).unwrap();
+
+        assert_eq!(
+            encode_message(message),
+            vec![0x16, 0x03, 0x03, 0x00, 0x08, 0x16, 0x00, 0x00, 0x04, 0x01, 0x00, 0x00, 0x00]
+        );
+    }
+}
@@ -3,23 +3,35 @@
 
 use puffin::algebra::error::FnError;
 use puffin::codec::{Codec, Reader};
-use puffin::protocol::{OpaqueProtocolMessageFlight, ProtocolMessageFlight};
+use puffin::protocol::{record_decryption, OpaqueProtocolMessageFlight, ProtocolMessageFlight};
+use puffin::put::receiving_put_options;
+use ring::aead::MAX_TAG_LEN;
+use ring::hkdf;
 
+use crate::claims::Finished;
 use crate::protocol::{MessageFlight, OpaqueMessageFlight};
 use crate::tls::key_exchange::{tls12_key_exchange, tls12_new_secrets};
 use crate::tls::key_schedule::*;
+use crate::tls::rustls::cipher::MessageEncrypter;
 use crate::tls::rustls::conn::Side;
 use crate::tls::rustls::hash_hs::HandshakeHash;
 use crate::tls::rustls::key::Certificate;
-use crate::tls::rustls::msgs::base::PayloadU8;
-use crate::tls::rustls::msgs::enums::{HandshakeType, NamedGroup};
+use crate::tls::rustls::msgs::base::{Payload, PayloadU8};
+use crate::tls::rustls::msgs::enums::{
+    CipherSuite, ContentType, ExtensionType, HandshakeType, NamedGroup, ProtocolVersion,
+};
 use crate::tls::rustls::msgs::handshake::{
     CertificateEntries, CertificateEntry, CertificateExtension, CertificateExtensions,
-    HandshakeMessagePayload, HandshakePayload, Random, ServerECDHParams,
+    ClientExtension, ClientExtensions, HandshakeMessagePayload, HandshakePayload, Random,
+    ServerECDHParams, ServerExtensions, UnknownExtension,
 };
 use crate::tls::rustls::msgs::message::{Message, MessagePayload, OpaqueMessage, PlainMessage};
+use crate::tls::rustls::suites::{SupportedCipherSuite, ALL_CIPHER_SUITES};
 use crate::tls::rustls::tls12;
-use crate::tls::rustls::tls13::key_schedule::KeyScheduleEarly;
+use crate::tls::rustls::tls12::ConnectionSecrets;
+use crate::tls::rustls::tls13::key_schedule::{
+    export_keying_material, hkdf_expand, KeyScheduleEarly, PayloadU8Len,
+};
 
 // ----
 // seed_client_attacker()
@@ -64,6 +76,78 @@ pub fn fn_append_opaque_flight(
     Ok(new_flight)
 }
 
+/// Bundle all records of a flight into a single handshake record, like servers do when they
+/// coalesce a flight.
+///
+/// The payloads are concatenated as they are. This allows to also express bundles which mix
+/// encrypted and plaintext handshake messages, which is illegal.
+pub fn fn_concat_handshake(flight: &OpaqueMessageFlight) -> Result<OpaqueMessage, FnError> {
+    let version = flight
+        .messages
+        .first()
+        .map_or(ProtocolVersion::TLSv1_2, |msg| msg.version);
+
+    let payload = flight
+        .messages
+        .iter()
+        .flat_map(|msg| msg.payload.0.iter().copied())
+        .collect::<Vec<u8>>();
+
+    Ok(OpaqueMessage {
+        typ: ContentType::Handshake,
+        version,
+        payload: Payload::new(payload),
+    })
+}
+
+/// Change the outer content type of an already formed record, for example to label handshake bytes
+/// as application data.
+///
+/// The record is not re-encrypted. For encrypted TLS 1.3 records, the true content type inside the
+/// plaintext therefore stays unchanged.
+pub fn fn_retype_record(record: &OpaqueMessage, new_type: &u8) -> Result<OpaqueMessage, FnError> {
+    Ok(OpaqueMessage {
+        typ: ContentType::from(*new_type),
+        ..record.clone()
+    })
+}
+
+pub fn fn_content_type_handshake() -> Result<u8, FnError> {
+    Ok(ContentType::Handshake.get_u8())
+}
+
+pub fn fn_content_type_application_data() -> Result<u8, FnError> {
+    Ok(ContentType::ApplicationData.get_u8())
+}
+
+/// Keep only the first `keep` bytes of the AEAD tag of an encrypted record, such that the record
+/// fails authentication.
+///
+/// The tag is assumed to be the last [`MAX_TAG_LEN`] bytes of the payload, which holds for the
+/// AES-GCM and ChaCha20-Poly1305 cipher suites of TLS 1.2 and TLS 1.3. If `keep` is at least the
+/// tag length, or the payload is shorter than a tag, the record is returned unchanged.
+pub fn fn_truncate_tag(record: &OpaqueMessage, keep: &u8) -> Result<OpaqueMessage, FnError> {
+    let keep = usize::from(*keep);
+    let payload = &record.payload.0;
+
+    if keep >= MAX_TAG_LEN || payload.len() < MAX_TAG_LEN {
+        return Ok(record.clone());
+    }
+
+    Ok(OpaqueMessage {
+        payload: Payload::new(&payload[..payload.len() - MAX_TAG_LEN + keep]),
+        ..record.clone()
+    })
+}
+
+pub fn fn_tag_keep_none() -> Result<u8, FnError> {
+    Ok(0)
+}
+
+pub fn fn_tag_keep_half() -> Result<u8, FnError> {
+    Ok((MAX_TAG_LEN / 2) as u8)
+}
+
 /// Decrypt a whole flight of handshake messages and return a Vec of decrypted messages
 pub fn fn_decrypt_handshake_flight(
     flight: &MessageFlight,
@@ -120,11 +204,12 @@ pub fn fn_decrypt_multiple_handshake_messages(
         .tls13()
         .ok_or_else(|| FnError::Crypto("No tls 1.3 suite".to_owned()))?
         .derive_decrypter(&key);
-    let message = decrypter
-        .decrypt(
-            PlainMessage::from(application_data.clone()).into_unencrypted_opaque(),
-            *sequence,
-        )
+    let message = decrypter.decrypt(
+        PlainMessage::from(application_data.clone()).into_unencrypted_opaque(),
+        *sequence,
+    );
+    record_decryption(message.is_ok());
+    let message = message
         .map_err(|_err| FnError::Crypto("Failed to decrypt it fn_decrypt_handshake".to_string()))?;
 
     let payloads =
@@ -261,26 +346,55 @@ pub fn fn_decrypt_application(
     client: &bool,
     sequence: &u64,
 ) -> Result<Message, FnError> {
-    let (suite, key, _) = tls13_application_traffic_secret(
+    fn_decrypt_application_updated(
+        application_data,
+        server_hello_transcript,
+        server_finished_transcript,
+        server_key_share,
+        psk,
+        group,
+        client,
+        &0,
+        sequence,
+    )
+}
+
+/// Decrypt an application message of the peer after it sent `key_updates` KeyUpdate messages.
+///
+/// The `sequence` restarts at 0 with every key update.
+#[allow(clippy::too_many_arguments)]
+pub fn fn_decrypt_application_updated(
+    application_data: &Message,
+    server_hello_transcript: &HandshakeHash,
+    server_finished_transcript: &HandshakeHash,
+    server_key_share: &Option<Vec<u8>>,
+    psk: &Option<Vec<u8>>,
+    group: &NamedGroup,
+    client: &bool,
+    key_updates: &u64,
+    sequence: &u64,
+) -> Result<Message, FnError> {
+    let (suite, key) = tls13_updated_application_traffic_secret(
         server_hello_transcript,
         server_finished_transcript,
         server_key_share,
         psk,
         group,
         !*client,
+        *key_updates,
     )?;
     let decrypter = suite
         .tls13()
         .ok_or_else(|| FnError::Crypto("No tls 1.3 suite".to_owned()))?
         .derive_decrypter(&key);
-    let message = decrypter
-        .decrypt(
-            PlainMessage::from(application_data.clone()).into_unencrypted_opaque(),
-            *sequence,
-        )
-        .map_err(|_err| {
-            FnError::Crypto("Failed to decrypt it fn_decrypt_application".to_string())
-        })?;
+    let message = decrypter.decrypt(
+        PlainMessage::from(application_data.clone()).into_unencrypted_opaque(),
+        *sequence,
+    );
+    record_decryption(message.is_ok());
+    let message = message.map_err(|_err| {
+        FnError::Crypto("Failed to decrypt it fn_decrypt_application".to_string())
+    })?;
     Message::try_from(message)
         .map_err(|_err| FnError::Crypto("Failed to create Message from decrypted data".to_string()))
 }
@@ -300,7 +414,7 @@ pub fn fn_encrypt_handshake(
         .tls13()
         .ok_or_else(|| FnError::Crypto("No tls 1.3 suite".to_owned()))?
         .derive_encrypter(&key);
-    let application_data = encrypter
+    let application_data = for_receiving_put(encrypter)?
         .encrypt(PlainMessage::from(some_message.clone()).borrow(), *sequence)
         .map_err(|_err| FnError::Crypto("Failed to encrypt it fn_encrypt_handshake".to_string()))?;
     Ok(application_data)
@@ -315,19 +429,46 @@ pub fn fn_encrypt_application(
     group: &NamedGroup,
     sequence: &u64,
 ) -> Result<OpaqueMessage, FnError> {
-    let (suite, key, _) = tls13_application_traffic_secret(
+    fn_encrypt_application_updated(
+        some_message,
+        server_hello_transcript,
+        server_finished_transcript,
+        server_key_share,
+        psk,
+        group,
+        &0,
+        sequence,
+    )
+}
+
+/// Encrypt a message of the client after it sent `key_updates` KeyUpdate messages. This allows to
+/// interleave post-handshake messages like KeyUpdate or NewSessionTicket with application data.
+///
+/// The `sequence` restarts at 0 with every key update.
+pub fn fn_encrypt_application_updated(
+    some_message: &Message,
+    server_hello_transcript: &HandshakeHash,
+    server_finished_transcript: &HandshakeHash,
+    server_key_share: &Option<Vec<u8>>,
+    psk: &Option<Vec<u8>>,
+    group: &NamedGroup,
+    key_updates: &u64,
+    sequence: &u64,
+) -> Result<OpaqueMessage, FnError> {
+    let (suite, key) = tls13_updated_application_traffic_secret(
         server_hello_transcript,
         server_finished_transcript,
         server_key_share,
         psk,
         group,
         true,
+        *key_updates,
     )?;
     let encrypter = suite
         .tls13()
         .ok_or_else(|| FnError::Crypto("No tls 1.3 suite".to_owned()))?
         .derive_encrypter(&key);
-    let application_data = encrypter
+    let application_data = for_receiving_put(encrypter)?
         .encrypt(PlainMessage::from(some_message.clone()).borrow(), *sequence)
         .map_err(|_err| {
             FnError::Crypto("Failed to encrypt it fn_encrypt_application".to_string())
@@ -356,6 +497,29 @@ pub fn fn_derive_psk(
 }
 
 pub fn fn_derive_binder(full_client_hello: &Message, psk: &Vec<u8>) -> Result<Vec<u8>, FnError> {
+    // todo allow other cipher suites: https://github.com/tlspuffin/tlspuffin/issues/129
+    derive_binder(
+        full_client_hello,
+        psk,
+        &crate::tls::rustls::tls13::TLS13_AES_128_GCM_SHA256,
+    )
+}
+
+/// Like [`fn_derive_binder`], but with the hash of `cipher_suite`, which has to be the TLS 1.3
+/// suite of the resumed session.
+pub fn fn_derive_binder_with_suite(
+    full_client_hello: &Message,
+    psk: &Vec<u8>,
+    cipher_suite: &CipherSuite,
+) -> Result<Vec<u8>, FnError> {
+    derive_binder(full_client_hello, psk, &tls13_suite(cipher_suite)?)
+}
+
+fn derive_binder(
+    full_client_hello: &Message,
+    psk: &Vec<u8>,
+    suite: &SupportedCipherSuite,
+) -> Result<Vec<u8>, FnError> {
     let client_hello_payload: HandshakeMessagePayload = match full_client_hello.payload.clone() {
         MessagePayload::Handshake(payload) => Some(payload),
         _ => None,
@@ -364,7 +528,6 @@ pub fn fn_derive_binder(full_client_hello: &Message, psk: &Vec<u8>) -> Result<Ve
         FnError::Unknown("Only can fill binder in HandshakeMessagePayload".to_owned())
     })?;
 
-    let suite = &crate::tls::rustls::tls13::TLS13_AES_128_GCM_SHA256; // todo allow other cipher suites: https://github.com/tlspuffin/tlspuffin/issues/129
     let hkdf_alg = suite
         .tls13()
         .ok_or_else(|| FnError::Crypto("No tls 1.3 suite".to_owned()))?
@@ -387,6 +550,90 @@ pub fn fn_derive_binder(full_client_hello: &Message, psk: &Vec<u8>) -> Result<Ve
     Ok(Vec::from(real_binder.as_ref()))
 }
 
+/// Resumption master secret of the TLS 1.3 session which the agent of the Finished `claim`
+/// established.
+///
+/// Together with the nonce of a ticket, it yields the PSK to resume the session in a later trace,
+/// see [`fn_derive_resumption_psk`].
+pub fn fn_resumption_master_secret(claim: &Finished) -> Result<Vec<u8>, FnError> {
+    let suite = tls13_suite(&CipherSuite::from(claim.chosen_cipher))?;
+    // PUTs pad the secret to the longest supported hash
+    claim
+        .resumption_master_secret
+        .get(..suite.hash_algorithm().output_len)
+        .map(<[u8]>::to_vec)
+        .ok_or_else(|| FnError::Malformed("Claim has no resumption master secret".to_owned()))
+}
+
+/// PSK of a ticket with `ticket_nonce`, which the server issued after a handshake with
+/// `cipher_suite` (RFC 8446, Section 4.6.1)
+pub fn fn_derive_resumption_psk(
+    resumption_master_secret: &Vec<u8>,
+    ticket_nonce: &Vec<u8>,
+    cipher_suite: &CipherSuite,
+) -> Result<Vec<u8>, FnError> {
+    let suite = tls13_suite(cipher_suite)?;
+    let hkdf_algorithm = suite
+        .tls13()
+        .ok_or_else(|| FnError::Crypto("No tls 1.3 suite".to_owned()))?
+        .hkdf_algorithm;
+    let secret = hkdf::Prk::new_less_safe(hkdf_algorithm, resumption_master_secret);
+
+    let psk: PayloadU8 = hkdf_expand(
+        &secret,
+        PayloadU8Len(suite.hash_algorithm().output_len),
+        b"resumption",
+        ticket_nonce,
+    );
+    Ok(psk.into_inner())
+}
+
+/// Exports `length` bytes of keying material with `label` and `context` from the TLS 1.3 session
+/// which the agent of the Finished `claim` established (RFC 8446, Section 7.5)
+pub fn fn_export_keying_material(
+    claim: &Finished,
+    label: &Vec<u8>,
+    context: &Vec<u8>,
+    length: &u16,
+) -> Result<Vec<u8>, FnError> {
+    let suite = tls13_suite(&CipherSuite::from(claim.chosen_cipher))?;
+    let hash_len = suite.hash_algorithm().output_len;
+    // HKDF can not expand more, bail out before allocating the output
+    if usize::from(*length) > 255 * hash_len {
+        return Err(FnError::Malformed(format!(
+            "Can not export {length} bytes with a hash of {hash_len} bytes"
+        )));
+    }
+    // the label is prefixed with "tls13 " and its length is encoded in a single byte
+    if label.len() > 255 - 6 {
+        return Err(FnError::Malformed("Exporter label is too long".to_owned()));
+    }
+
+    let hkdf_algorithm = suite
+        .tls13()
+        .ok_or_else(|| FnError::Crypto("No tls 1.3 suite".to_owned()))?
+        .hkdf_algorithm;
+    // PUTs pad the secret to the longest supported hash
+    let exporter_master_secret = claim
+        .exporter_master_secret
+        .get(..hash_len)
+        .ok_or_else(|| FnError::Malformed("Claim has no exporter master secret".to_owned()))?;
+    let secret = hkdf::Prk::new_less_safe(hkdf_algorithm, exporter_master_secret);
+
+    let mut out = vec![0; usize::from(*length)];
+    export_keying_material(hkdf_algorithm, &secret, &mut out, label, Some(context))
+        .map_err(|err| FnError::Crypto(err.to_string()))?;
+    Ok(out)
+}
+
+fn tls13_suite(cipher_suite: &CipherSuite) -> Result<SupportedCipherSuite, FnError> {
+    ALL_CIPHER_SUITES
+        .iter()
+        .find(|suite| suite.suite() == *cipher_suite && suite.tls13().is_some())
+        .copied()
+        .ok_or_else(|| FnError::Crypto(format!("{cipher_suite:?} is not a TLS 1.3 suite")))
+}
+
 pub fn fn_fill_binder(full_client_hello: &Message, binder: &Vec<u8>) -> Result<Message, FnError> {
     match full_client_hello.payload.clone() {
         MessagePayload::Handshake(payload) => match payload.payload {
@@ -486,18 +733,89 @@ pub fn fn_encrypt12(
     client: &bool,
     sequence: &u64,
 ) -> Result<OpaqueMessage, FnError> {
-    let secrets = tls12_new_secrets(server_random, server_ecdh_pubkey, group)?;
+    let secrets = tls12_new_secrets(server_random, server_ecdh_pubkey, group, None)?;
+    encrypt12(&secrets, message, *client, *sequence)
+}
+
+/// Like [`fn_encrypt12`], but derives the master secret from `session_hash` if it is some, as
+/// with the `extended_master_secret` extension (RFC 7627).
+pub fn fn_encrypt12_ems(
+    message: &Message,
+    server_random: &Random,
+    server_ecdh_pubkey: &Vec<u8>,
+    group: &NamedGroup,
+    client: &bool,
+    sequence: &u64,
+    session_hash: &Option<Vec<u8>>,
+) -> Result<OpaqueMessage, FnError> {
+    let secrets = tls12_new_secrets(
+        server_random,
+        server_ecdh_pubkey,
+        group,
+        session_hash.as_deref(),
+    )?;
+    encrypt12(&secrets, message, *client, *sequence)
+}
 
-    let (_decrypter, encrypter) = secrets.make_cipher_pair(match *client {
+fn encrypt12(
+    secrets: &ConnectionSecrets,
+    message: &Message,
+    client: bool,
+    sequence: u64,
+) -> Result<OpaqueMessage, FnError> {
+    let (_decrypter, encrypter) = secrets.make_cipher_pair(match client {
         true => Side::Client,
         false => Side::Server,
     });
-    let encrypted = encrypter
-        .encrypt(PlainMessage::from(message.clone()).borrow(), *sequence)
+    let encrypted = for_receiving_put(encrypter)?
+        .encrypt(PlainMessage::from(message.clone()).borrow(), sequence)
         .map_err(|_err| FnError::Crypto("Failed to encrypt it fn_encrypt12".to_string()))?;
     Ok(encrypted)
 }
 
+/// Wraps `encrypter` according to the options of the PUT which receives the encrypted message,
+/// e.g. such that it corrupts records, see
+/// [`CORRUPT_EVERY_NTH_RECORD_OPTION`](crate::tls::rustls::cipher::CORRUPT_EVERY_NTH_RECORD_OPTION)
+fn for_receiving_put(
+    encrypter: Box<dyn MessageEncrypter>,
+) -> Result<Box<dyn MessageEncrypter>, FnError> {
+    match receiving_put_options() {
+        Some(options) => <dyn MessageEncrypter>::with_put_options(encrypter, &options)
+            .map_err(|err| FnError::Crypto(err.to_string())),
+        None => Ok(encrypter),
+    }
+}
+
+/// Session hash (RFC 7627) of `session_transcript` if the `extended_master_secret` extension was
+/// negotiated, else none.
+///
+/// The session transcript contains the handshake messages up to and including the
+/// ClientKeyExchange.
+pub fn fn_session_hash12(
+    session_transcript: &HandshakeHash,
+    extended_master_secret: &bool,
+) -> Result<Option<Vec<u8>>, FnError> {
+    Ok(extended_master_secret.then(|| session_transcript.get_current_hash_raw()))
+}
+
+/// Whether the `extended_master_secret` extension is negotiated, i.e. offered by the client and
+/// acknowledged by the server
+pub fn fn_extended_master_secret_negotiated(
+    client_extensions: &ClientExtensions,
+    server_extensions: &ServerExtensions,
+) -> Result<bool, FnError> {
+    let offered = client_extensions
+        .0
+        .iter()
+        .any(|extension| extension.get_type() == ExtensionType::ExtendedMasterSecret);
+    let acknowledged = server_extensions
+        .0
+        .iter()
+        .any(|extension| extension.get_type() == ExtensionType::ExtendedMasterSecret);
+
+    Ok(offered && acknowledged)
+}
+
 pub fn fn_new_certificate() -> Result<Certificate, FnError> {
     let der_cert = hex::decode(
         "308203473082022fa003020102021406f7fb1d20\
@@ -571,6 +889,556 @@ pub fn fn_named_group_x25519() -> Result<NamedGroup, FnError> {
     Ok(NamedGroup::X25519)
 }
 
+/// Reserved GREASE value (RFC 8701), which peers have to ignore
+pub fn fn_named_group_grease() -> Result<NamedGroup, FnError> {
+    Ok(NamedGroup::Unknown(0x0a0a))
+}
+
+// ----
+// GREASE (RFC 8701)
+// ----
+
+/// One of the 16 GREASE code points `0x0a0a`, `0x1a1a`, ..., `0xfafa`, selected by `index`.
+///
+/// The selection is left to the term instead of a random generator, such that executions stay
+/// deterministic while mutations of `index` cover all code points.
+fn grease(index: &u64) -> u16 {
+    0x0a0a + 0x1010 * (*index % 16) as u16
+}
+
+/// GREASE cipher suite, which a server has to ignore when offered and a client has to reject
+/// when selected
+pub fn fn_grease_cipher_suite(index: &u64) -> Result<CipherSuite, FnError> {
+    Ok(CipherSuite::Unknown(grease(index)))
+}
+
+/// GREASE extension with an empty body, which a server has to ignore when offered
+pub fn fn_grease_extension(index: &u64) -> Result<ClientExtension, FnError> {
+    Ok(ClientExtension::Unknown(UnknownExtension {
+        typ: ExtensionType::Unknown(grease(index)),
+        payload: Payload::empty(),
+    }))
+}
+
 pub fn fn_u64_to_u32(input: &u64) -> Result<u32, FnError> {
     Ok(*input as u32)
 }
+
+#[cfg(test)]
+mod tests {
+    use puffin::algebra::dynamic_function::TypeShape;
+    use puffin::algebra::{Term, TermType};
+    use puffin::error::Error;
+    use puffin::protocol::ProtocolMessage;
+    use puffin::term;
+    use puffin::trace::{Query, Spawner, TraceContext};
+
+    use super::*;
+    use crate::protocol::TLSProtocolTypes;
+    use crate::put_registry::tls_registry;
+    use crate::tls::fn_impl::*;
+
+    #[test_log::test]
+    fn test_grease_values() {
+        // RFC 8701, Section 2
+        let reserved: Vec<u16> = vec![
+            0x0a0a, 0x1a1a, 0x2a2a, 0x3a3a, 0x4a4a, 0x5a5a, 0x6a6a, 0x7a7a, 0x8a8a, 0x9a9a, 0xaaaa,
+            0xbaba, 0xcaca, 0xdada, 0xeaea, 0xfafa,
+        ];
+
+        let suites: Vec<u16> = (0..16)
+            .map(|index| fn_grease_cipher_suite(&index).unwrap().get_u16())
+            .collect();
+        assert_eq!(suites, reserved);
+
+        for index in [16, 42, u64::MAX] {
+            let suite = fn_grease_cipher_suite(&index).unwrap().get_u16();
+            assert!(reserved.contains(&suite));
+        }
+        assert!(reserved.contains(&fn_named_group_grease().unwrap().get_u16()));
+
+        assert_eq!(
+            fn_grease_extension(&1).unwrap().get_encoding(),
+            vec![0x1a, 0x1a, 0x00, 0x00]
+        );
+    }
+
+    #[test_log::test]
+    fn test_extended_master_secret_negotiated() {
+        let offered =
+            fn_client_extensions_make(&vec![fn_extended_master_secret_extension().unwrap()])
+                .unwrap();
+        let acknowledged =
+            fn_server_extensions_make(&vec![fn_extended_master_secret_server_extension().unwrap()])
+                .unwrap();
+        let not_offered = fn_client_extensions_make(&vec![]).unwrap();
+        let not_acknowledged = fn_server_extensions_make(&vec![]).unwrap();
+
+        assert!(fn_extended_master_secret_negotiated(&offered, &acknowledged).unwrap());
+        assert!(!fn_extended_master_secret_negotiated(&offered, &not_acknowledged).unwrap());
+        assert!(!fn_extended_master_secret_negotiated(&not_offered, &acknowledged).unwrap());
+    }
+
+    #[test_log::test]
+    fn test_application_data_after_key_update() {
+        let group = NamedGroup::secp384r1;
+        let server_key_share = Some(fn_new_pubkey12(&group).unwrap());
+        let server_hello = fn_new_transcript().unwrap();
+        let server_finished =
+            fn_append_transcript(&server_hello, &fn_finished(&vec![0xaa]).unwrap()).unwrap();
+
+        let encrypt = |message: Message, key_updates: u64, sequence: u64| {
+            fn_encrypt_application_updated(
+                &message,
+                &server_hello,
+                &server_finished,
+                &server_key_share,
+                &None,
+                &group,
+                &key_updates,
+                &sequence,
+            )
+            .unwrap()
+        };
+        // the server decrypts the records of the client
+        let decrypt = |record: &OpaqueMessage, key_updates: u64, sequence: u64| {
+            fn_decrypt_application_updated(
+                &Message::try_from(record.clone()).unwrap(),
+                &server_hello,
+                &server_finished,
+                &server_key_share,
+                &None,
+                &group,
+                &false,
+                &key_updates,
+                &sequence,
+            )
+        };
+        let application_data = |message: Result<Message, FnError>| match message.unwrap().payload {
+            MessagePayload::ApplicationData(payload) => payload.0,
+            payload => panic!("unexpected payload {payload:?}"),
+        };
+
+        let first = encrypt(fn_application_data(&b"first".to_vec()).unwrap(), 0, 0);
+        let key_update = encrypt(fn_key_update_not_requested().unwrap(), 0, 1);
+        let second = encrypt(fn_application_data(&b"second".to_vec()).unwrap(), 1, 0);
+
+        assert_eq!(application_data(decrypt(&first, 0, 0)), b"first");
+        assert!(matches!(
+            decrypt(&key_update, 0, 1).unwrap().payload,
+            MessagePayload::Handshake(HandshakeMessagePayload {
+                typ: HandshakeType::KeyUpdate,
+                ..
+            })
+        ));
+        assert_eq!(application_data(decrypt(&second, 1, 0)), b"second");
+        // the second record is not protected with the keys before the update
+        assert!(decrypt(&second, 0, 2).is_err());
+        // the unchanged application functions use the keys before any update
+        assert_eq!(
+            fn_encrypt_application(
+                &fn_application_data(&b"first".to_vec()).unwrap(),
+                &server_hello,
+                &server_finished,
+                &server_key_share,
+                &None,
+                &group,
+                &0,
+            )
+            .unwrap()
+            .payload,
+            first.payload
+        );
+
+        assert!(decrypt(&second, u64::MAX, 0).is_err());
+    }
+
+    #[test_log::test]
+    fn test_empty_application_data() {
+        let group = NamedGroup::secp384r1;
+        let server_key_share = Some(fn_new_pubkey12(&group).unwrap());
+        let server_hello = fn_new_transcript().unwrap();
+        let server_finished =
+            fn_append_transcript(&server_hello, &fn_finished(&vec![0xaa]).unwrap()).unwrap();
+
+        let record = fn_encrypt_application(
+            &fn_empty_application_data().unwrap(),
+            &server_hello,
+            &server_finished,
+            &server_key_share,
+            &None,
+            &group,
+            &0,
+        )
+        .unwrap();
+        // the content type and the tag of AES-128-GCM
+        assert_eq!(record.typ, ContentType::ApplicationData);
+        assert_eq!(record.payload.0.len(), 1 + 16);
+
+        // the server decrypts the record of the client
+        let decrypted = fn_decrypt_application(
+            &Message::try_from(record).unwrap(),
+            &server_hello,
+            &server_finished,
+            &server_key_share,
+            &None,
+            &group,
+            &false,
+            &0,
+        )
+        .unwrap();
+        assert!(matches!(
+            decrypted.payload,
+            MessagePayload::ApplicationData(payload) if payload.0.is_empty()
+        ));
+    }
+
+    #[test_log::test]
+    fn test_encrypt12_ems() {
+        let message = fn_finished(&vec![0xaa, 0xbb]).unwrap();
+        let server_random = Random([2; 32]);
+        let group = NamedGroup::secp384r1;
+        let pubkey = fn_new_pubkey12(&group).unwrap();
+        let session_transcript = fn_new_transcript12().unwrap();
+
+        let encrypt = |session_hash: &Option<Vec<u8>>| {
+            fn_encrypt12_ems(
+                &message,
+                &server_random,
+                &pubkey,
+                &group,
+                &true,
+                &0,
+                session_hash,
+            )
+            .unwrap()
+            .payload
+            .0
+        };
+
+        let without_ems = fn_encrypt12(&message, &server_random, &pubkey, &group, &true, &0)
+            .unwrap()
+            .payload
+            .0;
+        let not_negotiated = fn_session_hash12(&session_transcript, &false).unwrap();
+        let negotiated = fn_session_hash12(&session_transcript, &true).unwrap();
+
+        assert_eq!(encrypt(&not_negotiated), without_ems);
+        assert_ne!(encrypt(&negotiated), without_ems);
+    }
+
+    #[test_log::test]
+    fn test_concat_handshake() {
+        let flight = fn_append_opaque_flight(
+            &fn_append_opaque_flight(
+                &fn_new_opaque_flight().unwrap(),
+                &fn_server_hello_done().unwrap().create_opaque(),
+            )
+            .unwrap(),
+            &fn_finished(&vec![0xaa, 0xbb]).unwrap().create_opaque(),
+        )
+        .unwrap();
+
+        let bundle = fn_concat_handshake(&flight).unwrap();
+
+        assert_eq!(
+            bundle.get_encoding(),
+            vec![
+                0x16, 0x03, 0x03, 0x00, 0x0a, // record header
+                0x0e, 0x00, 0x00, 0x00, // ServerHelloDone
+                0x14, 0x00, 0x00, 0x02, 0xaa, 0xbb, // Finished
+            ]
+        );
+    }
+
+    #[test_log::test]
+    fn test_concat_encrypted_and_plaintext_handshake() {
+        let encrypted = OpaqueMessage {
+            typ: ContentType::ApplicationData,
+            version: ProtocolVersion::TLSv1_2,
+            payload: Payload::new(vec![0x01, 0x02, 0x03]),
+        };
+        let flight = fn_append_opaque_flight(
+            &fn_append_opaque_flight(
+                &fn_new_opaque_flight().unwrap(),
+                &fn_server_hello_done().unwrap().create_opaque(),
+            )
+            .unwrap(),
+            &encrypted,
+        )
+        .unwrap();
+
+        let bundle = fn_concat_handshake(&flight).unwrap();
+
+        assert_eq!(bundle.typ, ContentType::Handshake);
+        assert_eq!(
+            bundle.payload.0,
+            vec![0x0e, 0x00, 0x00, 0x00, 0x01, 0x02, 0x03]
+        );
+    }
+
+    #[test_log::test]
+    fn test_derive_binder_from_shared_secret() {
+        let mut ctx = TraceContext::new(Spawner::new(tls_registry()));
+        ctx.add_shared_secret("psk".to_owned(), vec![0x42; 32]);
+        assert!(ctx
+            .find_variable(TypeShape::of::<Vec<u8>>(), &Query::shared_secret("psk"))
+            .is_some());
+
+        let client_hello: Term<TLSProtocolTypes> = term! {
+            fn_client_hello(
+                fn_protocol_version12,
+                fn_new_random,
+                fn_new_session_id,
+                (fn_cipher_suites_make(
+                    (fn_append_cipher_suite(
+                        fn_new_cipher_suites,
+                        fn_cipher_suite13_aes_128_gcm_sha256
+                    ))
+                )),
+                fn_compressions,
+                (fn_client_extensions_make(fn_client_extensions_new))
+            )
+        };
+        let binder: Term<TLSProtocolTypes> = term! {
+            fn_derive_binder(
+                (@client_hello),
+                ((!"psk", 0) / Vec<u8>)
+            )
+        };
+
+        let evaluated = binder.evaluate_dy(&ctx).unwrap();
+        let message = client_hello.evaluate_dy(&ctx).unwrap();
+        let expected =
+            fn_derive_binder(message.as_any().downcast_ref().unwrap(), &vec![0x42; 32]).unwrap();
+
+        assert_eq!(
+            evaluated.as_any().downcast_ref::<Vec<u8>>(),
+            Some(&expected)
+        );
+
+        let unset: Term<TLSProtocolTypes> = term! {
+            fn_derive_binder(
+                (@client_hello),
+                ((!"unset", 0) / Vec<u8>)
+            )
+        };
+        assert!(matches!(unset.evaluate_dy(&ctx), Err(Error::Term(_))));
+    }
+
+    #[test_log::test]
+    fn test_resumption_psk() {
+        // RFC 8448, Section 3
+        let resumption_master_secret = [
+            0x7d, 0xf2, 0x35, 0xf2, 0x03, 0x1d, 0x2a, 0x05, 0x12, 0x87, 0xd0, 0x2b, 0x02, 0x41,
+            0xb0, 0xbf, 0xda, 0xf8, 0x6c, 0xc8, 0x56, 0x23, 0x1f, 0x2d, 0x5a, 0xba, 0x46, 0xc4,
+            0x34, 0xec, 0x19, 0x6c,
+        ];
+        let psk = vec![
+            0x4e, 0xcd, 0x0e, 0xb6, 0xec, 0x3b, 0x4d, 0x87, 0xf5, 0xd6, 0x02, 0x8f, 0x92, 0x2c,
+            0xa4, 0xc5, 0x85, 0x1a, 0x27, 0x7f, 0xd4, 0x13, 0x11, 0xc9, 0xe6, 0x2d, 0x2c, 0x94,
+            0x92, 0xe1, 0xc4, 0xf3,
+        ];
+
+        // the secret is padded in the claim
+        let mut padded = resumption_master_secret.to_vec();
+        padded.resize(64, 0);
+        let claim = Finished {
+            outbound: true,
+            client_random: Default::default(),
+            server_random: Default::default(),
+            session_id: Default::default(),
+            authenticate_peer: false,
+            peer_certificate: Default::default(),
+            master_secret: Default::default(),
+            resumption_master_secret: padded.into(),
+            exporter_master_secret: Default::default(),
+            extended_master_secret: false,
+            session_resumed: false,
+            chosen_cipher: CipherSuite::TLS13_AES_128_GCM_SHA256.get_u16(),
+            available_ciphers: Default::default(),
+            signature_algorithm: 0,
+            peer_signature_algorithm: 0,
+        };
+
+        let secret = fn_resumption_master_secret(&claim).unwrap();
+        assert_eq!(secret, resumption_master_secret);
+        assert_eq!(
+            fn_derive_resumption_psk(
+                &secret,
+                &vec![0x00, 0x00],
+                &CipherSuite::TLS13_AES_128_GCM_SHA256
+            )
+            .unwrap(),
+            psk
+        );
+
+        // TLS 1.2 handshakes have no resumption master secret
+        let claim = Finished {
+            resumption_master_secret: Default::default(),
+            ..claim
+        };
+        assert!(fn_resumption_master_secret(&claim).is_err());
+        assert!(fn_derive_resumption_psk(
+            &secret,
+            &vec![0x00, 0x00],
+            &CipherSuite::TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256
+        )
+        .is_err());
+    }
+
+    #[test_log::test]
+    fn test_export_keying_material() {
+        // exporter master secret of the simple 1-RTT handshake of RFC 8448, Section 3
+        let exporter_master_secret =
+            hex::decode("fe22f881176eda18eb8f44529e6792c50c9a3f89452f68d8ae311b4309d3cf50")
+                .unwrap();
+        // HKDF-Expand-Label(Derive-Secret(secret, label, ""), "exporter", Hash(context), 20)
+        let exported = hex::decode("46a5772c4fab43439c726a8e1adaafe8276836e0").unwrap();
+
+        let mut padded = exporter_master_secret;
+        padded.resize(64, 0);
+        let claim = Finished {
+            outbound: true,
+            client_random: Default::default(),
+            server_random: Default::default(),
+            session_id: Default::default(),
+            authenticate_peer: false,
+            peer_certificate: Default::default(),
+            master_secret: Default::default(),
+            resumption_master_secret: Default::default(),
+            exporter_master_secret: padded.into(),
+            extended_master_secret: false,
+            session_resumed: false,
+            chosen_cipher: CipherSuite::TLS13_AES_128_GCM_SHA256.get_u16(),
+            available_ciphers: Default::default(),
+            signature_algorithm: 0,
+            peer_signature_algorithm: 0,
+        };
+        let label = b"EXPORTER-puffin".to_vec();
+        let context = b"context".to_vec();
+
+        assert_eq!(
+            fn_export_keying_material(&claim, &label, &context, &20).unwrap(),
+            exported
+        );
+        assert!(fn_export_keying_material(&claim, &label, &context, &0)
+            .unwrap()
+            .is_empty());
+
+        // HKDF expands at most 255 blocks of SHA-256
+        assert_eq!(
+            fn_export_keying_material(&claim, &label, &context, &(255 * 32))
+                .unwrap()
+                .len(),
+            255 * 32
+        );
+        assert!(fn_export_keying_material(&claim, &label, &context, &(255 * 32 + 1)).is_err());
+        assert!(fn_export_keying_material(&claim, &label, &context, &u16::MAX).is_err());
+    }
+
+    #[test_log::test]
+    fn test_derive_binder_with_suite() {
+        let client_hello = fn_client_hello(
+            &ProtocolVersion::TLSv1_2,
+            &fn_new_random().unwrap(),
+            &fn_new_session_id().unwrap(),
+            &fn_cipher_suites_make(&vec![CipherSuite::TLS13_AES_256_GCM_SHA384]).unwrap(),
+            &fn_compressions().unwrap(),
+            // placeholder binder of the length of SHA-384
+            &fn_client_extensions_make(&vec![fn_pre_shared_key_extension(
+                &vec![
+                    crate::tls::rustls::msgs::handshake::PresharedKeyIdentity::new(vec![0xaa], 0),
+                ],
+                &vec![vec![0; 48]],
+            )
+            .unwrap()])
+            .unwrap(),
+        )
+        .unwrap();
+        let psk = vec![0x42; 48];
+
+        assert_eq!(
+            fn_derive_binder_with_suite(
+                &client_hello,
+                &psk,
+                &CipherSuite::TLS13_AES_128_GCM_SHA256
+            )
+            .unwrap(),
+            fn_derive_binder(&client_hello, &psk).unwrap()
+        );
+        let binder = fn_derive_binder_with_suite(
+            &client_hello,
+            &psk,
+            &CipherSuite::TLS13_AES_256_GCM_SHA384,
+        )
+        .unwrap();
+        assert_eq!(binder.len(), 48);
+    }
+
+    #[test_log::test]
+    fn test_retype_encrypted_record() {
+        // Encrypted TLS 1.3 record, the inner content type is part of the ciphertext
+        let encrypted = OpaqueMessage {
+            typ: ContentType::ApplicationData,
+            version: ProtocolVersion::TLSv1_2,
+            payload: Payload::new(vec![0x01, 0x02, 0x03]),
+        };
+
+        let retyped = fn_retype_record(&encrypted, &fn_content_type_handshake().unwrap()).unwrap();
+
+        assert_eq!(retyped.typ, ContentType::Handshake);
+        assert_eq!(retyped.version, encrypted.version);
+        assert_eq!(retyped.payload.0, encrypted.payload.0);
+        assert_eq!(
+            retyped.get_encoding(),
+            vec![0x16, 0x03, 0x03, 0x00, 0x03, 0x01, 0x02, 0x03]
+        );
+
+        let unknown = fn_retype_record(&encrypted, &0x42).unwrap();
+        assert_eq!(unknown.typ, ContentType::Unknown(0x42));
+        assert_eq!(unknown.get_encoding()[1..], encrypted.get_encoding()[1..]);
+    }
+
+    #[test_log::test]
+    fn test_truncate_tag() {
+        let ciphertext = vec![0xaa; 5];
+        let tag: Vec<u8> = (0..MAX_TAG_LEN as u8).collect();
+        let encrypted = OpaqueMessage {
+            typ: ContentType::ApplicationData,
+            version: ProtocolVersion::TLSv1_2,
+            payload: Payload::new([ciphertext.clone(), tag.clone()].concat()),
+        };
+
+        let half = fn_truncate_tag(&encrypted, &fn_tag_keep_half().unwrap()).unwrap();
+        assert_eq!(half.typ, encrypted.typ);
+        assert_eq!(half.version, encrypted.version);
+        assert_eq!(
+            half.payload.0.len(),
+            encrypted.payload.0.len() - MAX_TAG_LEN / 2
+        );
+        assert_eq!(
+            half.payload.0,
+            [ciphertext.clone(), tag[..MAX_TAG_LEN / 2].to_vec()].concat()
+        );
+
+        let none = fn_truncate_tag(&encrypted, &fn_tag_keep_none().unwrap()).unwrap();
+        assert_eq!(none.payload.0, ciphertext);
+
+        // Keeping the whole tag or more is a no-op
+        for keep in [MAX_TAG_LEN as u8, MAX_TAG_LEN as u8 + 1, u8::MAX] {
+            let unchanged = fn_truncate_tag(&encrypted, &keep).unwrap();
+            assert_eq!(unchanged.payload.0, encrypted.payload.0);
+        }
+
+        // Records shorter than a tag are not encrypted with an AEAD
+        let short = OpaqueMessage {
+            payload: Payload::new(vec![0x01, 0x02, 0x03]),
+            ..encrypted
+        };
+        assert_eq!(
+            fn_truncate_tag(&short, &0).unwrap().payload.0,
+            short.payload.0
+        );
+    }
+}
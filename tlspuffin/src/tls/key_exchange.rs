@@ -55,10 +55,15 @@ pub fn tls12_key_exchange(group: &NamedGroup) -> Result<KeyExchange, FnError> {
     Ok(kx)
 }
 
+/// Derives the TLS 1.2 secrets of the connection.
+///
+/// If `session_hash` is given, the master secret is derived from it as with the
+/// `extended_master_secret` extension (RFC 7627), else from the randoms of client and server.
 pub fn tls12_new_secrets(
     server_random: &Random,
     server_ecdh_pubkey: &[u8],
     group: &NamedGroup,
+    session_hash: Option<&[u8]>,
 ) -> Result<ConnectionSecrets, FnError> {
     let suite = &tls12::TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256; // todo https://github.com/tlspuffin/tlspuffin/issues/129
 
@@ -78,7 +83,7 @@ pub fn tls12_new_secrets(
         .tls12()
         .ok_or_else(|| FnError::Unknown("VersionNotCompatibleError".to_string()))?;
     let secrets =
-        ConnectionSecrets::from_key_exchange(kx, server_ecdh_pubkey, None, randoms, suite)
+        ConnectionSecrets::from_key_exchange(kx, server_ecdh_pubkey, session_hash, randoms, suite)
             .map_err(|_err| FnError::Crypto("Failed to shared secrets for TLS 1.2".to_string()))?;
     // master_secret is: 01 40 26 dd 53 3c 0a...
     Ok(secrets)
@@ -86,8 +91,11 @@ pub fn tls12_new_secrets(
 
 #[cfg(test)]
 mod tests {
-    use crate::tls::key_exchange::deterministic_key_exchange;
+    use crate::tls::key_exchange::{deterministic_key_exchange, tls12_new_secrets};
+    use crate::tls::rustls::hash_hs::HandshakeHash;
     use crate::tls::rustls::kx::SECP384R1;
+    use crate::tls::rustls::msgs::enums::NamedGroup;
+    use crate::tls::rustls::msgs::handshake::Random;
 
     #[test_log::test]
     fn test_deterministic_key() {
@@ -96,4 +104,28 @@ mod tests {
 
         assert_eq!(a.pubkey.as_ref(), b.pubkey.as_ref())
     }
+
+    #[test_log::test]
+    fn test_extended_master_secret() {
+        let server_random = Random([2; 32]);
+        let pubkey = deterministic_key_exchange(&SECP384R1).unwrap().pubkey;
+        let session_hash = HandshakeHash::new(&ring::digest::SHA256).get_current_hash_raw();
+
+        let secrets = tls12_new_secrets(
+            &server_random,
+            pubkey.as_ref(),
+            &NamedGroup::secp384r1,
+            None,
+        )
+        .unwrap();
+        let ems_secrets = tls12_new_secrets(
+            &server_random,
+            pubkey.as_ref(),
+            &NamedGroup::secp384r1,
+            Some(&session_hash),
+        )
+        .unwrap();
+
+        assert_ne!(secrets.master_secret, ems_secrets.master_secret);
+    }
 }
@@ -8,8 +8,8 @@ use crate::tls::rustls::key_log::NoKeyLog;
 use crate::tls::rustls::msgs::enums::NamedGroup;
 use crate::tls::rustls::suites::SupportedCipherSuite;
 use crate::tls::rustls::tls13::key_schedule::{
-    KeyScheduleEarly, KeyScheduleHandshake, KeyScheduleHandshakeStart, KeySchedulePreHandshake,
-    KeyScheduleTrafficWithClientFinishedPending,
+    hkdf_expand, KeyScheduleEarly, KeyScheduleHandshake, KeyScheduleHandshakeStart,
+    KeySchedulePreHandshake, KeyScheduleTrafficWithClientFinishedPending,
 };
 
 pub fn tls13_handshake_traffic_secret(
@@ -68,6 +68,47 @@ pub fn tls13_application_traffic_secret(
     ))
 }
 
+/// Upper bound of the key updates in [`tls13_updated_application_traffic_secret`], such that a
+/// mutated number of updates does not stall the execution
+const MAX_KEY_UPDATES: u64 = 1024;
+
+/// Application traffic secret of the client or the server after it sent `key_updates` KeyUpdate
+/// messages (RFC 8446, Section 7.2)
+pub fn tls13_updated_application_traffic_secret(
+    server_hello: &HandshakeHash,
+    server_finished: &HandshakeHash,
+    server_key_share: &Option<Vec<u8>>,
+    psk: &Option<Vec<u8>>,
+    group: &NamedGroup,
+    client: bool,
+    key_updates: u64,
+) -> Result<(&'static SupportedCipherSuite, Prk), FnError> {
+    if key_updates > MAX_KEY_UPDATES {
+        return Err(FnError::Malformed(format!(
+            "At most {MAX_KEY_UPDATES} key updates are supported"
+        )));
+    }
+
+    let (suite, mut secret, _) = tls13_application_traffic_secret(
+        server_hello,
+        server_finished,
+        server_key_share,
+        psk,
+        group,
+        client,
+    )?;
+    let hkdf_algorithm = suite
+        .tls13()
+        .ok_or_else(|| FnError::Crypto("No tls 1.3 suite".to_owned()))?
+        .hkdf_algorithm;
+
+    for _ in 0..key_updates {
+        secret = hkdf_expand(&secret, hkdf_algorithm, b"traffic upd", &[]);
+    }
+
+    Ok((suite, secret))
+}
+
 pub fn tls13_derive_psk(
     server_hello: &HandshakeHash,
     server_finished: &HandshakeHash,
@@ -88,6 +88,7 @@ define_signature!(
     // messages
     fn_alert_close_notify
     fn_application_data
+    fn_empty_application_data
     fn_certificate
     fn_certificate13
     fn_certificate_request
@@ -96,17 +97,23 @@ define_signature!(
     fn_certificate_verify
     fn_change_cipher_spec
     fn_client_hello
+    fn_client_hello_extensions_block
     fn_client_key_exchange
     fn_empty_handshake_message
     fn_encrypted_extensions // Just a wrapper, not encrypting per se
     fn_finished
+    fn_handshake_raw
     fn_heartbeat
     fn_heartbeat_fake_length // TODO: Was [get] but that was an error. TO TEST
+    fn_heartbeat_request
+    fn_heartbeat_payload_length
+    fn_heartbeat_large_length
     fn_hello_request
     fn_hello_retry_request
     fn_hello_retry_request_random
     fn_key_update
     fn_key_update_not_requested
+    fn_key_update_coalesced
     fn_message_hash
     fn_new_session_ticket
     fn_new_session_ticket13
@@ -117,6 +124,7 @@ define_signature!(
     fn_client_extensions_new
     fn_client_extensions_append [list]
     fn_client_extensions_make
+    fn_extensions_block_with_length
     fn_server_extensions_new
     fn_server_extensions_make
     fn_server_extensions_append [list]
@@ -135,9 +143,19 @@ define_signature!(
     fn_status_request_server_extension
     fn_status_request_certificate_extension
     fn_support_group_extension
+    fn_supported_groups_extension
+    fn_named_groups_new
+    fn_named_groups_append [list]
     fn_ec_point_formats_extension
+    fn_ec_point_formats_uncompressed
     fn_ec_point_formats_server_extension
+    fn_heartbeat_extension
+    fn_heartbeat_mode_peer_allowed_to_send
+    fn_heartbeat_mode_peer_not_allowed_to_send
     fn_signature_algorithm_extension
+    fn_signature_algorithms_extension
+    fn_signature_schemes_new
+    fn_signature_schemes_append [list]
     fn_signature_algorithm_cert_req_extension
     fn_empty_vec_of_vec
     fn_append_vec [list]
@@ -155,6 +173,7 @@ define_signature!(
     fn_empty_preshared_keys_identity_vec
     fn_append_preshared_keys_identity [list]
     fn_preshared_keys_extension_empty_binder
+    fn_pre_shared_key_extension
     fn_preshared_keys_server_extension
     fn_early_data_extension
     fn_early_data_new_session_ticket_extension
@@ -205,10 +224,12 @@ define_signature!(
     fn_verify_data [opaque]
     fn_verify_data_server [opaque]
     fn_sign_transcript
+    fn_sign_transcript_ems
     fn_cipher_suites_make
     fn_new_cipher_suites
     fn_append_cipher_suite [list]
     fn_cipher_suite12
+    fn_cipher_suite12_aes_256_gcm_sha384
     fn_cipher_suite13_aes_128_gcm_sha256
     fn_cipher_suite13_aes_256_gcm_sha384
     fn_cipher_suite13_aes_128_ccm_sha256
@@ -219,6 +240,13 @@ define_signature!(
     fn_append_flight [list]
     fn_new_opaque_flight
     fn_append_opaque_flight [list]
+    fn_concat_handshake
+    fn_retype_record
+    fn_content_type_handshake
+    fn_content_type_application_data
+    fn_truncate_tag
+    fn_tag_keep_none
+    fn_tag_keep_half
     fn_new_transcript
     fn_append_transcript [opaque] [list] // this one is opaque and not list since it returns the hash of all elements added to the list so far
     fn_decrypt_handshake_flight [opaque]
@@ -233,10 +261,16 @@ define_signature!(
     fn_no_psk
     fn_psk
     fn_decrypt_application [opaque]
+    fn_decrypt_application_updated [opaque]
     fn_encrypt_handshake [opaque]
     fn_encrypt_application [opaque]
+    fn_encrypt_application_updated [opaque]
     fn_derive_psk [opaque]
     fn_derive_binder [opaque]
+    fn_derive_binder_with_suite [opaque]
+    fn_resumption_master_secret [opaque]
+    fn_derive_resumption_psk [opaque]
+    fn_export_keying_material [opaque]
     fn_fill_binder [opaque]
     fn_get_ticket [get]
     fn_get_ticket_age_add [get]
@@ -246,6 +280,9 @@ define_signature!(
     fn_encode_ec_pubkey12
     fn_new_pubkey12 [opaque]
     fn_encrypt12 [opaque]
+    fn_encrypt12_ems [opaque]
+    fn_session_hash12
+    fn_extended_master_secret_negotiated
     fn_new_certificate
     fn_new_certificates
     fn_append_certificate [list]
@@ -253,6 +290,9 @@ define_signature!(
     fn_append_certificate_entry [list]
     fn_named_group_secp384r1
     fn_named_group_x25519
+    fn_named_group_grease
+    fn_grease_cipher_suite
+    fn_grease_extension
     fn_u64_to_u32
     fn_payload_u8
     fn_payload_u16
@@ -278,7 +318,6 @@ define_signature!(
     fn_random_ec_key
     fn_certificate_entry
     fn_empty_certificate_chain
-    fn_append_certificate_entry [list]
     fn_certificate_entries_make
     fn_chain_append_certificate_entry [list]
     fn_get_context [get]
@@ -289,6 +328,7 @@ define_signature!(
     fn_ecdsa_sign_server
     fn_rsa_pss_signature_algorithm
     fn_rsa_pkcs1_signature_algorithm
+    fn_rsa_pkcs1_sha1_signature_algorithm
     fn_invalid_signature_algorithm
     fn_ecdsa_signature_algorithm
 );
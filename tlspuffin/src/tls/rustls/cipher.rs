@@ -1,4 +1,7 @@
+use std::num::NonZeroU64;
+
 use puffin::codec;
+use puffin::put::PutOptions;
 use ring::{aead, hkdf};
 
 use crate::tls::rustls::error::Error;
@@ -16,10 +19,50 @@ pub trait MessageEncrypter: Send + Sync {
     fn encrypt(&self, m: BorrowedPlainMessage, seq: u64) -> Result<OpaqueMessage, Error>;
 }
 
+/// PUT option which enables the [`CorruptingMessageEncrypter`], e.g. `corrupt_every_nth_record=2`
+pub const CORRUPT_EVERY_NTH_RECORD_OPTION: &str = "corrupt_every_nth_record";
+
 impl dyn MessageEncrypter {
     pub fn invalid() -> Box<dyn MessageEncrypter> {
         Box::new(InvalidMessageEncrypter {})
     }
+
+    /// Wraps `inner` such that the AEAD tag of every `every_nth` record is corrupted.
+    pub fn corrupting(
+        inner: Box<dyn MessageEncrypter>,
+        every_nth: NonZeroU64,
+    ) -> Box<dyn MessageEncrypter> {
+        Box::new(CorruptingMessageEncrypter { inner, every_nth })
+    }
+
+    /// Wraps `inner` in a [`CorruptingMessageEncrypter`] if the option
+    /// [`CORRUPT_EVERY_NTH_RECORD_OPTION`] is set. Otherwise, `inner` is returned unchanged.
+    pub fn with_put_options(
+        inner: Box<dyn MessageEncrypter>,
+        options: &PutOptions,
+    ) -> Result<Box<dyn MessageEncrypter>, puffin::error::Error> {
+        Ok(match corrupt_every_nth_record(options)? {
+            Some(every_nth) => Self::corrupting(inner, every_nth),
+            None => inner,
+        })
+    }
+}
+
+/// Parses the option [`CORRUPT_EVERY_NTH_RECORD_OPTION`], which has to be a positive integer if
+/// it is set.
+pub fn corrupt_every_nth_record(
+    options: &PutOptions,
+) -> Result<Option<NonZeroU64>, puffin::error::Error> {
+    options
+        .get_option(CORRUPT_EVERY_NTH_RECORD_OPTION)
+        .map(|value| {
+            value.parse::<NonZeroU64>().map_err(|_| {
+                puffin::error::Error::Put(format!(
+                    "invalid value {value:?} of the PUT option {CORRUPT_EVERY_NTH_RECORD_OPTION}"
+                ))
+            })
+        })
+        .transpose()
 }
 
 impl dyn MessageDecrypter {
@@ -86,6 +129,31 @@ impl MessageEncrypter for InvalidMessageEncrypter {
     }
 }
 
+/// A `MessageEncrypter` decorator which flips a bit in the AEAD tag of every Nth record, such that
+/// the peer fails to decrypt it. With N=1, every record is corrupted.
+///
+/// Records are counted by their sequence number, such that the same records are corrupted no matter
+/// how often a trace is executed.
+struct CorruptingMessageEncrypter {
+    inner: Box<dyn MessageEncrypter>,
+    every_nth: NonZeroU64,
+}
+
+impl MessageEncrypter for CorruptingMessageEncrypter {
+    fn encrypt(&self, m: BorrowedPlainMessage, seq: u64) -> Result<OpaqueMessage, Error> {
+        let mut encrypted = self.inner.encrypt(m, seq)?;
+
+        if (seq + 1) % self.every_nth.get() == 0 {
+            // The tag is appended at the end of the payload
+            if let Some(last) = encrypted.payload.0.last_mut() {
+                *last ^= 0x01;
+            }
+        }
+
+        Ok(encrypted)
+    }
+}
+
 /// A `MessageDecrypter` which doesn't work.
 struct InvalidMessageDecrypter {}
 
@@ -94,3 +162,127 @@ impl MessageDecrypter for InvalidMessageDecrypter {
         Err(Error::DecryptError)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use ring::hkdf;
+
+    use super::*;
+    use crate::tls::rustls::msgs::enums::{ContentType, ProtocolVersion};
+    use crate::tls::rustls::tls13::TLS13_AES_128_GCM_SHA256_INTERNAL;
+
+    fn decryption_results(options: &PutOptions) -> Vec<bool> {
+        let secret = hkdf::Prk::new_less_safe(hkdf::HKDF_SHA256, &[1u8; 32]);
+        let suite = TLS13_AES_128_GCM_SHA256_INTERNAL;
+        let encrypter =
+            <dyn MessageEncrypter>::with_put_options(suite.derive_encrypter(&secret), options)
+                .unwrap();
+        let decrypter = suite.derive_decrypter(&secret);
+
+        (0..6)
+            .map(|seq| {
+                let message = BorrowedPlainMessage {
+                    typ: ContentType::ApplicationData,
+                    version: ProtocolVersion::TLSv1_3,
+                    payload: b"hello",
+                };
+                let encrypted = encrypter.encrypt(message, seq).unwrap();
+                decrypter.decrypt(encrypted, seq).is_ok()
+            })
+            .collect()
+    }
+
+    #[test_log::test]
+    fn test_corrupt_every_other_record() {
+        let options = PutOptions::from(vec![(CORRUPT_EVERY_NTH_RECORD_OPTION, "2")]);
+
+        assert_eq!(
+            decryption_results(&options),
+            vec![true, false, true, false, true, false]
+        );
+    }
+
+    #[test_log::test]
+    fn test_corrupt_every_record() {
+        let options = PutOptions::from(vec![(CORRUPT_EVERY_NTH_RECORD_OPTION, "1")]);
+
+        assert!(decryption_results(&options).iter().all(|ok| !ok));
+        assert!(decryption_results(&PutOptions::default())
+            .iter()
+            .all(|ok| *ok));
+    }
+
+    #[test_log::test]
+    fn test_reject_invalid_corrupt_every_nth_record() {
+        for value in ["0", "-1", "two", ""] {
+            let options = PutOptions::from(vec![(CORRUPT_EVERY_NTH_RECORD_OPTION, value)]);
+
+            assert!(corrupt_every_nth_record(&options).is_err());
+        }
+    }
+
+    /// Per-record nonce of RFC 8446, Section 5.3, constructed independently of [`make_nonce`]
+    fn rfc8446_nonce(iv: &[u8; aead::NONCE_LEN], seq: u64) -> [u8; aead::NONCE_LEN] {
+        // The sequence number is encoded in network byte order and left-padded with zeros
+        let mut padded_seq = [0u8; aead::NONCE_LEN];
+        padded_seq[aead::NONCE_LEN - 8..].copy_from_slice(&seq.to_be_bytes());
+
+        let mut nonce = *iv;
+        for (byte, seq_byte) in nonce.iter_mut().zip(padded_seq) {
+            *byte ^= seq_byte;
+        }
+        nonce
+    }
+
+    #[test_log::test]
+    fn test_make_nonce_matrix() {
+        let iv = Iv::copy(&[
+            0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb,
+        ]);
+        let algorithms = [
+            &aead::AES_128_GCM,
+            &aead::AES_256_GCM,
+            &aead::CHACHA20_POLY1305,
+        ];
+        let sequence_numbers = [0, 1, 1 << 32, u64::MAX];
+
+        for algorithm in algorithms {
+            assert_eq!(algorithm.nonce_len(), aead::NONCE_LEN);
+            let key = aead::LessSafeKey::new(
+                aead::UnboundKey::new(algorithm, &vec![0x42; algorithm.key_len()]).unwrap(),
+            );
+            let seal = |nonce: aead::Nonce| {
+                let mut in_out = b"hello".to_vec();
+                key.seal_in_place_append_tag(nonce, aead::Aad::empty(), &mut in_out)
+                    .unwrap();
+                in_out
+            };
+
+            for seq in sequence_numbers {
+                let expected = rfc8446_nonce(&iv.0, seq);
+
+                assert_eq!(
+                    make_nonce(&iv, seq).as_ref(),
+                    &expected,
+                    "{algorithm:?}, sequence number {seq}"
+                );
+                assert_eq!(
+                    seal(make_nonce(&iv, seq)),
+                    seal(aead::Nonce::assume_unique_for_key(expected)),
+                    "{algorithm:?}, sequence number {seq}"
+                );
+            }
+        }
+
+        // The high sequence numbers occupy exactly the last 8 bytes
+        let zero_iv = Iv::copy(&[0; aead::NONCE_LEN]);
+        assert_eq!(
+            make_nonce(&zero_iv, 1 << 32).as_ref(),
+            &[0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0]
+        );
+        assert_eq!(
+            make_nonce(&zero_iv, u64::MAX).as_ref(),
+            &[0, 0, 0, 0, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]
+        );
+    }
+}
@@ -8,6 +8,11 @@ use puffin::{atom_extract_knowledge, codec};
 
 use crate::protocol::TLSProtocolTypes;
 use crate::tls::rustls::key;
+
+/// Maximum length of u24-length-prefixed fields within handshake messages, e.g. certificate
+/// chains. Longer fields are rejected when decoding.
+pub const MAX_U24_PAYLOAD: usize = 0x10000;
+
 /// An externally length'd payload
 #[derive(Debug, Clone, PartialEq)]
 pub struct Payload(pub Vec<u8>);
@@ -44,7 +49,7 @@ impl Codec for key::Certificate {
 
     fn read(r: &mut Reader) -> Option<Self> {
         let len = codec::u24::read(r)?.0 as usize;
-        let mut sub = r.sub(len)?;
+        let mut sub = r.sub_checked(len, MAX_U24_PAYLOAD)?;
         let body = sub.rest().to_vec();
         Some(Self(body))
     }
@@ -68,7 +73,7 @@ impl Codec for PayloadU24 {
 
     fn read(r: &mut Reader) -> Option<Self> {
         let len = codec::u24::read(r)?.0 as usize;
-        let mut sub = r.sub(len)?;
+        let mut sub = r.sub_checked(len, MAX_U24_PAYLOAD)?;
         let body = sub.rest().to_vec();
         Some(Self(body))
     }
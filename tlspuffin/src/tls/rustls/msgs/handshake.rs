@@ -8,7 +8,7 @@ use puffin::{atom_extract_knowledge, codec};
 
 use crate::protocol::TLSProtocolTypes;
 use crate::tls::fn_impl::fn_hello_retry_request_random;
-use crate::tls::rustls::msgs::base::{Payload, PayloadU16, PayloadU24, PayloadU8};
+use crate::tls::rustls::msgs::base::{Payload, PayloadU16, PayloadU24, PayloadU8, MAX_U24_PAYLOAD};
 use crate::tls::rustls::msgs::enums::{
     CertificateStatusType, CipherSuite, ClientCertificateType, Compression, ECCurveType,
     ECPointFormat, ExtensionType, HandshakeType, HashAlgorithm, KeyUpdateRequest, NamedGroup,
@@ -92,7 +92,7 @@ macro_rules! declare_u24_vec_limited (
       }
 
       fn read(r: &mut puffin::codec::Reader) -> Option<Self> {
-        Some($name(puffin::codec::read_vec_u24_limited::<$itemtype>(r, 0x10000)?))
+        Some($name(puffin::codec::read_vec_u24_limited::<$itemtype>(r, MAX_U24_PAYLOAD)?))
       }
     }
   }
@@ -951,6 +951,62 @@ impl codec::Codec for ClientHelloPayload {
 }
 
 impl ClientHelloPayload {
+    /// Reads as much of a malformed ClientHello as possible, such that its fields remain available
+    /// as knowledge. Missing compression methods and extensions are left empty, extensions with a
+    /// malformed body are kept as [`ClientExtension::Unknown`] and trailing bytes which do not form
+    /// an extension are skipped.
+    ///
+    /// Besides the ClientHello, the bytes after the cipher suites are returned unchanged, see
+    /// [`HandshakePayload::PartialClientHello`].
+    pub fn read_partial(r: &mut codec::Reader) -> Option<(Self, Payload)> {
+        let mut ret = Self {
+            client_version: ProtocolVersion::read(r)?,
+            random: Random::read(r)?,
+            session_id: SessionID::read(r)?,
+            cipher_suites: CipherSuites::read(r)?,
+            compression_methods: Compressions(Vec::new()),
+            extensions: ClientExtensions(Vec::new()),
+        };
+
+        let tail = Payload::new(r.rest());
+        let mut r = Reader::init(&tail.0);
+
+        let Some(compression_methods) = Compressions::read(&mut r) else {
+            return Some((ret, tail));
+        };
+        ret.compression_methods = compression_methods;
+
+        // The length of the extension block is ignored, the extensions extend to the end
+        let _ = u16::read(&mut r);
+        let mut extensions = Reader::init(r.rest());
+
+        loop {
+            let start = extensions.checkpoint();
+            if let Some(extension) = ClientExtension::read(&mut extensions) {
+                ret.extensions.0.push(extension);
+                continue;
+            }
+
+            extensions.restore(start);
+            let Some(typ) = ExtensionType::read(&mut extensions) else {
+                break;
+            };
+            let Some(body) =
+                u16::read(&mut extensions).and_then(|len| extensions.take(usize::from(len)))
+            else {
+                break;
+            };
+            ret.extensions
+                .0
+                .push(ClientExtension::Unknown(UnknownExtension {
+                    typ,
+                    payload: Payload::new(body),
+                }));
+        }
+
+        Some((ret, tail))
+    }
+
     /// Returns true if there is more than one extension of a given
     /// type.
     pub fn has_duplicate_extension(&self) -> bool {
@@ -1091,7 +1147,10 @@ impl ClientHelloPayload {
     pub fn set_psk_binder(&mut self, binder: impl Into<Vec<u8>>) {
         let last_extension = self.extensions.0.last_mut();
         if let Some(ClientExtension::PresharedKey(ref mut offer)) = last_extension {
-            offer.binders.0[0] = PresharedKeyBinder::new(binder.into());
+            // A malformed offer can come without binders
+            if let Some(first) = offer.binders.0.first_mut() {
+                *first = PresharedKeyBinder::new(binder.into());
+            }
         }
     }
 
@@ -1378,7 +1437,10 @@ impl codec::Codec for CertificatePayload {
 
     fn read(r: &mut codec::Reader) -> Option<Self> {
         // 64KB of certificates is plenty, 16MB is obviously silly
-        Some(CertificatePayload(codec::read_vec_u24_limited(r, 0x10000)?))
+        Some(CertificatePayload(codec::read_vec_u24_limited(
+            r,
+            MAX_U24_PAYLOAD,
+        )?))
     }
 }
 
@@ -2192,6 +2254,10 @@ impl CertificateStatus {
 pub enum HandshakePayload {
     HelloRequest,
     ClientHello(ClientHelloPayload),
+    /// A malformed ClientHello, of which only the fields up to the cipher suites are encoded. The
+    /// remaining bytes are encoded from the unparsed tail, such that the message is sent as it was
+    /// received.
+    PartialClientHello(ClientHelloPayload, Payload),
     ServerHello(ServerHelloPayload),
     HelloRetryRequest(HelloRetryRequest),
     Certificate(CertificatePayload),
@@ -2219,6 +2285,13 @@ impl codec::Codec for HandshakePayload {
         match *self {
             HelloRequest | ServerHelloDone | EndOfEarlyData => {}
             ClientHello(ref x) => x.encode(bytes),
+            PartialClientHello(ref x, ref tail) => {
+                x.client_version.encode(bytes);
+                x.random.encode(bytes);
+                x.session_id.encode(bytes);
+                x.cipher_suites.encode(bytes);
+                tail.encode(bytes);
+            }
             ServerHello(ref x) => x.encode(bytes),
             HelloRetryRequest(ref x) => x.encode(bytes),
             Certificate(ref x) => x.encode(bytes),
@@ -2373,6 +2446,26 @@ impl HandshakeMessagePayload {
         }
     }
 
+    /// Reads a ClientHello whose handshake header is intact but whose body is malformed, see
+    /// [`ClientHelloPayload::read_partial`].
+    pub fn read_partial_client_hello(bytes: &[u8]) -> Option<Self> {
+        let mut r = Reader::init(bytes);
+        if HandshakeType::read(&mut r)? != HandshakeType::ClientHello {
+            return None;
+        }
+
+        let len = codec::u24::read(&mut r)?.0 as usize;
+        if len != r.left() {
+            return None;
+        }
+
+        let (client_hello, tail) = ClientHelloPayload::read_partial(&mut r)?;
+        Some(Self {
+            typ: HandshakeType::ClientHello,
+            payload: HandshakePayload::PartialClientHello(client_hello, tail),
+        })
+    }
+
     pub fn build_key_update_notify() -> Self {
         Self {
             typ: HandshakeType::KeyUpdate,
@@ -1,7 +1,7 @@
 use puffin::codec::{put_u16, Codec, Reader};
 use webpki::DnsNameRef;
 
-use super::base::{Payload, PayloadU16, PayloadU24, PayloadU8};
+use super::base::{Payload, PayloadU16, PayloadU24, PayloadU8, MAX_U24_PAYLOAD};
 use super::enums::*;
 use super::handshake::*;
 use crate::tls::fn_impl::fn_hello_retry_request_random;
@@ -1215,3 +1215,23 @@ fn cannot_decode_huge_certificate() {
     buf[6] = 0x01;
     assert!(HandshakeMessagePayload::read_bytes(&buf).is_none());
 }
+
+#[test_log::test]
+fn cannot_decode_payload_u24_above_limit() {
+    let mut buf = vec![0u8; 3 + MAX_U24_PAYLOAD + 1];
+
+    // a length of exactly the limit decodes fine
+    buf[..3].copy_from_slice(&[0x01, 0x00, 0x00]);
+    assert_eq!(
+        PayloadU24::read_bytes(&buf[..3 + MAX_U24_PAYLOAD])
+            .unwrap()
+            .0
+            .len(),
+        MAX_U24_PAYLOAD
+    );
+
+    // one byte more is rejected although the bytes are available
+    buf[..3].copy_from_slice(&[0x01, 0x00, 0x01]);
+    assert!(PayloadU24::read_bytes(&buf).is_none());
+    assert!(Certificate::read_bytes(&buf).is_none());
+}
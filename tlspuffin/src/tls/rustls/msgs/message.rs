@@ -33,6 +33,8 @@ pub enum MessagePayload {
     Handshake(HandshakeMessagePayload),
     // this type is for TLS 1.2 encrypted handshake messages
     TLS12EncryptedHandshake(Payload),
+    /// Several handshake messages which are sent in a single record
+    CoalescedHandshake(Vec<HandshakeMessagePayload>),
     ChangeCipherSpec(ChangeCipherSpecPayload),
     ApplicationData(Payload),
     Heartbeat(HeartbeatPayload),
@@ -56,6 +58,11 @@ impl MessagePayload {
             Self::Alert(ref x) => x.encode(bytes),
             Self::Handshake(ref x) => x.encode(bytes),
             Self::TLS12EncryptedHandshake(ref x) => x.encode(bytes),
+            Self::CoalescedHandshake(ref messages) => {
+                for message in messages {
+                    message.encode(bytes);
+                }
+            }
             Self::ChangeCipherSpec(ref x) => x.encode(bytes),
             Self::ApplicationData(ref x) => x.encode(bytes),
             Self::Heartbeat(ref x) => x.encode(bytes),
@@ -70,6 +77,8 @@ impl MessagePayload {
             ContentType::Alert => AlertMessagePayload::read(&mut r).map(MessagePayload::Alert),
             ContentType::Handshake => {
                 HandshakeMessagePayload::read_version(&mut r, vers)
+                    // keeps the readable fields of a malformed ClientHello as knowledge
+                    .or_else(|| HandshakeMessagePayload::read_partial_client_hello(&payload.0))
                     .map(MessagePayload::Handshake)
                     // this type is for TLS 1.2 encrypted handshake messages
                     .or(Some(MessagePayload::TLS12EncryptedHandshake(
@@ -95,12 +104,16 @@ impl MessagePayload {
         vers: ProtocolVersion,
         payload: Payload,
     ) -> Result<Vec<Self>, Error> {
+        // Application data is not framed, also a zero-length record is a single message
+        if typ == ContentType::ApplicationData {
+            return Ok(vec![Self::ApplicationData(payload)]);
+        }
+
         let fallback_payload = &payload;
         let mut r = Reader::init(&payload.0);
         let mut parsed: Vec<Self> = vec![];
         while r.any_left() {
             let parsed_msg = match typ {
-                ContentType::ApplicationData => Some(Self::ApplicationData(payload.clone())),
                 ContentType::Alert => AlertMessagePayload::read(&mut r).map(MessagePayload::Alert),
                 ContentType::Handshake => {
                     HandshakeMessagePayload::read_version(&mut r, vers)
@@ -131,6 +144,7 @@ impl MessagePayload {
             Self::Alert(_) => ContentType::Alert,
             Self::Handshake(_) => ContentType::Handshake,
             Self::TLS12EncryptedHandshake(_) => ContentType::Handshake,
+            Self::CoalescedHandshake(_) => ContentType::Handshake,
             Self::ChangeCipherSpec(_) => ContentType::ChangeCipherSpec,
             Self::ApplicationData(_) => ContentType::ApplicationData,
             Self::Heartbeat(_) => ContentType::Heartbeat,
@@ -337,6 +351,11 @@ impl Message {
             payload: MessagePayload::Handshake(HandshakeMessagePayload::build_key_update_notify()),
         }
     }
+
+    /// Hex dump of the record which encodes this message, for debugging
+    pub fn hex_dump(&self) -> String {
+        codec::hex_dump(&Codec::get_encoding(self))
+    }
 }
 
 /// Parses a plaintext message into a well-typed [`Message`].
@@ -410,6 +429,8 @@ impl VecCodecWoSize for Certificate {} // u24, no need?
 impl VecCodecWoSize for CertificateEntry {} // u24
 impl VecCodecWoSize for CipherSuite {} // u16
 impl VecCodecWoSize for PresharedKeyIdentity {} //u16
+impl VecCodecWoSize for NamedGroup {} // u16
+impl VecCodecWoSize for SignatureScheme {} // u16
 
 #[macro_export]
 macro_rules! try_read {
@@ -505,12 +526,14 @@ pub fn try_read_bytes(
         Vec<PresharedKeyIdentity>,
         PresharedKeyIdentity,
         AlertMessagePayload,
+        Vec<SignatureScheme>,
         SignatureScheme,
         ProtocolVersion,
         HandshakeHash,
         u64,
         u32,
-        // u8,
+        u16,
+        u8,
         // Vec<u64>,
         PayloadU24,
         PayloadU16,
@@ -535,6 +558,7 @@ pub fn try_read_bytes(
         // Result<Message FnError>,
         // MessagePayload,
         // ExtensionType,
+        Vec<NamedGroup>,
         NamedGroup
     )
 }
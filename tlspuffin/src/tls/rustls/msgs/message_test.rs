@@ -31,3 +31,12 @@ fn construct_all_types() {
         // println!("m' = {:?}", m);
     }
 }
+
+#[test_log::test]
+fn alert_hex_dump() {
+    let m = Message::build_alert(AlertLevel::Fatal, AlertDescription::DecodeError);
+    assert_eq!(
+        m.hex_dump(),
+        "00000000  15 03 03 00 02 02 32                              |......2|\n"
+    );
+}
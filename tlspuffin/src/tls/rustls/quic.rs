@@ -114,6 +114,8 @@ impl HeaderProtectionKey {
             BulkAlgorithm::Aes128Gcm => &aead::quic::AES_128,
             BulkAlgorithm::Aes256Gcm => &aead::quic::AES_256,
             BulkAlgorithm::Chacha20Poly1305 => &aead::quic::CHACHA20,
+            #[cfg(feature = "aes-ccm")]
+            BulkAlgorithm::Aes128Ccm | BulkAlgorithm::Aes128Ccm8 => &aead::quic::AES_128,
         };
 
         Self(hkdf_expand(secret, alg, b"quic hp", &[]))
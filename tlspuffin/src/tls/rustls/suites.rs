@@ -12,6 +12,8 @@ use crate::tls::rustls::tls13::{
     Tls13CipherSuite, TLS13_AES_128_GCM_SHA256, TLS13_AES_256_GCM_SHA384,
     TLS13_CHACHA20_POLY1305_SHA256,
 };
+#[cfg(feature = "aes-ccm")]
+use crate::tls::rustls::tls13::{TLS13_AES_128_CCM_8_SHA256, TLS13_AES_128_CCM_SHA256};
 use crate::tls::rustls::versions::{SupportedProtocolVersion, TLS12, TLS13};
 
 /// Bulk symmetric encryption scheme used by a cipher suite.
@@ -26,6 +28,14 @@ pub enum BulkAlgorithm {
 
     /// Chacha20 for confidentiality with poly1305 for authenticity.
     Chacha20Poly1305,
+
+    /// AES with 128-bit keys in counter mode with CBC-MAC.
+    #[cfg(feature = "aes-ccm")]
+    Aes128Ccm,
+
+    /// AES with 128-bit keys in counter mode with CBC-MAC, truncated to 8 bytes.
+    #[cfg(feature = "aes-ccm")]
+    Aes128Ccm8,
 }
 
 /// Common state for cipher suites (both for TLS 1.2 and TLS 1.3)
@@ -113,6 +123,10 @@ pub static ALL_CIPHER_SUITES: &[SupportedCipherSuite] = &[
     TLS13_AES_256_GCM_SHA384,
     TLS13_AES_128_GCM_SHA256,
     TLS13_CHACHA20_POLY1305_SHA256,
+    #[cfg(feature = "aes-ccm")]
+    TLS13_AES_128_CCM_SHA256,
+    #[cfg(feature = "aes-ccm")]
+    TLS13_AES_128_CCM_8_SHA256,
     // TLS1.2 suites
     TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384,
     TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256,
@@ -207,7 +207,7 @@ impl ConnectionSecrets {
     pub fn from_key_exchange(
         kx: kx::KeyExchange,
         peer_pub_key: &[u8],
-        ems_seed: Option<Digest>,
+        ems_seed: Option<&[u8]>,
         randoms: ConnectionRandoms,
         suite: &'static Tls12CipherSuite,
     ) -> Result<Self, Error> {
@@ -218,7 +218,7 @@ impl ConnectionSecrets {
         };
 
         let (label, seed) = match ems_seed {
-            Some(seed) => ("extended master secret", Seed::Ems(seed)),
+            Some(seed) => ("extended master secret", Seed::Ems(seed.to_vec())),
             None => (
                 "master secret",
                 Seed::Randoms(join_randoms(&ret.randoms.client, &ret.randoms.server)),
@@ -301,7 +301,7 @@ impl ConnectionSecrets {
         )
     }
 
-    fn make_key_block(&self) -> Vec<u8> {
+    pub fn make_key_block(&self) -> Vec<u8> {
         let suite = &self.suite;
         let common = &self.suite.common;
 
@@ -376,7 +376,7 @@ impl ConnectionSecrets {
 }
 
 enum Seed {
-    Ems(Digest),
+    Ems(Vec<u8>),
     Randoms([u8; 64]),
 }
 
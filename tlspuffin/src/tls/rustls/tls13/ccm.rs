@@ -0,0 +1,139 @@
+//! AES-CCM record protection for the TLS 1.3 suites `TLS_AES_128_CCM_SHA256` and
+//! `TLS_AES_128_CCM_8_SHA256`. ring does not implement CCM, therefore the records are protected
+//! using the RustCrypto implementation.
+
+use aes::Aes128;
+use ccm::aead::generic_array::typenum::Unsigned;
+use ccm::aead::generic_array::GenericArray;
+use ccm::aead::{AeadInPlace, KeyInit};
+use ccm::consts::{U12, U16, U8};
+use ccm::Ccm;
+use puffin::codec::Codec;
+use ring::hkdf;
+
+use super::key_schedule::{derive_traffic_iv, hkdf_expand, PayloadU8Len};
+use super::{make_tls13_aad, unpad_tls13};
+use crate::tls::rustls::cipher::{make_nonce, Iv, MessageDecrypter, MessageEncrypter};
+use crate::tls::rustls::error::Error;
+use crate::tls::rustls::msgs::base::{Payload, PayloadU8};
+use crate::tls::rustls::msgs::enums::{ContentType, ProtocolVersion};
+use crate::tls::rustls::msgs::fragmenter::MAX_FRAGMENT_LEN;
+use crate::tls::rustls::msgs::message::{BorrowedPlainMessage, OpaqueMessage, PlainMessage};
+
+/// AES-128-CCM with the full 16-byte tag
+pub type Aes128Ccm = Ccm<Aes128, U16, U12>;
+/// AES-128-CCM with the truncated 8-byte tag
+pub type Aes128Ccm8 = Ccm<Aes128, U8, U12>;
+
+/// Length of an AES-128 key
+const KEY_LEN: usize = 16;
+
+struct Tls13CcmMessageEncrypter<C> {
+    enc_key: C,
+    iv: Iv,
+}
+
+struct Tls13CcmMessageDecrypter<C> {
+    dec_key: C,
+    iv: Iv,
+}
+
+fn derive_key<C: KeyInit>(secret: &hkdf::Prk) -> C {
+    let key: PayloadU8 = hkdf_expand(secret, PayloadU8Len(KEY_LEN), b"key", &[]);
+    C::new_from_slice(&key.0).unwrap()
+}
+
+pub fn derive_encrypter<C>(secret: &hkdf::Prk) -> Box<dyn MessageEncrypter>
+where
+    C: AeadInPlace + KeyInit + Send + Sync + 'static,
+{
+    Box::new(Tls13CcmMessageEncrypter {
+        enc_key: derive_key::<C>(secret),
+        iv: derive_traffic_iv(secret),
+    })
+}
+
+pub fn derive_decrypter<C>(secret: &hkdf::Prk) -> Box<dyn MessageDecrypter>
+where
+    C: AeadInPlace + KeyInit + Send + Sync + 'static,
+{
+    Box::new(Tls13CcmMessageDecrypter {
+        dec_key: derive_key::<C>(secret),
+        iv: derive_traffic_iv(secret),
+    })
+}
+
+fn nonce_bytes(iv: &Iv, seq: u64) -> [u8; ring::aead::NONCE_LEN] {
+    *make_nonce(iv, seq).as_ref()
+}
+
+impl<C> MessageEncrypter for Tls13CcmMessageEncrypter<C>
+where
+    C: AeadInPlace + Send + Sync,
+{
+    fn encrypt(&self, msg: BorrowedPlainMessage, seq: u64) -> Result<OpaqueMessage, Error> {
+        let tag_len = C::TagSize::USIZE;
+        let total_len = msg.payload.len() + 1 + tag_len;
+        let mut payload = Vec::with_capacity(total_len);
+        payload.extend_from_slice(msg.payload);
+        msg.typ.encode(&mut payload);
+
+        let nonce = nonce_bytes(&self.iv, seq);
+        let aad = make_tls13_aad(total_len);
+
+        let tag = self
+            .enc_key
+            .encrypt_in_place_detached(GenericArray::from_slice(&nonce), aad.as_ref(), &mut payload)
+            .map_err(|_| Error::General("encrypt failed".to_string()))?;
+        payload.extend_from_slice(&tag);
+
+        Ok(OpaqueMessage {
+            typ: ContentType::ApplicationData,
+            version: ProtocolVersion::TLSv1_2,
+            payload: Payload::new(payload),
+        })
+    }
+}
+
+impl<C> MessageDecrypter for Tls13CcmMessageDecrypter<C>
+where
+    C: AeadInPlace + Send + Sync,
+{
+    fn decrypt(&self, mut msg: OpaqueMessage, seq: u64) -> Result<PlainMessage, Error> {
+        let tag_len = C::TagSize::USIZE;
+        let payload = &mut msg.payload.0;
+        if payload.len() < tag_len {
+            return Err(Error::DecryptError);
+        }
+
+        let nonce = nonce_bytes(&self.iv, seq);
+        let aad = make_tls13_aad(payload.len());
+        let tag = payload.split_off(payload.len() - tag_len);
+
+        self.dec_key
+            .decrypt_in_place_detached(
+                GenericArray::from_slice(&nonce),
+                aad.as_ref(),
+                payload,
+                GenericArray::from_slice(&tag),
+            )
+            .map_err(|_| Error::DecryptError)?;
+
+        if payload.len() > MAX_FRAGMENT_LEN + 1 {
+            return Err(Error::PeerSentOversizedRecord);
+        }
+
+        msg.typ = unpad_tls13(payload);
+        if msg.typ == ContentType::Unknown(0) {
+            let msg = "peer sent bad TLSInnerPlaintext".to_string();
+            return Err(Error::PeerMisbehavedError(msg));
+        }
+
+        if payload.len() > MAX_FRAGMENT_LEN {
+            return Err(Error::PeerSentOversizedRecord);
+        }
+
+        msg.version = ProtocolVersion::TLSv1_3;
+        Ok(msg.into_plain_message())
+    }
+}
@@ -474,30 +474,40 @@ impl KeySchedule {
         label: &[u8],
         context: Option<&[u8]>,
     ) -> Result<(), Error> {
-        let digest_alg = self.algorithm.hmac_algorithm().digest_algorithm();
-
-        let h_empty = digest::digest(digest_alg, &[]);
-        let secret: hkdf::Prk = hkdf_expand(
-            current_exporter_secret,
-            self.algorithm,
-            label,
-            h_empty.as_ref(),
-        );
-
-        let h_context = digest::digest(digest_alg, context.unwrap_or(&[]));
-
-        // TODO: Test what happens when this fails
-        hkdf_expand_info(
-            &secret,
-            PayloadU8Len(out.len()),
-            b"exporter",
-            h_context.as_ref(),
-            |okm| okm.fill(out),
-        )
-        .map_err(|_| Error::General("exporting too much".to_string()))
+        export_keying_material(self.algorithm, current_exporter_secret, out, label, context)
     }
 }
 
+/// Fills `out` with the keying material exported from `exporter_secret` (RFC 8446, Section 7.5).
+///
+/// Fails if more than 255 times the hash length is requested, which HKDF can not expand.
+pub fn export_keying_material(
+    algorithm: hkdf::Algorithm,
+    exporter_secret: &hkdf::Prk,
+    out: &mut [u8],
+    label: &[u8],
+    context: Option<&[u8]>,
+) -> Result<(), Error> {
+    let digest_alg = algorithm.hmac_algorithm().digest_algorithm();
+    if out.len() > 255 * digest_alg.output_len {
+        return Err(Error::General("exporting too much".to_string()));
+    }
+
+    let h_empty = digest::digest(digest_alg, &[]);
+    let secret: hkdf::Prk = hkdf_expand(exporter_secret, algorithm, label, h_empty.as_ref());
+
+    let h_context = digest::digest(digest_alg, context.unwrap_or(&[]));
+
+    hkdf_expand_info(
+        &secret,
+        PayloadU8Len(out.len()),
+        b"exporter",
+        h_context.as_ref(),
+        |okm| okm.fill(out),
+    )
+    .map_err(|_| Error::General("exporting too much".to_string()))
+}
+
 pub fn hkdf_expand<T, L>(secret: &hkdf::Prk, key_type: L, label: &[u8], context: &[u8]) -> T
 where
     T: for<'a> From<hkdf::Okm<'a, L>>,
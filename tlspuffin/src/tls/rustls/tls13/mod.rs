@@ -10,6 +10,8 @@ use crate::tls::rustls::msgs::fragmenter::MAX_FRAGMENT_LEN;
 use crate::tls::rustls::msgs::message::{BorrowedPlainMessage, OpaqueMessage, PlainMessage};
 use crate::tls::rustls::suites::{BulkAlgorithm, CipherSuiteCommon, SupportedCipherSuite};
 
+#[cfg(feature = "aes-ccm")]
+mod ccm;
 pub mod key_schedule;
 use key_schedule::{derive_traffic_iv, derive_traffic_key};
 use puffin::codec::Codec;
@@ -57,6 +59,40 @@ pub static TLS13_AES_128_GCM_SHA256_INTERNAL: &Tls13CipherSuite = &Tls13CipherSu
     integrity_limit: 1 << 52,
 };
 
+/// The TLS1.3 ciphersuite TLS_AES_128_CCM_SHA256
+///
+/// The `aead_algorithm` only determines the length of the traffic key, the records are protected
+/// with AES-CCM.
+#[cfg(feature = "aes-ccm")]
+pub static TLS13_AES_128_CCM_SHA256: SupportedCipherSuite =
+    SupportedCipherSuite::Tls13(&Tls13CipherSuite {
+        common: CipherSuiteCommon {
+            suite: CipherSuite::TLS13_AES_128_CCM_SHA256,
+            bulk: BulkAlgorithm::Aes128Ccm,
+            aead_algorithm: &ring::aead::AES_128_GCM,
+        },
+        hkdf_algorithm: ring::hkdf::HKDF_SHA256,
+        confidentiality_limit: 1 << 23,
+        integrity_limit: 1 << 52,
+    });
+
+/// The TLS1.3 ciphersuite TLS_AES_128_CCM_8_SHA256
+///
+/// The `aead_algorithm` only determines the length of the traffic key, the records are protected
+/// with AES-CCM and an 8-byte tag.
+#[cfg(feature = "aes-ccm")]
+pub static TLS13_AES_128_CCM_8_SHA256: SupportedCipherSuite =
+    SupportedCipherSuite::Tls13(&Tls13CipherSuite {
+        common: CipherSuiteCommon {
+            suite: CipherSuite::TLS13_AES_128_CCM_8_SHA256,
+            bulk: BulkAlgorithm::Aes128Ccm8,
+            aead_algorithm: &ring::aead::AES_128_GCM,
+        },
+        hkdf_algorithm: ring::hkdf::HKDF_SHA256,
+        confidentiality_limit: 1 << 23,
+        integrity_limit: 1 << 52,
+    });
+
 /// A TLS 1.3 cipher suite supported by rustls.
 pub struct Tls13CipherSuite {
     /// Common cipher suite fields.
@@ -68,6 +104,14 @@ pub struct Tls13CipherSuite {
 
 impl Tls13CipherSuite {
     pub fn derive_encrypter(&self, secret: &hkdf::Prk) -> Box<dyn MessageEncrypter> {
+        match self.common.bulk {
+            #[cfg(feature = "aes-ccm")]
+            BulkAlgorithm::Aes128Ccm => return ccm::derive_encrypter::<ccm::Aes128Ccm>(secret),
+            #[cfg(feature = "aes-ccm")]
+            BulkAlgorithm::Aes128Ccm8 => return ccm::derive_encrypter::<ccm::Aes128Ccm8>(secret),
+            _ => {}
+        }
+
         let key = derive_traffic_key(secret, self.common.aead_algorithm);
         let iv = derive_traffic_iv(secret);
 
@@ -80,6 +124,14 @@ impl Tls13CipherSuite {
     /// Derive a `MessageDecrypter` object from the concerned TLS 1.3
     /// cipher suite.
     pub fn derive_decrypter(&self, secret: &hkdf::Prk) -> Box<dyn MessageDecrypter> {
+        match self.common.bulk {
+            #[cfg(feature = "aes-ccm")]
+            BulkAlgorithm::Aes128Ccm => return ccm::derive_decrypter::<ccm::Aes128Ccm>(secret),
+            #[cfg(feature = "aes-ccm")]
+            BulkAlgorithm::Aes128Ccm8 => return ccm::derive_decrypter::<ccm::Aes128Ccm8>(secret),
+            _ => {}
+        }
+
         let key = derive_traffic_key(secret, self.common.aead_algorithm);
         let iv = derive_traffic_iv(secret);
 
@@ -131,6 +183,9 @@ struct Tls13MessageDecrypter {
     iv: Iv,
 }
 
+/// Strips the zero padding and the content type from the plaintext `v` of a record. If the record
+/// carries no data, `v` is empty afterwards. Returns `ContentType::Unknown(0)` if `v` consists of
+/// padding only.
 fn unpad_tls13(v: &mut Vec<u8>) -> ContentType {
     loop {
         match v.pop() {
@@ -211,3 +266,48 @@ impl MessageDecrypter for Tls13MessageDecrypter {
         Ok(msg.into_plain_message())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_log::test]
+    fn test_unpad_empty_record() {
+        let mut plaintext = vec![0x17, 0x00, 0x00];
+        assert_eq!(unpad_tls13(&mut plaintext), ContentType::ApplicationData);
+        assert!(plaintext.is_empty());
+
+        let mut plaintext = vec![0x00, 0x00];
+        assert_eq!(unpad_tls13(&mut plaintext), ContentType::Unknown(0));
+    }
+
+    #[cfg(feature = "aes-ccm")]
+    #[test_log::test]
+    fn test_ccm_8_record_fails_under_ccm() {
+        let secret = hkdf::Prk::new_less_safe(hkdf::HKDF_SHA256, &[0x42; 32]);
+        let ccm = TLS13_AES_128_CCM_SHA256.tls13().unwrap();
+        let ccm_8 = TLS13_AES_128_CCM_8_SHA256.tls13().unwrap();
+
+        let message = PlainMessage {
+            typ: ContentType::ApplicationData,
+            version: ProtocolVersion::TLSv1_3,
+            payload: Payload::new(b"hello".to_vec()),
+        };
+        let record = ccm_8
+            .derive_encrypter(&secret)
+            .encrypt(message.borrow(), 0)
+            .unwrap();
+        assert_eq!(record.payload.0.len(), b"hello".len() + 1 + 8);
+
+        let decrypted = ccm_8
+            .derive_decrypter(&secret)
+            .decrypt(record.clone(), 0)
+            .unwrap();
+        assert_eq!(decrypted.payload.0, b"hello");
+
+        assert!(matches!(
+            ccm.derive_decrypter(&secret).decrypt(record, 0),
+            Err(Error::DecryptError)
+        ));
+    }
+}
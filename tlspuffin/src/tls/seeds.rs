@@ -24,6 +24,9 @@ pub fn seed_successful_client_auth(
 ) -> Trace<TLSProtocolTypes> {
     Trace {
         prior_traces: vec![],
+        expected_outcome: None,
+        expected_flow: None,
+        seed: None,
         descriptors: vec![
             AgentDescriptor::from_config(
                 client,
@@ -164,6 +167,9 @@ pub fn seed_successful_client_auth(
 pub fn seed_successful(client: AgentName, server: AgentName) -> Trace<TLSProtocolTypes> {
     Trace {
         prior_traces: vec![],
+        expected_outcome: None,
+        expected_flow: None,
+        seed: None,
         descriptors: vec![
             TLSDescriptorConfig::new_client(client, TLSVersion::V1_3),
             TLSDescriptorConfig::new_server(server, TLSVersion::V1_3),
@@ -203,6 +209,9 @@ pub fn seed_successful(client: AgentName, server: AgentName) -> Trace<TLSProtoco
 pub fn seed_successful_mitm(client: AgentName, server: AgentName) -> Trace<TLSProtocolTypes> {
     Trace {
         prior_traces: vec![],
+        expected_outcome: None,
+        expected_flow: None,
+        seed: None,
         descriptors: vec![
             TLSDescriptorConfig::new_client(client, TLSVersion::V1_3),
             TLSDescriptorConfig::new_server(server, TLSVersion::V1_3),
@@ -286,6 +295,9 @@ pub fn seed_successful12_with_tickets(
 pub fn seed_successful12(client: AgentName, server: AgentName) -> Trace<TLSProtocolTypes> {
     Trace {
         prior_traces: vec![],
+        expected_outcome: None,
+        expected_flow: None,
+        seed: None,
         descriptors: vec![
             TLSDescriptorConfig::new_client(client, TLSVersion::V1_2),
             TLSDescriptorConfig::new_server(server, TLSVersion::V1_2),
@@ -401,6 +413,9 @@ pub fn seed_successful12(client: AgentName, server: AgentName) -> Trace<TLSProto
 pub fn seed_successful_with_ccs(client: AgentName, server: AgentName) -> Trace<TLSProtocolTypes> {
     Trace {
         prior_traces: vec![],
+        expected_outcome: None,
+        expected_flow: None,
+        seed: None,
         descriptors: vec![
             TLSDescriptorConfig::new_client(client, TLSVersion::V1_3),
             TLSDescriptorConfig::new_server(server, TLSVersion::V1_3),
@@ -613,12 +628,10 @@ pub fn seed_server_attacker_full(client: AgentName) -> Trace<TLSProtocolTypes> {
     let certificate_verify = term! {
         fn_certificate_verify(
             fn_rsa_pss_signature_algorithm,
-            (fn_payload_u16(
-                (fn_rsa_sign_server(
-                    (@certificate_transcript),
-                    fn_alice_key,
-                    fn_rsa_pss_signature_algorithm
-                ))
+            (fn_rsa_sign_server(
+                (@certificate_transcript),
+                fn_alice_key,
+                fn_rsa_pss_signature_algorithm
             ))
         )
     };
@@ -645,6 +658,9 @@ pub fn seed_server_attacker_full(client: AgentName) -> Trace<TLSProtocolTypes> {
 
     Trace {
         prior_traces: vec![],
+        expected_outcome: None,
+        expected_flow: None,
+        seed: None,
         descriptors: vec![AgentDescriptor::from_config(
             client,
             TLSDescriptorConfig {
@@ -787,12 +803,10 @@ pub fn seed_client_attacker_auth(server: AgentName) -> Trace<TLSProtocolTypes> {
     let certificate_verify = term! {
         fn_certificate_verify(
             fn_rsa_pss_signature_algorithm,
-            (fn_payload_u16(
-                (fn_rsa_sign_client(
-                    (fn_certificate_transcript(((server, 0)))),
-                    fn_bob_key,
-                    fn_rsa_pss_signature_algorithm
-                ))
+            (fn_rsa_sign_client(
+                (fn_certificate_transcript(((server, 0)))),
+                fn_bob_key,
+                fn_rsa_pss_signature_algorithm
             ))
         )
     };
@@ -811,6 +825,9 @@ pub fn seed_client_attacker_auth(server: AgentName) -> Trace<TLSProtocolTypes> {
 
     Trace {
         prior_traces: vec![],
+        expected_outcome: None,
+        expected_flow: None,
+        seed: None,
         descriptors: vec![AgentDescriptor::from_config(
             server,
             TLSDescriptorConfig {
@@ -920,6 +937,9 @@ pub fn seed_client_attacker(server: AgentName) -> Trace<TLSProtocolTypes> {
 
     Trace {
         prior_traces: vec![],
+        expected_outcome: None,
+        expected_flow: None,
+        seed: None,
         descriptors: vec![TLSDescriptorConfig::new_server(server, TLSVersion::V1_3)],
         steps: vec![
             Step {
@@ -980,7 +1000,7 @@ pub fn _seed_client_attacker12(
                                 )),
                                 fn_signature_algorithm_extension
                             )),
-                            fn_ec_point_formats_extension
+                            (fn_ec_point_formats_extension(fn_ec_point_formats_uncompressed))
                         )),
                         fn_signed_certificate_timestamp_extension
                     )),
@@ -1052,6 +1072,9 @@ pub fn _seed_client_attacker12(
 
     let trace = Trace {
         prior_traces: vec![],
+        expected_outcome: None,
+        expected_flow: None,
+        seed: None,
         descriptors: vec![TLSDescriptorConfig::new_server(server, TLSVersion::V1_2)],
         steps: vec![
             Step {
@@ -1091,6 +1114,60 @@ pub fn _seed_client_attacker12(
     (trace, client_verify_data)
 }
 
+/// TLS 1.2 session resumption by session id: After a full handshake, the attacker offers the
+/// session id of the ServerHello in a new ClientHello, such that the server resumes the session.
+pub fn seed_session_resumption12(
+    initial_server: AgentName,
+    server: AgentName,
+) -> Trace<TLSProtocolTypes> {
+    _seed_session_resumption12(initial_server, server, term! { fn_cipher_suite12 })
+}
+
+/// See [`seed_session_resumption12`]. The server must not resume the session if `cipher_suite`
+/// differs from the cipher suite of the session.
+pub fn _seed_session_resumption12(
+    initial_server: AgentName,
+    server: AgentName,
+    cipher_suite: Term<TLSProtocolTypes>,
+) -> Trace<TLSProtocolTypes> {
+    let initial_handshake = seed_client_attacker12(initial_server);
+
+    let client_hello = term! {
+          fn_client_hello(
+            fn_protocol_version12,
+            fn_new_random,
+            // the session id which the server assigned in the initial handshake
+            ((initial_server, 0)[Some(TlsQueryMatcher::Handshake(Some(HandshakeType::ServerHello)))]/SessionID),
+            (fn_cipher_suites_make(
+                (fn_append_cipher_suite(
+                  (fn_new_cipher_suites()),
+                  (@cipher_suite)
+            )))),
+            fn_compressions,
+            (fn_client_extensions_make(
+            (fn_client_extensions_append(
+                (fn_client_extensions_append(
+                    (fn_client_extensions_append(
+                        fn_client_extensions_new,
+                        (fn_support_group_extension(fn_named_group_secp384r1))
+                    )),
+                    fn_signature_algorithm_extension
+                )),
+                (fn_ec_point_formats_extension(fn_ec_point_formats_uncompressed))
+            ))
+        )))
+    };
+
+    Trace {
+        prior_traces: vec![initial_handshake],
+        expected_outcome: None,
+        expected_flow: None,
+        seed: None,
+        descriptors: vec![TLSDescriptorConfig::new_server(server, TLSVersion::V1_2)],
+        steps: vec![InputAction::new_step(server, client_hello)],
+    }
+}
+
 // TODO: `"Unable to find variable (Some(Agent(AgentName(0))), 1)[None]/MessageFlight!"` error with
 // BoringSSL
 pub fn seed_session_resumption_dhe(
@@ -1191,6 +1268,9 @@ pub fn seed_session_resumption_dhe(
 
     Trace {
         prior_traces: vec![initial_handshake],
+        expected_outcome: None,
+        expected_flow: None,
+        seed: None,
         descriptors: vec![TLSDescriptorConfig::new_server(server, TLSVersion::V1_3)],
         steps: vec![
             Step {
@@ -1319,6 +1399,9 @@ pub fn seed_session_resumption_ke(
 
     Trace {
         prior_traces: vec![initial_handshake],
+        expected_outcome: None,
+        expected_flow: None,
+        seed: None,
         descriptors: vec![TLSDescriptorConfig::new_server(server, TLSVersion::V1_3)],
         steps: vec![
             Step {
@@ -1477,6 +1560,9 @@ pub fn _seed_client_attacker_full(
 
     let trace = Trace {
         prior_traces: vec![],
+        expected_outcome: None,
+        expected_flow: None,
+        seed: None,
         descriptors: vec![TLSDescriptorConfig::new_server(server, TLSVersion::V1_3)],
         steps: vec![
             Step {
@@ -1667,6 +1753,9 @@ pub fn _seed_client_attacker_full_precomputation(
 
     let trace = Trace {
         prior_traces: vec![],
+        expected_outcome: None,
+        expected_flow: None,
+        seed: None,
         descriptors: vec![TLSDescriptorConfig::new_server(server, TLSVersion::V1_3)],
         steps: vec![
             Step {
@@ -1856,6 +1945,9 @@ pub fn seed_session_resumption_dhe_full(
 
     Trace {
         prior_traces: vec![initial_handshake],
+        expected_outcome: None,
+        expected_flow: None,
+        seed: None,
         descriptors: vec![TLSDescriptorConfig::new_server(server, TLSVersion::V1_3)],
         steps: vec![
             Step {
@@ -1937,16 +2029,143 @@ pub fn create_corpus(
         // Session resumption
         seed_session_resumption_dhe: put.supports("tls13") && put.supports("tls13-session-resumption"),
         seed_session_resumption_ke: put.supports("tls13") && put.supports("tls13-session-resumption"),
+        seed_session_resumption12: put.supports("tls12") && put.supports("tls12-session-resumption"),
         // Server Attackers
         seed_server_attacker_full: put.supports("tls13"),
     )
 }
 
+/// ClientHello which offers TLS 1.3 cipher suites only, such that it can not be completed as a
+/// TLS 1.2 handshake
+fn client_hello13_with_extensions(extensions: Term<TLSProtocolTypes>) -> Term<TLSProtocolTypes> {
+    term! {
+        fn_client_hello(
+            fn_protocol_version12,
+            fn_new_random,
+            fn_new_session_id,
+            (fn_cipher_suites_make(
+                (fn_append_cipher_suite(
+                    (fn_new_cipher_suites()),
+                    fn_cipher_suite13_aes_128_gcm_sha256
+                ))
+            )),
+            fn_compressions,
+            (fn_client_extensions_make((@extensions)))
+        )
+    }
+}
+
+/// A ClientHello without the supported_versions extension is a valid TLS 1.2 ClientHello. The
+/// server has to reject it nevertheless because only TLS 1.3 cipher suites are offered.
+pub fn seed_negative_missing_supported_versions13(server: AgentName) -> Trace<TLSProtocolTypes> {
+    let client_hello = client_hello13_with_extensions(term! {
+        fn_client_extensions_append(
+            (fn_client_extensions_append(
+                (fn_client_extensions_append(
+                    fn_client_extensions_new,
+                    (fn_support_group_extension(fn_named_group_secp384r1))
+                )),
+                fn_signature_algorithm_extension
+            )),
+            (fn_key_share_deterministic_extension(fn_named_group_secp384r1))
+        )
+    });
+
+    Trace {
+        prior_traces: vec![],
+        expected_outcome: None,
+        expected_flow: None,
+        seed: None,
+        descriptors: vec![TLSDescriptorConfig::new_server(server, TLSVersion::V1_3)],
+        steps: vec![InputAction::new_step(server, client_hello)],
+    }
+}
+
+/// The signature_algorithms extension is mandatory in a TLS 1.3 ClientHello which does not
+/// resume a session (RFC 8446, section 9.2).
+pub fn seed_negative_missing_signature_algorithms13(server: AgentName) -> Trace<TLSProtocolTypes> {
+    let client_hello = client_hello13_with_extensions(term! {
+        fn_client_extensions_append(
+            (fn_client_extensions_append(
+                (fn_client_extensions_append(
+                    fn_client_extensions_new,
+                    (fn_support_group_extension(fn_named_group_secp384r1))
+                )),
+                (fn_key_share_deterministic_extension(fn_named_group_secp384r1))
+            )),
+            fn_supported_versions13_extension
+        )
+    });
+
+    Trace {
+        prior_traces: vec![],
+        expected_outcome: None,
+        expected_flow: None,
+        seed: None,
+        descriptors: vec![TLSDescriptorConfig::new_server(server, TLSVersion::V1_3)],
+        steps: vec![InputAction::new_step(server, client_hello)],
+    }
+}
+
+/// The ClientHello of [`seed_client_attacker`] is sent again instead of the client's Finished
+pub fn seed_negative_out_of_order13(server: AgentName) -> Trace<TLSProtocolTypes> {
+    let mut trace = seed_client_attacker(server);
+    trace.steps.truncate(1);
+    trace.steps.push(trace.steps[0].clone());
+
+    trace
+}
+
+/// The ChangeCipherSpec of [`seed_client_attacker12`] is sent before the ClientKeyExchange
+pub fn seed_negative_out_of_order12(server: AgentName) -> Trace<TLSProtocolTypes> {
+    let mut trace = seed_client_attacker12(server);
+    trace.steps.swap(1, 2);
+
+    trace
+}
+
+/// Creates traces which violate TLS, such that the server spawned from `descriptor` has to reject
+/// them.
+///
+/// Deviations which the specification tolerates are not included, e.g. a wrong legacy_version in a
+/// TLS 1.3 ClientHello which servers have to ignore. Clients are not covered yet.
+pub fn create_negative_corpus(
+    descriptor: &AgentDescriptor<TLSDescriptorConfig>,
+) -> Vec<(Trace<TLSProtocolTypes>, &'static str)> {
+    use puffin::trace_helper::TraceHelper;
+
+    macro_rules! negative_corpus {
+        ( $( $func:ident ),* $(,)? ) => {
+            vec![ $( ($func(descriptor.name), $func.fn_name()) ),* ]
+        };
+    }
+
+    if descriptor.protocol_config.typ != AgentType::Server {
+        return vec![];
+    }
+
+    let mut corpus = match descriptor.protocol_config.tls_version {
+        TLSVersion::V1_3 => negative_corpus!(
+            seed_negative_missing_supported_versions13,
+            seed_negative_missing_signature_algorithms13,
+            seed_negative_out_of_order13,
+        ),
+        TLSVersion::V1_2 => negative_corpus!(seed_negative_out_of_order12),
+    };
+
+    for (trace, _) in &mut corpus {
+        trace.descriptors = vec![descriptor.clone()];
+    }
+
+    corpus
+}
+
 #[cfg(test)]
 pub mod tests {
     use puffin::algebra::TermType;
 
     use super::*;
+    use crate::protocol::CertificateKeyPair;
     #[allow(unused_imports)]
     use crate::{test_utils::prelude::*, tls::seeds::*};
 
@@ -1980,6 +2199,110 @@ pub mod tests {
         assert!(ctx.agents_successful());
     }
 
+    #[apply(test_puts, filter = all(tls13, transcript_extraction))]
+    fn test_seed_client_attacker_corrupted_finished(put: &str) {
+        use puffin::execution::Runner;
+        use puffin::put::PutDescriptor;
+        use puffin::trace::Spawner;
+
+        use crate::tls::rustls::cipher::CORRUPT_EVERY_NTH_RECORD_OPTION;
+
+        let trace = seed_client_attacker.build_trace();
+        let run = |value: &str| {
+            let registry = tls_registry();
+            let spawner = Spawner::new(registry.clone()).with_default(PutDescriptor::new(
+                put,
+                vec![(CORRUPT_EVERY_NTH_RECORD_OPTION, value)],
+            ));
+
+            Runner::new(registry, spawner).execute(&trace)
+        };
+
+        // The attacker encrypts a single record, the Finished with sequence number 0
+        assert!(!run("1").is_ok_and(|ctx| ctx.agents_successful()));
+        assert!(run("2").unwrap().agents_successful());
+
+        for value in ["0", "one"] {
+            assert!(run(value).is_err());
+        }
+    }
+
+    #[test_log::test]
+    fn test_client_attacker_template() {
+        use std::collections::HashMap;
+
+        use puffin::trace::{Spawner, TraceContext};
+        use puffin::trace_template::TraceTemplate;
+
+        use crate::tls::rustls::msgs::handshake::HandshakePayload;
+        use crate::tls::rustls::msgs::message::MessagePayload;
+
+        let server = AgentName::first();
+
+        let mut trace = seed_client_attacker(server);
+        trace.steps[0] = InputAction::new_step(
+            server,
+            term! {
+                  fn_client_hello(
+                    fn_protocol_version12,
+                    fn_new_random,
+                    fn_new_session_id,
+                    (fn_cipher_suites_make(
+                         (fn_append_cipher_suite(
+                          (fn_new_cipher_suites()),
+                          ((!"cipher_suite", 0)/CipherSuite)
+                    )))),
+                    fn_compressions,
+                    (fn_client_extensions_make(
+                        (fn_client_extensions_append(
+                        (fn_client_extensions_append(
+                            (fn_client_extensions_append(
+                                (fn_client_extensions_append(
+                                    fn_client_extensions_new,
+                                    (fn_support_group_extension(fn_named_group_secp384r1))
+                                )),
+                                fn_signature_algorithm_extension
+                            )),
+                            (fn_key_share_deterministic_extension(fn_named_group_secp384r1))
+                        )),
+                        fn_supported_versions13_extension
+                    ))
+                )))
+            },
+        );
+        let template = TraceTemplate::new(trace, ["cipher_suite"]);
+
+        let ctx = TraceContext::new(Spawner::new(tls_registry()));
+        let suites = [
+            CipherSuite::TLS13_AES_128_GCM_SHA256,
+            CipherSuite::TLS13_AES_256_GCM_SHA384,
+        ];
+        let params: [Term<TLSProtocolTypes>; 2] = [
+            term! { fn_cipher_suite13_aes_128_gcm_sha256 },
+            term! { fn_cipher_suite13_aes_256_gcm_sha384 },
+        ];
+
+        for (suite, param) in suites.into_iter().zip(params) {
+            let trace = template
+                .instantiate(&HashMap::from([("cipher_suite".to_owned(), param)]))
+                .unwrap();
+            let Action::Input(input) = &trace.steps[0].action else {
+                panic!("expected the ClientHello input");
+            };
+
+            let evaluated = input.recipe.evaluate_dy(&ctx).unwrap();
+            let message: &Message = evaluated.as_any().downcast_ref().unwrap();
+            let MessagePayload::Handshake(handshake) = &message.payload else {
+                panic!("expected a handshake message");
+            };
+            let HandshakePayload::ClientHello(client_hello) = &handshake.payload else {
+                panic!("expected a ClientHello");
+            };
+
+            assert_eq!(client_hello.cipher_suites.0, vec![suite]);
+        }
+    }
+
     #[apply(test_puts, filter = all(tls13, client_authentication_transcript_extraction, not(boringssl)))]
     fn test_seed_client_attacker_auth(put: &str) {
         let runner = default_runner_for(put);
@@ -2021,6 +2344,29 @@ pub mod tests {
         assert!(ctx.agents_successful());
     }
 
+    #[apply(test_puts, filter = all(tls12, tls12_session_resumption))]
+    fn test_seed_session_resumption12(put: &str) {
+        use crate::tls::violation::find_resumed_sessions12;
+
+        let runner = default_runner_for(put);
+        let (initial_server, server) = (AgentName::first(), AgentName::first().next());
+
+        let resumed = |trace: Trace<TLSProtocolTypes>| {
+            let ctx = runner.execute(trace).unwrap();
+            let resumed = !find_resumed_sessions12(ctx.claims().slice()).is_empty();
+            resumed
+        };
+
+        assert!(resumed(seed_session_resumption12.build_trace()));
+
+        // the ClientHello does not offer the cipher suite of the session
+        assert!(!resumed(_seed_session_resumption12(
+            initial_server,
+            server,
+            term! { fn_cipher_suite12_aes_256_gcm_sha384 }
+        )));
+    }
+
     #[apply(test_puts, filter = all(tls13, tls13_session_resumption, not(disable_postauth), not(boringssl)))]
     fn test_seed_session_resumption_dhe(put: &str) {
         let runner = default_runner_for(put);
@@ -2031,6 +2377,69 @@ pub mod tests {
         assert!(ctx.agents_successful());
     }
 
+    #[apply(test_puts, filter = all(tls13, tls13_session_resumption, not(disable_postauth), not(boringssl)))]
+    fn test_resumption_master_secret_claim(put: &str) {
+        use crate::claims::{ClaimData, ClaimDataMessage};
+
+        let runner = default_runner_for(put);
+        let server = AgentName::first();
+        let ctx = runner.execute(seed_client_attacker.build_trace()).unwrap();
+        assert!(ctx.agents_successful());
+
+        let claimed = ctx
+            .claims()
+            .iter()
+            .filter_map(|claim| match &claim.data {
+                ClaimData::Message(ClaimDataMessage::Finished(finished))
+                    if claim.agent_name == server =>
+                {
+                    Some(finished.clone())
+                }
+                _ => None,
+            })
+            .last()
+            .expect("the server claims the Finished of the client");
+
+        // The PSK of the ticket derived from the claimed secret is the one which the attacker
+        // derives from the transcripts, i.e. the one which resumes the session
+        let ticket = term! {
+            fn_find_server_ticket(
+                (fn_decrypt_application_flight(
+                    ((server, 1)/MessageFlight),
+                    (fn_server_hello_transcript(((server, 0)))),
+                    (fn_server_finished_transcript(((server, 0)))),
+                    (fn_get_server_key_share(((server, 0)))),
+                    fn_no_psk,
+                    fn_named_group_secp384r1,
+                    fn_true,
+                    fn_seq_0
+                ))
+            )
+        };
+        let nonce: Term<TLSProtocolTypes> = term! {fn_get_ticket_nonce((@ticket))};
+        let psk: Term<TLSProtocolTypes> = term! {
+            fn_derive_psk(
+                (fn_server_hello_transcript(((server, 0)))),
+                (fn_server_finished_transcript(((server, 0)))),
+                (fn_client_finished_transcript(((server, 0)))),
+                (fn_get_server_key_share(((server, 0)[Some(TlsQueryMatcher::Handshake(Some(HandshakeType::ServerHello)))]))),
+                (@nonce),
+                fn_named_group_secp384r1
+            )
+        };
+
+        let nonce = nonce.evaluate_dy(&ctx).unwrap();
+        let psk = psk.evaluate_dy(&ctx).unwrap();
+        let derived = fn_derive_resumption_psk(
+            &fn_resumption_master_secret(&claimed).unwrap(),
+            nonce.as_any().downcast_ref().unwrap(),
+            &CipherSuite::TLS13_AES_128_GCM_SHA256,
+        )
+        .unwrap();
+
+        assert_eq!(psk.as_any().downcast_ref::<Vec<u8>>(), Some(&derived));
+    }
+
     #[apply(test_puts, filter = all(tls13, tls13_session_resumption, not(disable_postauth), not(boringssl)))]
     fn test_seed_session_resumption_dhe_full(put: &str) {
         let runner = default_runner_for(put);
@@ -2061,6 +2470,95 @@ pub mod tests {
         assert!(ctx.agents_successful());
     }
 
+    #[apply(test_puts, filter = all(tls13, not(boringssl)))]
+    fn test_handshake_complete_flag(put: &str) {
+        let runner = default_runner_for(put);
+        let trace = seed_successful.build_trace();
+        let (client, server) = (AgentName::first(), AgentName::first().next());
+
+        // the server still waits for the Finished of the client
+        let mut ctx = runner.new_context();
+        trace.execute_until_step(&mut ctx, 3).unwrap();
+        assert!(ctx.find_agent(client).unwrap().is_handshake_complete());
+        assert!(!ctx.find_agent(server).unwrap().is_handshake_complete());
+
+        let ctx = runner.execute(trace).unwrap();
+        assert!(ctx.find_agent(client).unwrap().is_handshake_complete());
+        assert!(ctx.find_agent(server).unwrap().is_handshake_complete());
+    }
+
+    #[apply(test_puts, filter = all(tls13))]
+    fn test_sent_message_types(put: &str) {
+        let runner = default_runner_for(put);
+        let (client, server) = (AgentName::first(), AgentName::first().next());
+
+        let ctx = runner.execute(seed_successful.build_trace()).unwrap();
+        let sent = ctx.sent_message_types();
+
+        assert_eq!(sent.first(), Some(&(client, "ClientHello")));
+        assert!(sent.contains(&(server, "ServerHello")));
+    }
+
+    #[apply(test_puts, filter = all(tls13, not(boringssl)))]
+    fn test_seed_successful_read_chunks(put: &str) {
+        use puffin::execution::Runner;
+        use puffin::put::PutDescriptor;
+        use puffin::trace::Spawner;
+
+        use crate::put::READ_CHUNK_OPTION;
+
+        let trace = seed_successful.build_trace();
+        let (client, server) = (AgentName::first(), AgentName::first().next());
+
+        // the agents read a single byte at a time
+        let registry = tls_registry();
+        let descriptor = PutDescriptor::new(put, vec![(READ_CHUNK_OPTION, "1")]);
+        let spawner = Spawner::new(registry.clone())
+            .with_default(put)
+            .with_mapping(&[(client, descriptor.clone()), (server, descriptor)]);
+        let ctx = Runner::new(registry, spawner).execute(&trace).unwrap();
+
+        assert!(ctx.agents_successful());
+    }
+
+    #[apply(test_puts, filter = all(tls13))]
+    fn test_invalid_read_chunk(put: &str) {
+        use puffin::execution::Runner;
+        use puffin::put::PutDescriptor;
+        use puffin::trace::Spawner;
+
+        use crate::put::READ_CHUNK_OPTION;
+
+        let trace = seed_successful.build_trace();
+
+        for value in ["0", "-1", "one"] {
+            let registry = tls_registry();
+            let spawner = Spawner::new(registry.clone())
+                .with_default(PutDescriptor::new(put, vec![(READ_CHUNK_OPTION, value)]));
+
+            assert!(Runner::new(registry, spawner).execute(&trace).is_err());
+        }
+    }
+
+    #[apply(test_puts, filter = all(tls13, not(boringssl)))]
+    fn test_handshake_complete_counted(put: &str) {
+        use std::ptr;
+
+        use puffin::fuzzer::harness::{completion_counter, harness};
+        use puffin::fuzzer::stats_stage::HANDSHAKE_COMPLETE;
+
+        let runner = default_runner_for(put);
+        let mut ctx = runner.new_context();
+
+        // the counter is global and other tests increment it concurrently
+        let before = HANDSHAKE_COMPLETE.value();
+        harness(&tls_registry(), &mut ctx, &seed_successful.build_trace());
+        assert!(HANDSHAKE_COMPLETE.value() > before);
+        assert!(
+            completion_counter(&ctx).is_some_and(|counter| ptr::eq(counter, &HANDSHAKE_COMPLETE))
+        );
+    }
+
     #[apply(test_puts, filter = all(tls13, not(boringssl)))]
     fn test_seed_successful_client_auth(put: &str) {
         let runner = default_runner_for(put);
@@ -2122,6 +2620,479 @@ pub mod tests {
         assert!(ctx.agents_successful());
     }
 
+    #[apply(test_puts, filter = all(openssl_binding, tls12))]
+    fn test_export_session12(put: &str) {
+        let runner = default_runner_for(put);
+        let client = AgentName::first();
+        let trace = seed_successful12.build_trace();
+
+        let ctx = runner.execute(trace).unwrap();
+        let session = ctx
+            .find_agent(client)
+            .unwrap()
+            .export_session()
+            .expect("no session was negotiated");
+
+        assert!(!session.bytes.is_empty());
+        assert!(!session.is_expired());
+    }
+
+    #[apply(test_puts, filter = all(openssl_binding, tls12, tls12_session_resumption))]
+    fn test_import_session_of_prior_trace12(put: &str) {
+        use puffin::algebra::signature::Signature;
+        use puffin::trace::{Query, Source};
+
+        let runner = default_runner_for(put);
+        let client = AgentName::first();
+        let server = client.next();
+
+        // the client offers the session of the prior trace in its ClientHello
+        let client_hello = Term::from(DYTerm::Variable(Signature::new_var(
+            TypeShape::of::<MessageFlight>(),
+            Some(Source::Step(0)),
+            None,
+            0,
+        )));
+        let trace = Trace {
+            prior_traces: vec![seed_successful12.build_trace()],
+            expected_outcome: None,
+            expected_flow: None,
+            seed: None,
+            descriptors: vec![
+                TLSDescriptorConfig::new_client(client, TLSVersion::V1_2),
+                TLSDescriptorConfig::new_server(server, TLSVersion::V1_2),
+            ],
+            steps: vec![
+                OutputAction::new_step(client),
+                InputAction::new_step(server, client_hello),
+                OutputAction::new_step(server),
+            ],
+        };
+
+        let ctx = runner.execute(trace).unwrap();
+        let session_id = |agent: AgentName, typ: HandshakeType, counter: u16| {
+            let query = Query {
+                source: Some(Source::Agent(agent)),
+                matcher: Some(TlsQueryMatcher::Handshake(Some(typ))),
+                counter,
+            };
+            ctx.find_variable(TypeShape::of::<SessionID>(), &query)
+                .and_then(|term| term.as_any().downcast_ref::<SessionID>())
+                .cloned()
+                .expect("no session id was sent")
+        };
+
+        // the server of the second handshake resumes the session by echoing the offered session
+        // id, a full handshake would assign a new one
+        let offered = session_id(client, HandshakeType::ClientHello, 1);
+        assert!(!offered.is_empty());
+        assert_eq!(session_id(server, HandshakeType::ServerHello, 1), offered);
+    }
+
+    #[apply(test_puts, filter = all(openssl_binding, tls12))]
+    fn test_output_one_flight12(put: &str) {
+        use puffin::stream::Stream;
+        use puffin::trace::{Query, Source, TraceContext};
+
+        let runner = default_runner_for(put);
+        let client = AgentName::first();
+        let server = client.next();
+        let trace = Trace {
+            prior_traces: vec![],
+            expected_outcome: None,
+            expected_flow: None,
+            seed: None,
+            descriptors: vec![
+                TLSDescriptorConfig::new_client(client, TLSVersion::V1_2),
+                TLSDescriptorConfig::new_server(server, TLSVersion::V1_2),
+            ],
+            steps: vec![],
+        };
+
+        // the server receives the ClientHello twice, which is unexpected once it replied to the
+        // first one
+        let receive_client_hello_twice = || {
+            let mut ctx = runner.execute(trace.clone()).unwrap();
+            let agent = ctx.find_agent_mut(client).unwrap();
+            agent.progress().unwrap();
+            let client_hello = agent.drain_output();
+
+            let agent = ctx.find_agent_mut(server).unwrap();
+            agent.add_to_inbound(&client_hello);
+            agent.add_to_inbound(&client_hello);
+            ctx
+        };
+        let has_sent = |ctx: &TraceContext<TLSProtocolBehavior>, typ: HandshakeType| {
+            let query = Query {
+                source: Some(Source::Agent(server)),
+                matcher: Some(TlsQueryMatcher::Handshake(Some(typ))),
+                counter: 0,
+            };
+            ctx.find_variable(TypeShape::of::<Message>(), &query)
+                .is_some()
+        };
+
+        // without the flight boundary, the server processes both ClientHellos at once
+        let mut ctx = receive_client_hello_twice();
+        assert!(OutputAction::<TLSProtocolTypes>::new_step(server)
+            .execute(&mut ctx)
+            .is_err());
+
+        // with it, the server stops after its first flight and rejects the second ClientHello in
+        // the next step
+        let mut ctx = receive_client_hello_twice();
+        let flight_step = OutputAction::<TLSProtocolTypes>::new_flight_step(server);
+        flight_step.execute(&mut ctx).unwrap();
+        assert!(has_sent(&ctx, HandshakeType::ServerHello));
+        assert!(has_sent(&ctx, HandshakeType::ServerHelloDone));
+        assert!(flight_step.execute(&mut ctx).is_err());
+    }
+
+    #[apply(test_puts, filter = all(openssl111_binding, tls12))]
+    fn test_extended_master_secret12(put: &str) {
+        use crate::claims::MasterSecret12;
+
+        let runner = default_runner_for(put);
+        let master_secrets = |trace: Trace<TLSProtocolTypes>| {
+            let ctx = runner.execute(trace).unwrap();
+            let secrets: Vec<MasterSecret12> = ctx
+                .claims()
+                .iter()
+                .filter_map(MasterSecret12::from_claim)
+                .collect();
+            secrets
+        };
+
+        // the client of the PUT offers the extension and the server acknowledges it
+        let secrets = master_secrets(seed_successful12.build_trace());
+        assert!(!secrets.is_empty());
+        assert!(secrets.iter().all(|secret| secret.extended));
+        assert!(secrets
+            .iter()
+            .all(|secret| secret.master_secret == secrets[0].master_secret));
+
+        // the ClientHello of the attacker does not offer the extension
+        let secrets = master_secrets(seed_client_attacker12.build_trace());
+        assert!(!secrets.is_empty());
+        assert!(secrets.iter().all(|secret| !secret.extended));
+    }
+
+    #[apply(test_puts, filter = all(tls12, rust_harness))]
+    fn test_last_alert_handshake_failure12(put: &str) {
+        use crate::tls::rustls::msgs::enums::{AlertDescription, AlertLevel};
+
+        let runner = default_runner_for(put);
+        let server = AgentName::first();
+        let client_hello = term! {
+            fn_client_hello(
+                fn_protocol_version12,
+                fn_new_random,
+                fn_new_session_id,
+                // only TLS 1.3 cipher suites, which can not be negotiated in TLS 1.2
+                (fn_cipher_suites_make(
+                    (fn_append_cipher_suite(
+                        (fn_new_cipher_suites()),
+                        fn_cipher_suite13_aes_128_gcm_sha256
+                    ))
+                )),
+                fn_compressions,
+                (fn_client_extensions_make(
+                    (fn_client_extensions_append(
+                        (fn_client_extensions_append(
+                            fn_client_extensions_new,
+                            (fn_support_group_extension(fn_named_group_secp384r1))
+                        )),
+                        fn_signature_algorithm_extension
+                    ))
+                ))
+            )
+        };
+        let trace = Trace {
+            prior_traces: vec![],
+            expected_outcome: None,
+            expected_flow: None,
+            seed: None,
+            descriptors: vec![TLSDescriptorConfig::new_server(server, TLSVersion::V1_2)],
+            steps: vec![InputAction::new_step(server, client_hello)],
+        };
+
+        // the server aborts the handshake, so the execution fails
+        let mut ctx = runner.new_context();
+        assert!(runner.execute_in(&mut ctx, trace).is_err());
+
+        assert_eq!(
+            ctx.find_agent(server).unwrap().last_alert(),
+            Some((AlertLevel::Fatal, AlertDescription::HandshakeFailure))
+        );
+    }
+
+    #[apply(test_puts, filter = all(openssl_binding, tls13))]
+    fn test_supported_extensions13(put: &str) {
+        use crate::tls::rustls::msgs::enums::ExtensionType;
+
+        let runner = default_runner_for(put);
+        let trace = seed_successful.build_trace();
+
+        let agents: Vec<_> = trace.descriptors.iter().map(|d| d.name).collect();
+
+        let ctx = runner.execute(trace).unwrap();
+
+        for agent in agents {
+            let extensions = ctx.find_agent(agent).unwrap().supported_extensions();
+            assert!(extensions.contains(&ExtensionType::KeyShare));
+        }
+    }
+
+    #[apply(test_puts, filter = all(tls12))]
+    fn test_last_alert_none12(put: &str) {
+        let runner = default_runner_for(put);
+        let trace = seed_successful12.build_trace();
+
+        let agents: Vec<_> = trace.descriptors.iter().map(|d| d.name).collect();
+
+        let ctx = runner.execute(trace).unwrap();
+
+        for agent in agents {
+            assert_eq!(ctx.find_agent(agent).unwrap().last_alert(), None);
+        }
+    }
+
+    #[apply(test_puts, filter = all(tls12))]
+    fn test_feed_raw_garbage12(put: &str) {
+        let runner = default_runner_for(put);
+        let server = AgentName::first().next();
+        let mut trace = seed_successful12.build_trace();
+        // stop after the server received the ClientHello, in the middle of the handshake
+        trace.steps.truncate(2);
+
+        let mut ctx = runner.new_context();
+        runner.execute_in(&mut ctx, trace).unwrap();
+
+        let put = ctx.find_agent_mut(server).unwrap().put_mut();
+        // the bytes are no valid records, the PUT may reject them but must not crash
+        let _ = put.feed_raw(&[0xde; 1000]);
+        // a record header which announces more bytes than follow
+        let _ = put.feed_raw(&[0x16, 0x03, 0x03, 0xff, 0xff, 0x01]);
+        assert!(!put.is_handshake_complete());
+    }
+
+    #[allow(dead_code)]
+    fn seed_successful12_with_server_certificate(
+        server_certificate: CertificateKeyPair,
+    ) -> Trace<TLSProtocolTypes> {
+        let mut trace = seed_successful12.build_trace();
+        for descriptor in &mut trace.descriptors {
+            if descriptor.protocol_config.typ == AgentType::Server {
+                descriptor.protocol_config.server_certificate = Some(server_certificate.clone());
+            }
+        }
+
+        trace
+    }
+
+    #[apply(test_puts, filter = all(tls12))]
+    fn test_server_certificate12(put: &str) {
+        use puffin::trace::{Query, Source};
+
+        use crate::static_certs::{EVE_CERT, EVE_PRIVATE_KEY};
+        use crate::tls::rustls::msgs::handshake::CertificatePayload;
+
+        let runner = default_runner_for(put);
+        let server = AgentName::first().next();
+        let trace = seed_successful12_with_server_certificate(CertificateKeyPair::new(
+            EVE_CERT.0,
+            EVE_PRIVATE_KEY.0,
+        ));
+
+        let ctx = runner.execute(trace).unwrap();
+        let query = Query {
+            source: Some(Source::Agent(server)),
+            matcher: None,
+            counter: 0,
+        };
+        let certificates = ctx
+            .find_variable(TypeShape::of::<CertificatePayload>(), &query)
+            .and_then(|term| term.as_any().downcast_ref::<CertificatePayload>())
+            .expect("server did not send a certificate");
+
+        assert_eq!(certificates.0[0].0.as_slice(), EVE_CERT.1);
+    }
+
+    #[apply(test_puts, filter = all(tls12))]
+    fn test_server_certificate_mismatch12(put: &str) {
+        use crate::static_certs::{ALICE_PRIVATE_KEY, EVE_CERT};
+
+        let runner = default_runner_for(put);
+        let trace = seed_successful12_with_server_certificate(CertificateKeyPair::new(
+            EVE_CERT.0,
+            ALICE_PRIVATE_KEY.0,
+        ));
+
+        assert!(runner.execute(trace).is_err());
+    }
+
+    #[apply(test_puts, filter = all(tls12))]
+    fn test_skip_cert_verify12(put: &str) {
+        use puffin::execution::Runner;
+        use puffin::put::PutDescriptor;
+        use puffin::trace::Spawner;
+
+        use crate::put::SKIP_CERT_VERIFY_OPTION;
+        use crate::static_certs::{BOB_CERT, BOB_PRIVATE_KEY};
+
+        // the client only trusts the certificates of Alice and Eve
+        let trace = seed_successful12_with_server_certificate(CertificateKeyPair::new(
+            BOB_CERT.0,
+            BOB_PRIVATE_KEY.0,
+        ));
+        let client = trace
+            .descriptors
+            .iter()
+            .find(|descriptor| descriptor.protocol_config.typ == AgentType::Client)
+            .unwrap()
+            .name;
+
+        assert!(default_runner_for(put).execute(&trace).is_err());
+
+        // only the client skips the verification
+        let registry = tls_registry();
+        let spawner = Spawner::new(registry.clone())
+            .with_default(put)
+            .with_mapping(&[(
+                client,
+                PutDescriptor::new(put, vec![(SKIP_CERT_VERIFY_OPTION, "true")]),
+            )]);
+        let ctx = Runner::new(registry, spawner).execute(&trace).unwrap();
+
+        assert!(ctx.agents_successful());
+    }
+
+    #[apply(test_puts, filter = all(tls12))]
+    fn test_invalid_skip_cert_verify12(put: &str) {
+        use puffin::execution::Runner;
+        use puffin::put::PutDescriptor;
+        use puffin::trace::Spawner;
+
+        use crate::put::SKIP_CERT_VERIFY_OPTION;
+
+        let trace = seed_successful12.build_trace();
+
+        for value in ["yes", "1", ""] {
+            let registry = tls_registry();
+            let spawner = Spawner::new(registry.clone()).with_default(PutDescriptor::new(
+                put,
+                vec![(SKIP_CERT_VERIFY_OPTION, value)],
+            ));
+
+            assert!(Runner::new(registry, spawner).execute(&trace).is_err());
+        }
+    }
+
+    #[apply(test_puts, filter = all(tls12))]
+    fn test_skip_cert_verify_not_reused12(put: &str) {
+        use puffin::execution::Runner;
+        use puffin::put::PutDescriptor;
+        use puffin::trace::Spawner;
+
+        use crate::put::SKIP_CERT_VERIFY_OPTION;
+        use crate::static_certs::{BOB_CERT, BOB_PRIVATE_KEY};
+
+        let (a, b) = (AgentName::first(), AgentName::first().next());
+        let with_bob = |mut trace: Trace<TLSProtocolTypes>| {
+            for descriptor in &mut trace.descriptors {
+                if descriptor.protocol_config.typ == AgentType::Server {
+                    descriptor.protocol_config.server_certificate =
+                        Some(CertificateKeyPair::new(BOB_CERT.0, BOB_PRIVATE_KEY.0));
+                }
+            }
+            trace
+        };
+
+        // only the agent `a` skips the verification
+        let registry = tls_registry();
+        let spawner = Spawner::new(registry.clone())
+            .with_default(put)
+            .with_mapping(&[(
+                a,
+                PutDescriptor::new(put, vec![(SKIP_CERT_VERIFY_OPTION, "true")]),
+            )]);
+        let runner = Runner::new(registry, spawner);
+        let mut ctx = runner.new_context();
+
+        runner
+            .execute_in(&mut ctx, with_bob(seed_successful12(a, b)))
+            .unwrap();
+
+        // the idle client `a` must not be reused for the client `b`, which verifies the
+        // certificate of Bob
+        assert!(runner
+            .execute_in(&mut ctx, with_bob(seed_successful12(b, a)))
+            .is_err());
+    }
+
+    #[test_log::test]
+    fn test_negative_missing_supported_versions13() {
+        use puffin::algebra::dynamic_function::DescribableFunction;
+        use puffin::execution::Outcome;
+        use puffin::fuzzer::term_zoo::TermZoo;
+
+        let descriptor = TLSDescriptorConfig::new_server(AgentName::first(), TLSVersion::V1_3);
+        let traces = TermZoo::negative_traces::<TLSProtocolBehavior>(&descriptor);
+
+        let missing_supported_versions = traces
+            .iter()
+            .find(|trace| {
+                trace.count_functions_by_name(fn_client_hello.name()) > 0
+                    && trace.count_functions_by_name(fn_supported_versions13_extension.name()) == 0
+            })
+            .expect("no trace with a ClientHello lacking supported_versions");
+
+        assert_eq!(
+            missing_supported_versions.expected_outcome,
+            Some(Outcome::Rejected)
+        );
+        assert_eq!(missing_supported_versions.descriptors, vec![descriptor]);
+        assert!(traces
+            .iter()
+            .all(|trace| trace.expected_outcome == Some(Outcome::Rejected)));
+
+        // the negative traces attack servers only
+        let client = TLSDescriptorConfig::new_client(AgentName::first(), TLSVersion::V1_3);
+        assert!(TermZoo::negative_traces::<TLSProtocolBehavior>(&client).is_empty());
+    }
+
+    #[apply(test_puts)]
+    fn test_negative_traces_rejected(put: &str) {
+        use puffin::fuzzer::term_zoo::TermZoo;
+
+        let registry = tls_registry();
+        let factory = registry.find_by_id(put).unwrap();
+        let runner = default_runner_for(put);
+
+        let versions = [("tls12", TLSVersion::V1_2), ("tls13", TLSVersion::V1_3)];
+        for (_, version) in versions
+            .into_iter()
+            .filter(|(capability, _)| factory.supports(capability))
+        {
+            let server = TLSDescriptorConfig::new_server(AgentName::first(), version);
+            let traces = TermZoo::negative_traces::<TLSProtocolBehavior>(&server);
+            assert!(!traces.is_empty());
+
+            for trace in traces {
+                let mut ctx = runner.new_context();
+                let failed = runner.execute_in(&mut ctx, &trace).is_err();
+                let alerted = ctx
+                    .find_agent(server.name)
+                    .is_ok_and(|agent| agent.last_alert().is_some());
+
+                assert!(
+                    failed || alerted,
+                    "{put} accepted the negative trace:\n{trace}"
+                );
+            }
+        }
+    }
+
     #[test_log::test]
     fn test_corpus_file_sizes() {
         use puffin::trace::Action;
@@ -2170,6 +3141,7 @@ pub mod tests {
             seed_client_attacker12.build_named_trace(),
             seed_session_resumption_dhe.build_named_trace(),
             seed_session_resumption_ke.build_named_trace(),
+            seed_session_resumption12.build_named_trace(),
             seed_client_attacker_full.build_named_trace(),
             // _full can be large: seed_session_resumption_dhe_full.build_named_trace(),
         ] {
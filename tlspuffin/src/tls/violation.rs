@@ -1,4 +1,5 @@
 use itertools::Itertools;
+use puffin::agent::AgentName;
 use puffin::claims::SecurityViolationPolicy;
 
 use crate::claims::{ClaimData, ClaimDataMessage, Finished, TlsClaim};
@@ -11,6 +12,14 @@ impl SecurityViolationPolicy for TlsSecurityViolationPolicy {
     type C = TlsClaim;
 
     fn check_violation(claims: &[TlsClaim]) -> Option<&'static str> {
+        if find_unoffered_cipher(claims).is_some() {
+            return Some("Selected cipher was not offered");
+        }
+
+        if find_resumed_cipher_mismatch(claims).is_some() {
+            return Some("Resumed session with a different cipher");
+        }
+
         if let Some((claim_a, claim_b)) = find_two_finished_messages(claims) {
             if let Some(((client_claim, client), (server_claim, server))) =
                 get_client_server(claim_a, claim_b)
@@ -121,6 +130,108 @@ impl SecurityViolationPolicy for TlsSecurityViolationPolicy {
 
         None
     }
+
+    fn is_handshake_complete(claims: &[TlsClaim]) -> bool {
+        // A peer claims an inbound Finished once it received the Finished of the other peer
+        let finished_by = |origin: AgentType| {
+            claims.iter().any(|claim| {
+                claim.origin == origin
+                    && matches!(
+                        &claim.data,
+                        ClaimData::Message(ClaimDataMessage::Finished(finished))
+                            if !finished.outbound
+                    )
+            })
+        };
+
+        finished_by(AgentType::Client) && finished_by(AgentType::Server)
+    }
+}
+
+/// Finds a cipher suite selected in a ServerHello sent by a server which is not in the list of
+/// cipher suites of the ClientHello it answers. Servers which do not claim the offered cipher
+/// suites are skipped.
+///
+/// A ServerHello answers the last ClientHello which the server received before, e.g. the second
+/// ClientHello after a HelloRetryRequest or the ClientHello of a renegotiation.
+pub fn find_unoffered_cipher(claims: &[TlsClaim]) -> Option<(AgentName, u16)> {
+    claims
+        .iter()
+        .enumerate()
+        .find_map(|(i, claim)| match &claim.data {
+            ClaimData::Message(ClaimDataMessage::ServerHello(server_hello))
+                if claim.origin == AgentType::Server && server_hello.outbound =>
+            {
+                let client_hello = claims[..i]
+                    .iter()
+                    .rev()
+                    .filter(|other| other.agent_name == claim.agent_name)
+                    .find_map(|other| match &other.data {
+                        ClaimData::Message(ClaimDataMessage::ClientHello(client_hello))
+                            if !client_hello.outbound =>
+                        {
+                            Some(client_hello)
+                        }
+                        _ => None,
+                    })?;
+
+                (!client_hello.available_ciphers.is_empty()
+                    && !client_hello
+                        .available_ciphers
+                        .contains(&server_hello.chosen_cipher))
+                .then_some((claim.agent_name, server_hello.chosen_cipher))
+            }
+            _ => None,
+        })
+}
+
+/// Finds the TLS 1.2 handshakes which resumed a session, together with the Finished of the
+/// handshake which established the session.
+///
+/// A handshake resumed a session if the PUT claims so. The session was established by the
+/// handshake of the same agent right before, i.e. the last one with other randoms, which must have
+/// finished with the same non-empty session id.
+pub fn find_resumed_sessions12(claims: &[TlsClaim]) -> Vec<((&TlsClaim, &Finished), &Finished)> {
+    let finished = claims
+        .iter()
+        .filter_map(|claim| match &claim.data {
+            ClaimData::Message(ClaimDataMessage::Finished(finished))
+                if claim.protocol_version == TLSVersion::V1_2
+                    && !finished.session_id.is_empty() =>
+            {
+                Some((claim, finished))
+            }
+            _ => None,
+        })
+        .collect::<Vec<(&TlsClaim, &Finished)>>();
+
+    finished
+        .iter()
+        .enumerate()
+        .filter(|(_, (_, resumed))| resumed.session_resumed)
+        .filter_map(|(i, &(claim, resumed))| {
+            let &(_, established) =
+                finished[..i]
+                    .iter()
+                    .rev()
+                    .find(|(previous_claim, previous)| {
+                        previous_claim.agent_name == claim.agent_name
+                            && previous.client_random != resumed.client_random
+                    })?;
+
+            (established.session_id == resumed.session_id)
+                .then_some(((claim, resumed), established))
+        })
+        .collect()
+}
+
+/// Finds a TLS 1.2 session which an agent resumed with a different cipher suite than the one which
+/// was negotiated when the session was established.
+pub fn find_resumed_cipher_mismatch(claims: &[TlsClaim]) -> Option<(AgentName, u16)> {
+    find_resumed_sessions12(claims)
+        .into_iter()
+        .find(|((_, resumed), established)| established.chosen_cipher != resumed.chosen_cipher)
+        .map(|((claim, resumed), _)| (claim.agent_name, resumed.chosen_cipher))
 }
 
 pub fn find_two_finished_messages(
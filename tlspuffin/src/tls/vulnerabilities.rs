@@ -82,7 +82,7 @@ pub fn seed_cve_2022_25638(server: AgentName) -> Trace<TLSProtocolTypes> {
             // Option 1 (something random, only possible because of fn_empty_certificate_chain, if FAIL_IF_NO_PEER_CERT is unset):
             //fn_eve_cert // or fn_empty_bytes_vec
             // Option 2 (impersonating eve, you have to send eve cert):
-            (fn_payload_u16(fn_eve_pkcs1_signature))
+            fn_eve_pkcs1_signature
             // Option 3 (for testing):
             /* (fn_rsa_sign_client(
                 (fn_certificate_transcript(((server, 0)))),
@@ -106,6 +106,9 @@ pub fn seed_cve_2022_25638(server: AgentName) -> Trace<TLSProtocolTypes> {
 
     Trace {
         prior_traces: vec![],
+        expected_outcome: None,
+        expected_flow: None,
+        seed: None,
         descriptors: vec![AgentDescriptor::from_config(
             server,
             TLSDescriptorConfig {
@@ -246,6 +249,9 @@ pub fn seed_cve_2022_25640(server: AgentName) -> Trace<TLSProtocolTypes> {
 
     Trace {
         prior_traces: vec![],
+        expected_outcome: None,
+        expected_flow: None,
+        seed: None,
         descriptors: vec![AgentDescriptor::from_config(
             server,
             TLSDescriptorConfig {
@@ -322,7 +328,7 @@ pub fn seed_cve_2021_3449(server: AgentName) -> Trace<TLSProtocolTypes> {
                                 fn_client_extensions_new,
                                 (fn_support_group_extension(fn_named_group_secp384r1))
                             )),
-                            fn_ec_point_formats_extension
+                            (fn_ec_point_formats_extension(fn_ec_point_formats_uncompressed))
                         )),
                         fn_signed_certificate_timestamp_extension
                     )),
@@ -392,7 +398,7 @@ pub fn seed_heartbleed(client: AgentName, server: AgentName) -> Trace<TLSProtoco
                         fn_client_extensions_new,
                         (fn_support_group_extension(fn_named_group_secp384r1))
                     )),
-                    fn_ec_point_formats_extension
+                    (fn_ec_point_formats_extension(fn_ec_point_formats_uncompressed))
                 )),
                 fn_signed_certificate_timestamp_extension
             ))
@@ -401,6 +407,9 @@ pub fn seed_heartbleed(client: AgentName, server: AgentName) -> Trace<TLSProtoco
 
     Trace {
         prior_traces: vec![],
+        expected_outcome: None,
+        expected_flow: None,
+        seed: None,
         descriptors: vec![
             TLSDescriptorConfig::new_client(client, TLSVersion::V1_2),
             TLSDescriptorConfig::new_server(server, TLSVersion::V1_2),
@@ -426,6 +435,9 @@ pub fn seed_heartbleed(client: AgentName, server: AgentName) -> Trace<TLSProtoco
 pub fn seed_freak(client: AgentName, server: AgentName) -> Trace<TLSProtocolTypes> {
     Trace {
         prior_traces: vec![],
+        expected_outcome: None,
+        expected_flow: None,
+        seed: None,
         descriptors: vec![
             TLSDescriptorConfig::new_client(client, TLSVersion::V1_2),
             TLSDescriptorConfig::new_server(server, TLSVersion::V1_2),
@@ -559,6 +571,9 @@ pub fn seed_cve_2022_25640_simple(server: AgentName) -> Trace<TLSProtocolTypes>
 
     Trace {
         prior_traces: vec![],
+        expected_outcome: None,
+        expected_flow: None,
+        seed: None,
         descriptors: vec![AgentDescriptor::from_config(
             server,
             TLSDescriptorConfig {
@@ -598,6 +613,9 @@ pub fn seed_cve_2022_25640_simple(server: AgentName) -> Trace<TLSProtocolTypes>
 pub fn seed_cve_2022_38153(client: AgentName, server: AgentName) -> Trace<TLSProtocolTypes> {
     Trace {
         prior_traces: vec![],
+        expected_outcome: None,
+        expected_flow: None,
+        seed: None,
         descriptors: vec![
             TLSDescriptorConfig::new_client(client, TLSVersion::V1_2),
             TLSDescriptorConfig::new_server(server, TLSVersion::V1_2),
@@ -811,6 +829,9 @@ pub fn seed_cve_2022_39173(
         // Step 1: Prior trace performs an initial TLS 1.3 session with a full handshake and
         // establishes a PSK, including Client Hello number 1 (`CH1`).
         prior_traces: vec![initial_handshake],
+        expected_outcome: None,
+        expected_flow: None,
+        seed: None,
         descriptors: vec![TLSDescriptorConfig::new_server(server, TLSVersion::V1_3)],
         steps: vec![
             // Step 2: sends a Client Hello (CH2) with a missing support_group_extension that will
@@ -946,6 +967,9 @@ pub fn seed_cve_2022_39173_full(
 
     Trace {
         prior_traces: vec![initial_handshake],
+        expected_outcome: None,
+        expected_flow: None,
+        seed: None,
         descriptors: vec![TLSDescriptorConfig::new_server(server, TLSVersion::V1_3)],
         steps: vec![
             Step {
@@ -1050,6 +1074,9 @@ pub fn seed_cve_2022_39173_minimized(server: AgentName) -> Trace<TLSProtocolType
     Trace {
         // No more need for a prior trace and a full handshake.
         prior_traces: vec![], // WAS [initial_handshake],
+        expected_outcome: None,
+        expected_flow: None,
+        seed: None,
         descriptors: vec![TLSDescriptorConfig::new_server(server, TLSVersion::V1_3)],
         steps: vec![
             Step {
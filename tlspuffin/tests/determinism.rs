@@ -1,4 +1,11 @@
+use std::any::TypeId;
+
+use puffin::agent::AgentName;
+use puffin::claims::Claim;
+use puffin::trace::TraceContext;
+use tlspuffin::protocol::TLSProtocolBehavior;
 use tlspuffin::test_utils::prelude::*;
+use tlspuffin::tls::rustls::msgs::message::Message;
 
 #[apply(test_puts, filter = all(tls13, boringssl))]
 fn test_attacker_full_det_recreate(put: &str) {
@@ -15,3 +22,85 @@ fn test_attacker_full_det_recreate(put: &str) {
         assert_eq!(ctx_1, ctx_2);
     }
 }
+
+/// Claims and outputs of an execution without the random parts, i.e. which agent made which claim
+/// and which agent sent which kind of message
+fn execution_shape(ctx: &TraceContext<TLSProtocolBehavior>) -> (Vec<String>, Vec<String>) {
+    let claims = ctx
+        .claims()
+        .iter()
+        .map(|claim| format!("{} {}", claim.agent_name(), claim.id()))
+        .collect();
+    let outputs = [AgentName::first(), AgentName::first().next()]
+        .into_iter()
+        .map(|agent| {
+            let sent = ctx
+                .knowledge_store
+                .sent_messages(agent, TypeId::of::<Message>());
+            format!("{agent} {sent:?}")
+        })
+        .collect();
+
+    (claims, outputs)
+}
+
+#[apply(test_puts, filter = tls13)]
+fn test_soft_reset_like_recreate(put: &str) {
+    use tlspuffin::tls::seeds::seed_successful;
+
+    let runner = default_runner_for(put);
+    let trace = seed_successful.build_trace();
+
+    let recreated = runner.execute(&trace).unwrap();
+    assert!(recreated.agents_successful());
+
+    // The first execution spawns the agents, the following ones soft reset them
+    let mut ctx = runner.new_context();
+    for i in 0..3 {
+        println!("Attempt #{i}...");
+        runner.execute_in(&mut ctx, &trace).unwrap();
+
+        assert!(ctx.agents_successful());
+        assert_eq!(execution_shape(&ctx), execution_shape(&recreated));
+    }
+}
+
+#[apply(test_puts, filter = all(tls13, openssl111_binding))]
+fn test_soft_reset_deterministic(put: &str) {
+    use tlspuffin::tls::seeds::seed_successful;
+
+    let runner = default_runner_for(put);
+    let trace = seed_successful.build_trace();
+
+    let mut ctx = runner.new_context();
+    runner.execute_in(&mut ctx, &trace).unwrap();
+
+    // Soft resets do not draw from the random stream, hence all executions with reused agents
+    // send the same bytes and make the same claims
+    runner.execute_in(&mut ctx, &trace).unwrap();
+    let output_digest = ctx.knowledge_store.output_digest();
+    let claims = format!("{:?}", ctx.claims());
+
+    for i in 0..3 {
+        println!("Attempt #{i}...");
+        runner.execute_in(&mut ctx, &trace).unwrap();
+
+        assert_eq!(ctx.knowledge_store.output_digest(), output_digest);
+        assert_eq!(format!("{:?}", ctx.claims()), claims);
+    }
+}
+
+#[apply(test_puts, filter = all(tls13, boringssl))]
+fn test_replay_deterministic(put: &str) {
+    use puffin::execution::replay;
+    use tlspuffin::tls::seeds::seed_successful;
+
+    let runner = default_runner_for(put);
+    let trace = seed_successful.build_trace();
+
+    let report = replay(&runner, &trace, 5);
+
+    assert_eq!(report.runs.len(), 5);
+    assert!(report.runs.iter().all(|run| run.error.is_none()));
+    assert!(report.is_deterministic(), "{report:?}");
+}
@@ -82,7 +82,7 @@ fn test_mutate_seed_cve_2021_3449() {
                 // Check if we have a client hello in last encrypted one
 
                 let constraints = TermConstraints::default();
-                let mut mutator = ReplaceReuseMutator::new(constraints, true);
+                let mut mutator = ReplaceReuseMutator::new(constraints, usize::MAX, true);
 
                 loop {
                     attempts += 1;
@@ -181,7 +181,7 @@ fn test_mutate_seed_cve_2021_3449() {
 
                 // Sucessfully renegotiate
 
-                let mut mutator = ReplaceReuseMutator::new(constraints, true);
+                let mut mutator = ReplaceReuseMutator::new(constraints, usize::MAX, true);
 
                 loop {
                     attempts += 1;